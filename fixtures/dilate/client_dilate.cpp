@@ -0,0 +1,128 @@
+// The dilate of the DDNet client, `src/engine/gfx/image_manipulation.cpp`, with a main that
+// reads `<w> <h>` and the raw rgba pixels from stdin and writes the dilated pixels to stdout.
+// Only used to generate the reference outputs, see generate.py.
+#include <algorithm>
+#include <cstdint>
+#include <cstdio>
+#include <cstdlib>
+#include <cstring>
+#include <vector>
+
+static constexpr int DILATE_ALPHA_THRESHOLD = 10;
+
+static void Dilate(int w, int h, const uint8_t *pSrc, uint8_t *pDest)
+{
+	const int BPP = 4;
+	const int AlphaCompIndex = BPP - 1;
+	int ix, iy;
+	const int aDirX[] = {0, -1, 1, 0};
+	const int aDirY[] = {-1, 0, 0, 1};
+
+	int m = 0;
+	for(int y = 0; y < h; y++)
+	{
+		for(int x = 0; x < w; x++, m += BPP)
+		{
+			for(int i = 0; i < BPP; ++i)
+				pDest[m + i] = pSrc[m + i];
+			if(pSrc[m + AlphaCompIndex] > DILATE_ALPHA_THRESHOLD)
+				continue;
+
+			// clear pixels that are considered transparent
+			// this allows the image to always be black where no dilate is needed
+			for(int i = 0; i < BPP - 1; ++i)
+				pDest[m + i] = 0;
+
+			for(int c = 0; c < 4; c++)
+			{
+				ix = std::clamp(x + aDirX[c], 0, w - 1);
+				iy = std::clamp(y + aDirY[c], 0, h - 1);
+				int k = iy * w * BPP + ix * BPP;
+				if(pSrc[k + AlphaCompIndex] > DILATE_ALPHA_THRESHOLD)
+				{
+					for(int p = 0; p < BPP - 1; ++p)
+						pDest[m + p] = pSrc[k + p];
+					pDest[m + AlphaCompIndex] = 255;
+					break;
+				}
+			}
+		}
+	}
+}
+
+static void CopyColorValues(int w, int h, int BPP, const uint8_t *pImageBuff, uint8_t *pDestBuff)
+{
+	int m = 0;
+	for(int y = 0; y < h; y++)
+	{
+		for(int x = 0; x < w; x++, m += BPP)
+		{
+			for(int i = 0; i < BPP - 1; ++i)
+			{
+				if(pDestBuff[m + 3] == 0)
+					pDestBuff[m + i] = pImageBuff[m + i];
+			}
+		}
+	}
+}
+
+static void DilateImageSub(uint8_t *pImageBuff, int w, int h, int x, int y, int sw, int sh)
+{
+	const int BPP = 4;
+	uint8_t *apBuffer[2] = {nullptr, nullptr};
+
+	apBuffer[0] = (uint8_t *)malloc((size_t)sw * sh * sizeof(uint8_t) * BPP);
+	apBuffer[1] = (uint8_t *)malloc((size_t)sw * sh * sizeof(uint8_t) * BPP);
+	uint8_t *pBufferOriginal = (uint8_t *)malloc((size_t)sw * sh * sizeof(uint8_t) * BPP);
+
+	uint8_t *pPixelBuff = pImageBuff;
+
+	for(int Y = 0; Y < sh; ++Y)
+	{
+		int SrcImgOffset = ((y + Y) * w * BPP) + (x * BPP);
+		int DstImgOffset = (Y * sw * BPP);
+		int CopySize = sw * BPP;
+		memcpy(&pBufferOriginal[DstImgOffset], &pPixelBuff[SrcImgOffset], CopySize);
+	}
+
+	Dilate(sw, sh, pBufferOriginal, apBuffer[0]);
+
+	for(int i = 0; i < 5; i++)
+	{
+		Dilate(sw, sh, apBuffer[0], apBuffer[1]);
+		Dilate(sw, sh, apBuffer[1], apBuffer[0]);
+	}
+
+	CopyColorValues(sw, sh, BPP, apBuffer[0], pBufferOriginal);
+
+	free(apBuffer[0]);
+	free(apBuffer[1]);
+
+	for(int Y = 0; Y < sh; ++Y)
+	{
+		int SrcImgOffset = ((y + Y) * w * BPP) + (x * BPP);
+		int DstImgOffset = (Y * sw * BPP);
+		int CopySize = sw * BPP;
+		memcpy(&pPixelBuff[SrcImgOffset], &pBufferOriginal[DstImgOffset], CopySize);
+	}
+
+	free(pBufferOriginal);
+}
+
+static void DilateImage(uint8_t *pImageBuff, int w, int h)
+{
+	DilateImageSub(pImageBuff, w, h, 0, 0, w, h);
+}
+
+int main()
+{
+	int w, h;
+	if(scanf("%d %d\n", &w, &h) != 2 || w <= 0 || h <= 0)
+		return 1;
+	std::vector<uint8_t> Pixels((size_t)w * h * 4);
+	if(fread(Pixels.data(), 1, Pixels.size(), stdin) != Pixels.size())
+		return 1;
+	DilateImage(Pixels.data(), w, h);
+	fwrite(Pixels.data(), 1, Pixels.size(), stdout);
+	return 0;
+}
@@ -0,0 +1,80 @@
+"""Writes the fixture images and their reference outputs of the client's dilate.
+
+    g++ -O2 -std=c++17 -o /tmp/client_dilate client_dilate.cpp
+    python3 generate.py /tmp/client_dilate
+
+The inputs are `<name>.png`, what the client makes of them `<name>.client.png`.
+"""
+
+import random
+import struct
+import subprocess
+import sys
+import zlib
+from pathlib import Path
+
+
+def write_png(path, w, h, rgba):
+    rows = b"".join(b"\0" + bytes(rgba[y * w * 4 : (y + 1) * w * 4]) for y in range(h))
+
+    def chunk(kind, data):
+        body = kind + data
+        return struct.pack(">I", len(data)) + body + struct.pack(">I", zlib.crc32(body))
+
+    png = b"\x89PNG\r\n\x1a\n"
+    png += chunk(b"IHDR", struct.pack(">IIBBBBB", w, h, 8, 6, 0, 0, 0))
+    png += chunk(b"IDAT", zlib.compress(rows, 9))
+    png += chunk(b"IEND", b"")
+    path.write_bytes(png)
+
+
+def circles(w, h, rng):
+    """opaque shaded circles, like the parts of a skin"""
+    rgba = [0] * (w * h * 4)
+    for _ in range(6):
+        cx, cy, r = rng.randrange(w), rng.randrange(h), rng.randrange(4, h // 3)
+        color = [rng.randrange(256) for _ in range(3)]
+        for y in range(max(cy - r, 0), min(cy + r + 1, h)):
+            for x in range(max(cx - r, 0), min(cx + r + 1, w)):
+                if (x - cx) ** 2 + (y - cy) ** 2 <= r * r:
+                    i = (y * w + x) * 4
+                    rgba[i : i + 4] = [(c + x + y) % 256 for c in color] + [255]
+    return rgba
+
+
+def noise(w, h, rng):
+    """sparse pixels of any alpha, the transparent ones with a color editors leave behind"""
+    rgba = [0] * (w * h * 4)
+    for i in range(w * h):
+        if rng.random() < 0.05:
+            alpha = rng.choice([1, 10, 11, 128, 255])
+            rgba[i * 4 : i * 4 + 4] = [rng.randrange(256) for _ in range(3)] + [alpha]
+        elif rng.random() < 0.2:
+            rgba[i * 4 : i * 4 + 4] = [255, 255, 255, 0]
+    return rgba
+
+
+FIXTURES = [
+    ("circles_256x128", 256, 128, circles),
+    ("circles_512x256", 512, 256, circles),
+    ("noise_256x128", 256, 128, noise),
+    # odd sizes, the neighbours are clamped at the edges
+    ("noise_37x23", 37, 23, noise),
+]
+
+
+def main():
+    client = sys.argv[1]
+    out = Path(__file__).parent
+    rng = random.Random(3296)
+    for name, w, h, draw in FIXTURES:
+        rgba = draw(w, h, rng)
+        dilated = subprocess.run(
+            [client], input=f"{w} {h}\n".encode() + bytes(rgba), capture_output=True, check=True
+        ).stdout
+        write_png(out / f"{name}.png", w, h, rgba)
+        write_png(out / f"{name}.client.png", w, h, dilated)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,163 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
+use serde::Serialize;
+use serenity::all::{ShardManager, Timestamp};
+use serenity::gateway::ConnectionStage;
+use serenity::prelude::*;
+use tracing::{error, info};
+
+use crate::metrics::{Gauges, Metrics};
+use crate::SkinUploads;
+
+/// How often the shard is checked for a new heartbeat.
+const HEARTBEAT_SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Serenity only exposes the latency of the last heartbeat, a new latency means another
+/// heartbeat was acknowledged.
+#[derive(Default)]
+struct LastHeartbeat {
+    latency: Option<Duration>,
+    /// unix timestamp
+    at: Option<i64>,
+}
+
+async fn watch_heartbeats(shard_manager: Arc<ShardManager>, last: Arc<StdMutex<LastHeartbeat>>) {
+    let mut interval = tokio::time::interval(HEARTBEAT_SAMPLE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let latency = shard_manager
+            .runners
+            .lock()
+            .await
+            .values()
+            .next()
+            .and_then(|runner| runner.latency);
+        let mut last = last.lock().unwrap();
+        if latency.is_some() && latency != last.latency {
+            last.latency = latency;
+            last.at = Some(Timestamp::now().unix_timestamp());
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Health {
+    gateway_connected: bool,
+    /// `connected`, `resuming`, ... of the first shard
+    gateway_stage: String,
+    /// unix timestamp of the last acknowledged heartbeat, accurate to a few seconds, missing
+    /// before the first one
+    last_heartbeat_at: Option<i64>,
+    /// between the last heartbeat and its acknowledgement
+    heartbeat_latency_ms: Option<u128>,
+}
+
+async fn health(shard_manager: &ShardManager, last_heartbeat: &StdMutex<LastHeartbeat>) -> Health {
+    let runners = shard_manager.runners.lock().await;
+    let runner = runners.values().next();
+    Health {
+        gateway_connected: runner.is_some_and(|runner| runner.stage == ConnectionStage::Connected),
+        gateway_stage: runner.map_or_else(
+            || "not started".to_string(),
+            |runner| runner.stage.to_string(),
+        ),
+        last_heartbeat_at: last_heartbeat.lock().unwrap().at,
+        heartbeat_latency_ms: runner
+            .and_then(|runner| runner.latency)
+            .map(|latency| latency.as_millis()),
+    }
+}
+
+fn response(status: StatusCode, content_type: &str, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, content_type)
+        .body(Body::from(body))
+        .unwrap()
+}
+
+async fn respond(
+    request: Request<Body>,
+    data: Arc<RwLock<TypeMap>>,
+    shard_manager: Arc<ShardManager>,
+    last_heartbeat: Arc<StdMutex<LastHeartbeat>>,
+) -> Result<Response<Body>, Infallible> {
+    if request.method() != Method::GET {
+        return Ok(response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "text/plain",
+            String::default(),
+        ));
+    }
+    let response = match request.uri().path() {
+        "/healthz" => {
+            let health = health(&shard_manager, &last_heartbeat).await;
+            let status = if health.gateway_connected {
+                StatusCode::OK
+            } else {
+                StatusCode::SERVICE_UNAVAILABLE
+            };
+            let body = serde_json::to_string(&health).unwrap_or_default();
+            response(status, "application/json", body)
+        }
+        "/metrics" => {
+            let gateway_connected = health(&shard_manager, &last_heartbeat)
+                .await
+                .gateway_connected;
+            let data = data.read().await;
+            let gauges = Gauges {
+                active_sessions: data.get::<SkinUploads>().unwrap().uploads.len(),
+                gateway_connected,
+            };
+            let body = data.get::<Metrics>().unwrap().render(&gauges);
+            response(StatusCode::OK, "text/plain; version=0.0.4", body)
+        }
+        _ => response(StatusCode::NOT_FOUND, "text/plain", String::default()),
+    };
+    Ok(response)
+}
+
+/// Serves `/healthz` and `/metrics` until the bot stops. The endpoints have no auth,
+/// they should only be reachable by the orchestrator and the scraper.
+pub async fn serve_admin_http(
+    addr: SocketAddr,
+    data: Arc<RwLock<TypeMap>>,
+    shard_manager: Arc<ShardManager>,
+) {
+    let last_heartbeat = Arc::new(StdMutex::new(LastHeartbeat::default()));
+    tokio::spawn(watch_heartbeats(
+        shard_manager.clone(),
+        last_heartbeat.clone(),
+    ));
+    let make_service = make_service_fn(move |_| {
+        let data = data.clone();
+        let shard_manager = shard_manager.clone();
+        let last_heartbeat = last_heartbeat.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                respond(
+                    request,
+                    data.clone(),
+                    shard_manager.clone(),
+                    last_heartbeat.clone(),
+                )
+            }))
+        }
+    });
+    let server = match Server::try_bind(&addr) {
+        Ok(builder) => builder.serve(make_service),
+        Err(err) => {
+            error!("Could not listen on {addr} for /healthz and /metrics: {err}");
+            return;
+        }
+    };
+    info!("Serving /healthz and /metrics on {addr}");
+    if let Err(err) = server.await {
+        error!("The admin http server failed: {err}");
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+
+use serenity::all::{Mention, UserId};
+
+use crate::config::{AnnouncementConfig, TemplateConfig};
+use crate::SkinToUploadDB;
+
+pub struct UploadedSkin {
+    pub name: String,
+    pub database: SkinToUploadDB,
+    pub author_id: UserId,
+    pub author_name: String,
+    pub link: String,
+    /// of the community votes on the submission
+    pub positive_ratio: f64,
+}
+
+/// Replaces the `{placeholder}`s of a template in a single pass, so braces in the values,
+/// e.g. in a skin name, are kept as they are. Unknown placeholders are left in the text.
+pub fn fill_template(template: &str, values: &[(&str, &str)]) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        filled += &rest[..start];
+        rest = &rest[start..];
+        let value = rest.find('}').and_then(|end| {
+            let (_, value) = values.iter().find(|(key, _)| *key == &rest[1..end])?;
+            Some((end, value))
+        });
+        match value {
+            Some((end, value)) => {
+                filled.push_str(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                filled.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    filled + rest
+}
+
+/// The placeholders of a template that are not among the known ones.
+pub fn unknown_placeholders<'a>(template: &'a str, known: &[&str]) -> Vec<&'a str> {
+    template
+        .split('{')
+        .skip(1)
+        .filter_map(|part| part.split_once('}'))
+        .map(|(key, _)| key)
+        .filter(|key| {
+            !key.is_empty()
+                && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !known.contains(key)
+        })
+        .collect()
+}
+
+/// Splits the announcement of the skins uploaded to the database into messages, together with
+/// the users each message is allowed to ping. Large batches fall back to plain names.
+pub fn announcement_messages(
+    skins: &[&UploadedSkin],
+    database: SkinToUploadDB,
+    config: &AnnouncementConfig,
+    templates: &TemplateConfig,
+) -> Vec<(String, HashSet<UserId>)> {
+    let authors: HashSet<UserId> = skins.iter().map(|skin| skin.author_id).collect();
+    let plain_names = config.plain_names_above > 0 && authors.len() > config.plain_names_above;
+
+    let database_name = templates.database(database);
+    let mut msgs: Vec<(String, HashSet<UserId>)> = vec![(
+        fill_template(
+            &templates.announcement_header,
+            &[("database", database_name)],
+        ) + "\n",
+        HashSet::default(),
+    )];
+    for skin in skins {
+        let author = if plain_names {
+            skin.author_name.clone()
+        } else {
+            Mention::User(skin.author_id).to_string()
+        };
+        let ratio = format!("{:.0}%", skin.positive_ratio * 100.0);
+        let skin_msg = fill_template(
+            &templates.announcement_skin,
+            &[
+                ("name", &skin.name),
+                ("author", &author),
+                ("database", database_name),
+                ("link", &skin.link),
+                ("ratio", &ratio),
+            ],
+        ) + "\n";
+
+        let (msg, mentions) = msgs.last().unwrap();
+        let is_new_mention = !plain_names && !mentions.contains(&skin.author_id);
+        if msg.chars().count() + skin_msg.chars().count() > 2000
+            || (is_new_mention && mentions.len() >= config.max_mentions_per_message)
+        {
+            msgs.push((String::default(), HashSet::default()));
+        }
+        let (msg, mentions) = msgs.last_mut().unwrap();
+        *msg += &skin_msg;
+        if !plain_names {
+            mentions.insert(skin.author_id);
+        }
+    }
+    msgs
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::all::UserId;
+
+    use super::{announcement_messages, fill_template, unknown_placeholders, UploadedSkin};
+    use crate::config::{AnnouncementConfig, TemplateConfig};
+    use crate::SkinToUploadDB;
+
+    #[test]
+    fn fills_placeholders_once() {
+        let values = [("name", "{author}"), ("author", "Ninjed")];
+        assert_eq!(
+            fill_template("\"{name}\" von {author} {unknown} {", &values),
+            "\"{author}\" von Ninjed {unknown} {"
+        );
+        assert_eq!(
+            unknown_placeholders("{name} {ratio} {votes} {} { x }", &["name", "ratio"]),
+            ["votes"]
+        );
+    }
+
+    #[test]
+    fn announces_with_templates() {
+        let skin = UploadedSkin {
+            name: "greyfox".to_string(),
+            database: SkinToUploadDB::Community,
+            author_id: UserId::new(42),
+            author_name: "Ninjed".to_string(),
+            link: "https://discord.com/channels/1/2/3".to_string(),
+            positive_ratio: 0.75,
+        };
+        let templates = TemplateConfig {
+            announcement_header: "Neue Skins in der {database} Datenbank:".to_string(),
+            announcement_skin: "- {name} von {author} ({ratio})".to_string(),
+            community_database: "Community".to_string(),
+            ..TemplateConfig::default()
+        };
+        let msgs = announcement_messages(
+            &[&skin],
+            SkinToUploadDB::Community,
+            &AnnouncementConfig::default(),
+            &templates,
+        );
+        assert_eq!(msgs.len(), 1);
+        assert_eq!(
+            msgs[0].0,
+            "Neue Skins in der Community Datenbank:\n- greyfox von <@42> (75%)\n"
+        );
+        assert!(msgs[0].1.contains(&UserId::new(42)));
+    }
+}
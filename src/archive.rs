@@ -0,0 +1,259 @@
+use std::io::{Cursor, Read};
+
+use anyhow::{anyhow, bail};
+use flate2::read::DeflateDecoder;
+use flate2::Crc;
+
+const LOCAL_HEADER_SIG: u32 = 0x04034b50;
+const CENTRAL_HEADER_SIG: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIR_SIG: u32 = 0x06054b50;
+/// the end of central directory record is followed by a comment of at most u16::MAX bytes
+const END_OF_CENTRAL_DIR_MAX_OFFSET: usize = 22 + u16::MAX as usize;
+
+/// protects against zip bombs, skins are far smaller
+const MAX_ENTRY_SIZE: u64 = 16 * 1024 * 1024;
+const MAX_ENTRIES: usize = 64;
+/// of all files together, unpacked
+const MAX_TOTAL_SIZE: u64 = 64 * 1024 * 1024;
+const MIB: u64 = 1024 * 1024;
+
+pub fn is_zip(file: &[u8]) -> bool {
+    file.starts_with(&LOCAL_HEADER_SIG.to_le_bytes())
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> anyhow::Result<u16> {
+    buf.get(offset..offset + 2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| anyhow!("the zip file is truncated"))
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> anyhow::Result<u32> {
+    buf.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or_else(|| anyhow!("the zip file is truncated"))
+}
+
+/// Extracts the files of a zip archive, without directories.
+/// Only stored and deflated files are supported, which is what all common tools write.
+pub fn extract_zip(file: &[u8]) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+    let search_start = file.len().saturating_sub(END_OF_CENTRAL_DIR_MAX_OFFSET);
+    let end = (search_start..file.len().saturating_sub(21))
+        .rev()
+        .find(|&offset| read_u32(file, offset).ok() == Some(END_OF_CENTRAL_DIR_SIG))
+        .ok_or_else(|| anyhow!("the zip file has no central directory"))?;
+    let entry_count = read_u16(file, end + 10)? as usize;
+    if entry_count > MAX_ENTRIES {
+        bail!("the zip file contains more than {MAX_ENTRIES} files");
+    }
+
+    let mut files = Vec::new();
+    let mut total_size = 0;
+    let mut offset = read_u32(file, end + 16)? as usize;
+    for _ in 0..entry_count {
+        if read_u32(file, offset)? != CENTRAL_HEADER_SIG {
+            bail!("the central directory of the zip file is corrupt");
+        }
+        let flags = read_u16(file, offset + 8)?;
+        let method = read_u16(file, offset + 10)?;
+        let compressed_size = read_u32(file, offset + 20)? as usize;
+        let size = read_u32(file, offset + 24)? as u64;
+        let name_len = read_u16(file, offset + 28)? as usize;
+        let extra_len = read_u16(file, offset + 30)? as usize;
+        let comment_len = read_u16(file, offset + 32)? as usize;
+        let local_offset = read_u32(file, offset + 42)? as usize;
+        let name = file
+            .get(offset + 46..offset + 46 + name_len)
+            .map(|name| String::from_utf8_lossy(name).to_string())
+            .ok_or_else(|| anyhow!("the zip file is truncated"))?;
+        offset += 46 + name_len + extra_len + comment_len;
+
+        if name.ends_with('/') {
+            continue;
+        }
+        if flags & 1 != 0 {
+            bail!("{name} in the zip file is encrypted");
+        }
+        if size > MAX_ENTRY_SIZE {
+            bail!(
+                "{name} in the zip file is bigger than {} MiB",
+                MAX_ENTRY_SIZE / MIB
+            );
+        }
+        // the sizes in the local header can be missing, the ones of the central directory are used
+        if read_u32(file, local_offset)? != LOCAL_HEADER_SIG {
+            bail!("the zip file is corrupt");
+        }
+        let data_start = local_offset
+            + 30
+            + read_u16(file, local_offset + 26)? as usize
+            + read_u16(file, local_offset + 28)? as usize;
+        let data = file
+            .get(data_start..data_start + compressed_size)
+            .ok_or_else(|| anyhow!("the zip file is truncated"))?;
+        let content = match method {
+            0 => data.to_vec(),
+            8 => {
+                let mut content = Vec::with_capacity(size as usize);
+                // one byte more to notice when the size in the directory was a lie
+                DeflateDecoder::new(Cursor::new(data))
+                    .take(MAX_ENTRY_SIZE + 1)
+                    .read_to_end(&mut content)?;
+                content
+            }
+            _ => bail!("{name} in the zip file uses an unsupported compression ({method})"),
+        };
+        if content.len() as u64 > MAX_ENTRY_SIZE {
+            bail!(
+                "{name} in the zip file is bigger than {} MiB",
+                MAX_ENTRY_SIZE / MIB
+            );
+        }
+        total_size += content.len() as u64;
+        if total_size > MAX_TOTAL_SIZE {
+            bail!(
+                "the files in the zip file are bigger than {} MiB together",
+                MAX_TOTAL_SIZE / MIB
+            );
+        }
+        files.push((name, content));
+    }
+    Ok(files)
+}
+
+/// A zip with stored files, like `zip -0` writes them. The pngs are compressed already.
+pub fn create_zip(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut zip = Vec::new();
+    let mut central = Vec::new();
+    for (name, content) in files {
+        let local_offset = zip.len() as u32;
+        let mut crc = Crc::new();
+        crc.update(content);
+        let mut header = Vec::new();
+        header.extend(LOCAL_HEADER_SIG.to_le_bytes());
+        // version needed, flags, method, time and date
+        header.extend([20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        header.extend(crc.sum().to_le_bytes());
+        header.extend((content.len() as u32).to_le_bytes());
+        header.extend((content.len() as u32).to_le_bytes());
+        header.extend((name.len() as u16).to_le_bytes());
+        header.extend(0u16.to_le_bytes());
+        zip.extend(&header);
+        zip.extend(name.as_bytes());
+        zip.extend(content);
+
+        central.extend(CENTRAL_HEADER_SIG.to_le_bytes());
+        central.extend([20, 0]);
+        // version needed up to the name length are the same as in the local header
+        central.extend(&header[4..28]);
+        central.extend([0; 12]);
+        central.extend(local_offset.to_le_bytes());
+        central.extend(name.as_bytes());
+    }
+    let central_offset = zip.len() as u32;
+    zip.extend(&central);
+    zip.extend(END_OF_CENTRAL_DIR_SIG.to_le_bytes());
+    zip.extend([0; 4]);
+    zip.extend((files.len() as u16).to_le_bytes());
+    zip.extend((files.len() as u16).to_le_bytes());
+    zip.extend((central.len() as u32).to_le_bytes());
+    zip.extend(central_offset.to_le_bytes());
+    zip.extend(0u16.to_le_bytes());
+    zip
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+
+    use super::*;
+
+    #[test]
+    fn extracts_stored_files() {
+        let zip = create_zip(&[
+            ("skins/".to_string(), Vec::new()),
+            ("skins/a.png".to_string(), b"abc".to_vec()),
+            ("b.png".to_string(), b"de".to_vec()),
+        ]);
+        assert!(is_zip(&zip));
+        let files = extract_zip(&zip).unwrap();
+        assert_eq!(
+            files,
+            vec![
+                ("skins/a.png".to_string(), b"abc".to_vec()),
+                ("b.png".to_string(), b"de".to_vec())
+            ]
+        );
+        assert!(extract_zip(&zip[..zip.len() - 30]).is_err());
+    }
+
+    /// A zip of deflated files, the directory claims each to be `size` bytes unpacked.
+    fn deflated_zip(files: &[(String, Vec<u8>)], size: u32) -> Vec<u8> {
+        let mut zip = Vec::new();
+        let mut central = Vec::new();
+        for (name, content) in files {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::fast());
+            encoder.write_all(content).unwrap();
+            let data = encoder.finish().unwrap();
+            let mut header = Vec::new();
+            header.extend(LOCAL_HEADER_SIG.to_le_bytes());
+            // version needed, flags, method, time and date
+            header.extend([20, 0, 0, 0, 8, 0, 0, 0, 0, 0]);
+            // the crc isn't checked
+            header.extend(0u32.to_le_bytes());
+            header.extend((data.len() as u32).to_le_bytes());
+            header.extend(size.to_le_bytes());
+            header.extend((name.len() as u16).to_le_bytes());
+            header.extend(0u16.to_le_bytes());
+            central.extend(CENTRAL_HEADER_SIG.to_le_bytes());
+            central.extend([20, 0]);
+            central.extend(&header[4..28]);
+            central.extend([0; 12]);
+            central.extend((zip.len() as u32).to_le_bytes());
+            central.extend(name.as_bytes());
+            zip.extend(&header);
+            zip.extend(name.as_bytes());
+            zip.extend(data);
+        }
+        let central_offset = zip.len() as u32;
+        zip.extend(&central);
+        zip.extend(END_OF_CENTRAL_DIR_SIG.to_le_bytes());
+        zip.extend([0; 4]);
+        zip.extend((files.len() as u16).to_le_bytes());
+        zip.extend((files.len() as u16).to_le_bytes());
+        zip.extend((central.len() as u32).to_le_bytes());
+        zip.extend(central_offset.to_le_bytes());
+        zip.extend(0u16.to_le_bytes());
+        zip
+    }
+
+    #[test]
+    fn extracts_deflated_files() {
+        let files = vec![("a.png".to_string(), b"abcabcabc".to_vec())];
+        assert_eq!(extract_zip(&deflated_zip(&files, 9)).unwrap(), files);
+    }
+
+    #[test]
+    fn refuses_an_entry_bigger_than_claimed() {
+        let files = [("bomb.png".to_string(), vec![0; MAX_ENTRY_SIZE as usize + 1])];
+        let err = extract_zip(&deflated_zip(&files, 100)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "bomb.png in the zip file is bigger than 16 MiB"
+        );
+    }
+
+    #[test]
+    fn refuses_too_much_in_total() {
+        let files: Vec<_> = (0..5)
+            .map(|i| (format!("{i}.png"), vec![0; MAX_ENTRY_SIZE as usize]))
+            .collect();
+        let err = extract_zip(&deflated_zip(&files, MAX_ENTRY_SIZE as u32)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the files in the zip file are bigger than 64 MiB together"
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use serenity::all::Timestamp;
+use serenity::prelude::*;
+use tracing::error;
+
+use crate::naming::name_key;
+
+/// Every upload attempt and edit of the database with its result, one line per attempt.
+/// The file is only appended to, so it survives restarts and can't lose older entries.
+#[derive(Clone)]
+pub struct AuditLog {
+    path: String,
+}
+
+impl TypeMapKey for AuditLog {
+    type Value = Self;
+}
+
+impl AuditLog {
+    pub const fn new(path: String) -> Self {
+        Self { path }
+    }
+
+    /// Appends the attempt, e.g. `upload 512x256 normal` of a skin, failures are only logged.
+    /// The actor is the id of the discord user, or `editor` for the web skin editor.
+    pub fn record<E: Display>(
+        &self,
+        actor: impl Display,
+        action: &str,
+        skin_name: &str,
+        result: &Result<(), E>,
+    ) {
+        let result = match result {
+            Ok(()) => "ok".to_string(),
+            // keeps one attempt on one line
+            Err(err) => format!("failed: {}", err.to_string().replace('\n', " ")),
+        };
+        let line = format!(
+            "{}\t{actor}\t{action}\t{skin_name}\t{result}\n",
+            Timestamp::now()
+        );
+        let appended = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()));
+        if let Err(err) = appended {
+            error!("Could not write the audit log {}: {err}", self.path);
+        }
+    }
+
+    /// The skins that were uploaded successfully and not deleted since, by the `name_key` of
+    /// their name and their database, with the name of the last upload.
+    pub fn uploaded_skins(&self) -> HashMap<(String, String), String> {
+        let log = std::fs::read_to_string(&self.path).unwrap_or_default();
+        let mut uploaded = HashMap::new();
+        for line in log.lines() {
+            let mut fields = line.split('\t').skip(2);
+            let (Some(action), Some(skin_name), Some("ok")) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            if let Some(database) = action
+                .strip_prefix("upload ")
+                .and_then(|action| action.split(' ').nth(1))
+            {
+                uploaded.insert(
+                    (name_key(skin_name), database.to_string()),
+                    skin_name.to_string(),
+                );
+            } else if let Some(database) = action.strip_prefix("delete ") {
+                uploaded.remove(&(name_key(skin_name), database.to_string()));
+            }
+        }
+        uploaded
+    }
+
+    /// The last `count` entries, oldest first. A missing file means nothing was uploaded yet.
+    pub fn last(&self, count: usize) -> Vec<String> {
+        let log = std::fs::read_to_string(&self.path).unwrap_or_default();
+        let lines: Vec<_> = log.lines().map(str::to_string).collect();
+        lines[lines.len().saturating_sub(count)..].to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::all::UserId;
+
+    use super::AuditLog;
+
+    #[test]
+    fn keeps_the_last_entries() {
+        let path = std::env::temp_dir().join("ddnet_discord_audit_test.log");
+        let _ = std::fs::remove_file(&path);
+        let log = AuditLog::new(path.to_string_lossy().into_owned());
+        log.record(
+            UserId::new(1),
+            "upload 256x128 normal",
+            "first",
+            &Ok::<_, String>(()),
+        );
+        log.record(
+            UserId::new(1),
+            "upload 512x256 normal",
+            "First",
+            &Ok::<_, String>(()),
+        );
+        log.record(
+            UserId::new(1),
+            "upload 512x256 normal",
+            "second",
+            &Err("the database\nfailed".to_string()),
+        );
+        log.record(
+            UserId::new(1),
+            "upload 256x128 community",
+            "third",
+            &Ok::<_, String>(()),
+        );
+        log.record(
+            UserId::new(1),
+            "delete community",
+            "Third",
+            &Ok::<_, String>(()),
+        );
+
+        let entries = log.last(3);
+        assert_eq!(entries.len(), 3);
+        assert!(
+            entries[0].ends_with("\t1\tupload 512x256 normal\tsecond\tfailed: the database failed")
+        );
+        assert!(entries[2].ends_with("\t1\tdelete community\tThird\tok"));
+        assert_eq!(log.last(10).len(), 5);
+        // only the successful uploads that were not deleted count,
+        // the database ignores the case of the names
+        assert_eq!(
+            log.uploaded_skins().into_iter().collect::<Vec<_>>(),
+            [(
+                ("first".to_string(), "normal".to_string()),
+                "First".to_string()
+            )]
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,455 @@
+use serenity::all::{ChannelId, GetMessages, Message, MessageId, Timestamp, UserId};
+use serenity::http::Http;
+use tracing::warn;
+
+use crate::batches::{BatchRecord, BatchSkin};
+use crate::config::TemplateConfig;
+use crate::SkinToUploadDB;
+
+/// An announcement the bot posted before the batch history existed, in the format it had then.
+pub struct PastAnnouncement<'a> {
+    pub link: String,
+    pub posted_at: i64,
+    pub content: &'a str,
+}
+
+impl<'a> PastAnnouncement<'a> {
+    pub fn of(msg: &'a Message) -> Self {
+        Self {
+            link: msg.link(),
+            posted_at: msg.timestamp.unix_timestamp(),
+            content: &msg.content,
+        }
+    }
+}
+
+/// The messages of the bot in the channel after the message, oldest first.
+pub async fn fetch_announcements(
+    http: &Http,
+    channel_id: ChannelId,
+    bot_id: UserId,
+    after: MessageId,
+) -> Vec<Message> {
+    let mut announcements = Vec::default();
+    let mut after = after;
+    loop {
+        let mut msgs = match channel_id
+            .messages(http, GetMessages::new().after(after).limit(100))
+            .await
+        {
+            Ok(msgs) => msgs,
+            Err(err) => {
+                warn!("Could not fetch the messages of the channel: {err}");
+                break;
+            }
+        };
+        let Some(newest) = msgs.iter().map(|msg| msg.id).max() else {
+            break;
+        };
+        after = newest;
+        msgs.sort_by_key(|msg| msg.id);
+        announcements.extend(msgs.into_iter().filter(|msg| msg.author.id == bot_id));
+    }
+    announcements
+}
+
+/// The header and skin line of the announcements the bot posted before the templates existed,
+/// the database was a tag on every skin line.
+const BASELINE_FORMAT: (&str, &str) = (
+    "The following skins were added to the database:",
+    "- \"{name}\" [{database}] by {author} ({link})",
+);
+
+/// The text of the placeholders of a template, if the text has the form of the template.
+/// Values can contain the text after their placeholder, the longest value that fits is taken.
+fn match_template<'t, 'a>(template: &'t str, text: &'a str) -> Option<Vec<(&'t str, &'a str)>> {
+    let mut keys = Vec::new();
+    let mut literals = Vec::new();
+    let mut rest = template;
+    let mut literal_start = 0;
+    while let Some(start) = rest[literal_start..]
+        .find('{')
+        .map(|pos| pos + literal_start)
+    {
+        let key = rest[start + 1..]
+            .find('}')
+            .map(|end| &rest[start + 1..start + 1 + end])
+            .filter(|key| {
+                !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            });
+        match key {
+            Some(key) => {
+                literals.push(&rest[..start]);
+                keys.push(key);
+                rest = &rest[start + key.len() + 2..];
+                literal_start = 0;
+            }
+            None => literal_start = start + 1,
+        }
+    }
+    literals.push(rest);
+    let text = text.strip_prefix(literals[0])?;
+    match_placeholders(&keys, &literals[1..], text)
+}
+
+fn match_placeholders<'t, 'a>(
+    keys: &[&'t str],
+    literals: &[&'t str],
+    text: &'a str,
+) -> Option<Vec<(&'t str, &'a str)>> {
+    let Some((&key, keys)) = keys.split_first() else {
+        return text.is_empty().then(Vec::new);
+    };
+    let literal = literals[0];
+    if keys.is_empty() {
+        return Some(vec![(key, text.strip_suffix(literal)?)]);
+    }
+    text.rmatch_indices(literal).find_map(|(pos, _)| {
+        let mut values = match_placeholders(keys, &literals[1..], &text[pos + literal.len()..])?;
+        values.insert(0, (key, &text[..pos]));
+        Some(values)
+    })
+}
+
+/// `normal` or `community`, also for the database names of the templates.
+fn database_key(name: &str, templates: &TemplateConfig) -> Option<&'static str> {
+    let name = name.trim();
+    [
+        (SkinToUploadDB::Normal, "normal"),
+        (SkinToUploadDB::Community, "community"),
+    ]
+    .into_iter()
+    .find(|(database, key)| {
+        name.eq_ignore_ascii_case(key) || name.eq_ignore_ascii_case(templates.database(*database))
+    })
+    .map(|(_, key)| key)
+}
+
+/// One batch per announcement, the messages a long announcement was split into are joined.
+/// Announcements are read in the format of the configured templates, of the default templates
+/// and of the bot before the templates. The uploader is not known, the time is the one of the
+/// announcement.
+pub fn batches_from_announcements(
+    announcements: &[PastAnnouncement],
+    templates: &TemplateConfig,
+) -> Vec<BatchRecord> {
+    let defaults = TemplateConfig::default();
+    let formats = [
+        BASELINE_FORMAT,
+        (
+            templates.announcement_header.as_str(),
+            templates.announcement_skin.as_str(),
+        ),
+        (
+            defaults.announcement_header.as_str(),
+            defaults.announcement_skin.as_str(),
+        ),
+    ];
+    let value = |values: &[(&str, &str)], key: &str| {
+        values
+            .iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| value.trim().to_string())
+    };
+
+    let mut batches: Vec<BatchRecord> = Vec::new();
+    for announcement in announcements {
+        let mut header = false;
+        let mut database = None;
+        let mut skins = Vec::new();
+        for line in announcement.content.lines().map(str::trim) {
+            if let Some(values) = formats
+                .iter()
+                .find_map(|(header, _)| match_template(header.trim(), line))
+            {
+                header = true;
+                database =
+                    value(&values, "database").and_then(|name| database_key(&name, templates));
+            } else if let Some(values) = formats
+                .iter()
+                .find_map(|(_, skin)| match_template(skin.trim(), line))
+            {
+                let Some(name) = value(&values, "name").filter(|name| !name.is_empty()) else {
+                    continue;
+                };
+                let skin_database =
+                    value(&values, "database").and_then(|name| database_key(&name, templates));
+                let positive_ratio = value(&values, "ratio")
+                    .and_then(|ratio| ratio.strip_suffix('%')?.parse::<f64>().ok())
+                    .map(|percent| percent / 100.0);
+                skins.push(BatchSkin {
+                    name,
+                    database: skin_database.or(database).unwrap_or_default().to_string(),
+                    uploaded: true,
+                    error: None,
+                    submission: value(&values, "link").unwrap_or_default(),
+                    replaced: false,
+                    author: value(&values, "author"),
+                    positive_ratio,
+                    original_name: None,
+                    uploaded_sizes: Vec::new(),
+                });
+            }
+        }
+        if skins.is_empty() {
+            continue;
+        }
+        // without the header it is the continuation of the previous announcement
+        if header || batches.is_empty() {
+            batches.push(BatchRecord {
+                label: String::new(),
+                uploader: 0,
+                finished_at: announcement.posted_at,
+                skins: Vec::new(),
+                announcements: Vec::new(),
+                undone_at: None,
+                preview_renderer: None,
+            });
+        }
+        let batch = batches.last_mut().unwrap();
+        for skin in &mut skins {
+            if skin.database.is_empty() {
+                skin.database = batch
+                    .skins
+                    .last()
+                    .map_or_else(|| "normal".to_string(), |last| last.database.clone());
+            }
+        }
+        batch.announcements.push(announcement.link.clone());
+        batch.skins.extend(skins);
+    }
+    batches
+}
+
+/// The fields of a csv line, fields in double quotes can contain commas and `""`.
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+/// Reads a csv with a header line, one skin per line. `finished_at` (unix timestamp or rfc 3339),
+/// `name` and `database` are required, `label`, `uploader` (user id), `author` and `submission`
+/// optional.
+/// The skins of a label, or without labels of the same time, form a batch.
+pub fn batches_from_csv(csv: &str) -> Result<Vec<BatchRecord>, String> {
+    let mut lines = csv.lines().filter(|line| !line.trim().is_empty());
+    let header = csv_fields(lines.next().ok_or("the csv is empty")?);
+    let column = |name: &str| header.iter().position(|field| field.trim() == name);
+    let required = |name: &str| column(name).ok_or(format!("the column `{name}` is missing"));
+    let (finished_at, name, database) = (
+        required("finished_at")?,
+        required("name")?,
+        required("database")?,
+    );
+    let (label, uploader, submission, author) = (
+        column("label"),
+        column("uploader"),
+        column("submission"),
+        column("author"),
+    );
+
+    let mut batches: Vec<BatchRecord> = Vec::new();
+    for (index, line) in lines.enumerate() {
+        let fields = csv_fields(line);
+        let field = |column: usize| fields.get(column).map_or("", |field| field.trim());
+        let optional = |column: Option<usize>| column.map_or("", field);
+        let line_number = index + 2;
+
+        let time = field(finished_at);
+        let time = time
+            .parse()
+            .ok()
+            .or_else(|| Timestamp::parse(time).ok().map(|at| at.unix_timestamp()))
+            .ok_or(format!("line {line_number}: invalid finished_at `{time}`"))?;
+        let skin_name = field(name);
+        if skin_name.is_empty() {
+            return Err(format!("line {line_number}: the name is missing"));
+        }
+        let skin_database = field(database).to_lowercase();
+        if skin_database != "normal" && skin_database != "community" {
+            return Err(format!(
+                "line {line_number}: the database must be `normal` or `community`, not `{skin_database}`"
+            ));
+        }
+        let uploader_id = match optional(uploader) {
+            "" => 0,
+            id => id
+                .parse()
+                .map_err(|_| format!("line {line_number}: invalid uploader `{id}`"))?,
+        };
+        let batch_label = optional(label);
+
+        let position = batches.iter().position(|batch| {
+            if batch_label.is_empty() {
+                batch.label.is_empty() && batch.finished_at == time
+            } else {
+                batch.label == batch_label
+            }
+        });
+        let batch = match position {
+            Some(position) => &mut batches[position],
+            None => {
+                batches.push(BatchRecord {
+                    label: batch_label.to_string(),
+                    uploader: uploader_id,
+                    finished_at: time,
+                    skins: Vec::new(),
+                    announcements: Vec::new(),
+                    undone_at: None,
+                    preview_renderer: None,
+                });
+                batches.last_mut().unwrap()
+            }
+        };
+        batch.skins.push(BatchSkin {
+            name: skin_name.to_string(),
+            database: skin_database,
+            uploaded: true,
+            error: None,
+            submission: optional(submission).to_string(),
+            replaced: false,
+            author: Some(optional(author).to_string()).filter(|author| !author.is_empty()),
+            positive_ratio: None,
+            original_name: None,
+            uploaded_sizes: Vec::new(),
+        });
+    }
+    Ok(batches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{batches_from_announcements, batches_from_csv, PastAnnouncement};
+    use crate::config::TemplateConfig;
+
+    fn announcement<'a>(link: &str, content: &'a str) -> PastAnnouncement<'a> {
+        PastAnnouncement {
+            link: link.to_string(),
+            posted_at: 1000,
+            content,
+        }
+    }
+
+    #[test]
+    fn reads_split_announcements() {
+        let announcements = [
+            announcement(
+                "a",
+                "<@&5>\nThe following skins were added to the community database:\n- \"greyfox\" by <@42> (https://discord.com/channels/1/2/3) \n- \"a \"quoted\" one\" by Ninjed (https://discord.com/channels/1/2/4) \n",
+            ),
+            announcement("b", "- \"cat\" by <@7> (https://discord.com/channels/1/2/5) \n"),
+            announcement("c", "Uploading batch finished."),
+            announcement(
+                "d",
+                "The following skins were added to the normal database:\n- \"dog\" by <@7> (https://discord.com/channels/1/2/6) \n",
+            ),
+        ];
+        let batches = batches_from_announcements(&announcements, &TemplateConfig::default());
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].announcements, ["a", "b"]);
+        let names: Vec<_> = batches[0].skins.iter().map(|skin| &skin.name).collect();
+        assert_eq!(names, ["greyfox", "a \"quoted\" one", "cat"]);
+        assert!(batches[0]
+            .skins
+            .iter()
+            .all(|skin| skin.database == "community"));
+        assert_eq!(
+            batches[0].skins[2].submission,
+            "https://discord.com/channels/1/2/5"
+        );
+        assert_eq!(batches[1].skins[0].database, "normal");
+        assert_eq!(batches[1].uploader, 0);
+    }
+
+    #[test]
+    fn reads_announcements_from_before_the_templates() {
+        // verbatim, the database was a tag on every line and lines ended with a space
+        let announcements = [
+            announcement(
+                "a",
+                "The following skins were added to the database:\n- \"greyfox\" [normal] by <@42> (https://discord.com/channels/1/2/3) \n- \"cat (old)\" [community] by <@7> (https://discord.com/channels/1/2/4) \n",
+            ),
+            announcement(
+                "b",
+                "- \"dog\" [community] by <@7> (https://discord.com/channels/1/2/5) \n",
+            ),
+        ];
+        let batches = batches_from_announcements(&announcements, &TemplateConfig::default());
+        assert_eq!(batches.len(), 1);
+        let skins: Vec<_> = batches[0]
+            .skins
+            .iter()
+            .map(|skin| (skin.name.as_str(), skin.database.as_str()))
+            .collect();
+        assert_eq!(
+            skins,
+            [
+                ("greyfox", "normal"),
+                ("cat (old)", "community"),
+                ("dog", "community")
+            ]
+        );
+        assert_eq!(batches[0].skins[0].author.as_deref(), Some("<@42>"));
+        assert_eq!(
+            batches[0].skins[1].submission,
+            "https://discord.com/channels/1/2/4"
+        );
+    }
+
+    #[test]
+    fn reads_templated_announcements() {
+        let templates = TemplateConfig {
+            announcement_header: "Neu in der {database}-Datenbank:".to_string(),
+            announcement_skin: "• {name} von {author} ({ratio}) – {link}".to_string(),
+            community_database: "Community".to_string(),
+            ..TemplateConfig::default()
+        };
+        let announcements = [announcement(
+            "a",
+            "Neu in der Community-Datenbank:\n• grey fox von Ninjed (Pinky) (75%) – https://discord.com/channels/1/2/3\n",
+        )];
+        let batches = batches_from_announcements(&announcements, &templates);
+        let skin = &batches[0].skins[0];
+        assert_eq!(skin.name, "grey fox");
+        assert_eq!(skin.author.as_deref(), Some("Ninjed (Pinky)"));
+        assert_eq!(skin.database, "community");
+        assert_eq!(skin.positive_ratio, Some(0.75));
+        assert_eq!(skin.submission, "https://discord.com/channels/1/2/3");
+    }
+
+    #[test]
+    fn reads_csv() {
+        let csv = "finished_at,name,database,label,uploader\n\
+            2023-01-02T10:00:00Z,\"comma, skin\",normal,january,42\n\
+            1672653600,cat,Community,january,42\n\
+            1700000000,dog,community,,\n\
+            1700000000,\"say \"\"hi\"\"\",normal,,\n";
+        let batches = batches_from_csv(csv).unwrap();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].label, "january");
+        assert_eq!(batches[0].uploader, 42);
+        assert_eq!(batches[0].skins[0].name, "comma, skin");
+        assert_eq!(batches[0].skins[1].database, "community");
+        assert_eq!(batches[1].skins[1].name, "say \"hi\"");
+
+        assert!(batches_from_csv("name,database\n").is_err());
+        assert!(
+            batches_from_csv("finished_at,name,database\nsoon,cat,normal")
+                .unwrap_err()
+                .contains("line 2")
+        );
+    }
+}
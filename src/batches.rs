@@ -0,0 +1,482 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use serde::{Deserialize, Serialize};
+use serenity::all::{Mention, Timestamp, UserId};
+use serenity::prelude::*;
+use tracing::error;
+
+use crate::tee::TEE_RENDERER_VERSION;
+
+/// labels longer than this are cut, they are shown in lists
+pub const MAX_LABEL_LENGTH: usize = 50;
+/// followed by the label of the batch
+pub const UNDO_BATCH_BUTTON_PREFIX: &str = "upload_undo_confirm:";
+pub const CANCEL_UNDO_BUTTON_ID: &str = "upload_undo_cancel";
+/// the uploader of the skins of the web skin editor, no discord user has this id
+pub const EDITOR_UPLOADER: u64 = 1;
+
+/// A skin of a finished batch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchSkin {
+    pub name: String,
+    pub database: String,
+    pub uploaded: bool,
+    /// why the skin is not in the database, if it failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// the link of the submission
+    pub submission: String,
+    /// a skin of the same name was in the database before, undoing doesn't bring it back
+    #[serde(default)]
+    pub replaced: bool,
+    /// as named in the skin info, not recorded before `/skin_stats`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// of the community votes on the submission
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub positive_ratio: Option<f64>,
+    /// the name as written by the artist, if it was transliterated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_name: Option<String>,
+    /// the sizes that reached the database, e.g. `256x128`, recorded by the web editor whose
+    /// upload can fail after the first file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub uploaded_sizes: Vec<String>,
+}
+
+impl BatchSkin {
+    /// Whether any of its files is in the database, also if another one failed.
+    pub fn in_database(&self) -> bool {
+        self.uploaded || !self.uploaded_sizes.is_empty()
+    }
+}
+
+/// The receipt of a finished upload, what `/batches` shows.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRecord {
+    pub label: String,
+    /// the user id of the moderator that uploaded the batch
+    pub uploader: u64,
+    /// unix timestamp
+    pub finished_at: i64,
+    pub skins: Vec<BatchSkin>,
+    /// the links of the messages that announced the skins
+    pub announcements: Vec<String>,
+    /// unix timestamp, from the undo entry of the batch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undone_at: Option<i64>,
+    /// the version of the tee renderer that drew the previews of the announcements, none if
+    /// they have no previews
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview_renderer: Option<u32>,
+}
+
+impl BatchRecord {
+    /// Imported batches can be of an unknown uploader.
+    pub fn uploader_mention(&self) -> String {
+        match self.uploader {
+            0 => "an unknown moderator".to_string(),
+            EDITOR_UPLOADER => "the skin editor".to_string(),
+            uploader => Mention::User(UserId::new(uploader)).to_string(),
+        }
+    }
+
+    /// Whether both describe the same upload, so a batch is only imported once.
+    fn same_upload(&self, other: &Self) -> bool {
+        let names = |batch: &Self| {
+            let mut names: Vec<_> = batch.skins.iter().map(|skin| skin.name.clone()).collect();
+            names.sort();
+            names
+        };
+        self.announcements
+            .iter()
+            .any(|link| other.announcements.contains(link))
+            || (self.finished_at == other.finished_at && names(self) == names(other))
+    }
+
+    /// Whether an older tee renderer drew the previews of the announcements. The editor
+    /// announced its skins with previews before the version was recorded.
+    pub fn has_outdated_previews(&self) -> bool {
+        match self.preview_renderer {
+            Some(version) => version < TEE_RENDERER_VERSION,
+            None => self.uploader == EDITOR_UPLOADER && !self.announcements.is_empty(),
+        }
+    }
+
+    pub fn uploaded_count(&self) -> usize {
+        self.skins.iter().filter(|skin| skin.uploaded).count()
+    }
+
+    /// Why `/upload_undo` can't revert the batch.
+    pub fn undo_refusal(&self, window_mins: u64, now: i64) -> Option<String> {
+        if window_mins == 0 {
+            Some("Undoing batches is disabled".to_string())
+        } else if self.undone_at.is_some() {
+            Some(format!("The batch `{}` was undone already", self.label))
+        } else if !self.skins.iter().any(BatchSkin::in_database) {
+            Some(format!(
+                "No skin of the batch `{}` was uploaded, there is nothing to undo",
+                self.label
+            ))
+        } else if now - self.finished_at > window_mins as i64 * 60 {
+            Some(format!(
+                "The batch `{}` was finished <t:{}:R>, batches can only be undone within {window_mins} minutes",
+                self.label, self.finished_at
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// Marks a batch as reverted, appended after the batch.
+#[derive(Serialize, Deserialize)]
+struct BatchUndo {
+    undone: String,
+    by: u64,
+    at: i64,
+}
+
+/// Marks the previews of a batch as drawn again, appended after the batch.
+#[derive(Serialize, Deserialize)]
+struct BatchRerender {
+    rerendered: String,
+    renderer: u32,
+    at: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum HistoryEntry {
+    Batch(BatchRecord),
+    Undo(BatchUndo),
+    Rerender(BatchRerender),
+}
+
+/// Every finished batch, one json line each. Like the audit log the file is only appended to.
+#[derive(Clone)]
+pub struct BatchHistory {
+    path: String,
+}
+
+impl TypeMapKey for BatchHistory {
+    type Value = Self;
+}
+
+impl BatchHistory {
+    pub const fn new(path: String) -> Self {
+        Self { path }
+    }
+
+    /// Oldest first, lines that can't be read are skipped. A missing file means no batch
+    /// was uploaded yet.
+    pub fn all(&self) -> Vec<BatchRecord> {
+        let history = std::fs::read_to_string(&self.path).unwrap_or_default();
+        let mut batches: Vec<BatchRecord> = Vec::new();
+        for entry in history
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+        {
+            match entry {
+                HistoryEntry::Batch(batch) => batches.push(batch),
+                HistoryEntry::Undo(undo) => {
+                    if let Some(batch) = batches
+                        .iter_mut()
+                        .find(|batch| batch.label.eq_ignore_ascii_case(&undo.undone))
+                    {
+                        batch.undone_at = Some(undo.at);
+                    }
+                }
+                HistoryEntry::Rerender(rerender) => {
+                    if let Some(batch) = batches
+                        .iter_mut()
+                        .find(|batch| batch.label.eq_ignore_ascii_case(&rerender.rerendered))
+                    {
+                        batch.preview_renderer = Some(rerender.renderer);
+                    }
+                }
+            }
+        }
+        // imported batches are appended after newer ones
+        batches.sort_by_key(|batch| batch.finished_at);
+        batches
+    }
+
+    pub fn last(&self) -> Option<BatchRecord> {
+        self.all().pop()
+    }
+
+    /// Labels are compared case-insensitively.
+    pub fn find(&self, label: &str) -> Option<BatchRecord> {
+        self.all()
+            .into_iter()
+            .find(|batch| batch.label.eq_ignore_ascii_case(label.trim()))
+    }
+
+    /// The wanted label or `<date>-<n>` for the n-th batch of the day,
+    /// a label that is taken already gets a suffix.
+    pub fn new_label(&self, wanted: Option<&str>, now: Timestamp) -> String {
+        let batches = self.all();
+        let wanted = wanted
+            .map(|label| {
+                label
+                    .trim()
+                    .chars()
+                    .take(MAX_LABEL_LENGTH)
+                    .collect::<String>()
+            })
+            .filter(|label| !label.is_empty());
+        let label = wanted.unwrap_or_else(|| {
+            // rfc 3339, starts with the date
+            let date: String = now.to_string().chars().take(10).collect();
+            let of_the_day = batches
+                .iter()
+                .filter(|batch| batch.label.starts_with(&date))
+                .count();
+            format!("{date}-{}", of_the_day + 1)
+        });
+        let taken = |label: &str| {
+            batches
+                .iter()
+                .any(|batch| batch.label.eq_ignore_ascii_case(label))
+        };
+        let mut unique = label.clone();
+        let mut suffix = 2;
+        while taken(&unique) {
+            unique = format!("{label}-{suffix}");
+            suffix += 1;
+        }
+        unique
+    }
+
+    /// Failures are only logged, the upload itself already happened.
+    pub fn record(&self, batch: &BatchRecord) {
+        self.append(&HistoryEntry::Batch(batch.clone()));
+    }
+
+    /// Appends the batches that are not in the history yet, e.g. of uploads before it existed.
+    /// Batches without a label are labeled by the date they were finished.
+    /// Returns how many were imported.
+    pub fn import(&self, batches: Vec<BatchRecord>) -> usize {
+        let mut imported = 0;
+        for mut batch in batches {
+            if self.all().iter().any(|known| known.same_upload(&batch)) {
+                continue;
+            }
+            let finished_at = Timestamp::from_unix_timestamp(batch.finished_at)
+                .unwrap_or_else(|_| Timestamp::now());
+            let wanted = Some(batch.label.as_str()).filter(|label| !label.is_empty());
+            batch.label = self.new_label(wanted, finished_at);
+            self.record(&batch);
+            imported += 1;
+        }
+        imported
+    }
+
+    pub fn record_undo(&self, label: &str, by: u64, at: i64) {
+        self.append(&HistoryEntry::Undo(BatchUndo {
+            undone: label.to_string(),
+            by,
+            at,
+        }));
+    }
+
+    /// The previews of the announcements of the batch were drawn with `renderer`.
+    pub fn record_rerender(&self, label: &str, renderer: u32, at: i64) {
+        self.append(&HistoryEntry::Rerender(BatchRerender {
+            rerendered: label.to_string(),
+            renderer,
+            at,
+        }));
+    }
+
+    fn append(&self, entry: &HistoryEntry) {
+        let appended = serde_json::to_string(entry)
+            .map_err(std::io::Error::from)
+            .and_then(|line| {
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)
+                    .and_then(|mut file| writeln!(file, "{line}"))
+            });
+        if let Err(err) = appended {
+            error!("Could not write the batch history {}: {err}", self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::all::Timestamp;
+
+    use super::{BatchHistory, BatchRecord, BatchSkin, EDITOR_UPLOADER};
+    use crate::tee::TEE_RENDERER_VERSION;
+
+    #[test]
+    fn labels_are_unique() {
+        let path = std::env::temp_dir().join("ddnet_discord_batches_test.jsonl");
+        let _ = std::fs::remove_file(&path);
+        let history = BatchHistory::new(path.to_string_lossy().into_owned());
+        let now = Timestamp::parse("2024-05-01T12:00:00Z").unwrap();
+        let batch = |label: String| BatchRecord {
+            label,
+            uploader: 1,
+            finished_at: now.unix_timestamp(),
+            skins: Vec::new(),
+            announcements: Vec::new(),
+            undone_at: None,
+            preview_renderer: None,
+        };
+
+        assert_eq!(history.new_label(None, now), "2024-05-01-1");
+        history.record(&batch(history.new_label(None, now)));
+        assert_eq!(history.new_label(Some(" "), now), "2024-05-01-2");
+        history.record(&batch(history.new_label(Some("may skins"), now)));
+        assert_eq!(history.new_label(Some("May Skins"), now), "May Skins-2");
+        assert_eq!(history.all().len(), 2);
+        assert_eq!(history.find("MAY SKINS").unwrap().label, "may skins");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn undo_within_the_window() {
+        let path = std::env::temp_dir().join("ddnet_discord_batches_undo_test.jsonl");
+        let _ = std::fs::remove_file(&path);
+        let history = BatchHistory::new(path.to_string_lossy().into_owned());
+        history.record(&BatchRecord {
+            label: "june".to_string(),
+            uploader: 1,
+            finished_at: 1000,
+            skins: vec![BatchSkin {
+                name: "greyfox".to_string(),
+                database: "normal".to_string(),
+                uploaded: true,
+                error: None,
+                submission: String::new(),
+                replaced: false,
+                author: None,
+                positive_ratio: None,
+                original_name: None,
+                uploaded_sizes: Vec::new(),
+            }],
+            announcements: Vec::new(),
+            undone_at: None,
+            preview_renderer: None,
+        });
+
+        let batch = history.last().unwrap();
+        assert_eq!(batch.undo_refusal(60, 1000 + 59 * 60), None);
+        assert!(batch
+            .undo_refusal(60, 1000 + 61 * 60)
+            .unwrap()
+            .contains("within 60 minutes"));
+        assert!(batch.undo_refusal(0, 1000).is_some());
+
+        history.record_undo("June", 2, 1100);
+        let batch = history.last().unwrap();
+        assert_eq!(batch.undone_at, Some(1100));
+        assert!(batch
+            .undo_refusal(60, 1200)
+            .unwrap()
+            .contains("undone already"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn imports_once() {
+        let path = std::env::temp_dir().join("ddnet_discord_batches_import_test.jsonl");
+        let _ = std::fs::remove_file(&path);
+        let history = BatchHistory::new(path.to_string_lossy().into_owned());
+        let now = Timestamp::parse("2024-05-01T12:00:00Z").unwrap();
+        history.record(&BatchRecord {
+            label: history.new_label(None, now),
+            uploader: 1,
+            finished_at: now.unix_timestamp(),
+            skins: Vec::new(),
+            announcements: Vec::new(),
+            undone_at: None,
+            preview_renderer: None,
+        });
+        let imported = |link: &str| BatchRecord {
+            label: String::new(),
+            uploader: 0,
+            // 2023-01-02
+            finished_at: 1_672_653_600,
+            skins: vec![BatchSkin {
+                name: format!("skin of {link}"),
+                database: "normal".to_string(),
+                uploaded: true,
+                error: None,
+                submission: String::new(),
+                replaced: false,
+                author: None,
+                positive_ratio: None,
+                original_name: None,
+                uploaded_sizes: Vec::new(),
+            }],
+            announcements: vec![link.to_string()],
+            undone_at: None,
+            preview_renderer: None,
+        };
+
+        assert_eq!(history.import(vec![imported("a"), imported("b")]), 2);
+        assert_eq!(history.import(vec![imported("b"), imported("c")]), 1);
+        let batches = history.all();
+        let labels: Vec<_> = batches.iter().map(|batch| batch.label.as_str()).collect();
+        // sorted by the time they were finished, the current batch is still the last one
+        assert_eq!(
+            labels,
+            [
+                "2023-01-02-1",
+                "2023-01-02-2",
+                "2023-01-02-3",
+                "2024-05-01-1"
+            ]
+        );
+        assert_eq!(batches[0].uploader_mention(), "an unknown moderator");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rerendered_previews_are_current() {
+        let path = std::env::temp_dir().join("ddnet_discord_batches_rerender_test.jsonl");
+        let _ = std::fs::remove_file(&path);
+        let history = BatchHistory::new(path.to_string_lossy().into_owned());
+        let batch = |label: &str, uploader, preview_renderer| BatchRecord {
+            label: label.to_string(),
+            uploader,
+            finished_at: 0,
+            skins: Vec::new(),
+            announcements: vec![format!("https://discord.com/channels/1/2/{label}")],
+            undone_at: None,
+            preview_renderer,
+        };
+        history.record(&batch("editor", EDITOR_UPLOADER, None));
+        history.record(&batch("moderator", 42, None));
+        history.record(&batch(
+            "current",
+            EDITOR_UPLOADER,
+            Some(TEE_RENDERER_VERSION),
+        ));
+        history.record(&batch(
+            "old",
+            EDITOR_UPLOADER,
+            Some(TEE_RENDERER_VERSION - 1),
+        ));
+        let outdated = |history: &BatchHistory| {
+            history
+                .all()
+                .into_iter()
+                .filter(BatchRecord::has_outdated_previews)
+                .map(|batch| batch.label)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(outdated(&history), ["editor", "old"]);
+
+        history.record_rerender("OLD", TEE_RENDERER_VERSION, 10);
+        assert_eq!(outdated(&history), ["editor"]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,102 @@
+use std::path::Path;
+use std::process::ExitCode;
+
+use ddnet_discord::dilate::{dilate_file, is_dilated, verify_against_client, DilateOptions};
+use ddnet_discord::naming::sanitize_skin_name;
+use ddnet_discord::skin::{analyze_skin_file, SkinFileError};
+
+const USAGE: &str = "usage:
+  skin_tool dilate <input> <output.png>   dilates an image of any size
+  skin_tool check <skin>...               checks skins like the bot does before an upload
+  skin_tool verify-dilate <dir>           compares the dilate with the client's output, e.g. fixtures/dilate";
+
+/// Checks a skin file like the bot does, the name is taken from the file.
+fn check(path: &str) -> Result<Vec<String>, String> {
+    let file = std::fs::read(path).map_err(|err| format!("could not read it: {err}"))?;
+    let skin = analyze_skin_file(&file, false).map_err(|err| match err {
+        SkinFileError::Download | SkinFileError::Decode => "not a valid image".to_string(),
+        SkinFileError::Size => "not a 256x128 or 512x256 skin".to_string(),
+    })?;
+    let name = Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let sanitized = sanitize_skin_name(&name).map_err(|err| format!("the skin {err}"))?;
+
+    let mut notes = Vec::new();
+    if sanitized != name {
+        notes.push(format!("is uploaded as `{sanitized}`"));
+    }
+    if let Some(conversion) = skin.conversion {
+        notes.push(format!("is converted from {conversion} to an rgba png"));
+    }
+    if !skin.stray_pixels.is_empty() {
+        notes.push(format!("has {} stray pixels", skin.stray_pixels.len()));
+    }
+    let (w, h) = (skin.width as usize, skin.width as usize / 2);
+    let alpha_threshold = DilateOptions::default().alpha_threshold;
+    if !is_dilated(&skin.img, w, h, 4, alpha_threshold) {
+        notes.push("is not dilated yet".to_string());
+    }
+    Ok(notes)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["dilate", input, output] => {
+            let dilated = std::fs::read(input)
+                .map_err(anyhow::Error::from)
+                .and_then(|file| dilate_file(&file, &DilateOptions::default()))
+                .and_then(|(png, _, _)| Ok(std::fs::write(output, png)?));
+            if let Err(err) = dilated {
+                eprintln!("{input}: {err}");
+                return ExitCode::FAILURE;
+            }
+            ExitCode::SUCCESS
+        }
+        ["check", paths @ ..] if !paths.is_empty() => {
+            let mut all_valid = true;
+            for path in paths {
+                match check(path) {
+                    Ok(notes) if notes.is_empty() => println!("{path}: ok"),
+                    Ok(notes) => println!("{path}: ok, {}", notes.join(", ")),
+                    Err(err) => {
+                        println!("{path}: {err}");
+                        all_valid = false;
+                    }
+                }
+            }
+            if all_valid {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        ["verify-dilate", dir] => match verify_against_client(Path::new(dir)) {
+            Ok(mismatches) if mismatches.is_empty() => {
+                println!("the dilate matches the client");
+                ExitCode::SUCCESS
+            }
+            Ok(mismatches) => {
+                for mismatch in mismatches {
+                    println!("{mismatch}");
+                }
+                ExitCode::FAILURE
+            }
+            Err(err) => {
+                eprintln!("{dir}: {err}");
+                ExitCode::FAILURE
+            }
+        },
+        _ => {
+            eprintln!("{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+use serenity::all::UserId;
+use serenity::prelude::*;
+
+use crate::naming::name_key;
+
+/// What a blocklist entry matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockKind {
+    /// the author named in the skin info
+    Author,
+    /// the discord user that posted the submission, by id
+    User,
+    /// the skin name, before and after it was transliterated or sanitized
+    Name,
+}
+
+impl BlockKind {
+    pub fn from_option(value: &str) -> Option<Self> {
+        match value {
+            "author" => Some(Self::Author),
+            "user" => Some(Self::User),
+            "name" => Some(Self::Name),
+            _ => None,
+        }
+    }
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Author => "author",
+            Self::User => "user",
+            Self::Name => "name",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockEntry {
+    pub kind: BlockKind,
+    /// authors and names are matched case-insensitively, users by their id
+    pub value: String,
+    pub reason: String,
+    /// the user id of the moderator that added the entry
+    pub added_by: u64,
+    /// unix timestamp
+    pub added_at: i64,
+}
+
+impl BlockEntry {
+    fn matches(&self, kind: BlockKind, value: &str) -> bool {
+        self.kind == kind && name_key(self.value.trim()) == name_key(value.trim())
+    }
+}
+
+/// Authors, users and skin names that must not be uploaded, managed with `/blocklist`.
+/// Stored as a json array, rewritten on every change.
+pub struct Blocklist {
+    path: String,
+    entries: Vec<BlockEntry>,
+}
+
+impl TypeMapKey for Blocklist {
+    type Value = Self;
+}
+
+impl Blocklist {
+    /// A missing file means nothing is blocked yet. A file that can't be read is an error, it
+    /// must not be replaced with an empty list on the next change.
+    pub fn load(path: String) -> anyhow::Result<Self> {
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(entries) => serde_json::from_str(&entries)
+                .map_err(|err| anyhow::anyhow!("could not parse the blocklist {path}: {err}"))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => anyhow::bail!("could not read the blocklist {path}: {err}"),
+        };
+        Ok(Self { path, entries })
+    }
+
+    pub fn entries(&self) -> &[BlockEntry] {
+        &self.entries
+    }
+
+    /// Replaces the reason if the value is blocked already.
+    pub fn add(&mut self, entry: BlockEntry) -> std::io::Result<()> {
+        self.entries
+            .retain(|blocked| !blocked.matches(entry.kind, &entry.value));
+        self.entries.push(entry);
+        self.store()
+    }
+
+    /// Whether the value was blocked.
+    pub fn remove(&mut self, kind: BlockKind, value: &str) -> std::io::Result<bool> {
+        let count = self.entries.len();
+        self.entries.retain(|blocked| !blocked.matches(kind, value));
+        if self.entries.len() == count {
+            return Ok(false);
+        }
+        self.store().map(|()| true)
+    }
+
+    /// The entry that blocks the submission, the names are the ones the skin was given
+    /// in the message and after renaming. Skins of the editor have no user that posted them.
+    pub fn blocked(
+        &self,
+        author: &str,
+        user_id: Option<UserId>,
+        names: &[&str],
+    ) -> Option<&BlockEntry> {
+        let user_id = user_id.map(|user_id| user_id.to_string());
+        self.entries.iter().find(|blocked| {
+            blocked.matches(BlockKind::Author, author)
+                || user_id
+                    .as_ref()
+                    .is_some_and(|user_id| blocked.matches(BlockKind::User, user_id))
+                || names
+                    .iter()
+                    .any(|name| blocked.matches(BlockKind::Name, name))
+        })
+    }
+
+    fn store(&self) -> std::io::Result<()> {
+        let entries = serde_json::to_string_pretty(&self.entries).map_err(std::io::Error::from)?;
+        std::fs::write(&self.path, entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::all::UserId;
+
+    use super::{BlockEntry, BlockKind, Blocklist};
+
+    #[test]
+    fn blocks_authors_users_and_names() {
+        let path = std::env::temp_dir().join("ddnet_discord_blocklist_test.json");
+        let _ = std::fs::remove_file(&path);
+        let mut blocklist = Blocklist::load(path.to_string_lossy().into_owned()).unwrap();
+        let entry = |kind, value: &str| BlockEntry {
+            kind,
+            value: value.to_string(),
+            reason: "stolen art".to_string(),
+            added_by: 1,
+            added_at: 0,
+        };
+        blocklist.add(entry(BlockKind::Author, "Thief")).unwrap();
+        blocklist.add(entry(BlockKind::User, "42")).unwrap();
+        blocklist.add(entry(BlockKind::Name, "free_robux")).unwrap();
+        // the same value again only replaces the reason
+        blocklist.add(entry(BlockKind::Name, "Free_Robux")).unwrap();
+
+        let blocklist = Blocklist::load(path.to_string_lossy().into_owned()).unwrap();
+        assert_eq!(blocklist.entries().len(), 3);
+        let blocked = |author: &str, user_id, names: &[&str]| {
+            blocklist
+                .blocked(author, Some(UserId::new(user_id)), names)
+                .map(|entry| entry.kind)
+        };
+        assert_eq!(blocked("thief ", 7, &["greyfox"]), Some(BlockKind::Author));
+        assert_eq!(blocked("Ninjed", 42, &["greyfox"]), Some(BlockKind::User));
+        assert_eq!(
+            blocked("Ninjed", 7, &["greyfox", "FREE_ROBUX"]),
+            Some(BlockKind::Name)
+        );
+        assert_eq!(blocked("Ninjed", 7, &["greyfox"]), None);
+        // skins of the editor have no user
+        assert!(blocklist.blocked("Ninjed", None, &["greyfox"]).is_none());
+
+        let mut blocklist = blocklist;
+        assert!(blocklist.remove(BlockKind::Author, "THIEF").unwrap());
+        assert!(!blocklist.remove(BlockKind::Author, "thief").unwrap());
+        assert!(blocklist
+            .blocked("thief", Some(UserId::new(7)), &[])
+            .is_none());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn refuses_to_load_a_corrupt_file() {
+        let path = std::env::temp_dir().join("ddnet_discord_corrupt_blocklist_test.json");
+        std::fs::write(&path, "[{\"kind\": \"author\"").unwrap();
+        assert!(Blocklist::load(path.to_string_lossy().into_owned()).is_err());
+
+        let _ = std::fs::remove_file(&path);
+        let blocklist = Blocklist::load(path.to_string_lossy().into_owned()).unwrap();
+        assert!(blocklist.entries().is_empty());
+    }
+}
@@ -0,0 +1,146 @@
+use std::io::Cursor;
+
+use image::{ColorType, ImageFormat};
+
+const TW_CLEANUP_ALPHA_THRESHOLD: u8 = 10;
+
+/// Removes semi-transparent pixels that have no visible neighbour (usually eraser slips).
+/// Returns the indices of the pixels that were cleared.
+pub fn remove_stray_pixels(img_buff: &mut [u8], w: usize, h: usize, bpp: usize) -> Vec<usize> {
+    let alpha_comp_index = bpp - 1;
+    let src_buff = img_buff.to_vec();
+
+    let is_visible = |x: i64, y: i64| {
+        if x < 0 || y < 0 || x >= w as i64 || y >= h as i64 {
+            return false;
+        }
+        let k = (y as usize * w + x as usize) * bpp;
+        src_buff[k + alpha_comp_index] > TW_CLEANUP_ALPHA_THRESHOLD
+    };
+
+    let mut removed = Vec::new();
+    for i in 0..w * h {
+        let alpha = src_buff[i * bpp + alpha_comp_index];
+        // fully opaque pixels are intended, fully transparent ones are handled by dilate
+        if alpha == 0 || alpha == 255 {
+            continue;
+        }
+
+        let x = (i % w) as i64;
+        let y = (i / w) as i64;
+        let has_neighbour = (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+            .filter(|&(dx, dy)| dx != 0 || dy != 0)
+            .any(|(dx, dy)| is_visible(x + dx, y + dy));
+        if !has_neighbour {
+            img_buff[i * bpp..(i + 1) * bpp].fill(0);
+            removed.push(i);
+        }
+    }
+    removed
+}
+
+/// Makes the semi-transparent pixels inside the body of the tee opaque, the game draws the body
+/// as a solid shape and blends what shines through oddly. Pixels next to a transparent one are
+/// the anti-aliased outline of the body and keep their alpha. Only for 4 bytes per pixel.
+/// Returns how many pixels were changed.
+pub fn normalize_body_alpha(img_buff: &mut [u8], w: usize, h: usize) -> usize {
+    const BPP: usize = 4;
+    // the body is the circle in the top left 96x96 cell of a 256x128 skin
+    let scale = w / 256;
+    let (center, radius) = (48 * scale as i64, 48 * scale as i64);
+    let src_buff = img_buff.to_vec();
+    let alpha = |x: i64, y: i64| {
+        if x < 0 || y < 0 || x >= w as i64 || y >= h as i64 {
+            return 0;
+        }
+        src_buff[(y as usize * w + x as usize) * BPP + 3]
+    };
+
+    let mut changed = 0;
+    for y in 0..2 * center {
+        for x in 0..2 * center {
+            let (dx, dy) = (x - center, y - center);
+            if dx * dx + dy * dy > radius * radius {
+                continue;
+            }
+            let a = alpha(x, y);
+            if a <= TW_CLEANUP_ALPHA_THRESHOLD || a == 255 {
+                continue;
+            }
+            let on_outline = (-1..=1)
+                .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                .any(|(dx, dy)| alpha(x + dx, y + dy) <= TW_CLEANUP_ALPHA_THRESHOLD);
+            if !on_outline {
+                img_buff[(y as usize * w + x as usize) * BPP + 3] = 255;
+                changed += 1;
+            }
+        }
+    }
+    changed
+}
+
+/// Renders the original image next to the cleaned one as PNG,
+/// the removed pixels are highlighted in magenta on the left side.
+pub fn render_cleanup_diff(
+    src_buff: &[u8],
+    w: usize,
+    h: usize,
+    removed: &[usize],
+) -> anyhow::Result<Vec<u8>> {
+    const BPP: usize = 4;
+    let mut before = src_buff.to_vec();
+    let mut after = src_buff.to_vec();
+    for &i in removed {
+        before[i * BPP..(i + 1) * BPP].copy_from_slice(&[255, 0, 255, 255]);
+        after[i * BPP..(i + 1) * BPP].fill(0);
+    }
+
+    let mut diff = Vec::with_capacity(w * 2 * h * BPP);
+    for y in 0..h {
+        diff.extend_from_slice(&before[y * w * BPP..(y + 1) * w * BPP]);
+        diff.extend_from_slice(&after[y * w * BPP..(y + 1) * w * BPP]);
+    }
+
+    let mut png = Vec::new();
+    image::write_buffer_with_format(
+        &mut Cursor::new(&mut png),
+        &diff,
+        (w * 2) as u32,
+        h as u32,
+        ColorType::Rgba8,
+        ImageFormat::Png,
+    )?;
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::normalize_body_alpha;
+
+    #[test]
+    fn body_becomes_opaque_except_its_outline() {
+        let (w, h) = (256, 128);
+        let mut img = vec![0; w * h * 4];
+        // a half-transparent body, and a half-transparent foot that is left alone
+        for (i, px) in img.chunks_exact_mut(4).enumerate() {
+            let (x, y) = ((i % w) as i64, (i / w) as i64);
+            let in_body = (x - 48).pow(2) + (y - 48).pow(2) <= 40 * 40;
+            let in_foot = (192..256).contains(&x) && (32..64).contains(&y);
+            if in_body || in_foot {
+                px.copy_from_slice(&[200, 100, 50, 128]);
+            }
+        }
+        let changed = normalize_body_alpha(&mut img, w, h);
+        assert!(changed > 0);
+        let alpha = |x: usize, y: usize| img[(y * w + x) * 4 + 3];
+        assert_eq!(alpha(48, 48), 255);
+        // the outline of the body and other parts keep their alpha
+        assert_eq!(alpha(48, 8), 128);
+        assert_eq!(alpha(200, 40), 128);
+        assert_eq!(
+            img.chunks_exact(4).filter(|px| px[3] == 255).count(),
+            changed
+        );
+    }
+}
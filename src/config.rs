@@ -0,0 +1,886 @@
+use std::env;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use serenity::all::{ChannelId, GuildId, RoleId, Timestamp};
+use serenity::prelude::TypeMapKey;
+
+use crate::announce::unknown_placeholders;
+use crate::dilate::DilateOptions;
+use crate::emoji::emojis_match;
+use crate::license::DEFAULT_ALLOWED_LICENSES;
+use crate::safety::ContentLimits;
+use crate::SkinToUploadDB;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EmojiConfig {
+    /// brownbear by default
+    pub positive_vote_id: u64,
+    /// cammostripes by default
+    pub negative_vote_id: u64,
+    /// reactions that queue a skin for the normal database,
+    /// unicode emojis or ids of custom emojis
+    pub queue_normal: Vec<String>,
+    /// reactions that queue a skin for the community database
+    pub queue_community: Vec<String>,
+}
+
+impl Default for EmojiConfig {
+    fn default() -> Self {
+        Self {
+            positive_vote_id: 346683497701834762,
+            negative_vote_id: 346683496476966913,
+            queue_normal: vec!["✅".to_string()],
+            queue_community: vec!["☑️".to_string()],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DilateConfig {
+    pub radius: usize,
+    pub iterations: usize,
+    pub alpha_threshold: u8,
+}
+
+impl Default for DilateConfig {
+    fn default() -> Self {
+        let options = DilateOptions::default();
+        Self {
+            radius: options.radius,
+            iterations: options.iterations,
+            alpha_threshold: options.alpha_threshold,
+        }
+    }
+}
+
+impl DilateConfig {
+    pub fn options(&self) -> DilateOptions {
+        DilateOptions {
+            radius: self.radius,
+            iterations: self.iterations,
+            alpha_threshold: self.alpha_threshold,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UploadConfig {
+    /// seconds without any activity until an upload session is dropped,
+    /// every queued skin and pressed button starts it again
+    pub timeout_secs: u64,
+    /// the uploader is warned this many seconds before the session is dropped, 0 disables it
+    pub expiry_warning_secs: u64,
+    pub cleanup_stray_pixels: bool,
+    /// semi-transparent pixels inside the body are made opaque after the dilation,
+    /// its anti-aliased outline is kept
+    pub normalize_body_alpha: bool,
+    /// shrink images that are an exact multiple of a skin size, instead of rejecting them
+    pub auto_resize: bool,
+    /// the channels skins are collected from, including their threads, empty allows all channels
+    pub allowed_channels: Vec<u64>,
+    /// a second moderator has to approve uploads that contain normal database skins
+    pub require_approval: bool,
+    /// the review message is pinned in its channel until the upload was reviewed or the session
+    /// ended, needs the manage messages permission
+    pub pin_review: bool,
+    /// caps the rate the skins are sent to the database with, 0 disables it
+    pub max_bytes_per_sec: u64,
+    /// an upload stops accepting skins once it reaches any of these limits, 0 disables them
+    pub max_queued_skins: usize,
+    /// the size of the decoded skins, kept in memory until the upload
+    pub max_decoded_mib: u64,
+    /// the total time spent checking the skins of an upload
+    pub max_validation_secs: u64,
+    /// the summary of an upload session is edited at most once in this many milliseconds,
+    /// skins queued in the meantime are shown together, 0 edits it after every check
+    pub summary_refresh_ms: u64,
+    /// every upload attempt and database edit is appended to this file, `/upload_log` shows it
+    pub audit_log_file: String,
+    /// the finished batches with their skins and announcements, `/batches` shows them
+    pub batch_history_file: String,
+    /// the authors, users and skin names blocked with `/blocklist`
+    pub blocklist_file: String,
+    /// how long after it finished the last batch can be deleted again with `/upload_undo`,
+    /// 0 disables it
+    pub undo_window_mins: u64,
+    /// how long a stop signal waits for the running upload to finish its current skin
+    pub shutdown_timeout_secs: u64,
+    /// the interrupted sessions and skins that were not uploaded are appended to this file
+    pub shutdown_state_file: String,
+    /// submissions older than this are flagged to double-check them against the current rules,
+    /// 0 disables it
+    pub flag_older_than_months: u32,
+    /// after the gateway resumed or with the `Resync` button, the reactions of the uploader on the
+    /// submissions of this many days before the upload started are read again
+    pub resync_days: u64,
+}
+
+impl Default for UploadConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 300,
+            expiry_warning_secs: 60,
+            cleanup_stray_pixels: false,
+            normalize_body_alpha: false,
+            auto_resize: false,
+            allowed_channels: Vec::new(),
+            require_approval: false,
+            pin_review: false,
+            max_bytes_per_sec: 0,
+            max_queued_skins: 100,
+            max_decoded_mib: 256,
+            max_validation_secs: 600,
+            summary_refresh_ms: 2000,
+            audit_log_file: "upload_audit.log".to_string(),
+            batch_history_file: "batch_history.jsonl".to_string(),
+            blocklist_file: "blocklist.json".to_string(),
+            undo_window_mins: 60,
+            shutdown_timeout_secs: 60,
+            shutdown_state_file: "interrupted_uploads.txt".to_string(),
+            flag_older_than_months: 12,
+            resync_days: 14,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LicensePolicyKind {
+    Reject,
+    Flag,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LicenseConfig {
+    pub allow_list: Vec<String>,
+    pub policy: LicensePolicyKind,
+    /// stricter or looser rules for submissions posted since a date
+    pub epochs: Vec<LicenseEpoch>,
+}
+
+/// The rules for submissions posted since `since`, until the next epoch starts.
+/// Leaving out a field keeps the value of the base rules.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LicenseEpoch {
+    /// rfc 3339, e.g. `2024-01-01T00:00:00Z`
+    pub since: Timestamp,
+    pub allow_list: Option<Vec<String>>,
+    pub policy: Option<LicensePolicyKind>,
+}
+
+impl Default for LicenseConfig {
+    fn default() -> Self {
+        Self {
+            allow_list: DEFAULT_ALLOWED_LICENSES
+                .iter()
+                .map(|license| license.to_string())
+                .collect(),
+            policy: LicensePolicyKind::Reject,
+            epochs: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VotePolicyKind {
+    Reject,
+    Flag,
+}
+
+/// Community votes a skin needs for the normal database,
+/// skins below it are suggested for the community database instead
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VoteConfig {
+    /// between 0 and 1
+    pub min_positive_ratio: f64,
+    /// positive and negative votes together
+    pub min_votes: u64,
+    pub policy: VotePolicyKind,
+}
+
+impl Default for VoteConfig {
+    fn default() -> Self {
+        Self {
+            min_positive_ratio: 0.7,
+            min_votes: 10,
+            policy: VotePolicyKind::Flag,
+        }
+    }
+}
+
+/// Leaderboard of the community votes, posted regularly
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VoteReportConfig {
+    /// the submission channel, the scheduled report is disabled without it
+    pub channel_id: Option<u64>,
+    /// where the report is posted, the submission channel by default, which a forum can't be
+    pub report_channel_id: Option<u64>,
+    pub interval_days: u64,
+    /// how many days of submissions the report contains
+    pub days: u64,
+}
+
+impl Default for VoteReportConfig {
+    fn default() -> Self {
+        Self {
+            channel_id: None,
+            report_channel_id: None,
+            interval_days: 7,
+            days: 7,
+        }
+    }
+}
+
+/// Planned maintenance of the database, announced by the website
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MaintenanceConfig {
+    /// answers `1` or `true` during maintenance, uploads wait until it is over
+    pub flag_url: Option<String>,
+    /// seconds between checking the flag while waiting
+    pub poll_secs: u64,
+    /// minutes to wait, the remaining skins can be retried afterwards
+    pub max_wait_mins: u64,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            flag_url: None,
+            poll_secs: 30,
+            max_wait_mins: 60,
+        }
+    }
+}
+
+/// Tags applied to forum posts, once a decision about the submission was made
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ForumConfig {
+    pub uploaded_normal_tag: Option<u64>,
+    pub uploaded_community_tag: Option<u64>,
+    pub rejected_tag: Option<u64>,
+    /// the submitters of media posts without readable info are asked to enter it,
+    /// it becomes the title of the post
+    pub ask_media_submitters: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnnouncementConfig {
+    /// further skins are announced in a new message
+    pub max_mentions_per_message: usize,
+    /// if more users than this would be pinged, their names are written instead, 0 disables it
+    pub plain_names_above: usize,
+    /// delay between the announcement messages
+    pub message_delay_ms: u64,
+    /// the skins of each database are announced separately,
+    /// in the channel of the upload if no channel is set
+    pub normal_channel_id: Option<u64>,
+    pub community_channel_id: Option<u64>,
+    /// roles pinged with the announcement of their database
+    pub normal_role_id: Option<u64>,
+    pub community_role_id: Option<u64>,
+    /// also tell the authors by DM, unless they opted out
+    pub dm_authors: bool,
+    /// where the authors that opted out of the DMs are stored
+    pub dm_opt_out_file: String,
+}
+
+impl Default for AnnouncementConfig {
+    fn default() -> Self {
+        Self {
+            max_mentions_per_message: 20,
+            plain_names_above: 0,
+            message_delay_ms: 2000,
+            normal_channel_id: None,
+            community_channel_id: None,
+            normal_role_id: None,
+            community_role_id: None,
+            dm_authors: false,
+            dm_opt_out_file: "dm_opt_out.txt".to_string(),
+        }
+    }
+}
+
+/// The texts of the announcements and the upload report, to translate or restyle them.
+/// Placeholders like `{name}` are replaced, unknown ones are rejected.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TemplateConfig {
+    /// `{database}`
+    pub announcement_header: String,
+    /// one line per skin: `{name}`, `{author}`, `{database}`, `{link}`, `{ratio}`
+    pub announcement_skin: String,
+    /// `{name}`, `{database}`, `{link}`
+    pub author_dm: String,
+    /// `{name}`, `{author}`, `{license}`, `{database}`
+    pub editor_announcement: String,
+    /// the first line of the upload report: `{label}`
+    pub batch_finished: String,
+    pub batch_errors: String,
+    pub batch_report: String,
+    /// how `{database}` is written
+    pub normal_database: String,
+    pub community_database: String,
+}
+
+impl Default for TemplateConfig {
+    fn default() -> Self {
+        Self {
+            announcement_header: "The following skins were added to the {database} database:"
+                .to_string(),
+            announcement_skin: "- \"{name}\" by {author} ({link})".to_string(),
+            author_dm: "Your skin \"{name}\" was added to the {database} database: {link}"
+                .to_string(),
+            editor_announcement:
+                "`{name}` by `{author}` ({license}) was uploaded to the {database} database from the web skin editor"
+                    .to_string(),
+            batch_finished: "Uploading batch `{label}` finished.".to_string(),
+            batch_errors: "But there were the following errors:".to_string(),
+            batch_report: "Processed skins:".to_string(),
+            normal_database: "normal".to_string(),
+            community_database: "community".to_string(),
+        }
+    }
+}
+
+impl TemplateConfig {
+    pub fn database(&self, database: SkinToUploadDB) -> &str {
+        match database {
+            SkinToUploadDB::Normal => &self.normal_database,
+            SkinToUploadDB::Community => &self.community_database,
+        }
+    }
+
+    /// The templates with the placeholders they know.
+    fn placeholders(&self) -> [(&'static str, &str, &'static [&'static str]); 7] {
+        [
+            (
+                "announcement_header",
+                &self.announcement_header,
+                &["database"],
+            ),
+            (
+                "announcement_skin",
+                &self.announcement_skin,
+                &["name", "author", "database", "link", "ratio"],
+            ),
+            ("author_dm", &self.author_dm, &["name", "database", "link"]),
+            (
+                "editor_announcement",
+                &self.editor_announcement,
+                &["name", "author", "license", "database"],
+            ),
+            ("batch_finished", &self.batch_finished, &["label"]),
+            ("batch_errors", &self.batch_errors, &[]),
+            ("batch_report", &self.batch_report, &[]),
+        ]
+    }
+}
+
+/// The community test server, told to reload the skins after `/upload_export`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TestServerConfig {
+    /// receives the exported zip as `skins` and the skin names as `names`
+    pub webhook_url: Option<String>,
+    /// receives the rcon commands as json `{"commands": [...]}` and runs them on the server
+    pub rcon_url: Option<String>,
+    pub rcon_commands: Vec<String>,
+    /// sent as bearer token to both urls
+    pub token: Option<String>,
+}
+
+/// Wasm modules that check the skins on top of the built-in checks,
+/// needs a build with the `wasm-plugins` feature
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PluginConfig {
+    /// `.wasm` or `.wat` files, all of them check every skin file
+    pub paths: Vec<String>,
+    /// instructions a plugin may run per skin file, stops endless loops
+    pub fuel: u64,
+    pub max_memory_mib: u64,
+}
+
+impl Default for PluginConfig {
+    fn default() -> Self {
+        Self {
+            paths: Vec::new(),
+            fuel: 100_000_000,
+            max_memory_mib: 64,
+        }
+    }
+}
+
+/// The endpoint the web skin editor submits skins to, they go through the checks and the
+/// upload of the bot and are announced with a preview
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EditorApiConfig {
+    /// e.g. `0.0.0.0:9200`, the endpoint is disabled without it
+    pub listen: Option<String>,
+    /// the editor sends it as bearer token
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatermarkPolicyKind {
+    #[default]
+    Off,
+    /// the skin is queued, the moderator sees the suspicious regions outlined
+    Warn,
+    Block,
+}
+
+/// Signatures and watermarks outside the parts of the tee, the database doesn't allow them
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WatermarkConfig {
+    pub normal: WatermarkPolicyKind,
+    pub community: WatermarkPolicyKind,
+}
+
+impl WatermarkConfig {
+    pub const fn policy(&self, database: SkinToUploadDB) -> WatermarkPolicyKind {
+        match database {
+            SkinToUploadDB::Normal => self.normal,
+            SkinToUploadDB::Community => self.community,
+        }
+    }
+}
+
+/// Recompresses the pngs before they are uploaded, without changing a pixel
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PngOptimizationConfig {
+    pub normal: bool,
+    pub community: bool,
+}
+
+impl Default for PngOptimizationConfig {
+    fn default() -> Self {
+        Self {
+            normal: true,
+            community: true,
+        }
+    }
+}
+
+impl PngOptimizationConfig {
+    pub const fn enabled(&self, database: SkinToUploadDB) -> bool {
+        match database {
+            SkinToUploadDB::Normal => self.normal,
+            SkinToUploadDB::Community => self.community,
+        }
+    }
+}
+
+/// Blank sheets, recolors of the default skins and low-effort skins,
+/// the moderator has to acknowledge them before they are uploaded
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContentCheckConfig {
+    pub enabled: bool,
+    /// the pngs submissions are compared to, e.g. the `data/skins` folder of the client,
+    /// without it only blank and low-effort skins are flagged
+    pub default_skins_dir: Option<String>,
+    /// bits of the 128 bit perceptual hash, up to which a skin is a recolor of a default skin
+    pub max_default_distance: u32,
+    /// of the parts of the tee the client draws
+    pub min_drawn_percent: u32,
+    /// distinct colors of the drawn pixels
+    pub min_colors: usize,
+}
+
+impl Default for ContentCheckConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            default_skins_dir: None,
+            max_default_distance: 12,
+            min_drawn_percent: 25,
+            min_colors: 8,
+        }
+    }
+}
+
+impl ContentCheckConfig {
+    pub const fn limits(&self) -> ContentLimits {
+        ContentLimits {
+            max_default_distance: self.max_default_distance,
+            min_drawn_percent: self.min_drawn_percent,
+            min_colors: self.min_colors,
+        }
+    }
+}
+
+/// `/healthz` and `/metrics` for the orchestrator and prometheus
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdminHttpConfig {
+    /// e.g. `0.0.0.0:9100`, the endpoints are disabled without it
+    pub listen: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub discord_token: String,
+    pub guild_id: u64,
+    pub role_id: u64,
+    pub database_url: String,
+    pub username: String,
+    pub password: String,
+    /// warnings for the admins, e.g. that the database check failed
+    pub admin_channel_id: Option<u64>,
+    pub emojis: EmojiConfig,
+    pub dilate: DilateConfig,
+    pub upload: UploadConfig,
+    pub license: LicenseConfig,
+    pub votes: VoteConfig,
+    pub vote_report: VoteReportConfig,
+    pub maintenance: MaintenanceConfig,
+    pub forum: ForumConfig,
+    pub announcements: AnnouncementConfig,
+    pub templates: TemplateConfig,
+    pub test_server: TestServerConfig,
+    pub plugins: PluginConfig,
+    pub admin_http: AdminHttpConfig,
+    pub watermark: WatermarkConfig,
+    pub editor_api: EditorApiConfig,
+    pub content_checks: ContentCheckConfig,
+    pub png_optimization: PngOptimizationConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            discord_token: String::default(),
+            guild_id: 0,
+            role_id: 0,
+            database_url: "https://ddnet.org/skins/".to_string(),
+            username: String::default(),
+            password: String::default(),
+            admin_channel_id: None,
+            emojis: EmojiConfig::default(),
+            dilate: DilateConfig::default(),
+            upload: UploadConfig::default(),
+            license: LicenseConfig::default(),
+            votes: VoteConfig::default(),
+            vote_report: VoteReportConfig::default(),
+            maintenance: MaintenanceConfig::default(),
+            forum: ForumConfig::default(),
+            announcements: AnnouncementConfig::default(),
+            templates: TemplateConfig::default(),
+            test_server: TestServerConfig::default(),
+            plugins: PluginConfig::default(),
+            admin_http: AdminHttpConfig::default(),
+            watermark: WatermarkConfig::default(),
+            editor_api: EditorApiConfig::default(),
+            content_checks: ContentCheckConfig::default(),
+            png_optimization: PngOptimizationConfig::default(),
+        }
+    }
+}
+
+fn env_override<T: FromStr>(name: &str, value: &mut T, errors: &mut Vec<String>) {
+    if let Ok(env_value) = env::var(name) {
+        match env_value.parse() {
+            Ok(parsed) => *value = parsed,
+            Err(_) => errors.push(format!("{name} has an invalid value: {env_value}")),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the file from `CONFIG_PATH` (default `config.toml`), if it exists,
+    /// and applies the environment variables on top of it.
+    pub fn load() -> anyhow::Result<Self> {
+        let path = env::var("CONFIG_PATH").unwrap_or_else(|_| "config.toml".to_string());
+        let mut config = match std::fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content)
+                .map_err(|err| anyhow::anyhow!("could not parse {path}: {err}"))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(err) => anyhow::bail!("could not read {path}: {err}"),
+        };
+
+        let mut errors = Vec::new();
+        env_override("DISCORD_TOKEN", &mut config.discord_token, &mut errors);
+        env_override("GUILD_ID", &mut config.guild_id, &mut errors);
+        env_override("ROLE_ID", &mut config.role_id, &mut errors);
+        env_override("DATABASE_URL", &mut config.database_url, &mut errors);
+        env_override("USERNAME", &mut config.username, &mut errors);
+        env_override("PASSWORD", &mut config.password, &mut errors);
+        env_override(
+            "UPLOAD_TIMEOUT_SECS",
+            &mut config.upload.timeout_secs,
+            &mut errors,
+        );
+        if let Ok(flag_url) = env::var("MAINTENANCE_FLAG_URL") {
+            config.maintenance.flag_url = Some(flag_url);
+        }
+        if let Ok(listen) = env::var("ADMIN_HTTP_LISTEN") {
+            config.admin_http.listen = Some(listen);
+        }
+        if let Ok(listen) = env::var("EDITOR_API_LISTEN") {
+            config.editor_api.listen = Some(listen);
+        }
+        if let Ok(token) = env::var("EDITOR_API_TOKEN") {
+            config.editor_api.token = Some(token);
+        }
+        if let Ok(token) = env::var("TEST_SERVER_TOKEN") {
+            config.test_server.token = Some(token);
+        }
+        if let Ok(cleanup) = env::var("CLEANUP_STRAY_PIXELS") {
+            config.upload.cleanup_stray_pixels = cleanup == "1" || cleanup == "true";
+        }
+        if let Ok(auto_resize) = env::var("AUTO_RESIZE") {
+            config.upload.auto_resize = auto_resize == "1" || auto_resize == "true";
+        }
+        if let Ok(allow_list) = env::var("LICENSE_ALLOW_LIST") {
+            config.license.allow_list = allow_list.split(',').map(str::to_string).collect();
+        }
+        if let Ok(policy) = env::var("LICENSE_POLICY") {
+            config.license.policy = match policy.as_str() {
+                "flag" => LicensePolicyKind::Flag,
+                "reject" => LicensePolicyKind::Reject,
+                _ => {
+                    errors.push(format!(
+                        "LICENSE_POLICY must be `reject` or `flag`, not: {policy}"
+                    ));
+                    config.license.policy
+                }
+            };
+        }
+        if let Ok(policy) = env::var("VOTE_POLICY") {
+            config.votes.policy = match policy.as_str() {
+                "flag" => VotePolicyKind::Flag,
+                "reject" => VotePolicyKind::Reject,
+                _ => {
+                    errors.push(format!(
+                        "VOTE_POLICY must be `reject` or `flag`, not: {policy}"
+                    ));
+                    config.votes.policy
+                }
+            };
+        }
+
+        errors.extend(config.validate());
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            anyhow::bail!("invalid configuration:\n- {}", errors.join("\n- "))
+        }
+    }
+
+    fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.discord_token.is_empty() {
+            errors.push("discord_token (DISCORD_TOKEN) is missing".to_string());
+        }
+        if self.guild_id == 0 {
+            errors.push("guild_id (GUILD_ID) is missing".to_string());
+        }
+        if self.role_id == 0 {
+            errors.push("role_id (ROLE_ID) is missing".to_string());
+        }
+        if self.username.is_empty() || self.password.is_empty() {
+            errors.push(
+                "username and password (USERNAME, PASSWORD) for http auth are missing".to_string(),
+            );
+        }
+        if !self.database_url.starts_with("http://") && !self.database_url.starts_with("https://") {
+            errors.push(format!(
+                "database_url (DATABASE_URL) must be a http(s) url: {}",
+                self.database_url
+            ));
+        } else if !self.database_url.ends_with('/') {
+            errors.push(format!(
+                "database_url (DATABASE_URL) must end with a `/`: {}",
+                self.database_url
+            ));
+        }
+        if self.emojis.queue_normal.is_empty() || self.emojis.queue_community.is_empty() {
+            errors.push(
+                "emojis.queue_normal and emojis.queue_community must not be empty".to_string(),
+            );
+        }
+        if let Some(emoji) = self
+            .emojis
+            .queue_normal
+            .iter()
+            .find(|emoji| emojis_match(emoji, &self.emojis.queue_community))
+        {
+            errors.push(format!(
+                "emojis.queue_normal and emojis.queue_community both contain {emoji}"
+            ));
+        }
+        if self.dilate.radius == 0 {
+            errors.push("dilate.radius must be greater than 0".to_string());
+        }
+        if self.upload.timeout_secs == 0 {
+            errors.push("upload.timeout_secs must be greater than 0".to_string());
+        }
+        if self.upload.expiry_warning_secs >= self.upload.timeout_secs {
+            errors.push(
+                "upload.expiry_warning_secs must be less than upload.timeout_secs".to_string(),
+            );
+        }
+        if self.license.allow_list.is_empty() {
+            errors.push("license.allow_list must contain at least one license".to_string());
+        }
+        for epoch in &self.license.epochs {
+            if epoch
+                .allow_list
+                .as_ref()
+                .is_some_and(|allow_list| allow_list.is_empty())
+            {
+                errors.push(format!(
+                    "license.epochs since {} must allow at least one license",
+                    epoch.since
+                ));
+            }
+        }
+        if !(0.0..=1.0).contains(&self.votes.min_positive_ratio) {
+            errors.push(format!(
+                "votes.min_positive_ratio must be between 0 and 1: {}",
+                self.votes.min_positive_ratio
+            ));
+        }
+        if self.vote_report.interval_days == 0 || self.vote_report.days == 0 {
+            errors.push(
+                "vote_report.interval_days and vote_report.days must be greater than 0".to_string(),
+            );
+        }
+        if self.content_checks.max_default_distance > 128 {
+            errors.push(format!(
+                "content_checks.max_default_distance must be at most 128: {}",
+                self.content_checks.max_default_distance
+            ));
+        }
+        if self.content_checks.min_drawn_percent > 100 {
+            errors.push(format!(
+                "content_checks.min_drawn_percent must be at most 100: {}",
+                self.content_checks.min_drawn_percent
+            ));
+        }
+        if self.maintenance.poll_secs == 0 {
+            errors.push("maintenance.poll_secs must be greater than 0".to_string());
+        }
+        for (field, url) in [
+            ("test_server.webhook_url", &self.test_server.webhook_url),
+            ("test_server.rcon_url", &self.test_server.rcon_url),
+        ] {
+            if let Some(url) = url {
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    errors.push(format!("{field} must be a http(s) url: {url}"));
+                }
+            }
+        }
+        if self.test_server.rcon_url.is_some() && self.test_server.rcon_commands.is_empty() {
+            errors.push(
+                "test_server.rcon_url needs at least one of test_server.rcon_commands".to_string(),
+            );
+        }
+        if !self.plugins.paths.is_empty() {
+            if self.plugins.fuel == 0 {
+                errors.push("plugins.fuel must be greater than 0".to_string());
+            }
+            if self.plugins.max_memory_mib == 0 {
+                errors.push("plugins.max_memory_mib must be greater than 0".to_string());
+            }
+        }
+        if let Some(listen) = &self.admin_http.listen {
+            if listen.parse::<SocketAddr>().is_err() {
+                errors.push(format!(
+                    "admin_http.listen (ADMIN_HTTP_LISTEN) must be an address like `0.0.0.0:9100`: {listen}"
+                ));
+            }
+        }
+        if let Some(listen) = &self.editor_api.listen {
+            if listen.parse::<SocketAddr>().is_err() {
+                errors.push(format!(
+                    "editor_api.listen (EDITOR_API_LISTEN) must be an address like `0.0.0.0:9200`: {listen}"
+                ));
+            }
+            if self
+                .editor_api
+                .token
+                .as_deref()
+                .unwrap_or_default()
+                .is_empty()
+            {
+                errors.push(
+                    "editor_api.token (EDITOR_API_TOKEN) is needed for editor_api.listen"
+                        .to_string(),
+                );
+            }
+            if self.announcements.normal_channel_id.is_none()
+                || self.announcements.community_channel_id.is_none()
+            {
+                errors.push(
+                    "editor_api.listen needs announcements.normal_channel_id and announcements.community_channel_id, the submissions of the editor are announced there"
+                        .to_string(),
+                );
+            }
+        }
+        if self.announcements.max_mentions_per_message == 0 {
+            errors
+                .push("announcements.max_mentions_per_message must be greater than 0".to_string());
+        }
+        for (field, template, known) in self.templates.placeholders() {
+            for placeholder in unknown_placeholders(template, known) {
+                let known = if known.is_empty() {
+                    "none".to_string()
+                } else {
+                    known.join(", ")
+                };
+                errors.push(format!(
+                    "templates.{field} has an unknown placeholder {{{placeholder}}}, it knows: {known}"
+                ));
+            }
+        }
+        errors
+    }
+
+    pub fn guild_id(&self) -> GuildId {
+        GuildId::new(self.guild_id)
+    }
+
+    pub fn role_id(&self) -> RoleId {
+        RoleId::new(self.role_id)
+    }
+
+    pub fn is_channel_allowed(&self, channel_id: ChannelId) -> bool {
+        self.upload.allowed_channels.is_empty()
+            || self.upload.allowed_channels.contains(&channel_id.get())
+    }
+
+    /// The allowed channels as mentions, for error messages.
+    pub fn allowed_channels_list(&self) -> String {
+        self.upload
+            .allowed_channels
+            .iter()
+            .map(|channel_id| format!("<#{channel_id}>"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl TypeMapKey for Config {
+    type Value = Arc<Self>;
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use serenity::all::UserId;
+use serenity::prelude::*;
+
+use crate::SkinToUploadDB;
+
+pub const CONFIRM_EDIT_BUTTON_ID: &str = "skin_edit_confirm";
+pub const CANCEL_EDIT_BUTTON_ID: &str = "skin_edit_cancel";
+
+/// A change of a skin that is already in the database.
+#[derive(Debug, Clone)]
+pub enum SkinEdit {
+    Delete {
+        name: String,
+        database: SkinToUploadDB,
+    },
+    /// only the given fields are changed
+    Update {
+        name: String,
+        database: SkinToUploadDB,
+        author: Option<String>,
+        license: Option<String>,
+        skin_pack: Option<String>,
+    },
+}
+
+impl SkinEdit {
+    /// The question the moderator has to confirm.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Delete { name, database } => format!(
+                "Delete **{name}** from the {} database?",
+                database.to_string()
+            ),
+            Self::Update {
+                name,
+                database,
+                author,
+                license,
+                skin_pack,
+            } => {
+                let mut msg = format!(
+                    "Update **{name}** in the {} database?",
+                    database.to_string()
+                );
+                for (field, value) in [
+                    ("author", author),
+                    ("license", license),
+                    ("skin pack", skin_pack),
+                ] {
+                    if let Some(value) = value {
+                        msg += &format!("\n> {field}: {value}");
+                    }
+                }
+                msg
+            }
+        }
+    }
+
+    /// How the edit shows up in the audit log.
+    pub fn action(&self) -> String {
+        match self {
+            Self::Delete { database, .. } => format!("delete {}", database.to_string()),
+            Self::Update { database, .. } => format!("update {}", database.to_string()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Delete { name, .. } | Self::Update { name, .. } => name,
+        }
+    }
+
+    /// The fields for the skin editor, the same as those of an upload.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Self::Delete { name, database } => {
+                let mut fields = base_fields(name, *database);
+                fields.push(("modifyaction", "delete".to_string()));
+                fields
+            }
+            Self::Update {
+                name,
+                database,
+                author,
+                license,
+                skin_pack,
+            } => {
+                let mut fields = base_fields(name, *database);
+                fields.push(("modifyaction", "update".to_string()));
+                for (field, value) in [
+                    ("creator", author),
+                    ("skin_license", license),
+                    ("skin_pack", skin_pack),
+                ] {
+                    if let Some(value) = value {
+                        fields.push((field, value.clone()));
+                    }
+                }
+                fields
+            }
+        }
+    }
+}
+
+fn base_fields(name: &str, database: SkinToUploadDB) -> Vec<(&'static str, String)> {
+    vec![
+        ("skin_name", name.to_string()),
+        ("skin_type", database.to_string()),
+        ("game_version", "tw-0.6".to_string()),
+        ("skin_part", "full".to_string()),
+    ]
+}
+
+/// Edits waiting for the confirmation of the moderator that requested them.
+pub struct PendingSkinEdits;
+
+impl TypeMapKey for PendingSkinEdits {
+    type Value = HashMap<UserId, SkinEdit>;
+}
@@ -0,0 +1,634 @@
+use std::fmt::{Display, Formatter};
+use std::io::{Cursor, Read};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest::blocking::multipart::{Form, Part};
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use serenity::all::ChannelId;
+use serenity::prelude::*;
+use tracing::{error, info, warn};
+
+use crate::cleanup::{normalize_body_alpha, remove_stray_pixels};
+use crate::config::Config;
+use crate::dilate::{dilate_image, is_dilated, DilateOptions};
+use crate::optimize::{default_png, optimized_png};
+
+const MAX_ATTEMPTS: u32 = 4;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+/// the throttled body is read in small chunks, so the rate stays even during a single file
+const THROTTLE_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Caps the rate the skin files are sent with, shared by all uploads,
+/// so big batches don't saturate the uplink and starve the gateway connection.
+#[derive(Clone)]
+struct UploadThrottle {
+    /// 0 sends the files as fast as possible
+    bytes_per_sec: u64,
+    /// the time the bytes sent so far are paid off
+    next_free: Arc<Mutex<Instant>>,
+}
+
+impl UploadThrottle {
+    fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec,
+            next_free: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Blocks until sending `bytes` more stays within the rate.
+    fn wait(&self, bytes: usize) {
+        if self.bytes_per_sec == 0 {
+            return;
+        }
+        let duration = Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64);
+        let until = {
+            let mut next_free = self.next_free.lock().unwrap();
+            // an idle uplink doesn't save up a burst
+            *next_free = (*next_free).max(Instant::now()) + duration;
+            *next_free
+        };
+        std::thread::sleep(until.saturating_duration_since(Instant::now()));
+    }
+
+    /// The png as a multipart part that is read no faster than the rate.
+    fn png_part(&self, content: Vec<u8>, file_name: String) -> Part {
+        let length = content.len() as u64;
+        let reader = ThrottledReader {
+            inner: Cursor::new(content),
+            throttle: self.clone(),
+        };
+        Part::reader_with_length(reader, length)
+            .file_name(file_name)
+            .mime_str("image/png")
+            .unwrap()
+    }
+}
+
+struct ThrottledReader<R> {
+    inner: R,
+    throttle: UploadThrottle,
+}
+
+impl<R: Read> Read for ThrottledReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(THROTTLE_CHUNK_SIZE);
+        let read = self.inner.read(&mut buf[..len])?;
+        self.throttle.wait(read);
+        Ok(read)
+    }
+}
+
+#[derive(Debug)]
+pub enum UploadError {
+    /// the http auth credentials were refused, retrying won't help
+    Auth(StatusCode, String),
+    /// the database refused the skin itself, e.g. because it exists already
+    Validation(StatusCode, String),
+    /// the server failed or was not reachable, even after retrying
+    Server(String),
+}
+
+impl Display for UploadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Auth(status, body) => write!(
+                f,
+                "the database refused the credentials ({status}), check USERNAME and PASSWORD: {body}"
+            ),
+            Self::Validation(status, body) => {
+                write!(f, "the database refused the skin ({status}): {body}")
+            }
+            Self::Server(err) => write!(
+                f,
+                "the database failed {MAX_ATTEMPTS} times: {err}. Please manually check if this broke the database"
+            ),
+        }
+    }
+}
+
+/// The text of the response without html tags, shortened to fit into the error report.
+fn server_message(body: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in body.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(200)
+        .collect()
+}
+
+/// The database answers with 200 even if php failed, so the body has to be checked as well.
+/// Only the failure output of the script and the errors that stop php count, warnings and
+/// notices are printed next to a successful upload.
+fn reports_error(message: &str) -> bool {
+    let message = message.trim_start().to_lowercase();
+    message.starts_with("error")
+        || message.starts_with("failed")
+        || ["fatal error:", "parse error:"]
+            .iter()
+            .any(|marker| message.contains(marker))
+}
+
+fn classify_response(status: StatusCode, body: &str) -> Result<(), UploadError> {
+    let body = server_message(body);
+    if status.is_success() {
+        if reports_error(&body) {
+            Err(UploadError::Validation(status, body))
+        } else {
+            Ok(())
+        }
+    } else if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        Err(UploadError::Auth(status, body))
+    } else if status.is_server_error()
+        || status == StatusCode::REQUEST_TIMEOUT
+        || status == StatusCode::TOO_MANY_REQUESTS
+    {
+        Err(UploadError::Server(format!("{status}: {body}")))
+    } else {
+        Err(UploadError::Validation(status, body))
+    }
+}
+
+/// What the answer to the probe says about the url and the credentials, the script doesn't
+/// have to support HEAD, as long as it is there and accepts the credentials.
+fn classify_probe(status: StatusCode, url: &str) -> Result<(), String> {
+    if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        Err(format!(
+            "the database refused the credentials ({status}), check USERNAME and PASSWORD"
+        ))
+    } else if status == StatusCode::NOT_FOUND || status == StatusCode::GONE {
+        Err(format!(
+            "{url} does not exist ({status}), check DATABASE_URL"
+        ))
+    } else if status.is_server_error() {
+        Err(format!("the database failed ({status})"))
+    } else {
+        Ok(())
+    }
+}
+
+/// The fields the database stores with a skin file, the name is taken from the file name.
+#[derive(Debug, Clone)]
+pub struct SkinForm {
+    pub name: String,
+    pub author: String,
+    pub license: String,
+    pub skin_pack: String,
+    /// `normal` or `community`
+    pub database: String,
+    /// the 512x256 version of the skin
+    pub uhd: bool,
+}
+
+/// The skin editor of the database. The calls block and retry on their own,
+/// the bot runs them on the blocking pool.
+pub trait SkinDatabaseClient: Send + Sync {
+    /// Adds a skin file, `png` is sent as `<name>.png`.
+    fn upload(&self, form: &SkinForm, png: &[u8]) -> Result<(), UploadError>;
+
+    /// Posts the fields of a change to a skin that is already in the database.
+    fn modify(&self, fields: &[(&'static str, String)]) -> Result<(), UploadError>;
+
+    /// Checks that the database is reachable and accepts the credentials, without changing it.
+    fn probe(&self) -> Result<(), String>;
+}
+
+/// The client the uploads and edits go through.
+pub struct SkinDatabase;
+
+impl TypeMapKey for SkinDatabase {
+    type Value = Arc<dyn SkinDatabaseClient>;
+}
+
+/// The result of the last probe of the database, `/upload_finish` is refused while it fails.
+/// Assumed fine until the first probe is done.
+pub struct DatabaseStatus;
+
+impl TypeMapKey for DatabaseStatus {
+    type Value = Result<(), String>;
+}
+
+/// Probes the database and records the result, a failure is logged and posted to the admin
+/// channel. Returns the result.
+pub async fn check_database(ctx: &Context) -> Result<(), String> {
+    let (config, database) = {
+        let data = ctx.data.read().await;
+        (
+            data.get::<Config>().unwrap().clone(),
+            data.get::<SkinDatabase>().unwrap().clone(),
+        )
+    };
+    let status = tokio::task::spawn_blocking(move || database.probe())
+        .await
+        .unwrap();
+    let mut data = ctx.data.write().await;
+    let failed_before = data
+        .get::<DatabaseStatus>()
+        .is_some_and(|status| status.is_err());
+    data.insert::<DatabaseStatus>(status.clone());
+    drop(data);
+    match &status {
+        Ok(()) if failed_before => info!("The database check succeeds again"),
+        Ok(()) => {}
+        Err(err) => {
+            error!("The database check failed: {err}");
+            if let Some(channel_id) = config.admin_channel_id.map(ChannelId::new) {
+                let warning = format!(
+                    "⚠️ The skin database check failed, uploads are refused until it succeeds: {err}"
+                );
+                if let Err(err) = channel_id.say(ctx, warning).await {
+                    warn!("Could not post the failed database check: {err}");
+                }
+            }
+        }
+    }
+    status
+}
+
+/// Talks to `edit/modify_skin.php` of the database with http basic auth.
+pub struct HttpDatabaseClient {
+    url: String,
+    user_name: String,
+    password: String,
+    throttle: UploadThrottle,
+}
+
+impl HttpDatabaseClient {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            url: config.database_url.clone() + "edit/modify_skin.php",
+            user_name: config.username.clone(),
+            password: config.password.clone(),
+            throttle: UploadThrottle::new(config.upload.max_bytes_per_sec),
+        }
+    }
+
+    /// The form is rebuilt for every attempt, because a multipart form can only be sent once.
+    fn post(&self, make_form: impl Fn() -> Form) -> Result<(), UploadError> {
+        // the blocking client must not be created on the async runtime
+        let client = Client::new();
+        with_retries(INITIAL_BACKOFF, || {
+            client
+                .post(&self.url)
+                .multipart(make_form())
+                .basic_auth(&self.user_name, Some(&self.password))
+                .send()
+                .map_err(|err| UploadError::Server(err.to_string()))
+                .and_then(|res| {
+                    let status = res.status();
+                    classify_response(status, &res.text().unwrap_or_default())
+                })
+        })
+    }
+}
+
+impl SkinDatabaseClient for HttpDatabaseClient {
+    /// The image is attached as `image` and sent within the rate of the throttle.
+    fn upload(&self, form: &SkinForm, png: &[u8]) -> Result<(), UploadError> {
+        self.post(|| {
+            Form::new()
+                .text("creator", form.author.clone())
+                .text("skin_pack", form.skin_pack.clone())
+                .text("skin_license", form.license.clone())
+                .text("skin_type", form.database.clone())
+                .text("game_version", "tw-0.6")
+                .text("skin_part", "full")
+                .text("modifyaction", "add")
+                .text("skinisuhd", form.uhd.to_string())
+                .part(
+                    "image",
+                    self.throttle
+                        .png_part(png.to_vec(), format!("{}.png", form.name)),
+                )
+        })
+    }
+
+    fn modify(&self, fields: &[(&'static str, String)]) -> Result<(), UploadError> {
+        self.post(|| {
+            fields.iter().fold(Form::new(), |form, (name, value)| {
+                form.text(*name, value.clone())
+            })
+        })
+    }
+
+    /// A single HEAD request, the bot should not wait long at startup.
+    fn probe(&self) -> Result<(), String> {
+        let client = Client::builder()
+            .timeout(PROBE_TIMEOUT)
+            .build()
+            .map_err(|err| err.to_string())?;
+        let res = client
+            .head(&self.url)
+            .basic_auth(&self.user_name, Some(&self.password))
+            .send()
+            .map_err(|err| format!("the database is not reachable, check DATABASE_URL: {err}"))?;
+        classify_probe(res.status(), &self.url)
+    }
+}
+
+/// Retries server errors with exponential backoff, other errors won't go away by retrying.
+fn with_retries(
+    mut backoff: Duration,
+    mut send: impl FnMut() -> Result<(), UploadError>,
+) -> Result<(), UploadError> {
+    let mut attempt = 1;
+    loop {
+        match send() {
+            Err(UploadError::Server(err)) if attempt < MAX_ATTEMPTS => {
+                warn!("upload attempt {attempt} failed, retrying in {backoff:?}: {err}");
+                std::thread::sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+            res => return res,
+        }
+    }
+}
+
+/// How uploading one file of a skin went, for the report of the upload.
+pub struct FileUpload {
+    pub png_size: usize,
+    /// the size the png had before it was optimized, if it was
+    pub unoptimized_size: Option<usize>,
+    pub stray_pixels: usize,
+    /// semi-transparent pixels of the body that were made opaque
+    pub body_alpha_fixed: usize,
+    pub already_dilated: bool,
+    /// spent on the image, without the upload
+    pub processing_time: Duration,
+    /// part of the processing time, zero if the skin was already dilated
+    pub dilate_time: Duration,
+    pub result: Result<(), UploadError>,
+}
+
+/// Cleans up, dilates, optionally makes the body opaque and optimizes the rgba image like it
+/// is uploaded, then uploads it. Dilating again could alter the colors the artist chose,
+/// so dilated skins are kept.
+pub fn process_and_upload(
+    database: &dyn SkinDatabaseClient,
+    mut img: Vec<u8>,
+    form: &SkinForm,
+    apply_cleanup: bool,
+    dilate_options: &DilateOptions,
+    normalize_body: bool,
+    optimize: bool,
+) -> FileUpload {
+    let start = Instant::now();
+    let (w, h) = if form.uhd { (512, 256) } else { (256, 128) };
+    let already_dilated = is_dilated(&img, w, h, 4, dilate_options.alpha_threshold);
+    let stray_pixels = if apply_cleanup {
+        remove_stray_pixels(&mut img, w, h, 4).len()
+    } else {
+        0
+    };
+    let dilate_start = Instant::now();
+    if !already_dilated {
+        dilate_image(&mut img, w, h, 4, dilate_options);
+    }
+    let dilate_time = dilate_start.elapsed();
+    let body_alpha_fixed = if normalize_body {
+        normalize_body_alpha(&mut img, w, h)
+    } else {
+        0
+    };
+    let mut png = default_png(&img, w as u32, h as u32);
+    let mut unoptimized_size = None;
+    if optimize {
+        unoptimized_size = Some(png.len());
+        png = optimized_png(&img, w as u32, h as u32);
+    }
+    let processing_time = start.elapsed();
+    FileUpload {
+        png_size: png.len(),
+        unoptimized_size,
+        stray_pixels,
+        body_alpha_fixed,
+        already_dilated,
+        processing_time,
+        dilate_time,
+        result: database.upload(form, &png),
+    }
+}
+
+/// Whether the website announces a maintenance of the database.
+/// An unreachable flag doesn't hold back the upload, the uploads are retried on their own.
+pub async fn maintenance_active(flag_url: &str) -> bool {
+    let flag = async {
+        reqwest::get(flag_url)
+            .await?
+            .error_for_status()?
+            .text()
+            .await
+    };
+    match flag.await {
+        Ok(flag) => matches!(flag.trim().to_lowercase().as_str(), "1" | "true"),
+        Err(err) => {
+            warn!("Could not check the maintenance flag: {err}");
+            false
+        }
+    }
+}
+
+/// Stands in for the database in tests: records the calls and answers with the queued
+/// results, once they are used up every call succeeds.
+#[cfg(any(test, feature = "test-harness"))]
+#[derive(Default)]
+pub struct MockDatabaseClient {
+    pub uploads: Mutex<Vec<(SkinForm, Vec<u8>)>>,
+    pub edits: Mutex<Vec<Vec<(&'static str, String)>>>,
+    results: Mutex<std::collections::VecDeque<Result<(), UploadError>>>,
+}
+
+#[cfg(any(test, feature = "test-harness"))]
+impl MockDatabaseClient {
+    pub fn answering(results: impl IntoIterator<Item = Result<(), UploadError>>) -> Self {
+        Self {
+            results: Mutex::new(results.into_iter().collect()),
+            ..Self::default()
+        }
+    }
+
+    fn next_result(&self) -> Result<(), UploadError> {
+        self.results.lock().unwrap().pop_front().unwrap_or(Ok(()))
+    }
+}
+
+#[cfg(any(test, feature = "test-harness"))]
+impl SkinDatabaseClient for MockDatabaseClient {
+    fn upload(&self, form: &SkinForm, png: &[u8]) -> Result<(), UploadError> {
+        self.uploads
+            .lock()
+            .unwrap()
+            .push((form.clone(), png.to_vec()));
+        self.next_result()
+    }
+
+    fn modify(&self, fields: &[(&'static str, String)]) -> Result<(), UploadError> {
+        self.edits.lock().unwrap().push(fields.to_vec());
+        self.next_result()
+    }
+
+    fn probe(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use reqwest::StatusCode;
+
+    use super::{
+        classify_probe, classify_response, process_and_upload, with_retries, MockDatabaseClient,
+        SkinForm, UploadError, MAX_ATTEMPTS,
+    };
+    use crate::dilate::DilateOptions;
+
+    fn form(uhd: bool) -> SkinForm {
+        SkinForm {
+            name: "greyfox".to_string(),
+            author: "nameless tee".to_string(),
+            license: "CC0".to_string(),
+            skin_pack: String::default(),
+            database: "normal".to_string(),
+            uhd,
+        }
+    }
+
+    #[test]
+    fn classifies_responses() {
+        assert!(classify_response(StatusCode::OK, "<p>skin added</p>").is_ok());
+        assert!(matches!(
+            classify_response(StatusCode::OK, "<b>Fatal error</b>: in modify_skin.php"),
+            Err(UploadError::Validation(..))
+        ));
+        assert!(matches!(
+            classify_response(StatusCode::OK, "Error: the skin exists already"),
+            Err(UploadError::Validation(..))
+        ));
+        // php keeps going after warnings, the skin was added
+        assert!(classify_response(
+            StatusCode::OK,
+            "<b>Warning</b>: Undefined array key \"exception\" in modify_skin.php<br>skin added"
+        )
+        .is_ok());
+        assert!(matches!(
+            classify_response(StatusCode::UNAUTHORIZED, ""),
+            Err(UploadError::Auth(..))
+        ));
+        assert!(matches!(
+            classify_response(StatusCode::BAD_GATEWAY, ""),
+            Err(UploadError::Server(_))
+        ));
+        assert!(matches!(
+            classify_response(StatusCode::CONFLICT, "exists already"),
+            Err(UploadError::Validation(..))
+        ));
+    }
+
+    #[test]
+    fn classifies_probes() {
+        let url = "https://ddnet.org/skins/edit/modify_skin.php";
+        // the script only answers POST properly
+        assert!(classify_probe(StatusCode::OK, url).is_ok());
+        assert!(classify_probe(StatusCode::METHOD_NOT_ALLOWED, url).is_ok());
+        assert!(classify_probe(StatusCode::UNAUTHORIZED, url)
+            .unwrap_err()
+            .contains("USERNAME"));
+        assert!(classify_probe(StatusCode::NOT_FOUND, url)
+            .unwrap_err()
+            .contains("DATABASE_URL"));
+        assert!(classify_probe(StatusCode::BAD_GATEWAY, url).is_err());
+    }
+
+    #[test]
+    fn retries_server_errors_only() {
+        let mut attempts = 0;
+        let res = with_retries(Duration::ZERO, || {
+            attempts += 1;
+            Err(UploadError::Server("bad gateway".to_string()))
+        });
+        assert!(matches!(res, Err(UploadError::Server(_))));
+        assert_eq!(attempts, MAX_ATTEMPTS);
+
+        let mut attempts = 0;
+        let res = with_retries(Duration::ZERO, || {
+            attempts += 1;
+            if attempts == 1 {
+                Err(UploadError::Server("timeout".to_string()))
+            } else {
+                Ok(())
+            }
+        });
+        assert!(res.is_ok());
+        assert_eq!(attempts, 2);
+
+        let mut attempts = 0;
+        let res = with_retries(Duration::ZERO, || {
+            attempts += 1;
+            Err(UploadError::Auth(StatusCode::FORBIDDEN, String::default()))
+        });
+        assert!(matches!(res, Err(UploadError::Auth(..))));
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn uploads_the_processed_skin() {
+        let database = MockDatabaseClient::answering([
+            Ok(()),
+            Err(UploadError::Validation(
+                StatusCode::CONFLICT,
+                "exists already".to_string(),
+            )),
+        ]);
+        let mut img = vec![0; 512 * 256 * 4];
+        // a lone semi-transparent pixel is removed by the cleanup
+        img[3] = 128;
+        let upload = process_and_upload(
+            &database,
+            img.clone(),
+            &form(true),
+            true,
+            &DilateOptions::default(),
+            false,
+            true,
+        );
+        assert!(upload.result.is_ok());
+        assert_eq!(upload.stray_pixels, 1);
+        assert!(upload.png_size <= upload.unoptimized_size.unwrap());
+
+        let upload = process_and_upload(
+            &database,
+            img,
+            &form(true),
+            false,
+            &DilateOptions::default(),
+            false,
+            false,
+        );
+        assert!(matches!(upload.result, Err(UploadError::Validation(..))));
+        assert_eq!(upload.stray_pixels, 0);
+
+        let uploads = database.uploads.lock().unwrap();
+        assert_eq!(uploads.len(), 2);
+        let (form, png) = &uploads[1];
+        assert!(form.uhd);
+        assert_eq!(png.len(), upload.png_size);
+        let decoded = image::load_from_memory(png).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (512, 256));
+    }
+}
@@ -1,61 +1,141 @@
-const TW_DILATE_ALPHA_THRESHOLD: u8 = 10;
+use std::io::Cursor;
+use std::path::Path;
 
+use image::{ColorType, DynamicImage, ImageFormat};
+use rayon::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DilateOptions {
+    /// how many pixels a single pass looks for an opaque neighbour in each direction
+    pub radius: usize,
+    /// every iteration runs two passes after the first one
+    pub iterations: usize,
+    /// pixels up to this alpha are considered transparent
+    pub alpha_threshold: u8,
+}
+
+impl Default for DilateOptions {
+    fn default() -> Self {
+        Self {
+            radius: 1,
+            iterations: 5,
+            alpha_threshold: 10,
+        }
+    }
+}
+
+/// Only images with 2 (luma + alpha) or 4 (rgba) bytes per pixel have an alpha channel,
+/// without one there is nothing to dilate.
+fn has_alpha(bpp: usize) -> bool {
+    assert!(
+        (1..=4).contains(&bpp),
+        "only 1 to 4 bytes per pixel are supported, got {bpp}"
+    );
+    bpp == 2 || bpp == 4
+}
+
+/// One dilate pass over the whole image, rows are processed in parallel.
+/// Returns whether any transparent pixel was filled.
 pub fn dilate(
     w: usize,
     h: usize,
     bpp: usize,
     src_buff: &[u8],
     dest_buff: &mut [u8],
-    alpha_threshold: u8,
-) {
-    let dirs_x = [0, -1, 1, 0];
-    let dirs_y = [-1, 0, 0, 1];
-
+    options: &DilateOptions,
+) -> bool {
+    let alpha_threshold = options.alpha_threshold;
     let alpha_comp_index = bpp - 1;
+    let stride = w * bpp;
 
-    dest_buff
-        .chunks_exact_mut(bpp)
+    dest_buff[..h * stride]
+        .par_chunks_exact_mut(stride)
         .enumerate()
-        .take(w * h)
-        .for_each(|(i, dst)| {
-            let x = i % w;
-            let y = i / w;
-
-            let m = y * w * bpp + x * bpp;
-            dst.copy_from_slice(&src_buff[m..(bpp + m)]);
-            if src_buff[m + alpha_comp_index] > alpha_threshold {
-                return;
-            }
+        .map(|(y, row)| {
+            let src_row = &src_buff[y * stride..(y + 1) * stride];
+            row.copy_from_slice(src_row);
 
-            // clear pixels that are considered transparent
-            // this allows the image to always be black where no dilate is needed
-            dst[0..(bpp - 1)].fill(0);
-
-            let mut sums_of_opaque = [0, 0, 0];
-            let mut counter = 0;
-            for c in 0..4 {
-                let ix = (x as i64 + dirs_x[c]).clamp(0, w as i64 - 1) as usize;
-                let iy = (y as i64 + dirs_y[c]).clamp(0, h as i64 - 1) as usize;
-                let k = iy * w * bpp + ix * bpp;
-                if src_buff[k + alpha_comp_index] > alpha_threshold {
-                    for p in 0..bpp - 1 {
-                        // Seems safe for BPP = 3, 4 which we use.
-                        sums_of_opaque[p] += src_buff[k + p] as u32;
-                    }
-                    counter += 1;
-                    break;
+            let mut changed = false;
+            for (x, dst) in row.chunks_exact_mut(bpp).enumerate() {
+                if dst[alpha_comp_index] > alpha_threshold {
+                    continue;
                 }
-            }
 
-            if counter > 0 {
-                for i in 0..bpp - 1 {
-                    sums_of_opaque[i] /= counter;
-                    dst[i] = sums_of_opaque[i] as u8;
+                // clear pixels that are considered transparent
+                // this allows the image to always be black where no dilate is needed
+                dst[0..alpha_comp_index].fill(0);
+
+                // take the color of the closest opaque neighbour, on the same distance
+                // the first one of: up, left, right, down
+                if let Some(k) = (1..=options.radius)
+                    .flat_map(|d| {
+                        [
+                            (x, y.saturating_sub(d)),
+                            (x.saturating_sub(d), y),
+                            ((x + d).min(w - 1), y),
+                            (x, (y + d).min(h - 1)),
+                        ]
+                    })
+                    .map(|(ix, iy)| iy * stride + ix * bpp)
+                    .find(|k| src_buff[k + alpha_comp_index] > alpha_threshold)
+                {
+                    dst[0..alpha_comp_index].copy_from_slice(&src_buff[k..k + alpha_comp_index]);
+                    dst[alpha_comp_index] = 255;
+                    changed = true;
                 }
+            }
+            changed
+        })
+        .reduce(|| false, |a, b| a || b)
+}
 
-                dst[alpha_comp_index] = 255;
+/// share of the transparent pixels around the visible ones, that must carry the color of a
+/// neighbour, to consider the image dilated already
+const DILATED_MIN_COLORED_RATIO: f64 = 0.9;
+
+/// Whether the fully transparent pixels around the visible ones already carry the color
+/// of their neighbours, which means the artist dilated the image before.
+pub fn is_dilated(img_buff: &[u8], w: usize, h: usize, bpp: usize, alpha_threshold: u8) -> bool {
+    if !has_alpha(bpp) {
+        return false;
+    }
+    let alpha_comp_index = bpp - 1;
+    let mut border_pixels = 0;
+    let mut colored_pixels = 0;
+    for y in 0..h {
+        for x in 0..w {
+            let m = (y * w + x) * bpp;
+            if img_buff[m + alpha_comp_index] != 0 {
+                continue;
             }
-        });
+            let visible_neighbours: Vec<usize> = [
+                (x, y.saturating_sub(1)),
+                (x.saturating_sub(1), y),
+                ((x + 1).min(w - 1), y),
+                (x, (y + 1).min(h - 1)),
+            ]
+            .into_iter()
+            .map(|(ix, iy)| (iy * w + ix) * bpp)
+            .filter(|k| img_buff[k + alpha_comp_index] > alpha_threshold)
+            .collect();
+            if visible_neighbours.is_empty() {
+                continue;
+            }
+            border_pixels += 1;
+            // editors often fill transparent pixels with white, so only colors
+            // similar to a neighbour count
+            let has_neighbour_color = visible_neighbours.iter().any(|&k| {
+                (0..alpha_comp_index)
+                    .map(|c| img_buff[m + c].abs_diff(img_buff[k + c]) as u32)
+                    .sum::<u32>()
+                    <= 48
+            });
+            if has_neighbour_color {
+                colored_pixels += 1;
+            }
+        }
+    }
+    border_pixels > 0 && colored_pixels as f64 / border_pixels as f64 >= DILATED_MIN_COLORED_RATIO
 }
 
 fn copy_color_values(w: usize, h: usize, bpp: usize, src_buffer: &[u8], dest_buffer: &mut [u8]) {
@@ -79,7 +159,11 @@ pub fn dilate_image_sub(
     y: usize,
     sw: usize,
     sh: usize,
+    options: &DilateOptions,
 ) {
+    if !has_alpha(bpp) {
+        return;
+    }
     let [mut buffer_data1, mut buffer_data2] = [
         vec![0; sw * sh * std::mem::size_of::<u8>() * bpp],
         vec![0; sw * sh * std::mem::size_of::<u8>() * bpp],
@@ -103,26 +187,24 @@ pub fn dilate_image_sub(
         bpp,
         buffer_data_original.as_slice(),
         buffer_data1.as_mut_slice(),
-        TW_DILATE_ALPHA_THRESHOLD,
+        options,
     );
 
-    for _i in 0..5 {
-        dilate(
+    for _i in 0..options.iterations * 2 {
+        let changed = dilate(
             sw,
             sh,
             bpp,
             buffer_data1.as_slice(),
             buffer_data2.as_mut_slice(),
-            TW_DILATE_ALPHA_THRESHOLD,
-        );
-        dilate(
-            sw,
-            sh,
-            bpp,
-            buffer_data2.as_slice(),
-            buffer_data1.as_mut_slice(),
-            TW_DILATE_ALPHA_THRESHOLD,
+            options,
         );
+        // the buffers are reused, the result of the pass is always in buffer_data1
+        std::mem::swap(&mut buffer_data1, &mut buffer_data2);
+        // without a filled pixel, every further pass would produce the same image
+        if !changed {
+            break;
+        }
     }
 
     copy_color_values(
@@ -142,6 +224,289 @@ pub fn dilate_image_sub(
     }
 }
 
-pub fn dilate_image(img_buff: &mut [u8], w: usize, h: usize, bpp: usize) {
-    dilate_image_sub(img_buff, w, h, bpp, 0, 0, w, h);
+pub fn dilate_image(img_buff: &mut [u8], w: usize, h: usize, bpp: usize, options: &DilateOptions) {
+    dilate_image_sub(img_buff, w, h, bpp, 0, 0, w, h, options);
+}
+
+/// Dilates an image file of any size, images without alpha channel are converted to rgba.
+/// Returns the png and its dimensions.
+pub fn dilate_file(file: &[u8], options: &DilateOptions) -> anyhow::Result<(Vec<u8>, u32, u32)> {
+    let img = image::load_from_memory(file)?;
+    let (w, h) = (img.width(), img.height());
+    let (mut img_buff, bpp, color_type) = match img {
+        DynamicImage::ImageLumaA8(img) => (img.into_raw(), 2, ColorType::La8),
+        img => (img.into_rgba8().into_raw(), 4, ColorType::Rgba8),
+    };
+    dilate_image(&mut img_buff, w as usize, h as usize, bpp, options);
+
+    let mut png = Vec::new();
+    image::write_buffer_with_format(
+        &mut Cursor::new(&mut png),
+        &img_buff,
+        w,
+        h,
+        color_type,
+        ImageFormat::Png,
+    )?;
+    Ok((png, w, h))
+}
+
+/// Dilates every `<name>.png` of the directory like the client does and compares it with
+/// `<name>.client.png`, the output of the client's dilate. Returns the fixtures that differ,
+/// with the first differing pixel.
+pub fn verify_against_client(dir: &Path) -> anyhow::Result<Vec<String>> {
+    let mut inputs: Vec<_> = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    inputs.retain(|path| {
+        path.extension().is_some_and(|ext| ext == "png")
+            && !path.to_string_lossy().ends_with(".client.png")
+    });
+    inputs.sort();
+    if inputs.is_empty() {
+        anyhow::bail!("{} contains no fixtures", dir.display());
+    }
+    let mut mismatches = Vec::new();
+    for input in inputs {
+        let reference = input.with_extension("client.png");
+        let mut img = image::open(&input)?.into_rgba8();
+        let expected = image::open(&reference)
+            .map_err(|err| anyhow::anyhow!("{}: {err}", reference.display()))?
+            .into_rgba8();
+        let (w, h) = img.dimensions();
+        if expected.dimensions() != (w, h) {
+            mismatches.push(format!(
+                "{}: the reference has another size",
+                input.display()
+            ));
+            continue;
+        }
+        // the client always dilates with the default options
+        dilate_image(
+            &mut img,
+            w as usize,
+            h as usize,
+            4,
+            &DilateOptions::default(),
+        );
+        if let Some(i) = img
+            .chunks_exact(4)
+            .zip(expected.chunks_exact(4))
+            .position(|(px, expected)| px != expected)
+        {
+            mismatches.push(format!(
+                "{}: differs from the client first at {}x{}",
+                input.display(),
+                i % w as usize,
+                i / w as usize
+            ));
+        }
+    }
+    Ok(mismatches)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::time::Instant;
+
+    use super::{
+        copy_color_values, dilate_image, is_dilated, verify_against_client, DilateOptions,
+    };
+
+    /// The sequential dilate from before the passes were parallelized and stopped early, kept
+    /// to compare the output and the speed against.
+    fn reference_dilate(
+        w: usize,
+        h: usize,
+        bpp: usize,
+        src_buff: &[u8],
+        dest_buff: &mut [u8],
+        alpha_threshold: u8,
+    ) {
+        let dirs_x = [0, -1, 1, 0];
+        let dirs_y = [-1, 0, 0, 1];
+
+        let alpha_comp_index = bpp - 1;
+
+        dest_buff
+            .chunks_exact_mut(bpp)
+            .enumerate()
+            .take(w * h)
+            .for_each(|(i, dst)| {
+                let x = i % w;
+                let y = i / w;
+
+                let m = y * w * bpp + x * bpp;
+                dst.copy_from_slice(&src_buff[m..(bpp + m)]);
+                if src_buff[m + alpha_comp_index] > alpha_threshold {
+                    return;
+                }
+
+                dst[0..(bpp - 1)].fill(0);
+
+                for c in 0..4 {
+                    let ix = (x as i64 + dirs_x[c]).clamp(0, w as i64 - 1) as usize;
+                    let iy = (y as i64 + dirs_y[c]).clamp(0, h as i64 - 1) as usize;
+                    let k = iy * w * bpp + ix * bpp;
+                    if src_buff[k + alpha_comp_index] > alpha_threshold {
+                        dst[0..alpha_comp_index]
+                            .copy_from_slice(&src_buff[k..k + alpha_comp_index]);
+                        dst[alpha_comp_index] = 255;
+                        break;
+                    }
+                }
+            });
+    }
+
+    /// `dilate_image` of before, always runs all iterations.
+    fn reference_dilate_image(
+        img_buff: &mut [u8],
+        w: usize,
+        h: usize,
+        bpp: usize,
+        iterations: usize,
+        alpha_threshold: u8,
+    ) {
+        let mut buffer_data1 = vec![0; w * h * bpp];
+        let mut buffer_data2 = vec![0; w * h * bpp];
+        reference_dilate(w, h, bpp, img_buff, &mut buffer_data1, alpha_threshold);
+        for _ in 0..iterations {
+            reference_dilate(w, h, bpp, &buffer_data1, &mut buffer_data2, alpha_threshold);
+            reference_dilate(w, h, bpp, &buffer_data2, &mut buffer_data1, alpha_threshold);
+        }
+        copy_color_values(w, h, bpp, &buffer_data1, img_buff);
+    }
+
+    /// a 512x256 image of random pixels, a third of them transparent and some half transparent
+    fn noise_skin() -> Vec<u8> {
+        let mut state = 0x2545_f491_u32;
+        (0..512 * 256)
+            .flat_map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                let [r, g, b, a] = state.to_le_bytes();
+                [r, g, b, if a < 85 { a / 8 } else { a }]
+            })
+            .collect()
+    }
+
+    /// a 512x256 test skin with a few opaque circles on a transparent background
+    fn test_skin() -> Vec<u8> {
+        let (w, h) = (512, 256);
+        let mut img = vec![0; w * h * 4];
+        for (cx, cy, r) in [(128, 128, 100), (320, 80, 50), (400, 190, 40)] {
+            for y in 0..h {
+                for x in 0..w {
+                    let (dx, dy) = (x as i64 - cx, y as i64 - cy);
+                    if dx * dx + dy * dy <= r * r {
+                        let i = (y * w + x) * 4;
+                        img[i..i + 4].copy_from_slice(&[x as u8, y as u8, 128, 255]);
+                    }
+                }
+            }
+        }
+        img
+    }
+
+    #[test]
+    fn detects_dilated_skin() {
+        let mut skin = test_skin();
+        assert!(!is_dilated(&skin, 512, 256, 4, 10));
+        dilate_image(&mut skin, 512, 256, 4, &DilateOptions::default());
+        assert!(is_dilated(&skin, 512, 256, 4, 10));
+    }
+
+    #[test]
+    fn dilates_any_channel_count() {
+        let options = DilateOptions {
+            radius: 2,
+            iterations: 0,
+            alpha_threshold: 10,
+        };
+        // one opaque pixel in the middle of a 5x1 image
+        for bpp in 1..=4 {
+            let mut img = vec![0; 5 * bpp];
+            img[2 * bpp..3 * bpp].fill(200);
+            let original = img.clone();
+            dilate_image(&mut img, 5, 1, bpp, &options);
+            if bpp == 1 || bpp == 3 {
+                assert_eq!(img, original, "{bpp} bytes per pixel have no alpha");
+                continue;
+            }
+            for x in 0..5 {
+                let pixel = &img[x * bpp..(x + 1) * bpp];
+                // colors are spread up to the radius, the transparent alpha is kept
+                assert!(pixel[..bpp - 1].iter().all(|&c| c == 200), "pixel {x}");
+                assert_eq!(pixel[bpp - 1], if x == 2 { 200 } else { 0 });
+            }
+        }
+    }
+
+    /// the reference outputs are generated with the client's code, see `fixtures/dilate`
+    #[test]
+    fn matches_client_output() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/dilate");
+        let mismatches = verify_against_client(&fixtures).unwrap();
+        assert!(mismatches.is_empty(), "{mismatches:#?}");
+    }
+
+    #[test]
+    fn matches_the_previous_dilate() {
+        let options = DilateOptions::default();
+        for skin in [test_skin(), noise_skin()] {
+            let mut img = skin.clone();
+            dilate_image(&mut img, 512, 256, 4, &options);
+            let mut reference = skin;
+            reference_dilate_image(
+                &mut reference,
+                512,
+                256,
+                4,
+                options.iterations,
+                options.alpha_threshold,
+            );
+            assert!(img == reference);
+        }
+    }
+
+    /// `cargo test --release -- --ignored --nocapture bench_dilate`
+    #[test]
+    #[ignore]
+    fn bench_dilate() {
+        let skins = [("circles", test_skin()), ("noise", noise_skin())];
+        let options = DilateOptions::default();
+        for (name, skin) in &skins {
+            let time = |dilate: &dyn Fn(&mut [u8])| {
+                let start = Instant::now();
+                for _ in 0..30 {
+                    let mut img = skin.clone();
+                    dilate(&mut img);
+                }
+                start.elapsed()
+            };
+            let current = |img: &mut [u8]| dilate_image(img, 512, 256, 4, &options);
+            let previous = time(&|img| {
+                reference_dilate_image(
+                    img,
+                    512,
+                    256,
+                    4,
+                    options.iterations,
+                    options.alpha_threshold,
+                )
+            });
+            let single_thread = rayon::ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build()
+                .unwrap()
+                .install(|| time(&current));
+            let parallel = time(&current);
+            println!(
+                "30 {name} skins 512x256: previous {previous:?}, single thread {single_thread:?}, {} threads {parallel:?}",
+                rayon::current_num_threads()
+            );
+        }
+    }
 }
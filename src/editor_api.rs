@@ -0,0 +1,593 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use hyper::body::HttpBody;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, HeaderMap, Method, Request, Response, Server, StatusCode};
+use serde::Serialize;
+use serenity::all::{
+    ChannelId, CreateAllowedMentions, CreateAttachment, CreateMessage, Http, Timestamp,
+};
+use serenity::prelude::*;
+use tracing::{error, info, info_span, warn};
+
+use crate::announce::fill_template;
+use crate::audit::AuditLog;
+use crate::batches::{BatchHistory, BatchRecord, BatchSkin, EDITOR_UPLOADER};
+use crate::blocklist::Blocklist;
+use crate::config::{Config, WatermarkPolicyKind};
+use crate::db_upload::{process_and_upload, SkinDatabase, SkinForm};
+use crate::license::LicenseRules;
+use crate::metrics::Metrics;
+use crate::naming::name_key;
+use crate::plugins::PluginSkinInfo;
+use crate::resample::{downscale_hd, render_png, SdResampler};
+use crate::session::{blocklist_refusal, CheckedFile, FileChecks, SkinName};
+use crate::skin::SkinFileError;
+use crate::tee::{render_tee, TEE_RENDERER_VERSION};
+use crate::watermark::render_watermark_preview;
+use crate::{SkinToUploadDB, SkinUploads};
+
+/// a 512x256 png is far below it, even without compression
+const MAX_BODY_BYTES: usize = 4 * 1024 * 1024;
+/// the uploads of the editor in the audit log
+const AUDIT_ACTOR: &str = "editor";
+
+/// A skin sent by the web skin editor, the metadata is in the query of the request.
+pub struct EditorSubmission {
+    pub name: String,
+    pub author: String,
+    pub license: String,
+    pub database: SkinToUploadDB,
+    pub skin_pack: Option<String>,
+    pub png: Vec<u8>,
+}
+
+/// The json answer of the endpoint.
+#[derive(Debug, Default, Serialize)]
+pub struct SubmissionAnswer {
+    /// the name the skin was uploaded as, it can differ from the submitted one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub warnings: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+impl SubmissionAnswer {
+    fn error(err: String) -> Self {
+        Self {
+            errors: vec![err],
+            ..Self::default()
+        }
+    }
+}
+
+/// `name`, `author`, `license` and `database` (`normal` or `community`) are required,
+/// `skin_pack` is optional.
+fn parse_submission(query: Option<&str>, png: Vec<u8>) -> Result<EditorSubmission, String> {
+    let url = reqwest::Url::parse(&format!("http://editor/?{}", query.unwrap_or_default()))
+        .map_err(|err| format!("invalid query: {err}"))?;
+    let field = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    let required = |name: &str| field(name).ok_or_else(|| format!("`{name}` is missing"));
+    let database = match required("database")?.as_str() {
+        "normal" => SkinToUploadDB::Normal,
+        "community" => SkinToUploadDB::Community,
+        database => return Err(format!("unknown database `{database}`")),
+    };
+    Ok(EditorSubmission {
+        name: required("name")?,
+        author: required("author")?,
+        license: required("license")?,
+        database,
+        skin_pack: field("skin_pack"),
+        png,
+    })
+}
+
+/// Why the blocklist refuses the skin, the editor has no discord user to match.
+fn blocked(blocklist: &Blocklist, skin_name: &SkinName, author: &str) -> Option<String> {
+    blocklist_refusal(blocklist, skin_name, author, None).map(|err| format!("the skin {err}"))
+}
+
+/// Checks the skin like a queued submission and uploads it, the upload waits for the upload
+/// of a moderator that is running. The skin is announced with a preview of the tee and recorded
+/// as a batch of its own, so it shows up in `/batches` and can be undone.
+pub async fn submit_skin(
+    data: &RwLock<TypeMap>,
+    http: &Http,
+    submission: EditorSubmission,
+) -> (StatusCode, SubmissionAnswer) {
+    let (config, database_client, audit_log, batch_history, metrics, upload_lock) = {
+        let data = data.read().await;
+        let uploads = data.get::<SkinUploads>().unwrap();
+        if uploads.shutting_down {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                SubmissionAnswer::error("the bot is shutting down, try again later".to_string()),
+            );
+        }
+        (
+            data.get::<Config>().unwrap().clone(),
+            data.get::<SkinDatabase>().unwrap().clone(),
+            data.get::<AuditLog>().unwrap().clone(),
+            data.get::<BatchHistory>().unwrap().clone(),
+            data.get::<Metrics>().unwrap().clone(),
+            uploads.upload_lock.clone(),
+        )
+    };
+    let EditorSubmission {
+        name,
+        author,
+        license,
+        database,
+        skin_pack,
+        png,
+    } = submission;
+
+    let mut answer = SubmissionAnswer::default();
+    let skin_name = match SkinName::normalize(&name) {
+        Ok(skin_name) => skin_name,
+        Err(err) => {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                SubmissionAnswer::error(format!("the skin {err}")),
+            )
+        }
+    };
+    if let Some(original) = &skin_name.original {
+        answer
+            .warnings
+            .push(format!("transliterated from `{original}`"));
+    }
+    if let Some(renamed_from) = &skin_name.renamed_from {
+        answer
+            .warnings
+            .push(format!("renamed from `{renamed_from}`"));
+    }
+    let blocked = blocked(
+        data.read().await.get::<Blocklist>().unwrap(),
+        &skin_name,
+        &author,
+    );
+    if let Some(err) = blocked {
+        return (StatusCode::FORBIDDEN, SubmissionAnswer::error(err));
+    }
+    let name = skin_name.name.clone();
+    if let Some(conflict) = name_taken(&audit_log, &name, database) {
+        return (StatusCode::CONFLICT, conflict);
+    }
+
+    let license_rules = LicenseRules::new(&config.license);
+    let license_policy = license_rules.at(Timestamp::now());
+    let license = match license_policy.validate(&license) {
+        Ok(license) => license,
+        Err(license) if license_policy.rejects_unknown() => {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                SubmissionAnswer::error(format!("the license `{license}` is not allowed")),
+            );
+        }
+        Err(license) => {
+            answer
+                .warnings
+                .push(format!("the license `{license}` is not in the allow-list"));
+            license
+        }
+    };
+
+    let checks = FileChecks::new(data, &config, database).await;
+    let watermark_policy = checks.watermark_policy;
+    let plugin_info = PluginSkinInfo {
+        name: name.clone(),
+        author: author.clone(),
+        license: license.clone(),
+        database: database.to_string(),
+    };
+    let checked = tokio::task::spawn_blocking(move || checks.check(&png, &plugin_info))
+        .await
+        .unwrap();
+    let CheckedFile {
+        skin,
+        plugin_findings,
+        watermarks,
+        content_flags,
+    } = match checked {
+        Ok(checked) => checked,
+        Err(SkinFileError::Download | SkinFileError::Decode) => {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                SubmissionAnswer::error("the file is not a valid image".to_string()),
+            );
+        }
+        Err(SkinFileError::Size) => {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                SubmissionAnswer::error("the file is not a 256x128 or 512x256 skin".to_string()),
+            );
+        }
+    };
+    answer.errors.extend(
+        plugin_findings
+            .errors
+            .into_iter()
+            .map(|err| format!("rejected by {err}")),
+    );
+    answer.warnings.extend(plugin_findings.warnings);
+    if !watermarks.is_empty() {
+        if watermark_policy == WatermarkPolicyKind::Block {
+            answer.errors.push(
+                "looks like it has a signature or watermark outside the tee, which the database does not allow"
+                    .to_string(),
+            );
+        } else {
+            answer
+                .warnings
+                .push("possible signature or watermark".to_string());
+        }
+    }
+    // a moderator has to acknowledge flagged skins, there is none in the editor
+    answer.errors.extend(
+        content_flags
+            .iter()
+            .map(|flag| format!("{flag}, a moderator has to upload it")),
+    );
+    if !answer.errors.is_empty() {
+        return (StatusCode::UNPROCESSABLE_ENTITY, answer);
+    }
+
+    let (w, h) = (skin.width as usize, skin.width as usize / 2);
+    let sd_img = if w == 512 {
+        downscale_hd(&skin.img, SdResampler::Lanczos)
+    } else {
+        skin.img.clone()
+    };
+    let mut files = vec![("256x128", false, sd_img.clone())];
+    if w == 512 {
+        files.push(("512x256", true, skin.img.clone()));
+    }
+
+    let dilate_options = config.dilate.options();
+    let apply_cleanup = config.upload.cleanup_stray_pixels;
+    let normalize_body = config.upload.normalize_body_alpha;
+    let optimize = config.png_optimization.enabled(database);
+    let _g = upload_lock.lock().await;
+    // checked again, another submission of the name could have been uploaded meanwhile
+    if let Some(conflict) = name_taken(&audit_log, &name, database) {
+        return (StatusCode::CONFLICT, conflict);
+    }
+    let mut uploaded_sizes = Vec::new();
+    for (size, uhd, img) in files {
+        let form = SkinForm {
+            name: name.clone(),
+            author: author.clone(),
+            license: license.clone(),
+            skin_pack: skin_pack
+                .clone()
+                .or_else(|| skin_name.original.clone())
+                .unwrap_or_default(),
+            database: database.to_string(),
+            uhd,
+        };
+        let database_client = database_client.clone();
+        let span = info_span!("editor_upload", skin = %name);
+        let upload = tokio::task::spawn_blocking(move || {
+            let _span = span.entered();
+            process_and_upload(
+                &*database_client,
+                img,
+                &form,
+                apply_cleanup,
+                &dilate_options,
+                normalize_body,
+                optimize,
+            )
+        })
+        .await
+        .unwrap();
+        metrics.record_upload(upload.result.is_ok());
+        if !upload.already_dilated {
+            metrics.record_dilate(upload.dilate_time);
+        }
+        audit_log.record(
+            AUDIT_ACTOR,
+            &format!("upload {size} {}", database.to_string()),
+            &name,
+            &upload.result,
+        );
+        if upload.body_alpha_fixed > 0 {
+            answer.warnings.push(format!(
+                "{} semi-transparent body pixels of the {size} skin were made opaque",
+                upload.body_alpha_fixed
+            ));
+        }
+        match upload.result {
+            Ok(()) => uploaded_sizes.push(size.to_string()),
+            Err(err) => answer
+                .errors
+                .push(format!("uploading the {size} skin failed: {err}")),
+        }
+    }
+
+    let mut announcements = Vec::new();
+    let channel_id = match database {
+        SkinToUploadDB::Normal => config.announcements.normal_channel_id,
+        SkinToUploadDB::Community => config.announcements.community_channel_id,
+    };
+    if let Some(channel_id) = channel_id
+        .map(ChannelId::new)
+        .filter(|_| answer.errors.is_empty())
+    {
+        let mut content = fill_template(
+            &config.templates.editor_announcement,
+            &[
+                ("name", &name),
+                ("author", &author),
+                ("license", &license),
+                ("database", config.templates.database(database)),
+            ],
+        );
+        for warning in &answer.warnings {
+            content += &format!("\n- {warning}");
+        }
+        // the author is only a name, nothing of the submission may ping
+        let mut message = CreateMessage::new()
+            .allowed_mentions(CreateAllowedMentions::new())
+            .content(content);
+        if let Ok(tee) = render_tee(&sd_img, 256) {
+            message = message.add_file(CreateAttachment::bytes(tee, format!("{name}_tee.png")));
+            // `/batches rerender` draws the tee again from it
+            if let Ok(png) = render_png(&sd_img, 256, 128) {
+                message = message.add_file(CreateAttachment::bytes(png, format!("{name}.png")));
+            }
+        }
+        if !watermarks.is_empty() {
+            if let Ok(preview) = render_watermark_preview(&skin.img, w, h, &watermarks) {
+                message = message.add_file(CreateAttachment::bytes(
+                    preview,
+                    format!("{name}_{w}x{h}_watermark.png"),
+                ));
+            }
+        }
+        match channel_id.send_message(http, message).await {
+            Ok(msg) => announcements.push(msg.link()),
+            Err(err) => warn!("Could not announce the skin {name} of the editor: {err}"),
+        }
+    }
+
+    let preview_renderer = (!announcements.is_empty()).then_some(TEE_RENDERER_VERSION);
+    // like the batches of the moderators, under the upload lock two can't get the same label
+    batch_history.record(&BatchRecord {
+        label: batch_history.new_label(None, Timestamp::now()),
+        uploader: EDITOR_UPLOADER,
+        finished_at: Timestamp::now().unix_timestamp(),
+        skins: vec![BatchSkin {
+            name: name.clone(),
+            database: database.to_string(),
+            uploaded: answer.errors.is_empty(),
+            error: answer.errors.first().cloned(),
+            submission: String::new(),
+            replaced: false,
+            author: Some(author),
+            positive_ratio: None,
+            original_name: skin_name.original.clone(),
+            uploaded_sizes,
+        }],
+        announcements,
+        undone_at: None,
+        preview_renderer,
+    });
+    if !answer.errors.is_empty() {
+        return (StatusCode::BAD_GATEWAY, answer);
+    }
+    answer.name = Some(name);
+    (StatusCode::OK, answer)
+}
+
+/// Refuses a name that is already in the database, replacing a skin has to be confirmed by
+/// a moderator.
+fn name_taken(
+    audit_log: &AuditLog,
+    name: &str,
+    database: SkinToUploadDB,
+) -> Option<SubmissionAnswer> {
+    audit_log
+        .uploaded_skins()
+        .contains_key(&(name_key(name), database.to_string()))
+        .then(|| {
+            SubmissionAnswer::error(format!(
+                "a skin named `{name}` is already in the {} database",
+                database.to_string()
+            ))
+        })
+}
+
+/// Compares in constant time, so the token can't be guessed from the response times.
+fn is_authorized(headers: &HeaderMap, token: &str) -> bool {
+    let Some(sent) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    sent.len() == token.len()
+        && sent
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn read_body(mut body: Body) -> Result<Vec<u8>, StatusCode> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|_| StatusCode::BAD_REQUEST)?;
+        if bytes.len() + chunk.len() > MAX_BODY_BYTES {
+            return Err(StatusCode::PAYLOAD_TOO_LARGE);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+fn response(status: StatusCode, answer: &SubmissionAnswer) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            serde_json::to_string(answer).unwrap_or_default(),
+        ))
+        .unwrap()
+}
+
+async fn respond(
+    request: Request<Body>,
+    token: Arc<str>,
+    data: Arc<RwLock<TypeMap>>,
+    http: Arc<Http>,
+) -> Result<Response<Body>, Infallible> {
+    if request.uri().path() != "/skins" {
+        return Ok(response(
+            StatusCode::NOT_FOUND,
+            &SubmissionAnswer::error("only `/skins` exists".to_string()),
+        ));
+    }
+    if request.method() != Method::POST {
+        return Ok(response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            &SubmissionAnswer::error("skins are submitted with POST".to_string()),
+        ));
+    }
+    if !is_authorized(request.headers(), &token) {
+        return Ok(response(
+            StatusCode::UNAUTHORIZED,
+            &SubmissionAnswer::error("the bearer token is missing or wrong".to_string()),
+        ));
+    }
+    let query = request.uri().query().map(str::to_string);
+    let png = match read_body(request.into_body()).await {
+        Ok(png) => png,
+        Err(status) => {
+            return Ok(response(
+                status,
+                &SubmissionAnswer::error(format!(
+                    "the body must be a png of at most {MAX_BODY_BYTES} bytes"
+                )),
+            ))
+        }
+    };
+    let submission = match parse_submission(query.as_deref(), png) {
+        Ok(submission) => submission,
+        Err(err) => {
+            return Ok(response(
+                StatusCode::BAD_REQUEST,
+                &SubmissionAnswer::error(err),
+            ))
+        }
+    };
+    let (status, answer) = submit_skin(&data, &http, submission).await;
+    Ok(response(status, &answer))
+}
+
+/// Serves `POST /skins` for the web skin editor until the bot stops.
+pub async fn serve_editor_api(
+    addr: SocketAddr,
+    token: String,
+    data: Arc<RwLock<TypeMap>>,
+    http: Arc<Http>,
+) {
+    let token: Arc<str> = token.into();
+    let make_service = make_service_fn(move |_| {
+        let token = token.clone();
+        let data = data.clone();
+        let http = http.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                respond(request, token.clone(), data.clone(), http.clone())
+            }))
+        }
+    });
+    let server = match Server::try_bind(&addr) {
+        Ok(builder) => builder.serve(make_service),
+        Err(err) => {
+            error!("Could not listen on {addr} for the skin editor: {err}");
+            return;
+        }
+    };
+    info!("Accepting skins of the editor on {addr}");
+    if let Err(err) = server.await {
+        error!("The skin editor endpoint failed: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{header, HeaderMap};
+
+    use super::{blocked, is_authorized, parse_submission};
+    use crate::blocklist::{BlockEntry, BlockKind, Blocklist};
+    use crate::session::SkinName;
+    use crate::SkinToUploadDB;
+
+    #[test]
+    fn checks_the_request() {
+        let mut headers = HeaderMap::new();
+        assert!(!is_authorized(&headers, "secret"));
+        headers.insert(header::AUTHORIZATION, "Bearer secreT".parse().unwrap());
+        assert!(!is_authorized(&headers, "secret"));
+        headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        assert!(is_authorized(&headers, "secret"));
+
+        let submission = parse_submission(
+            Some("name=grey%20fox&author=artist&license=CC0&database=community"),
+            Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(submission.name, "grey fox");
+        assert_eq!(submission.database, SkinToUploadDB::Community);
+        assert_eq!(submission.skin_pack, None);
+        assert_eq!(
+            parse_submission(Some("name=fox&author=artist&license=CC0"), Vec::new())
+                .err()
+                .unwrap(),
+            "`database` is missing"
+        );
+    }
+
+    #[test]
+    fn refuses_blocked_skins() {
+        let path = std::env::temp_dir().join("ddnet_discord_editor_blocklist_test.json");
+        let _ = std::fs::remove_file(&path);
+        let mut blocklist = Blocklist::load(path.to_string_lossy().into_owned()).unwrap();
+        for (kind, value) in [
+            (BlockKind::Author, "Thief"),
+            (BlockKind::Name, "free robux"),
+        ] {
+            blocklist
+                .add(BlockEntry {
+                    kind,
+                    value: value.to_string(),
+                    reason: "stolen art".to_string(),
+                    added_by: 1,
+                    added_at: 0,
+                })
+                .unwrap();
+        }
+        let name = |name: &str| SkinName::normalize(name).unwrap();
+
+        assert!(blocked(&blocklist, &name("greyfox"), "artist").is_none());
+        assert_eq!(
+            blocked(&blocklist, &name("greyfox"), "thief").as_deref(),
+            Some("the skin is blocked, the author `Thief` is on the blocklist: stolen art")
+        );
+        // names ignore the case and repeated spaces
+        assert!(blocked(&blocklist, &name("Free  Robux"), "artist").is_some());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,106 @@
+use serenity::all::{Reaction, ReactionType};
+
+use crate::config::EmojiConfig;
+use crate::SkinToUploadDB;
+
+/// Emojis that are drawn the same, but have different code points.
+const EQUIVALENT_EMOJIS: [(char, char); 2] = [
+    // ballot box with bold check
+    ('\u{1F5F9}', '☑'),
+    // light check mark
+    ('\u{1F5F8}', '✓'),
+];
+
+/// Clients differ in sending the variation selectors, which only change how
+/// an emoji is drawn, so they are removed before comparing.
+fn normalize(emoji: &str) -> String {
+    emoji
+        .chars()
+        .filter(|c| !matches!(c, '\u{FE0E}' | '\u{FE0F}'))
+        .map(|c| {
+            EQUIVALENT_EMOJIS
+                .iter()
+                .find(|(variant, _)| *variant == c)
+                .map_or(c, |(_, emoji)| *emoji)
+        })
+        .collect()
+}
+
+/// Whether the unicode emoji or custom emoji id is one of the emojis.
+pub fn emojis_match(emoji: &str, emojis: &[String]) -> bool {
+    let emoji = normalize(emoji);
+    emojis.iter().any(|other| normalize(other) == emoji)
+}
+
+/// Whether the reaction is one of the emojis,
+/// entries that are a number are compared to the id of custom emojis.
+pub fn reaction_matches(reaction: &ReactionType, emojis: &[String]) -> bool {
+    match reaction {
+        ReactionType::Unicode(name) => emojis_match(name, emojis),
+        ReactionType::Custom { id, .. } => emojis_match(&id.to_string(), emojis),
+        _ => false,
+    }
+}
+
+impl EmojiConfig {
+    /// The database a reaction queues a skin for.
+    pub fn queue_database(&self, reaction: &Reaction) -> Option<SkinToUploadDB> {
+        self.queue_database_of(&reaction.emoji)
+    }
+
+    /// Like `queue_database`, for the reactions of a fetched message.
+    pub fn queue_database_of(&self, emoji: &ReactionType) -> Option<SkinToUploadDB> {
+        if reaction_matches(emoji, &self.queue_normal) {
+            Some(SkinToUploadDB::Normal)
+        } else if reaction_matches(emoji, &self.queue_community) {
+            Some(SkinToUploadDB::Community)
+        } else {
+            None
+        }
+    }
+
+    pub fn queue_emojis(&self, database: SkinToUploadDB) -> &[String] {
+        match database {
+            SkinToUploadDB::Normal => &self.queue_normal,
+            SkinToUploadDB::Community => &self.queue_community,
+        }
+    }
+
+    /// The first queue emoji of the database, formatted for a message.
+    pub fn queue_label(&self, database: SkinToUploadDB) -> String {
+        let emoji = self
+            .queue_emojis(database)
+            .first()
+            .map_or("", String::as_str);
+        if emoji.parse::<u64>().is_ok() {
+            format!("<:queue:{emoji}>")
+        } else {
+            emoji.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_variation_selectors() {
+        let emojis = ["☑️".to_string()];
+        assert!(emojis_match("☑", &emojis));
+        assert!(emojis_match("☑\u{FE0E}", &emojis));
+        assert!(emojis_match("\u{1F5F9}", &emojis));
+        assert!(!emojis_match("✅", &emojis));
+
+        let custom = ReactionType::Custom {
+            animated: false,
+            id: 346683497701834762.into(),
+            name: None,
+        };
+        assert!(reaction_matches(
+            &custom,
+            &["346683497701834762".to_string()]
+        ));
+        assert!(!reaction_matches(&custom, &emojis));
+    }
+}
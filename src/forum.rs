@@ -0,0 +1,135 @@
+use serenity::all::{
+    CacheHttp, Channel, ChannelId, ChannelType, ForumTagId, GuildChannel, Message, MessageId,
+};
+use serenity::builder::EditThread;
+use serenity::prelude::*;
+
+use crate::config::{Config, ForumConfig};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForumDecision {
+    UploadedNormal,
+    UploadedCommunity,
+    Rejected,
+}
+
+impl ForumConfig {
+    fn tag(&self, decision: ForumDecision) -> Option<ForumTagId> {
+        match decision {
+            ForumDecision::UploadedNormal => self.uploaded_normal_tag,
+            ForumDecision::UploadedCommunity => self.uploaded_community_tag,
+            ForumDecision::Rejected => self.rejected_tag,
+        }
+        .map(ForumTagId::new)
+    }
+}
+
+/// media channels are forums for attachments, serenity has no variant for them yet
+const MEDIA_CHANNEL_TYPE: u8 = 16;
+
+fn has_posts(kind: ChannelType) -> bool {
+    kind == ChannelType::Forum || is_media_channel(kind)
+}
+
+pub fn is_media_channel(kind: ChannelType) -> bool {
+    u8::from(kind) == MEDIA_CHANNEL_TYPE
+}
+
+/// The post and its forum or media channel, if the message starts a post.
+pub async fn post_of(ctx: &Context, msg: &Message) -> Option<(GuildChannel, GuildChannel)> {
+    // the starter message of a post shares the id of the thread
+    if msg.id.get() != msg.channel_id.get() {
+        return None;
+    }
+    let post = msg.channel_id.to_channel(ctx).await.ok()?.guild()?;
+    let parent = post.parent_id?.to_channel(ctx).await.ok()?.guild()?;
+    has_posts(parent.kind).then_some((post, parent))
+}
+
+/// If the channel is a post in a forum or media channel, returns the id of its starter message,
+/// which contains the submission.
+pub async fn forum_post_starter(ctx: &Context, channel_id: ChannelId) -> Option<MessageId> {
+    let Ok(Channel::Guild(channel)) = channel_id.to_channel(ctx).await else {
+        return None;
+    };
+    if channel.kind != ChannelType::PublicThread {
+        return None;
+    }
+    let parent = channel.parent_id?.to_channel(ctx).await.ok()?.guild()?;
+    // the starter message of a post shares the id of the thread
+    has_posts(parent.kind).then(|| MessageId::new(channel.id.get()))
+}
+
+/// The forum or media channel, if the channel is one or a post in one.
+pub async fn forum_of(cache_http: impl CacheHttp, channel_id: ChannelId) -> Option<GuildChannel> {
+    let channel = channel_id.to_channel(&cache_http).await.ok()?.guild()?;
+    match channel.kind {
+        kind if has_posts(kind) => Some(channel),
+        ChannelType::PublicThread => {
+            let parent = channel
+                .parent_id?
+                .to_channel(&cache_http)
+                .await
+                .ok()?
+                .guild()?;
+            has_posts(parent.kind).then_some(parent)
+        }
+        _ => None,
+    }
+}
+
+/// Whether skins can be collected from the channel,
+/// threads and forum posts count as the channel they were created in.
+pub async fn is_upload_channel(ctx: &Context, config: &Config, channel_id: ChannelId) -> bool {
+    if config.is_channel_allowed(channel_id) {
+        return true;
+    }
+    let Ok(Channel::Guild(channel)) = channel_id.to_channel(ctx).await else {
+        return false;
+    };
+    channel.thread_metadata.is_some()
+        && channel
+            .parent_id
+            .is_some_and(|parent_id| config.is_channel_allowed(parent_id))
+}
+
+/// Applies the configured tag of the decision to the forum post,
+/// tags of previous decisions are removed. Does nothing for normal channels.
+pub async fn tag_forum_post(
+    ctx: &Context,
+    thread_id: ChannelId,
+    decision: ForumDecision,
+    config: &ForumConfig,
+) -> anyhow::Result<()> {
+    let Some(tag) = config.tag(decision) else {
+        return Ok(());
+    };
+    if forum_post_starter(ctx, thread_id).await.is_none() {
+        return Ok(());
+    }
+    let Channel::Guild(thread) = thread_id.to_channel(ctx).await? else {
+        return Ok(());
+    };
+
+    let decision_tags: Vec<ForumTagId> = [
+        ForumDecision::UploadedNormal,
+        ForumDecision::UploadedCommunity,
+        ForumDecision::Rejected,
+    ]
+    .into_iter()
+    .filter_map(|decision| config.tag(decision))
+    .collect();
+    let mut tags: Vec<ForumTagId> = thread
+        .applied_tags
+        .into_iter()
+        .filter(|applied_tag| !decision_tags.contains(applied_tag))
+        .collect();
+    // discord allows at most 5 tags per post
+    tags.truncate(4);
+    tags.push(tag);
+
+    thread_id
+        .edit_thread(ctx, EditThread::new().applied_tags(tags))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,937 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use serenity::all::{ClientBuilder, GatewayIntents, Http, HttpBuilder, UserId};
+use serenity::prelude::{RwLock, TypeMap};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::select;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+use crate::blocklist::Blocklist;
+use crate::config::{AnnouncementConfig, Config, MaintenanceConfig, UploadConfig};
+use crate::db_upload::{HttpDatabaseClient, SkinDatabaseClient};
+use crate::plugins::ValidationPlugins;
+use crate::safety::DefaultSkins;
+use crate::{insert_client_data, Handler, SkinUploads};
+
+pub const GUILD_ID: u64 = 100;
+pub const ROLE_ID: u64 = 200;
+pub const BOT_ID: u64 = 1;
+pub const MODERATOR_ID: u64 = 300;
+pub const ARTIST_ID: u64 = 301;
+pub const CHANNEL_ID: u64 = 400;
+
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub body: String,
+}
+
+#[derive(Default)]
+struct State {
+    requests: Vec<Request>,
+    /// messages the bot can fetch, by id
+    messages: HashMap<u64, Value>,
+    /// files served as attachments, by path
+    files: HashMap<String, Vec<u8>>,
+    /// the maintenance flag of the website
+    maintenance: bool,
+}
+
+/// Stands in for discord while the handler runs: a http server answers the REST calls
+/// (and those to the skin database), a websocket gateway dispatches synthesized events.
+pub struct FakeDiscord {
+    state: Arc<StdMutex<State>>,
+    events: mpsc::UnboundedSender<(String, Value)>,
+    http_url: String,
+    next_id: AtomicU64,
+    /// the data of the bot, to see when an upload session exists
+    pub data: Arc<RwLock<TypeMap>>,
+    /// the rest client of the bot, for the parts that don't start with an event
+    pub http: Arc<Http>,
+}
+
+fn user_json(id: u64) -> Value {
+    json!({
+        "id": id.to_string(),
+        "username": format!("user{id}"),
+        "discriminator": "0",
+        "global_name": null,
+        "avatar": null,
+        "bot": id == BOT_ID,
+    })
+}
+
+fn member_json(id: u64) -> Value {
+    json!({
+        "user": user_json(id),
+        "roles": if id == MODERATOR_ID { vec![ROLE_ID.to_string()] } else { vec![] },
+        "joined_at": "2024-01-01T00:00:00+00:00",
+        "deaf": false,
+        "mute": false,
+        "flags": 0,
+    })
+}
+
+fn message_json(id: u64, author_id: u64, content: &str, attachments: Value) -> Value {
+    json!({
+        "id": id.to_string(),
+        "channel_id": CHANNEL_ID.to_string(),
+        "guild_id": GUILD_ID.to_string(),
+        "author": user_json(author_id),
+        "content": content,
+        "timestamp": "2024-01-01T00:00:00+00:00",
+        "edited_timestamp": null,
+        "tts": false,
+        "mention_everyone": false,
+        "mentions": [],
+        "mention_roles": [],
+        "attachments": attachments,
+        "embeds": [],
+        "reactions": [],
+        "pinned": false,
+        "type": 0,
+    })
+}
+
+impl FakeDiscord {
+    /// Starts the fake discord and the bot connected to it, returns once the bot is ready.
+    /// The skins are uploaded to the fake database of the http server.
+    pub async fn start(timeout_secs: u64) -> Self {
+        Self::start_with_database(timeout_secs, None).await
+    }
+
+    /// Like `start`, but the bot talks to the given database client instead of the http server.
+    pub async fn start_with_database(
+        timeout_secs: u64,
+        database: Option<Arc<dyn SkinDatabaseClient>>,
+    ) -> Self {
+        let http_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let gateway_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let http_url = format!("http://{}", http_listener.local_addr().unwrap());
+        let gateway_url = format!("ws://{}", gateway_listener.local_addr().unwrap());
+
+        let state: Arc<StdMutex<State>> = Arc::default();
+        let http_state = state.clone();
+        let http_gateway_url = gateway_url.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = http_listener.accept().await {
+                tokio::spawn(serve_http(
+                    stream,
+                    http_state.clone(),
+                    http_gateway_url.clone(),
+                ));
+            }
+        });
+
+        let (events, events_rx) = mpsc::unbounded_channel();
+        tokio::spawn(serve_gateway(gateway_listener, events_rx, gateway_url));
+
+        let config = Config {
+            discord_token: "token".to_string(),
+            guild_id: GUILD_ID,
+            role_id: ROLE_ID,
+            database_url: format!("{http_url}/skins/"),
+            username: "user".to_string(),
+            password: "password".to_string(),
+            upload: UploadConfig {
+                timeout_secs,
+                expiry_warning_secs: timeout_secs / 2,
+                // keeps the working directory clean
+                audit_log_file: std::env::temp_dir()
+                    .join("ddnet_discord_harness_audit.log")
+                    .to_string_lossy()
+                    .into_owned(),
+                batch_history_file: std::env::temp_dir()
+                    .join("ddnet_discord_harness_batches.jsonl")
+                    .to_string_lossy()
+                    .into_owned(),
+                ..UploadConfig::default()
+            },
+            maintenance: MaintenanceConfig {
+                flag_url: Some(format!("{http_url}/maintenance")),
+                poll_secs: 1,
+                ..MaintenanceConfig::default()
+            },
+            announcements: AnnouncementConfig {
+                normal_channel_id: Some(CHANNEL_ID),
+                community_channel_id: Some(CHANNEL_ID),
+                ..AnnouncementConfig::default()
+            },
+            ..Config::default()
+        };
+        let http = HttpBuilder::new(&config.discord_token)
+            .proxy(http_url.clone())
+            .ratelimiter_disabled(true)
+            .build();
+        let intents = GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT;
+        let mut client = ClientBuilder::new_with_http(http, intents)
+            .event_handler(Handler)
+            .await
+            .unwrap();
+        let database = database.unwrap_or_else(|| Arc::new(HttpDatabaseClient::new(&config)));
+        let blocklist = Blocklist::load(config.upload.blocklist_file.clone()).unwrap();
+        insert_client_data(
+            &client,
+            config,
+            ValidationPlugins::default(),
+            DefaultSkins::default(),
+            blocklist,
+            database,
+        )
+        .await;
+        let data = client.data.clone();
+        let http = client.http.clone();
+        tokio::spawn(async move { client.start().await });
+
+        let fake = Self {
+            state,
+            events,
+            http_url,
+            next_id: AtomicU64::new(1000),
+            data,
+            http,
+        };
+        // the commands are registered once the bot is ready
+        fake.wait_for_request("PUT", "/commands", "").await;
+        fake
+    }
+
+    fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Waits until the bot sent a matching request, panics after a few seconds.
+    pub async fn wait_for_request(&self, method: &str, path: &str, body: &str) -> Request {
+        for _ in 0..500 {
+            if let Some(request) = self.requests().into_iter().find(|request| {
+                request.method == method
+                    && request.path.contains(path)
+                    && request.body.contains(body)
+            }) {
+                return request;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!(
+            "no {method} request to {path} containing {body:?}, got: {:#?}",
+            self.requests()
+        );
+    }
+
+    /// The moderator starts an upload, returns once the session accepts skins.
+    /// The session is only stored after the bot's response, so waiting for the response
+    /// alone would race with the following events.
+    pub async fn start_upload(&self) {
+        self.command("upload");
+        self.wait_for_request("POST", "/callback", "You are about to upload skins")
+            .await;
+        for _ in 0..500 {
+            if self
+                .data
+                .read()
+                .await
+                .get::<SkinUploads>()
+                .unwrap()
+                .uploads
+                .contains_key(&UserId::new(MODERATOR_ID))
+            {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("the upload session was never started");
+    }
+
+    pub fn set_maintenance(&self, maintenance: bool) {
+        self.state.lock().unwrap().maintenance = maintenance;
+    }
+
+    /// Posts a submission of the artist, the skin file is optional.
+    pub fn post_submission(&self, content: &str, skin: Option<Vec<u8>>) -> u64 {
+        let id = self.next_id();
+        let mut state = self.state.lock().unwrap();
+        let attachments = if let Some(skin) = skin {
+            let path = format!("/attachments/{id}.png");
+            let attachment = json!([{
+                "id": id.to_string(),
+                "filename": format!("{id}.png"),
+                "size": skin.len(),
+                "url": format!("{}{path}", self.http_url),
+                "proxy_url": format!("{}{path}", self.http_url),
+                "width": 256,
+                "height": 128,
+                "content_type": "image/png",
+            }]);
+            state.files.insert(path, skin);
+            attachment
+        } else {
+            json!([])
+        };
+        state
+            .messages
+            .insert(id, message_json(id, ARTIST_ID, content, attachments));
+        id
+    }
+
+    fn dispatch(&self, event: &str, data: Value) {
+        self.events.send((event.to_string(), data)).unwrap();
+    }
+
+    /// The moderator uses a slash command.
+    pub fn command(&self, name: &str) {
+        let id = self.next_id();
+        self.dispatch(
+            "INTERACTION_CREATE",
+            json!({
+                "id": id.to_string(),
+                "application_id": BOT_ID.to_string(),
+                "type": 2,
+                "data": { "id": id.to_string(), "name": name, "type": 1 },
+                "guild_id": GUILD_ID.to_string(),
+                "channel_id": CHANNEL_ID.to_string(),
+                "member": member_json(MODERATOR_ID),
+                "token": format!("token{id}"),
+                "version": 1,
+                "app_permissions": "0",
+                "locale": "en-US",
+                "guild_locale": "en-US",
+                "entitlements": [],
+            }),
+        );
+    }
+
+    /// The moderator presses a button of the upload message.
+    pub fn button(&self, custom_id: &str) {
+        let id = self.next_id();
+        self.dispatch(
+            "INTERACTION_CREATE",
+            json!({
+                "id": id.to_string(),
+                "application_id": BOT_ID.to_string(),
+                "type": 3,
+                "data": { "custom_id": custom_id, "component_type": 2 },
+                "message": message_json(self.next_id(), BOT_ID, "", json!([])),
+                "guild_id": GUILD_ID.to_string(),
+                "channel_id": CHANNEL_ID.to_string(),
+                "member": member_json(MODERATOR_ID),
+                "token": format!("token{id}"),
+                "version": 1,
+                "app_permissions": "0",
+                "locale": "en-US",
+                "guild_locale": "en-US",
+                "entitlements": [],
+            }),
+        );
+    }
+
+    /// The moderator reacts to a message.
+    pub fn react(&self, message_id: u64, emoji: &str) {
+        self.dispatch(
+            "MESSAGE_REACTION_ADD",
+            json!({
+                "user_id": MODERATOR_ID.to_string(),
+                "channel_id": CHANNEL_ID.to_string(),
+                "message_id": message_id.to_string(),
+                "guild_id": GUILD_ID.to_string(),
+                "member": member_json(MODERATOR_ID),
+                "emoji": { "id": null, "name": emoji },
+                "burst": false,
+                "burst_colors": [],
+                "type": 0,
+            }),
+        );
+    }
+}
+
+/// Reads a http/1.1 request, bodies can be sent with a length or chunked.
+async fn read_request(stream: &mut BufReader<TcpStream>) -> Option<Request> {
+    let mut line = String::new();
+    stream.read_line(&mut line).await.ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut content_length = 0;
+    let mut chunked = false;
+    loop {
+        let mut header = String::new();
+        stream.read_line(&mut header).await.ok()?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header.split_once(':')?;
+        match name.to_lowercase().as_str() {
+            "content-length" => content_length = value.trim().parse().ok()?,
+            "transfer-encoding" => chunked = value.trim() == "chunked",
+            _ => {}
+        }
+    }
+
+    let mut body = Vec::new();
+    if chunked {
+        loop {
+            let mut size = String::new();
+            stream.read_line(&mut size).await.ok()?;
+            let size = usize::from_str_radix(size.trim(), 16).ok()?;
+            let mut chunk = vec![0; size + 2];
+            stream.read_exact(&mut chunk).await.ok()?;
+            if size == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..size]);
+        }
+    } else {
+        body.resize(content_length, 0);
+        stream.read_exact(&mut body).await.ok()?;
+    }
+    Some(Request {
+        method,
+        path,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
+
+async fn serve_http(stream: TcpStream, state: Arc<StdMutex<State>>, gateway_url: String) {
+    let mut stream = BufReader::new(stream);
+    let Some(request) = read_request(&mut stream).await else {
+        return;
+    };
+
+    let (status, body): (&str, Vec<u8>) = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(request.clone());
+        let segments: Vec<&str> = request.path.split('/').collect();
+        match (request.method.as_str(), segments.as_slice()) {
+            ("GET", ["", "api", "v10", "gateway", ..]) => (
+                "200 OK",
+                json!({
+                    "url": gateway_url,
+                    "shards": 1,
+                    "session_start_limit": {
+                        "total": 1000,
+                        "remaining": 1000,
+                        "reset_after": 0,
+                        "max_concurrency": 1,
+                    },
+                })
+                .to_string()
+                .into_bytes(),
+            ),
+            ("GET", ["", "api", "v10", "guilds", _, "members", user_id]) => (
+                "200 OK",
+                member_json(user_id.parse().unwrap())
+                    .to_string()
+                    .into_bytes(),
+            ),
+            ("GET", ["", "api", "v10", "channels", _, "messages", message_id]) => {
+                match state.messages.get(&message_id.parse().unwrap_or(0)) {
+                    Some(message) => ("200 OK", message.to_string().into_bytes()),
+                    None => (
+                        "404 Not Found",
+                        json!({ "message": "Unknown Message", "code": 10008 })
+                            .to_string()
+                            .into_bytes(),
+                    ),
+                }
+            }
+            ("GET", ["", "maintenance"]) => (
+                "200 OK",
+                if state.maintenance { b"1" } else { b"0" }.to_vec(),
+            ),
+            ("GET", ["", "attachments", _]) => match state.files.get(&request.path) {
+                Some(file) => ("200 OK", file.clone()),
+                None => ("404 Not Found", Vec::new()),
+            },
+            ("PATCH", ["", "api", "v10", "webhooks", ..])
+            | ("POST", ["", "api", "v10", "webhooks", ..])
+            | ("POST", ["", "api", "v10", "channels", _, "messages"]) => (
+                "200 OK",
+                message_json(1, BOT_ID, "", json!([]))
+                    .to_string()
+                    .into_bytes(),
+            ),
+            ("PUT", ["", "api", "v10", "applications", .., "commands"]) => {
+                ("200 OK", b"[]".to_vec())
+            }
+            ("POST", ["", "skins", "edit", "modify_skin.php"]) => ("200 OK", b"ok".to_vec()),
+            _ => ("204 No Content", Vec::new()),
+        }
+    };
+
+    let content_type = if request.path.starts_with("/attachments/") {
+        "image/png"
+    } else {
+        "application/json"
+    };
+    let head = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    let stream = stream.get_mut();
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(&body).await;
+}
+
+/// Speaks just enough of the gateway protocol to get the bot ready,
+/// then dispatches the events of the test.
+async fn serve_gateway(
+    listener: TcpListener,
+    mut events: mpsc::UnboundedReceiver<(String, Value)>,
+    gateway_url: String,
+) {
+    let Ok((stream, _)) = listener.accept().await else {
+        return;
+    };
+    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+    let send = |payload: Value| WsMessage::Text(payload.to_string());
+
+    ws.send(send(
+        json!({ "op": 10, "d": { "heartbeat_interval": 45000 } }),
+    ))
+    .await
+    .unwrap();
+
+    let mut seq = 0;
+    loop {
+        select! {
+            msg = ws.next() => {
+                let Some(Ok(WsMessage::Text(text))) = msg else {
+                    return;
+                };
+                let payload: Value = serde_json::from_str(&text).unwrap();
+                match payload["op"].as_u64() {
+                    // heartbeat
+                    Some(1) => {
+                        let _ = ws.send(send(json!({ "op": 11 }))).await;
+                    }
+                    // identify
+                    Some(2) => {
+                        seq += 1;
+                        let ready = json!({
+                            "op": 0,
+                            "s": seq,
+                            "t": "READY",
+                            "d": {
+                                "v": 10,
+                                "user": user_json(BOT_ID),
+                                "guilds": [{ "id": GUILD_ID.to_string(), "unavailable": true }],
+                                "session_id": "session",
+                                "resume_gateway_url": gateway_url,
+                                "shard": [0, 1],
+                                "application": { "id": BOT_ID.to_string(), "flags": 0 },
+                            },
+                        });
+                        let _ = ws.send(send(ready)).await;
+                    }
+                    _ => {}
+                }
+            }
+            event = events.recv() => {
+                let Some((event, data)) = event else {
+                    return;
+                };
+                seq += 1;
+                let _ = ws
+                    .send(send(json!({ "op": 0, "s": seq, "t": event, "d": data })))
+                    .await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use std::sync::Arc;
+
+    use image::{ImageFormat, Rgba, RgbaImage};
+
+    use hyper::StatusCode;
+
+    use super::FakeDiscord;
+    use crate::batches::BatchHistory;
+    use crate::db_upload::{MockDatabaseClient, UploadError};
+    use crate::editor_api::{submit_skin, EditorSubmission};
+    use crate::SkinToUploadDB;
+
+    fn skin_png() -> Vec<u8> {
+        skin_png_of_width(256)
+    }
+
+    fn skin_png_of_width(width: u32) -> Vec<u8> {
+        let scale = width / 256;
+        let img = RgbaImage::from_fn(width, width / 2, |x, y| {
+            let (x, y) = (x / scale, y / scale);
+            if (64..192).contains(&x) && (32..96).contains(&y) {
+                Rgba([x as u8, y as u8, 128, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        });
+        let mut png = Vec::new();
+        img.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn collect_and_finish() {
+        let discord = FakeDiscord::start(60).await;
+        let submission =
+            discord.post_submission("\"harness_finish\" by artist (CC0)", Some(skin_png()));
+
+        discord.start_upload().await;
+        discord.react(submission, "✅");
+        discord
+            .wait_for_request("PATCH", "/messages/@original", "harness_finish")
+            .await;
+
+        discord.button("ok");
+        discord
+            .wait_for_request("PATCH", "/messages/@original", "Confirm the upload")
+            .await;
+        discord.button("upload_confirm");
+        discord
+            .wait_for_request("POST", "/skins/edit/modify_skin.php", "artist")
+            .await;
+        discord
+            .wait_for_request("POST", "/channels/400/messages", "harness_finish")
+            .await;
+        discord
+            .wait_for_request("PATCH", "/messages/@original", "Uploading batch `")
+            .await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn retry_failed_upload() {
+        let database = Arc::new(MockDatabaseClient::answering([Err(UploadError::Server(
+            "bad gateway".to_string(),
+        ))]));
+        let discord = FakeDiscord::start_with_database(60, Some(database.clone())).await;
+        let submission =
+            discord.post_submission("\"harness_retry\" by artist (CC0)", Some(skin_png()));
+
+        discord.start_upload().await;
+        discord.react(submission, "✅");
+        discord
+            .wait_for_request("PATCH", "/messages/@original", "harness_retry")
+            .await;
+
+        discord.button("ok");
+        discord
+            .wait_for_request("PATCH", "/messages/@original", "Confirm the upload")
+            .await;
+        discord.button("upload_confirm");
+        discord
+            .wait_for_request("PATCH", "/messages/@original", "Retry 1 failed skins")
+            .await;
+        // nothing is announced before the skin is in the database
+        assert!(!discord
+            .requests()
+            .iter()
+            .any(|request| request.method == "POST"
+                && request.path.contains("/channels/400/messages")
+                && request.body.contains("harness_retry")));
+
+        discord.button("retry_failed");
+        discord
+            .wait_for_request("POST", "/channels/400/messages", "harness_retry")
+            .await;
+        let uploads = database.uploads.lock().unwrap();
+        assert_eq!(uploads.len(), 2);
+        assert!(uploads.iter().all(|(form, _)| form.name == "harness_retry"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn back_from_confirmation() {
+        let discord = FakeDiscord::start(60).await;
+        let submission =
+            discord.post_submission("\"harness_back\" by artist (CC0)", Some(skin_png()));
+
+        discord.start_upload().await;
+        discord.react(submission, "✅");
+        discord
+            .wait_for_request("PATCH", "/messages/@original", "harness_back")
+            .await;
+
+        discord.button("ok");
+        discord
+            .wait_for_request("PATCH", "/messages/@original", "Confirm the upload")
+            .await;
+        discord.button("upload_back");
+        discord
+            .wait_for_request("POST", "/callback", "Nothing was uploaded")
+            .await;
+        assert!(!discord
+            .requests()
+            .iter()
+            .any(|request| request.method == "POST" && request.path.contains("modify_skin.php")));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn wait_for_maintenance() {
+        let discord = FakeDiscord::start(60).await;
+        discord.set_maintenance(true);
+        let submission =
+            discord.post_submission("\"harness_maintenance\" by artist (CC0)", Some(skin_png()));
+
+        discord.start_upload().await;
+        discord.react(submission, "✅");
+        discord
+            .wait_for_request("PATCH", "/messages/@original", "harness_maintenance")
+            .await;
+
+        discord.button("ok");
+        discord
+            .wait_for_request("PATCH", "/messages/@original", "Confirm the upload")
+            .await;
+        discord.button("upload_confirm");
+        discord
+            .wait_for_request("PATCH", "/messages/@original", "in maintenance")
+            .await;
+        assert!(!discord
+            .requests()
+            .iter()
+            .any(|request| request.method == "POST" && request.path.contains("modify_skin.php")));
+
+        discord.set_maintenance(false);
+        discord
+            .wait_for_request("POST", "/skins/edit/modify_skin.php", "artist")
+            .await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn coalesce_summary_edits() {
+        let discord = FakeDiscord::start(60).await;
+        let submissions: Vec<_> = (0..5)
+            .map(|i| {
+                discord.post_submission(
+                    &format!("\"harness_rapid_{i}\" by artist (CC0)"),
+                    Some(skin_png()),
+                )
+            })
+            .collect();
+
+        discord.start_upload().await;
+        for submission in submissions {
+            discord.react(submission, "✅");
+        }
+        // the skins are checked in order, the last one is in the flushed summary
+        discord
+            .wait_for_request("PATCH", "/messages/@original", "harness_rapid_4")
+            .await;
+        let edits = discord
+            .requests()
+            .into_iter()
+            .filter(|request| {
+                request.method == "PATCH" && request.path.contains("/messages/@original")
+            })
+            .count();
+        assert!(edits < 5, "the summary was edited {edits} times");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn collect_invalid_submission() {
+        let discord = FakeDiscord::start(60).await;
+        let submission = discord.post_submission("just a nice skin", Some(skin_png()));
+
+        discord.start_upload().await;
+        discord.react(submission, "✅");
+        discord
+            .wait_for_request(
+                "PATCH",
+                "/messages/@original",
+                "name, author or license not found",
+            )
+            .await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn conflicting_submissions() {
+        let discord = FakeDiscord::start(60).await;
+        let first = discord.post_submission("\"harness_first\" by artist (CC0)", Some(skin_png()));
+        let second =
+            discord.post_submission("\"harness_second\" by artist (CC0)", Some(skin_png()));
+
+        discord.start_upload().await;
+        discord.react(first, "✅");
+        discord
+            .wait_for_request("PATCH", "/messages/@original", "harness_first")
+            .await;
+        discord.react(second, "✅");
+        discord
+            .wait_for_request("PATCH", "/messages/@original", "has the same image as")
+            .await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn editor_submission() {
+        let database = Arc::new(MockDatabaseClient::answering([]));
+        let discord = FakeDiscord::start_with_database(60, Some(database.clone())).await;
+        // the audit log outlives the test, the name of an earlier run would be refused
+        let name = format!("editor_{}", std::process::id());
+        let submission = |png| EditorSubmission {
+            name: name.clone(),
+            author: "artist".to_string(),
+            license: "CC0".to_string(),
+            database: SkinToUploadDB::Community,
+            skin_pack: None,
+            png,
+        };
+
+        let (status, answer) =
+            submit_skin(&discord.data, &discord.http, submission(b"no png".to_vec())).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(answer.errors, ["the file is not a valid image"]);
+
+        let (status, answer) =
+            submit_skin(&discord.data, &discord.http, submission(skin_png())).await;
+        assert_eq!(status, StatusCode::OK, "{answer:?}");
+        assert_eq!(answer.name.as_deref(), Some(name.as_str()));
+        discord
+            .wait_for_request("POST", "/channels/400/messages", "from the web skin editor")
+            .await;
+        assert_eq!(database.uploads.lock().unwrap().len(), 1);
+
+        let (status, _) = submit_skin(&discord.data, &discord.http, submission(skin_png())).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn simultaneous_editor_submissions() {
+        let database = Arc::new(MockDatabaseClient::answering([]));
+        let discord = FakeDiscord::start_with_database(60, Some(database.clone())).await;
+        let name = format!("editor_twice_{}", std::process::id());
+        let submission = || EditorSubmission {
+            name: name.clone(),
+            author: "artist".to_string(),
+            license: "CC0".to_string(),
+            database: SkinToUploadDB::Community,
+            skin_pack: None,
+            png: skin_png(),
+        };
+
+        let ((first, _), (second, _)) = tokio::join!(
+            submit_skin(&discord.data, &discord.http, submission()),
+            submit_skin(&discord.data, &discord.http, submission()),
+        );
+        let mut statuses = [first, second];
+        statuses.sort();
+        assert_eq!(statuses, [StatusCode::OK, StatusCode::CONFLICT]);
+        assert_eq!(database.uploads.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn editor_hd_upload_fails() {
+        let database = Arc::new(MockDatabaseClient::answering([
+            Ok(()),
+            Err(UploadError::Validation(
+                reqwest::StatusCode::BAD_REQUEST,
+                "too large".to_string(),
+            )),
+        ]));
+        let discord = FakeDiscord::start_with_database(60, Some(database.clone())).await;
+        let name = format!("editor_hd_{}", std::process::id());
+        let submission = EditorSubmission {
+            name: name.clone(),
+            author: "artist".to_string(),
+            license: "CC0".to_string(),
+            database: SkinToUploadDB::Community,
+            skin_pack: None,
+            png: skin_png_of_width(512),
+        };
+
+        let (status, answer) = submit_skin(&discord.data, &discord.http, submission).await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert!(answer.errors[0].starts_with("uploading the 512x256 skin failed"));
+        let batch = discord
+            .data
+            .read()
+            .await
+            .get::<BatchHistory>()
+            .unwrap()
+            .all()
+            .into_iter()
+            .rfind(|batch| batch.skins.iter().any(|skin| skin.name == name))
+            .unwrap();
+        let skin = &batch.skins[0];
+        assert!(!skin.uploaded);
+        assert_eq!(skin.uploaded_sizes, ["256x128"]);
+        // the sd skin is in the database, undoing the batch deletes it
+        assert_eq!(batch.undo_refusal(60, batch.finished_at), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn busy_message() {
+        let discord = FakeDiscord::start(60).await;
+
+        discord.start_upload().await;
+        discord.command("upload");
+        let busy = discord
+            .wait_for_request("POST", "/callback", "already uploading")
+            .await;
+        assert!(busy.body.contains("<@300> is uploading since <t:"));
+        assert!(busy.body.contains("0 skins are queued"));
+
+        // the moderator is no admin
+        discord.button("upload_sessions");
+        discord
+            .wait_for_request("POST", "/callback", "Only admins")
+            .await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cancel() {
+        let discord = FakeDiscord::start(60).await;
+
+        discord.start_upload().await;
+        discord.command("upload_cancel");
+        discord
+            .wait_for_request("POST", "/callback", "Skin upload cancelled")
+            .await;
+        discord
+            .wait_for_request("DELETE", "/messages/@original", "")
+            .await;
+        assert!(!discord
+            .requests()
+            .iter()
+            .any(|request| request.method == "POST" && request.path.contains("modify_skin.php")));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn expiry_warning() {
+        let discord = FakeDiscord::start(2).await;
+
+        discord.start_upload().await;
+        discord
+            .wait_for_request("POST", "/webhooks", "expires in 1s")
+            .await;
+        discord
+            .wait_for_request("PATCH", "/messages/@original", "Upload timed out")
+            .await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn timeout() {
+        let discord = FakeDiscord::start(1).await;
+
+        discord.start_upload().await;
+        discord
+            .wait_for_request("PATCH", "/messages/@original", "Upload timed out")
+            .await;
+    }
+}
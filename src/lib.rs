@@ -0,0 +1,50 @@
+//! The skin processing of the bot, without discord: decoding, checking, dilating and
+//! resampling skins. Build it with `--no-default-features` for tools or the web editor.
+//! With the `discord` feature it also holds the upload itself: the config, the parsing of
+//! submissions, the upload sessions and the database client, the bot only wires them to the
+//! gateway.
+
+#[cfg(feature = "discord")]
+pub mod announce;
+pub mod archive;
+#[cfg(feature = "discord")]
+pub mod audit;
+#[cfg(feature = "discord")]
+pub mod blocklist;
+pub mod cleanup;
+#[cfg(feature = "discord")]
+pub mod config;
+#[cfg(feature = "discord")]
+pub mod db_upload;
+pub mod dilate;
+#[cfg(feature = "discord")]
+pub mod emoji;
+#[cfg(feature = "discord")]
+pub mod forum;
+#[cfg(feature = "discord")]
+pub mod license;
+#[cfg(feature = "discord")]
+pub mod metadata;
+pub mod naming;
+pub mod optimize;
+#[cfg(feature = "discord")]
+pub mod plugins;
+pub mod resample;
+pub mod safety;
+#[cfg(feature = "discord")]
+pub mod scan;
+#[cfg(feature = "discord")]
+pub mod session;
+pub mod skin;
+pub mod tee;
+pub mod translit;
+#[cfg(feature = "discord")]
+pub mod uploads;
+#[cfg(feature = "discord")]
+pub mod votes;
+pub mod watermark;
+
+#[cfg(feature = "discord")]
+pub use uploads::{
+    DefaultSkinHashes, FailedUploads, SkinToUpload, SkinToUploadDB, SkinUploadItem, SkinUploads,
+};
@@ -0,0 +1,106 @@
+use serenity::all::Timestamp;
+
+use crate::config::{LicenseConfig, LicensePolicyKind};
+
+pub const DEFAULT_ALLOWED_LICENSES: [&str; 7] = [
+    "CC0",
+    "CC-BY",
+    "CC-BY-SA",
+    "CC-BY-NC",
+    "CC-BY-NC-SA",
+    "CC-BY-ND",
+    "CC-BY-NC-ND",
+];
+
+/// Brings common spellings ("cc0 1.0", "CC 0", "Creative Commons BY-SA 4.0") into one form.
+pub fn normalize_license(license: &str) -> String {
+    let upper = license
+        .trim()
+        .to_uppercase()
+        .replace("CREATIVE COMMONS", "CC")
+        .replace("LICENSE", "")
+        .replace(['_', ' ', '/'], "-");
+
+    let parts: Vec<&str> = upper
+        .split('-')
+        .filter(|part| !part.is_empty())
+        // drop versions like 1.0 or 4.0
+        .filter(|part| !part.chars().all(|c| c.is_ascii_digit() || c == '.') || *part == "0")
+        .collect();
+
+    let normalized = parts.join("-");
+    match normalized.as_str() {
+        "CC-0" | "CCO" | "CC-ZERO" => "CC0".to_string(),
+        _ => normalized,
+    }
+}
+
+pub struct LicensePolicy {
+    allowed: Vec<String>,
+    /// if false, unknown licenses are only flagged
+    reject_unknown: bool,
+}
+
+impl LicensePolicy {
+    pub fn new(allow_list: &[String], policy: LicensePolicyKind) -> Self {
+        Self {
+            allowed: allow_list
+                .iter()
+                .map(|license| normalize_license(license))
+                .filter(|license| !license.is_empty())
+                .collect(),
+            reject_unknown: policy == LicensePolicyKind::Reject,
+        }
+    }
+
+    pub const fn rejects_unknown(&self) -> bool {
+        self.reject_unknown
+    }
+
+    /// Returns the normalized license, or an error containing it if it's not allowed.
+    pub fn validate(&self, license: &str) -> Result<String, String> {
+        let normalized = normalize_license(license);
+        if self.allowed.contains(&normalized) {
+            Ok(normalized)
+        } else {
+            Err(normalized)
+        }
+    }
+}
+
+/// The license policies of all epochs, submissions are judged by the rules of the time they were posted.
+pub struct LicenseRules {
+    base: LicensePolicy,
+    /// sorted by their start
+    epochs: Vec<(Timestamp, LicensePolicy)>,
+}
+
+impl LicenseRules {
+    pub fn new(config: &LicenseConfig) -> Self {
+        let mut epochs: Vec<_> = config
+            .epochs
+            .iter()
+            .map(|epoch| {
+                let policy = LicensePolicy::new(
+                    epoch.allow_list.as_ref().unwrap_or(&config.allow_list),
+                    epoch.policy.unwrap_or(config.policy),
+                );
+                (epoch.since, policy)
+            })
+            .collect();
+        epochs.sort_by_key(|(since, _)| *since);
+        Self {
+            base: LicensePolicy::new(&config.allow_list, config.policy),
+            epochs,
+        }
+    }
+
+    /// The policy of the newest epoch that started before the submission was posted.
+    pub fn at(&self, posted: Timestamp) -> &LicensePolicy {
+        self.epochs
+            .iter()
+            .rev()
+            .find(|(since, _)| *since <= posted)
+            .map_or(&self.base, |(_, policy)| policy)
+    }
+}
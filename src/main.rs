@@ -1,27 +1,104 @@
-mod dilate;
+mod admin_http;
+mod backfill;
+mod batches;
+mod db_edit;
+mod editor_api;
+#[cfg(all(test, feature = "test-harness"))]
+mod harness;
+mod metrics;
+mod notify;
+mod practice;
+mod resync;
+mod shutdown;
+mod stats;
+mod test_server;
+mod vote_round;
 
-use std::collections::{HashMap, HashSet, VecDeque};
-use std::env;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use dilate::dilate_image;
+use admin_http::serve_admin_http;
+use announce::{announcement_messages, fill_template, UploadedSkin};
+use archive::create_zip;
+use audit::AuditLog;
+use backfill::{
+    batches_from_announcements, batches_from_csv, fetch_announcements, PastAnnouncement,
+};
+use batches::{
+    BatchHistory, BatchRecord, BatchSkin, CANCEL_UNDO_BUTTON_ID, MAX_LABEL_LENGTH,
+    UNDO_BATCH_BUTTON_PREFIX,
+};
+use blocklist::{BlockEntry, BlockKind, Blocklist};
+use config::Config;
+use db_edit::{PendingSkinEdits, SkinEdit, CANCEL_EDIT_BUTTON_ID, CONFIRM_EDIT_BUTTON_ID};
+use db_upload::{
+    check_database, maintenance_active, process_and_upload, DatabaseStatus, HttpDatabaseClient,
+    SkinDatabase, SkinDatabaseClient, SkinForm,
+};
+use ddnet_discord::{
+    announce, archive, audit, blocklist, cleanup, config, db_upload, dilate, emoji, forum, license,
+    metadata, naming, plugins, resample, safety, scan, session, skin, tee, translit, uploads,
+    votes, watermark,
+};
+use ddnet_discord::{
+    DefaultSkinHashes, FailedUploads, SkinToUpload, SkinToUploadDB, SkinUploadItem, SkinUploads,
+};
+use dilate::dilate_file;
+use editor_api::serve_editor_api;
+use emoji::reaction_matches;
+use forum::{
+    forum_post_starter, is_media_channel, is_upload_channel, post_of, tag_forum_post, ForumDecision,
+};
 use hashlink::LinkedHashMap;
-use image::{ColorType, ImageFormat};
+use license::LicenseRules;
+use metadata::{
+    is_sidecar, modal_metadata, parse_skin_info_id, post_title, skin_info_button, skin_info_modal,
+    submission_metadata, title_metadata, MAX_POST_TITLE_LENGTH, POST_INFO_BUTTON_PREFIX,
+    POST_INFO_MODAL_PREFIX, SKIN_INFO_BUTTON_PREFIX, SKIN_INFO_MODAL_PREFIX,
+};
+use metrics::Metrics;
+use naming::name_key;
+use notify::{notify_author, DmOptOuts, OPT_OUT_BUTTON_ID};
+use plugins::ValidationPlugins;
+use practice::{Practice, PracticeAnswer, PracticeSessions};
+use resample::{downscale_hd, SdResampler};
+use resync::{resync_all_sessions, resync_reactions};
+use safety::DefaultSkins;
+use scan::{fetch_submissions, message_id_at, queue_submissions};
 use serenity::all::{
-    ChannelId, CommandInteraction, ComponentInteraction, GuildId, Interaction, Mention, Message,
-    MessageId, Reaction, ReactionType, Ready, RoleId, UserId,
+    ButtonStyle, ChannelId, CommandDataOption, CommandDataOptionValue, CommandInteraction,
+    CommandOptionType, CommandType, ComponentInteraction, ComponentInteractionDataKind, GuildId,
+    Interaction, Member, Mention, Message, MessageId, ModalInteraction, Reaction, Ready,
+    ResolvedTarget, ResumedEvent, RoleId, ScheduledEvent, ScheduledEventStatus, Timestamp, UserId,
 };
 use serenity::async_trait;
 use serenity::builder::{
-    CreateAllowedMentions, CreateButton, CreateCommand, CreateEmbed, CreateInteractionResponse,
-    CreateInteractionResponseMessage, CreateMessage, EditInteractionResponse,
+    CreateActionRow, CreateAllowedMentions, CreateAttachment, CreateButton, CreateCommand,
+    CreateCommandOption, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseFollowup,
+    CreateInteractionResponseMessage, CreateMessage, EditAttachments, EditInteractionResponse,
+    EditMessage, EditThread,
 };
 use serenity::framework::standard::StandardFramework;
 use serenity::model::Colour;
 use serenity::prelude::*;
-use tokio::select;
-use tokio::sync::Notify;
+use session::{
+    Session, SessionEvent, SessionState, UploadSession, APPROVE_BUTTON_PREFIX,
+    REJECT_BUTTON_PREFIX, SWITCH_DATABASE_SELECT_ID,
+};
+use shutdown::shutdown_on_signal;
+use skin::processed_png;
+use stats::{month_period, stats_csv, upload_stats};
+use tee::{render_tee_png, TEE_RENDERER_VERSION};
+use test_server::notify_test_server;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{error, info_span, warn, Instrument};
+use tracing_subscriber::EnvFilter;
+use uploads::{upload_buttons, RESYNC_BUTTON_ID};
+use vote_round::{close_vote_round_of_event, start_vote_round, VoteRounds};
+use votes::{leaderboard_messages, schedule_vote_reports, tally_votes};
+use watermark::WatermarkRegion;
 
 enum CommandWrapper<'a> {
     Cmd(&'a CommandInteraction),
@@ -59,23 +136,91 @@ impl<'a> CommandWrapper<'a> {
     }
 }
 
-fn parse_skin_info(text: &str) -> anyhow::Result<(String, String, String)> {
-    let matches_text = regex::Regex::new("(?i)\"(.+)\" by (.+) \\((.+)\\)").unwrap();
-    let caps = matches_text.captures(text);
-    if caps.is_some() && caps.as_ref().unwrap().len() > 2 {
-        Ok((
-            caps.as_ref().unwrap().get(1).unwrap().as_str().to_string(),
-            caps.as_ref().unwrap().get(2).unwrap().as_str().to_string(),
-            caps.as_ref().unwrap().get(3).unwrap().as_str().to_string(),
-        ))
+fn format_file_size(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
     } else {
-        Err(anyhow::Error::msg(format!(
-            "name, author or license not found in msg: {}",
-            text.replace('\n', "")
-        )))
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    }
+}
+
+/// The skins of a batch with their outcome, cut to fit into a message.
+fn batch_details(batch: &BatchRecord) -> String {
+    let mut details = format!(
+        "**Batch `{}`**, finished <t:{}:f> by {}\n",
+        batch.label,
+        batch.finished_at,
+        batch.uploader_mention()
+    );
+    if let Some(undone_at) = batch.undone_at {
+        details += &format!("Undone <t:{undone_at}:f>, its skins were deleted again\n");
+    }
+    if !batch.announcements.is_empty() {
+        details += &format!("Announced in {}\n", batch.announcements.join(" "));
+    }
+    let mut omitted = 0;
+    for skin in &batch.skins {
+        let outcome = match &skin.error {
+            _ if skin.uploaded => "uploaded".to_string(),
+            Some(err) if skin.in_database() => format!(
+                "partially uploaded ({}), failed ({err})",
+                skin.uploaded_sizes.join(", ")
+            ),
+            Some(err) => format!("failed ({err})"),
+            None => "failed".to_string(),
+        };
+        let line = format!(
+            "- `{}` {}: {outcome} {}\n",
+            skin.name, skin.database, skin.submission
+        );
+        // leave some space for the omitted hint
+        if omitted > 0 || details.chars().count() + line.chars().count() > 1950 {
+            omitted += 1;
+        } else {
+            details += &line;
+        }
+    }
+    if omitted > 0 {
+        details += &format!("... and {omitted} more\n");
     }
+    details
+}
+
+/// Who is uploading, since when, how many skins are queued and when the session times out.
+fn session_overview(uploads: &SkinUploads) -> String {
+    uploads
+        .uploads
+        .iter()
+        .map(|(owner, item)| {
+            format!(
+                "{} is uploading since <t:{}:R>, {} skins are queued. If the upload disconnected, it times out <t:{}:R>.",
+                Mention::User(*owner),
+                item.started_at,
+                item.queued_skins,
+                item.expires_at
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn is_admin(member: Option<&Member>) -> bool {
+    member
+        .and_then(|member| member.permissions)
+        .is_some_and(|permissions| permissions.administrator())
 }
 
+const CONFIRM_UPLOAD_BUTTON_ID: &str = "upload_confirm";
+const BACK_UPLOAD_BUTTON_ID: &str = "upload_back";
+const SESSIONS_BUTTON_ID: &str = "upload_sessions";
+const END_SESSION_BUTTON_PREFIX: &str = "upload_end_session:";
+
+/// `/batches rerender` edits the announcements of at most this many batches at once
+const MAX_RERENDER_BATCHES: usize = 25;
+
+/// keeps `/dilate` from blocking the bot with huge images
+const DILATE_MAX_PIXELS: u64 = 2048 * 2048;
+
 struct Handler;
 
 impl Handler {
@@ -87,23 +232,22 @@ impl Handler {
             .uploads
             .get_mut(&user_id)
         {
-            if item.state == SkinUploadState::Collecting {
+            if item.session.cancel().is_ok() {
                 let data = CreateInteractionResponseMessage::new()
                     .content("Skin upload cancelled")
                     .ephemeral(true);
                 let builder = CreateInteractionResponse::Message(data);
                 if let Err(why) = command.create_response(&ctx.http, builder).await {
-                    println!("Could not respond to slash command: {why}");
+                    warn!("Could not respond to slash command: {why}");
                 }
-                item.state = SkinUploadState::Cancelled;
-                item.notify.notify_one();
+                item.send(SessionEvent::CancelRequested);
             } else {
                 let data = CreateInteractionResponseMessage::new()
                     .content("Cannot cancel upload at this point anymore")
                     .ephemeral(true);
                 let builder = CreateInteractionResponse::Message(data);
                 if let Err(why) = command.create_response(&ctx.http, builder).await {
-                    println!("Could not respond to slash command: {why}");
+                    warn!("Could not respond to slash command: {why}");
                 }
             }
         } else {
@@ -112,25 +256,16 @@ impl Handler {
                 .ephemeral(true);
             let builder = CreateInteractionResponse::Message(data);
             if let Err(why) = command.create_response(&ctx.http, builder).await {
-                println!("Could not respond to slash command: {why}");
+                warn!("Could not respond to slash command: {why}");
             }
         }
     }
 
-    async fn upload_finish<'a>(ctx: Context, user_id: UserId, command: &CommandWrapper<'a>) {
-        let database_url =
-            env::var("DATABASE_URL").unwrap_or_else(|_| "https://ddnet.org/skins/".to_string());
-        let basic_auth_user_name =
-            env::var("USERNAME").expect("Expected USERNAME for http auth in environment");
-        let basic_auth_password =
-            env::var("PASSWORD").expect("Expected PASSWORD for http auth in environment");
-        let guild_id = GuildId::new(
-            env::var("GUILD_ID")
-                .expect("Expected GUILD_ID in environment")
-                .parse()
-                .expect("GUILD_ID must be an integer"),
-        );
-
+    async fn upload_toggle_cleanup<'a>(
+        ctx: Context,
+        user_id: UserId,
+        command: &CommandWrapper<'a>,
+    ) {
         let mut data = ctx.data.write().await;
         if let Some(item) = data
             .get_mut::<SkinUploads>()
@@ -138,852 +273,3663 @@ impl Handler {
             .uploads
             .get_mut(&user_id)
         {
-            if item.state == SkinUploadState::Collecting {
-                item.state = SkinUploadState::Uploading;
-                item.notify.notify_one();
-
-                // let's upload
-                let mut skins_to_upload = item.skins_to_upload.clone();
-                let upload_lock = data.get_mut::<SkinUploads>().unwrap().upload_lock.clone();
-                drop(data);
-
-                let _g = upload_lock.lock().await;
-
+            if item.session.state().is_collecting() {
+                item.apply_cleanup = !item.apply_cleanup;
                 let data = CreateInteractionResponseMessage::new()
-                    .content("Starting to upload")
+                    .content(if item.apply_cleanup {
+                        "Stray pixels will be removed before uploading"
+                    } else {
+                        "Stray pixels will be kept"
+                    })
                     .ephemeral(true);
                 let builder = CreateInteractionResponse::Message(data);
                 if let Err(why) = command.create_response(&ctx.http, builder).await {
-                    println!("Could not respond to slash command: {why}");
-                }
-
-                let errors: Arc<Mutex<Vec<String>>> = Arc::default();
-                let mut uploaded_skins_msg: Vec<String> = Vec::default();
-                uploaded_skins_msg
-                    .push("The following skins were added to the database:\n".to_string());
-                let mut uploaded_skin_users: HashSet<UserId> = HashSet::default();
-                let were_skins_uploaded = !skins_to_upload.is_empty();
-                for (skin_name, skin_to_upload) in skins_to_upload.drain() {
-                    let author = skin_to_upload.author;
-                    let license = skin_to_upload.license;
-                    let database = skin_to_upload.database.to_string();
-                    let get_form_base = Arc::new(move |img_name: String| {
-                        let mut form = reqwest::blocking::multipart::Form::new();
-                        form = form.file("image", img_name + ".png").unwrap();
-                        form = form.text("creator", author.clone());
-                        form = form.text("skin_pack", "");
-                        form = form.text("skin_license", license.clone());
-                        form = form.text("skin_type", database.clone());
-                        form = form.text("game_version", "tw-0.6");
-                        form = form.text("skin_part", "full");
-                        form = form.text("modifyaction", "add");
-                        form
-                    });
-
-                    if !skin_to_upload.file_256x128.is_empty() {
-                        let errors_clone = errors.clone();
-                        let skin_name_clone = skin_name.clone();
-                        let get_form_base_clone = get_form_base.clone();
-                        let basic_auth_user_name = basic_auth_user_name.clone();
-                        let basic_auth_password = basic_auth_password.clone();
-                        let db_url = database_url.clone();
-                        tokio::task::spawn_blocking(move || {
-                            let mut img = skin_to_upload.file_256x128.clone();
-                            dilate_image(&mut img, 256, 128, 4);
-                            image::save_buffer_with_format(
-                                skin_name_clone.clone() + ".png",
-                                &img,
-                                256,
-                                128,
-                                ColorType::Rgba8,
-                                ImageFormat::Png,
-                            )
-                            .unwrap();
-                            let form = get_form_base_clone(skin_name_clone.clone())
-                                .text("skinisuhd", "false");
-                            if let Err(err) = reqwest::blocking::Client::new()
-                                .post(db_url + "edit/modify_skin.php")
-                                .multipart(form)
-                                .basic_auth(basic_auth_user_name, Some(basic_auth_password))
-                                .send()
-                            {
-                                errors_clone.blocking_lock().push(format!("There was an error while uploading {err}.\nPlease manually check if this broke the database\n"));
-                            }
-                        }).await.unwrap();
-
-                        tokio::fs::remove_file(skin_name.clone() + ".png")
-                            .await
-                            .unwrap();
-                    }
-
-                    if !skin_to_upload.file_512x256.is_empty() {
-                        let errors_clone = errors.clone();
-                        let skin_name_clone = skin_name.clone();
-                        let basic_auth_user_name = basic_auth_user_name.clone();
-                        let basic_auth_password = basic_auth_password.clone();
-                        let db_url = database_url.clone();
-                        tokio::task::spawn_blocking(move || {
-                            let mut img = skin_to_upload.file_512x256.clone();
-                            dilate_image(&mut img, 512, 256, 4);
-                            image::save_buffer_with_format(
-                                skin_name_clone.clone() + ".png",
-                                &img,
-                                512,
-                                256,
-                                ColorType::Rgba8,
-                                ImageFormat::Png,
-                            )
-                            .unwrap();
-                            let form = get_form_base(skin_name_clone.clone())
-                                .text("skinisuhd", "true");
-                            if let Err(err) = reqwest::blocking::Client::new()
-                                .post(db_url + "edit/modify_skin.php")
-                                .multipart(form)
-                                .basic_auth(basic_auth_user_name, Some(basic_auth_password))
-                                .send()
-                            {
-                                errors_clone.blocking_lock().push(format!("There was an error while uploading {err}.\nPlease manually check if this broke the database\n"));
-                            }}
-                        ).await.unwrap();
-
-                        tokio::fs::remove_file(skin_name.clone() + ".png")
-                            .await
-                            .unwrap();
-                    }
-
-                    if let Ok(msg) = command
-                        .channel_id()
-                        .message(&ctx, skin_to_upload.original_msg_id)
-                        .await
-                    {
-                        let skin_msg = "- \"".to_string()
-                            + &skin_name
-                            + "\" ["
-                            + &skin_to_upload.database.to_string()
-                            + "] by "
-                            + &Mention::User(msg.author.id).to_string()
-                            + " ("
-                            + &format!(
-                                "https://discord.com/channels/{}/{}/{}",
-                                guild_id,
-                                command.channel_id(),
-                                msg.id
-                            )
-                            + ") \n";
-                        if uploaded_skins_msg.last().unwrap().chars().count()
-                            + skin_msg.chars().count()
-                            <= 2000
-                        {
-                            *uploaded_skins_msg.last_mut().unwrap() += &skin_msg;
-                        } else {
-                            uploaded_skins_msg.push(skin_msg);
-                        }
-                        uploaded_skin_users.insert(msg.author.id);
-                    }
-                }
-
-                if were_skins_uploaded {
-                    for upload_msg in &uploaded_skins_msg {
-                        if let Err(err) = command
-                            .channel_id()
-                            .send_message(
-                                &ctx,
-                                CreateMessage::new()
-                                    .allowed_mentions(
-                                        CreateAllowedMentions::new()
-                                            .users(uploaded_skin_users.clone()),
-                                    )
-                                    .content(upload_msg),
-                            )
-                            .await
-                        {
-                            println!("sending global uploaded skins message failed {err}.");
-                        }
-                    }
-                }
-
-                let mut new_msg = String::default();
-                new_msg += "Uploading the skins finished.\n";
-                if !errors.lock().await.is_empty() {
-                    new_msg += "But there were the following errors:\n";
-                    for err in errors.lock().await.iter() {
-                        new_msg += &(err.clone() + "\n");
-                    }
-                }
-                if let Err(err) = command
-                    .edit_response(&ctx, EditInteractionResponse::new().content(new_msg))
-                    .await
-                {
-                    println!("Could edit responds of upload finish: {err}");
+                    warn!("Could not respond to slash command: {why}");
                 }
+                item.send(SessionEvent::OptionsChanged);
             } else {
                 let data = CreateInteractionResponseMessage::new()
-                    .content("An upload is already in progress, wait for the previous to end")
+                    .content("Cannot change the cleanup at this point anymore")
                     .ephemeral(true);
                 let builder = CreateInteractionResponse::Message(data);
                 if let Err(why) = command.create_response(&ctx.http, builder).await {
-                    println!("Could not respond to slash command: {why}");
+                    warn!("Could not respond to slash command: {why}");
                 }
             }
         } else {
             let data = CreateInteractionResponseMessage::new()
-                .content("You never started an upload, please use `/upload`")
+                .content("You never started an upload using `/upload`.")
                 .ephemeral(true);
             let builder = CreateInteractionResponse::Message(data);
             if let Err(why) = command.create_response(&ctx.http, builder).await {
-                println!("Could not respond to slash command: {why}");
+                warn!("Could not respond to slash command: {why}");
             }
         }
     }
-}
 
-#[async_trait]
-impl EventHandler for Handler {
-    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
-        if let Interaction::Component(comp) = interaction {
-            match comp.data.custom_id.as_str() {
-                "cancel" => {
-                    Self::upload_cancel(ctx, comp.user.id, &CommandWrapper::Btn(&comp)).await;
+    async fn upload_exclude_resized<'a>(
+        ctx: Context,
+        user_id: UserId,
+        command: &CommandWrapper<'a>,
+    ) {
+        let mut data = ctx.data.write().await;
+        if let Some(item) = data
+            .get_mut::<SkinUploads>()
+            .unwrap()
+            .uploads
+            .get_mut(&user_id)
+        {
+            if item.session.queue().is_ok() {
+                let data = CreateInteractionResponseMessage::new()
+                    .content("Auto-resized skins will not be uploaded")
+                    .ephemeral(true);
+                let builder = CreateInteractionResponse::Message(data);
+                if let Err(why) = command.create_response(&ctx.http, builder).await {
+                    warn!("Could not respond to slash command: {why}");
                 }
-                "ok" => {
-                    Self::upload_finish(ctx, comp.user.id, &CommandWrapper::Btn(&comp)).await;
+                item.send(SessionEvent::ResizedExcluded);
+            } else {
+                let data = CreateInteractionResponseMessage::new()
+                    .content("Cannot exclude skins at this point anymore")
+                    .ephemeral(true);
+                let builder = CreateInteractionResponse::Message(data);
+                if let Err(why) = command.create_response(&ctx.http, builder).await {
+                    warn!("Could not respond to slash command: {why}");
                 }
-                _ => {}
             }
-        } else if let Interaction::Command(command) = interaction {
-            let guild_id = GuildId::new(
-                env::var("GUILD_ID")
-                    .expect("Expected GUILD_ID in environment")
-                    .parse()
-                    .expect("GUILD_ID must be an integer"),
-            );
-            if command
-                .user
-                .has_role(
-                    ctx.clone(),
-                    guild_id,
-                    RoleId::new(
-                        env::var("ROLE_ID")
-                            .expect("Expected ROLE_ID in environment")
-                            .parse()
-                            .expect("ROLE_ID must be an integer"),
-                    ),
+        } else {
+            let data = CreateInteractionResponseMessage::new()
+                .content("You never started an upload using `/upload`.")
+                .ephemeral(true);
+            let builder = CreateInteractionResponse::Message(data);
+            if let Err(why) = command.create_response(&ctx.http, builder).await {
+                warn!("Could not respond to slash command: {why}");
+            }
+        }
+    }
+
+    /// Reads the queue reactions of the uploader again, for reactions missed while disconnected.
+    async fn upload_resync(ctx: Context, comp: &ComponentInteraction) {
+        let collecting = ctx
+            .data
+            .read()
+            .await
+            .get::<SkinUploads>()
+            .unwrap()
+            .uploads
+            .get(&comp.user.id)
+            .map(|item| {
+                matches!(
+                    item.session.state(),
+                    SessionState::Collecting | SessionState::Validated
                 )
-                .await
-                .unwrap_or(false)
-            {
-                let main_cmd_str = Mention::User(command.user.id).to_string()
-                    + "\n\
-                    __**:art: You are about to upload skins to the database.**__\n\n\
-                    ";
-                let main_cmd_embed = CreateEmbed::new().color(Colour::TEAL).field(
-                    "Please react to all skins you want to upload:",
-                    "\
-                        - React with ✅ to upload a skin to the normal database\n\
-                        - React with ☑️ to upload a skin to the community database\n",
-                    false,
-                );
-                let main_cmd_end_embed = CreateEmbed::new().color(Colour::ORANGE).field(
-                    "",
-                    "\
-                    Once you are done, use the 🆗 button or the command `/upload_finish`\n\
-                    To cancel the upload, use the 🇽 button or the command `/upload_cancel`\n",
-                    false,
-                );
-                let content = match command.data.name.as_str() {
-                    "upload" => Some(main_cmd_str.clone()),
-                    "upload_finish" => {
-                        Self::upload_finish(
-                            ctx.clone(),
-                            command.user.id,
-                            &CommandWrapper::Cmd(&command),
-                        )
-                        .await;
-                        return;
-                    }
-                    "upload_cancel" => {
-                        Self::upload_cancel(
-                            ctx.clone(),
-                            command.user.id,
-                            &CommandWrapper::Cmd(&command),
-                        )
-                        .await;
-                        return;
-                    }
-                    _ => None,
-                };
+            });
+        let refusal = match collecting {
+            None => Some("You never started an upload using `/upload`."),
+            Some(false) => Some("Cannot change the queued skins at this point anymore"),
+            Some(true) => None,
+        };
+        if let Some(refusal) = refusal {
+            let data = CreateInteractionResponseMessage::new()
+                .content(refusal)
+                .ephemeral(true);
+            let builder = CreateInteractionResponse::Message(data);
+            if let Err(why) = comp.create_response(&ctx.http, builder).await {
+                warn!("Could not respond to slash command: {why}");
+            }
+            return;
+        }
 
-                if !ctx
-                    .data
-                    .read()
-                    .await
-                    .get::<SkinUploads>()
-                    .unwrap()
-                    .uploads
-                    .is_empty()
-                {
-                    let data = CreateInteractionResponseMessage::new()
-                        .content("Someone is already uploading skins. Please wait. If the upload disconnected, wait ~2 minutes, until the timeout kicks in.")
-                        .ephemeral(true);
-                    let builder = CreateInteractionResponse::Message(data);
-                    if let Err(why) = command.create_response(&ctx.http, builder).await {
-                        println!("Could not respond to slash command: {why}");
-                    }
-                    return;
+        // reading the reactions of every submission can take longer than discord waits
+        let builder = CreateInteractionResponse::Defer(
+            CreateInteractionResponseMessage::new().ephemeral(true),
+        );
+        if let Err(why) = comp.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+            return;
+        }
+        let content = match resync_reactions(&ctx, comp.user.id).await {
+            Some((0, 0)) => "The queued skins match your reactions".to_string(),
+            Some((queued, unqueued)) => {
+                format!("Resynced with your reactions: {queued} queued, {unqueued} removed")
+            }
+            None => "Cannot change the queued skins at this point anymore".to_string(),
+        };
+        if let Err(why) = comp
+            .edit_response(&ctx.http, EditInteractionResponse::new().content(content))
+            .await
+        {
+            warn!("Could not edit response from command: {why}");
+        }
+    }
+
+    /// Moves the skins picked in the menu of the status message to the other database.
+    async fn upload_switch_database(
+        ctx: Context,
+        comp: &ComponentInteraction,
+        msg_ids: HashSet<MessageId>,
+    ) {
+        let mut data = ctx.data.write().await;
+        let content = match data
+            .get_mut::<SkinUploads>()
+            .unwrap()
+            .uploads
+            .get_mut(&comp.user.id)
+        {
+            Some(item) => {
+                if item.session.queue().is_ok() {
+                    item.send(SessionEvent::DatabaseSwitched { msg_ids });
+                    "The skins are moved to the other database"
+                } else {
+                    "Cannot change the skins at this point anymore"
                 }
+            }
+            None => "You never started an upload using `/upload`.",
+        };
+        drop(data);
+        let data = CreateInteractionResponseMessage::new()
+            .content(content)
+            .ephemeral(true);
+        let builder = CreateInteractionResponse::Message(data);
+        if let Err(why) = comp.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+    }
 
-                if let Some(content) = content {
-                    let data = CreateInteractionResponseMessage::new()
-                        .content(content)
-                        .ephemeral(true)
-                        .add_embeds(vec![main_cmd_embed, main_cmd_end_embed])
-                        .button(
-                            CreateButton::new("ok").emoji(ReactionType::Unicode("🆗".to_string())),
-                        )
-                        .button(
-                            CreateButton::new("cancel")
-                                .emoji(ReactionType::Unicode("🇽".to_string())),
-                        );
-                    let builder = CreateInteractionResponse::Message(data);
-                    if let Err(why) = command.create_response(&ctx.http, builder).await {
-                        println!("Could not respond to slash command: {why}");
-                    } else {
-                        let notify = Arc::new(Notify::new());
-                        ctx.data
-                            .write()
-                            .await
-                            .get_mut::<SkinUploads>()
-                            .unwrap()
-                            .uploads
-                            .insert(
-                                command.user.id,
-                                SkinUploadItem {
-                                    notify: notify.clone(),
-                                    reaction_list: LinkedHashMap::default(),
-                                    skins_try_upload: LinkedHashMap::default(),
-                                    state: SkinUploadState::Collecting,
-                                    errors: VecDeque::default(),
-                                    skins_to_upload: LinkedHashMap::default(),
-                                },
-                            );
-
-                        loop {
-                            let was_notified = select! {
-                                _ = tokio::time::sleep(Duration::from_secs(120)) => {false}
-                                _ = notify.notified() => {true}
-                            };
-
-                            let mut data = ctx.data.write().await;
-                            // if data is still there, tell that the process was cancelled
-                            if let Some(item) = data
-                                .get_mut::<SkinUploads>()
-                                .unwrap()
-                                .uploads
-                                .get_mut(&command.user.id)
-                            {
-                                if was_notified {
-                                    match item.state {
-                                        SkinUploadState::Collecting => {
-                                            // check if all skins are valid
-                                            for (msg_id, msg_database) in
-                                                item.skins_try_upload.drain()
-                                            {
-                                                match ctx
-                                                    .http
-                                                    .get_message(command.channel_id, msg_id)
-                                                    .await
-                                                {
-                                                    Ok(skin_msg) => {
-                                                        let text = skin_msg.content;
-                                                        let mut all_required_info = true;
-                                                        let mut skin_name = String::default();
-                                                        let mut author_name = String::default();
-                                                        let mut license_name = String::default();
-                                                        match parse_skin_info(&text) {
-                                                            Ok((
-                                                                skin_name_res,
-                                                                author_name_res,
-                                                                license_name_res,
-                                                            )) => {
-                                                                skin_name = skin_name_res;
-                                                                author_name = author_name_res;
-                                                                license_name = license_name_res;
-                                                                if let Some(skin) = item
-                                                                    .skins_to_upload
-                                                                    .get(&skin_name)
-                                                                {
-                                                                    if skin.database != msg_database
-                                                                    {
-                                                                        item.errors.push_back(format!(
-                                                                    "you changed the database upload type of: {skin_name}. If you did a mistake cancel the upload and try again."
-                                                                ));
-                                                                        all_required_info = false;
-                                                                    }
-                                                                }
-                                                            }
-                                                            Err(err) => {
-                                                                item.errors
-                                                                    .push_back(err.to_string());
-                                                                all_required_info = false;
-                                                            }
-                                                        }
-                                                        if all_required_info {
-                                                            for attachment in &skin_msg.attachments
-                                                            {
-                                                                if let Ok(file) =
-                                                                    attachment.download().await
-                                                                {
-                                                                    if let Ok(img) =
-                                                                        image::load_from_memory(
-                                                                            &file,
-                                                                        )
-                                                                    {
-                                                                        if let Some(img_rgba) =
-                                                                            img.as_rgba8()
-                                                                        {
-                                                                            if img_rgba.dimensions()
-                                                                                == (256, 128)
-                                                                                || img_rgba
-                                                                                    .dimensions()
-                                                                                    == (512, 256)
-                                                                            {
-                                                                                if !item
-                                                                                    .skins_to_upload
-                                                                                    .contains_key(
-                                                                                        &skin_name,
-                                                                                    )
-                                                                                {
-                                                                                    let mut
-                                                                                    positive_count =
-                                                                                        0;
-                                                                                    let mut
-                                                                                    negative_count =
-                                                                                        0;
-                                                                                    if let Ok(
-                                                                                        original_msg,
-                                                                                    ) = command
-                                                                                        .channel_id
-                                                                                        .message(
-                                                                                            &ctx,
-                                                                                            msg_id,
-                                                                                        )
-                                                                                        .await
-                                                                                    {
-                                                                                        original_msg.reactions.iter().for_each(|reaction| {
-                                                                                        if let ReactionType::Custom { animated: _, id, name: _ } = &reaction.reaction_type {
-                                                                                            // brownbear emoji id
-                                                                                            if id.get() == 346683497701834762 {
-                                                                                                positive_count = reaction.count - 1;
-                                                                                            }
-                                                                                            // cammostripes emoji id
-                                                                                            else if id.get() == 346683496476966913 {
-                                                                                                negative_count = reaction.count - 1;
-                                                                                            }
-                                                                                        }
-                                                                                    });
-                                                                                    }
-                                                                                    item.skins_to_upload.insert(skin_name.clone(), SkinToUpload {
-                                                                                    author: author_name.clone(),
-                                                                                    license: license_name.clone(),
-                                                                                    file_256x128: Vec::new(),
-                                                                                    file_512x256: Vec::new(),
-                                                                                    database: msg_database,
-                                                                                    original_msg_id: msg_id,
-                                                                                    positive_ratio: if positive_count + negative_count > 0 { positive_count as f64 / (positive_count + negative_count) as f64 } else { 0.0 },
-                                                                                });
-                                                                                }
-                                                                                if img_rgba
-                                                                                    .dimensions()
-                                                                                    == (256, 128)
-                                                                                {
-                                                                                    item.skins_to_upload
-                                                                                .get_mut(&skin_name)
-                                                                                .unwrap()
-                                                                                .file_256x128 =
-                                                                                img_rgba.to_vec();
-                                                                                } else {
-                                                                                    item.skins_to_upload
-                                                                                    .get_mut(&skin_name)
-                                                                                    .unwrap()
-                                                                                    .file_512x256 =
-                                                                                    img_rgba.to_vec();
-                                                                                }
-                                                                            } else {
-                                                                                item.errors.push_back(format!("skin: {} did not contain a valid 256x128 or 512x256 skin", skin_name.clone()));
-                                                                            }
-                                                                        } else {
-                                                                            item.errors.push_back("One of the reacted messages contained an image file that could not be converted to RGBA...".to_string());
-                                                                        }
-                                                                    } else {
-                                                                        item.errors.push_back("One of the reacted messages contained an invalid image file...".to_string());
-                                                                    }
-                                                                } else {
-                                                                    item.errors.push_back("One of the reacted messages did not contain a valid skin file...".to_string());
-                                                                }
-                                                            }
-
-                                                            if skin_msg.attachments.is_empty() {
-                                                                item.errors.push_back("No skin file attachments found in one of the messages you reacted to...".to_string());
-                                                            }
-
-                                                            if let Some(skin) =
-                                                                item.skins_to_upload.get(&skin_name)
-                                                            {
-                                                                if skin.file_256x128.is_empty() {
-                                                                    item.skins_to_upload
-                                                                        .remove(&skin_name);
-                                                                    // there must be a non hd skin
-                                                                    item.errors.push_back("The skin ".to_string() + &skin_name + " had no 256x128 skin. This is not allowed");
-                                                                }
-                                                            }
-                                                        }
-                                                    }
-                                                    Err(err) => {
-                                                        println!("{err}");
-                                                        item.errors.push_back("One of the reacted messages was not found anymore...".to_string());
-                                                    }
-                                                }
-                                            }
-                                        }
-                                        SkinUploadState::Uploading | SkinUploadState::Cancelled => {
-                                            if (command.delete_response(&ctx).await).is_err() {
-                                                println!("Response not deleted.");
-                                            }
-                                            data.get_mut::<SkinUploads>()
-                                                .unwrap()
-                                                .uploads
-                                                .remove(&command.user.id);
-                                            break;
-                                        }
-                                    };
-
-                                    // edit msg
-                                    let mut new_msg = main_cmd_str.clone();
-                                    if !item.errors.is_empty() {
-                                        new_msg += "__**Errors**__:\n";
-                                        item.errors.iter().for_each(|err| {
-                                            new_msg += "> - ";
-                                            new_msg += err;
-                                            new_msg += "\n";
-                                        });
-                                    }
-                                    if !item.skins_to_upload.is_empty() {
-                                        new_msg += "__Skins to upload:__\n";
-                                        item.skins_to_upload.iter().for_each(
-                                            |(skin_name, skin)| {
-                                                let mut add_msg = "> - ".to_string();
-                                                if matches!(&skin.database, SkinToUploadDB::Normal)  {
-                                                    add_msg += "✅ ";
-                                                }
-                                                else{
-                                                    add_msg += "☑️ ";
-                                                }
-                                                add_msg += "`";
-                                                add_msg += skin_name;
-                                                add_msg += "` by `";
-                                                add_msg += &skin.author;
-                                                add_msg += "` license: `";
-                                                add_msg += &skin.license;
-                                                add_msg += &format!("` (has 256x128 skin: {}, has 512x256 skin: {})", !skin.file_256x128.is_empty(), !skin.file_512x256.is_empty());
-                                                if skin.positive_ratio > 0.0 {
-                                                    add_msg += &format!(" - positive ratio: {}%", skin.positive_ratio * 100.0);
-                                                }
-                                                add_msg += &format!(
-                                                    " https://discord.com/channels/{}/{}/{}",
-                                                    guild_id,
-                                                    command.channel_id,
-                                                    skin.original_msg_id
-                                                );
-                                                add_msg += "\n";
-                                                new_msg += &add_msg;
-                                            },
-                                        );
-                                    }
-
-                                    if new_msg.chars().count() >= 2000 {
-                                        // try a compact view
-                                        new_msg = main_cmd_str.clone();
-                                        if !item.errors.is_empty() {
-                                            new_msg += &format!(
-                                                "There are {} errors\n",
-                                                item.errors.len()
-                                            );
-                                        }
-                                        if !item.skins_to_upload.is_empty() {
-                                            new_msg += "Upload:\n";
-                                            item.skins_to_upload.iter().for_each(
-                                                |(skin_name, skin)| {
-                                                    let mut add_msg = String::new();
-                                                    if matches!(
-                                                        &skin.database,
-                                                        SkinToUploadDB::Normal
-                                                    ) {
-                                                        add_msg += "✅ ";
-                                                    } else {
-                                                        add_msg += "☑️ ";
-                                                    }
-                                                    add_msg += "`";
-                                                    add_msg += skin_name;
-                                                    add_msg += "` by `";
-                                                    add_msg += &skin.author;
-                                                    add_msg += "` license: `";
-                                                    add_msg += &skin.license;
-                                                    add_msg += "`\n";
-                                                    new_msg += &add_msg;
-                                                },
-                                            );
-                                        }
-                                    }
-                                    // if still over 2000, simply say how many skins to upload
-                                    if new_msg.chars().count() >= 2000 {
-                                        new_msg = main_cmd_str.clone();
-                                        if !item.errors.is_empty() {
-                                            new_msg += &format!(
-                                                "There are {} errors\n",
-                                                item.errors.len()
-                                            );
-                                        }
-                                        if !item.skins_to_upload.is_empty() {
-                                            new_msg += &format!(
-                                                "{} skins will be uploaded\n",
-                                                item.skins_to_upload.len()
-                                            );
-                                        }
-                                    }
-                                    if let Err(err) = command
-                                        .edit_response(
-                                            ctx.clone(),
-                                            EditInteractionResponse::new().content(new_msg),
-                                        )
-                                        .await
-                                    {
-                                        println!("Could not edit response from command: {err}");
-                                    }
-                                } else {
-                                    if let Err(err) = command
-                                    .edit_response(
-                                        ctx.clone(),
-                                        EditInteractionResponse::new().content(
-                                            "Upload timed out. Also only do one upload at a time",
-                                        ),
-                                    )
-                                    .await
-                                    {
-                                        println!("Could not edit response from command: {err}");
-                                    }
-                                    data.get_mut::<SkinUploads>()
-                                        .unwrap()
-                                        .uploads
-                                        .remove(&command.user.id);
-                                    break;
-                                }
-                            } else {
-                                break;
-                            }
-                        }
-                    }
+    async fn upload_select_resampler<'a>(
+        ctx: Context,
+        user_id: UserId,
+        command: &CommandWrapper<'a>,
+        resampler: SdResampler,
+    ) {
+        let mut data = ctx.data.write().await;
+        if let Some(item) = data
+            .get_mut::<SkinUploads>()
+            .unwrap()
+            .uploads
+            .get_mut(&user_id)
+        {
+            if item.session.state().is_collecting() {
+                item.sd_resampler = resampler;
+                let data = CreateInteractionResponseMessage::new()
+                    .content(format!(
+                        "Missing 256x128 skins will be generated using {resampler}"
+                    ))
+                    .ephemeral(true);
+                let builder = CreateInteractionResponse::Message(data);
+                if let Err(why) = command.create_response(&ctx.http, builder).await {
+                    warn!("Could not respond to slash command: {why}");
                 }
+                item.send(SessionEvent::OptionsChanged);
             } else {
                 let data = CreateInteractionResponseMessage::new()
-                    .content(
-                        "You don't have the required permissions to use this command".to_string(),
-                    )
+                    .content("Cannot change the resampler at this point anymore")
                     .ephemeral(true);
                 let builder = CreateInteractionResponse::Message(data);
                 if let Err(why) = command.create_response(&ctx.http, builder).await {
-                    println!("Could not respond to slash command: {why}");
+                    warn!("Could not respond to slash command: {why}");
                 }
             }
+        } else {
+            let data = CreateInteractionResponseMessage::new()
+                .content("You never started an upload using `/upload`.")
+                .ephemeral(true);
+            let builder = CreateInteractionResponse::Message(data);
+            if let Err(why) = command.create_response(&ctx.http, builder).await {
+                warn!("Could not respond to slash command: {why}");
+            }
         }
     }
 
-    async fn reaction_add(&self, ctx: Context, add_reaction: Reaction) {
-        if add_reaction.user_id.is_none() {
+    /// Queues the message (or the whole forum post) a context menu command was used on.
+    async fn queue_message(ctx: Context, command: &CommandInteraction, database: SkinToUploadDB) {
+        let Some(ResolvedTarget::Message(target_msg)) = command.data.target() else {
             return;
+        };
+        let msg_id = forum_post_starter(&ctx, target_msg.channel_id)
+            .await
+            .unwrap_or(target_msg.id);
+        let config = ctx.data.read().await.get::<Config>().unwrap().clone();
+
+        let content = if !is_upload_channel(&ctx, &config, target_msg.channel_id).await {
+            format!(
+                "Skins cannot be uploaded from this channel, only from {}",
+                config.allowed_channels_list()
+            )
+        } else if let Some(item) = ctx
+            .data
+            .write()
+            .await
+            .get_mut::<SkinUploads>()
+            .unwrap()
+            .uploads
+            .get_mut(&command.user.id)
+        {
+            if item.session.queue().is_ok() {
+                item.send(SessionEvent::SkinQueued {
+                    msg_id,
+                    channel_id: target_msg.channel_id,
+                    database,
+                });
+                format!("Queued for the {} database", database.to_string())
+            } else {
+                "Cannot queue skins at this point anymore".to_string()
+            }
+        } else {
+            "You never started an upload, please use `/upload`".to_string()
+        };
+        let data = CreateInteractionResponseMessage::new()
+            .content(content)
+            .ephemeral(true);
+        let builder = CreateInteractionResponse::Message(data);
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
         }
-        if add_reaction.emoji.unicode_eq("✅") {
-            if let Some(skin_upload) = ctx
-                .clone()
-                .data
-                .write()
-                .await
-                .get_mut::<SkinUploads>()
-                .unwrap()
-                .uploads
-                .get_mut(&add_reaction.user_id.unwrap())
-            {
-                skin_upload
-                    .reaction_list
-                    .insert(add_reaction.message_id, add_reaction.user_id.unwrap());
-                if let Ok(msg) = add_reaction.message(&ctx).await {
-                    if (msg
-                        .delete_reaction_emoji(&ctx, ReactionType::Unicode("☑️".to_string()))
-                        .await)
-                        .is_err()
-                    {
-                        println!("no permissions to delete reaction");
-                    }
-                    // remove the already inserted skin, if any
-                    if let Ok((skin_name, _, _)) = parse_skin_info(&msg.content) {
-                        skin_upload.skins_to_upload.remove(&skin_name);
-                    }
-                }
-                skin_upload
-                    .skins_try_upload
-                    .insert(add_reaction.message_id, SkinToUploadDB::Normal);
-                skin_upload.notify.notify_one();
+    }
+
+    /// Drops the message a context menu command was used on from the upload again.
+    async fn unqueue_message(ctx: Context, command: &CommandInteraction) {
+        let Some(ResolvedTarget::Message(target_msg)) = command.data.target() else {
+            return;
+        };
+        let msg_id = forum_post_starter(&ctx, target_msg.channel_id)
+            .await
+            .unwrap_or(target_msg.id);
+
+        let content = if let Some(item) = ctx
+            .data
+            .write()
+            .await
+            .get_mut::<SkinUploads>()
+            .unwrap()
+            .uploads
+            .get_mut(&command.user.id)
+        {
+            if item.session.queue().is_ok() {
+                item.send(SessionEvent::SkinUnqueued { msg_id });
+                "Removed from the upload".to_string()
+            } else {
+                "Cannot remove skins at this point anymore".to_string()
             }
-        } else if add_reaction.emoji.unicode_eq("☑️") {
-            if let Some(skin_upload) = ctx
-                .clone()
-                .data
-                .write()
-                .await
-                .get_mut::<SkinUploads>()
-                .unwrap()
-                .uploads
-                .get_mut(&add_reaction.user_id.unwrap())
-            {
-                skin_upload
-                    .reaction_list
-                    .insert(add_reaction.message_id, add_reaction.user_id.unwrap());
-                if let Ok(msg) = add_reaction.message(&ctx).await {
-                    if (msg
-                        .delete_reaction_emoji(&ctx, ReactionType::Unicode("✅".to_string()))
-                        .await)
-                        .is_err()
-                    {
-                        println!("no permissions to delete reaction");
-                    }
-                    // remove the already inserted skin, if any
-                    if let Ok((skin_name, _, _)) = parse_skin_info(&msg.content) {
-                        skin_upload.skins_to_upload.remove(&skin_name);
-                    }
-                }
-                skin_upload
-                    .reaction_list
-                    .insert(add_reaction.message_id, add_reaction.user_id.unwrap());
-                skin_upload
-                    .skins_try_upload
-                    .insert(add_reaction.message_id, SkinToUploadDB::Community);
-                skin_upload.notify.notify_one();
+        } else {
+            "You never started an upload, please use `/upload`".to_string()
+        };
+        let data = CreateInteractionResponseMessage::new()
+            .content(content)
+            .ephemeral(true);
+        let builder = CreateInteractionResponse::Message(data);
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+    }
+
+    /// Downloads and checks a single queued skin again, the others are kept as they are.
+    async fn revalidate(ctx: Context, command: &CommandInteraction) {
+        let name = command
+            .data
+            .options
+            .iter()
+            .find_map(|option| match (option.name.as_str(), &option.value) {
+                ("name", CommandDataOptionValue::String(value)) => Some(value.trim().to_string()),
+                _ => None,
+            })
+            .unwrap_or_default();
+        let content = if let Some(item) = ctx
+            .data
+            .write()
+            .await
+            .get_mut::<SkinUploads>()
+            .unwrap()
+            .uploads
+            .get_mut(&command.user.id)
+        {
+            if item.session.queue().is_ok() {
+                let content = format!("`{name}` is checked again, the summary shows the result");
+                item.send(SessionEvent::SkinRevalidated { name });
+                content
+            } else {
+                "Cannot check skins again at this point anymore".to_string()
             }
+        } else {
+            "You never started an upload, please use `/upload`".to_string()
+        };
+        let data = CreateInteractionResponseMessage::new()
+            .content(content)
+            .ephemeral(true);
+        let builder = CreateInteractionResponse::Message(data);
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
         }
     }
 
-    async fn reaction_remove(&self, ctx: Context, removed_reaction: Reaction) {
-        if removed_reaction.user_id.is_none() {
+    /// Marks the forum post a context menu command was used on as rejected.
+    async fn reject_submission(ctx: Context, command: &CommandInteraction, config: &Config) {
+        let Some(ResolvedTarget::Message(target_msg)) = command.data.target() else {
             return;
-        }
-        if removed_reaction.emoji.unicode_eq("✅") || removed_reaction.emoji.unicode_eq("☑️") {
-            if let Some(skin_upload) = ctx
-                .clone()
+        };
+        let content = if let Some(msg_id) = forum_post_starter(&ctx, target_msg.channel_id).await {
+            // don't upload it by accident, if it was queued before
+            if let Some(item) = ctx
                 .data
                 .write()
                 .await
                 .get_mut::<SkinUploads>()
                 .unwrap()
                 .uploads
-                .get_mut(&removed_reaction.user_id.unwrap())
+                .get_mut(&command.user.id)
             {
-                skin_upload
-                    .reaction_list
-                    .remove(&removed_reaction.message_id);
-                if let Ok(msg) = removed_reaction.message(&ctx).await {
-                    // remove the already inserted skin, if any
-                    if let Ok((skin_name, _, _)) = parse_skin_info(&msg.content) {
-                        skin_upload.skins_to_upload.remove(&skin_name);
-                    }
+                if item.session.queue().is_ok() {
+                    item.send(SessionEvent::SkinUnqueued { msg_id });
                 }
-                skin_upload
-                    .skins_try_upload
-                    .remove(&removed_reaction.message_id);
-                skin_upload.notify.notify_one();
             }
+            match tag_forum_post(
+                &ctx,
+                target_msg.channel_id,
+                ForumDecision::Rejected,
+                &config.forum,
+            )
+            .await
+            {
+                Ok(()) => "Skin rejected".to_string(),
+                Err(err) => format!("Could not tag the forum post: {err}"),
+            }
+        } else {
+            "Only forum posts can be rejected".to_string()
+        };
+        let data = CreateInteractionResponseMessage::new()
+            .content(content)
+            .ephemeral(true);
+        let builder = CreateInteractionResponse::Message(data);
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
         }
     }
 
-    async fn ready(&self, ctx: Context, _ready: Ready) {
-        let guild_id = GuildId::new(
-            env::var("GUILD_ID")
-                .expect("Expected GUILD_ID in environment")
-                .parse()
-                .expect("GUILD_ID must be an integer"),
-        );
+    async fn vote_round(ctx: Context, guild_id: GuildId, command: &CommandInteraction) {
+        let mut name = String::default();
+        let mut days = 7;
+        let mut create_event = true;
+        for option in &command.data.options {
+            match (option.name.as_str(), &option.value) {
+                ("name", CommandDataOptionValue::String(value)) => name = value.clone(),
+                ("days", CommandDataOptionValue::Integer(value)) => days = *value as u64,
+                ("create_event", CommandDataOptionValue::Boolean(value)) => create_event = *value,
+                _ => {}
+            }
+        }
 
-        let upload_cmd = CreateCommand::new("upload")
-            .description("Upload a skin to the database")
-            .dm_permission(false);
-        let upload_finish_cmd = CreateCommand::new("upload_finish")
-            .description("Finish an upload, previously started with the `/upload` command")
-            .dm_permission(false);
+        let content =
+            match start_vote_round(&ctx, guild_id, command.channel_id, name, days, create_event)
+                .await
+            {
+                Ok(()) => "Voting round started".to_string(),
+                Err(err) => format!("Could not start the voting round: {err}"),
+            };
+        let data = CreateInteractionResponseMessage::new()
+            .content(content)
+            .ephemeral(true);
+        let builder = CreateInteractionResponse::Message(data);
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+    }
 
-        let upload_cancel_cmd = CreateCommand::new("upload_cancel")
-            .description("Cancel an ongoing upload, that was started using the `/upload` command")
-            .dm_permission(false);
+    /// Queues the submissions of the last days in the channel of the command,
+    /// for the upload session it started.
+    async fn upload_scan(ctx: Context, command: CommandInteraction, config: Arc<Config>) {
+        let mut days = 7;
+        let mut limit = 50;
+        for option in &command.data.options {
+            match (option.name.as_str(), &option.value) {
+                ("days", CommandDataOptionValue::Integer(value)) => days = *value,
+                ("limit", CommandDataOptionValue::Integer(value)) => limit = *value as usize,
+                _ => {}
+            }
+        }
 
-        if (guild_id
-            .set_commands(
-                &ctx.http,
-                vec![upload_cmd, upload_finish_cmd, upload_cancel_cmd],
+        let since = Timestamp::now().unix_timestamp() - days * 24 * 60 * 60;
+        let submissions =
+            fetch_submissions(&ctx.http, command.channel_id, message_id_at(since), limit).await;
+        let queued = queue_submissions(&ctx, command.user.id, &submissions, &config).await;
+        if let Err(err) = command
+            .create_followup(
+                &ctx,
+                CreateInteractionResponseFollowup::new()
+                    .content(format!(
+                        "Queued {queued} skins of the last {days} days, remove the ones that should not be uploaded with `Apps > Remove from upload`"
+                    ))
+                    .ephemeral(true),
             )
-            .await)
-            .is_err()
+            .await
         {
-            // ignore for now
+            warn!("Could not send the scan result: {err}");
+        }
+    }
+
+    /// Replies with the leaderboard of the skins posted in this channel during the last days.
+    async fn skin_votes(ctx: Context, guild_id: GuildId, command: &CommandInteraction) {
+        let mut days = 7;
+        for option in &command.data.options {
+            if let ("days", CommandDataOptionValue::Integer(value)) =
+                (option.name.as_str(), &option.value)
+            {
+                days = *value;
+            }
+        }
+
+        // fetching the channel history can take longer than discord waits for a response
+        let builder = CreateInteractionResponse::Defer(
+            CreateInteractionResponseMessage::new().ephemeral(true),
+        );
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+            return;
+        }
+
+        let emojis = ctx
+            .data
+            .read()
+            .await
+            .get::<Config>()
+            .unwrap()
+            .emojis
+            .clone();
+        let since = Timestamp::now().unix_timestamp() - days * 24 * 60 * 60;
+        let results =
+            tally_votes(&ctx.http, command.channel_id, message_id_at(since), &emojis).await;
+        let msgs = leaderboard_messages(
+            format!("__**:ballot_box: Votes of the skins posted in the last {days} days**__\n"),
+            &results,
+            guild_id,
+        );
+        for (i, msg) in msgs.into_iter().enumerate() {
+            let res = if i == 0 {
+                command
+                    .edit_response(&ctx, EditInteractionResponse::new().content(msg))
+                    .await
+                    .map(|_| ())
+            } else {
+                command
+                    .create_followup(
+                        &ctx,
+                        CreateInteractionResponseFollowup::new()
+                            .content(msg)
+                            .ephemeral(true),
+                    )
+                    .await
+                    .map(|_| ())
+            };
+            if let Err(err) = res {
+                warn!("Could not send the vote leaderboard: {err}");
+            }
+        }
+    }
+
+    /// Replies with the uploads of a month from the batch history, optionally as csv.
+    async fn skin_stats(ctx: Context, command: &CommandInteraction) {
+        // rfc 3339, starts with the month
+        let mut month: String = Timestamp::now().to_string().chars().take(7).collect();
+        let mut with_csv = false;
+        for option in &command.data.options {
+            match (option.name.as_str(), &option.value) {
+                ("month", CommandDataOptionValue::String(value)) => {
+                    month = value.trim().to_string()
+                }
+                ("csv", CommandDataOptionValue::Boolean(value)) => with_csv = *value,
+                _ => {}
+            }
+        }
+        let data = match month_period(&month) {
+            None => CreateInteractionResponseMessage::new()
+                .content(format!("`{month}` is not a month like `2024-05`")),
+            Some((since, until)) => {
+                let history = ctx.data.read().await.get::<BatchHistory>().unwrap().clone();
+                let batches = history.all();
+                let stats = upload_stats(&batches, since, until);
+                let top_authors = stats
+                    .top_authors
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (author, count))| format!("{}. {author}: {count}", i + 1))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let embed = CreateEmbed::new()
+                    .title(format!("Skin uploads of {month}"))
+                    .color(Colour::TEAL)
+                    .field(
+                        "Skins",
+                        format!(
+                            "{} normal, {} community in {} batches",
+                            stats.normal, stats.community, stats.batches
+                        ),
+                        false,
+                    )
+                    .field(
+                        "Top authors",
+                        if top_authors.is_empty() {
+                            "-".to_string()
+                        } else {
+                            top_authors
+                        },
+                        true,
+                    )
+                    .field(
+                        "Votes",
+                        stats.average_ratio.map_or_else(
+                            || "not recorded".to_string(),
+                            |ratio| format!("{:.0}% positive on average", ratio * 100.0),
+                        ),
+                        true,
+                    )
+                    .field(
+                        "Busiest day",
+                        stats.busiest_day.map_or_else(
+                            || "-".to_string(),
+                            |(day, count)| format!("{day} with {count} skins"),
+                        ),
+                        true,
+                    );
+                let mut data = CreateInteractionResponseMessage::new().embed(embed);
+                if with_csv {
+                    data = data.add_file(CreateAttachment::bytes(
+                        stats_csv(&batches, since, until).into_bytes(),
+                        format!("skin_uploads_{month}.csv"),
+                    ));
+                }
+                data
+            }
+        };
+        // names of authors are written as they are, nothing may ping
+        let data = data
+            .allowed_mentions(CreateAllowedMentions::new())
+            .ephemeral(true);
+        let builder = CreateInteractionResponse::Message(data);
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+    }
+
+    /// Asks for a confirmation of `/skin_delete` or `/skin_update`, before the database is changed.
+    async fn skin_edit(ctx: Context, command: &CommandInteraction, config: &Config) {
+        let mut name = String::new();
+        let mut database = SkinToUploadDB::Normal;
+        let (mut author, mut license, mut skin_pack) = (None, None, None);
+        for option in &command.data.options {
+            let CommandDataOptionValue::String(value) = &option.value else {
+                continue;
+            };
+            match option.name.as_str() {
+                "name" => name = value.clone(),
+                "database" if value == "community" => database = SkinToUploadDB::Community,
+                "author" => author = Some(value.clone()),
+                "license" => license = Some(value.clone()),
+                "skin_pack" => skin_pack = Some(value.clone()),
+                _ => {}
+            }
+        }
+
+        // the rules of today apply, like for a new submission
+        let license_rules = LicenseRules::new(&config.license);
+        let license_policy = license_rules.at(Timestamp::now());
+        let license = match license.map(|license| license_policy.validate(&license)) {
+            Some(Err(license)) if license_policy.rejects_unknown() => {
+                Err(format!("The license `{license}` is not allowed"))
+            }
+            Some(Ok(license) | Err(license)) => Ok(Some(license)),
+            None => Ok(None),
+        };
+        let edit = match (command.data.name.as_str(), license) {
+            (_, Err(err)) => Err(err),
+            ("skin_delete", _) => Ok(SkinEdit::Delete { name, database }),
+            (_, Ok(None)) if author.is_none() && skin_pack.is_none() => {
+                Err("Nothing to update, pass an author, license or skin pack".to_string())
+            }
+            (_, Ok(license)) => Ok(SkinEdit::Update {
+                name,
+                database,
+                author,
+                license,
+                skin_pack,
+            }),
+        };
+
+        let data = match edit {
+            Ok(edit) => {
+                let is_delete = matches!(edit, SkinEdit::Delete { .. });
+                let data = CreateInteractionResponseMessage::new()
+                    .content(edit.describe())
+                    .components(vec![CreateActionRow::Buttons(vec![
+                        CreateButton::new(CONFIRM_EDIT_BUTTON_ID)
+                            .label(if is_delete { "Delete" } else { "Update" })
+                            .style(if is_delete {
+                                ButtonStyle::Danger
+                            } else {
+                                ButtonStyle::Primary
+                            }),
+                        CreateButton::new(CANCEL_EDIT_BUTTON_ID)
+                            .label("Cancel")
+                            .style(ButtonStyle::Secondary),
+                    ])]);
+                ctx.data
+                    .write()
+                    .await
+                    .get_mut::<PendingSkinEdits>()
+                    .unwrap()
+                    .insert(command.user.id, edit);
+                data
+            }
+            Err(err) => CreateInteractionResponseMessage::new().content(err),
+        };
+        let builder = CreateInteractionResponse::Message(data.ephemeral(true));
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+    }
+
+    /// Sends the edit the moderator confirmed to the database, or drops it.
+    async fn confirm_skin_edit(ctx: Context, comp: &ComponentInteraction, confirmed: bool) {
+        let edit = ctx
+            .data
+            .write()
+            .await
+            .get_mut::<PendingSkinEdits>()
+            .unwrap()
+            .remove(&comp.user.id);
+        let content = match (&edit, confirmed) {
+            (None, _) => "This edit was already handled".to_string(),
+            (Some(_), false) => "Edit cancelled".to_string(),
+            (Some(edit), true) => {
+                format!("{}\nSending the change to the database...", edit.describe())
+            }
+        };
+        let builder = CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .content(content)
+                .components(vec![]),
+        );
+        if let Err(why) = comp.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+        let Some(edit) = edit.filter(|_| confirmed) else {
+            return;
+        };
+
+        let upload_lock = ctx
+            .data
+            .read()
+            .await
+            .get::<SkinUploads>()
+            .unwrap()
+            .upload_lock
+            .clone();
+        // don't interfere with an upload that is in progress
+        let _g = upload_lock.lock().await;
+        let description = edit.describe();
+        let (action, skin_name) = (edit.action(), edit.name().to_string());
+        let database = ctx.data.read().await.get::<SkinDatabase>().unwrap().clone();
+        let res = tokio::task::spawn_blocking(move || database.modify(&edit.fields()))
+            .await
+            .unwrap();
+        let audit_log = ctx.data.read().await.get::<AuditLog>().unwrap().clone();
+        audit_log.record(comp.user.id, &action, &skin_name, &res);
+        let content = match res {
+            Ok(()) => format!("{description}\nDone"),
+            Err(err) => format!("{description}\nFailed: {err}"),
+        };
+        if let Err(err) = comp
+            .edit_response(&ctx, EditInteractionResponse::new().content(content))
+            .await
+        {
+            warn!("Could not edit the skin edit response: {err}");
+        }
+    }
+
+    /// Replies with a zip of the checked skins in the layout of the client `skins` folder,
+    /// processed like they would be uploaded.
+    async fn upload_export(ctx: Context, command: &CommandInteraction, config: &Config) {
+        let (reply, collected_skins) = oneshot::channel();
+        let options = {
+            let data = ctx.data.read().await;
+            let item = data
+                .get::<SkinUploads>()
+                .unwrap()
+                .uploads
+                .get(&command.user.id);
+            item.map(|item| {
+                item.send(SessionEvent::ExportRequested(reply));
+                (item.apply_cleanup, item.sd_resampler)
+            })
+        };
+        let Some((apply_cleanup, sd_resampler)) = options else {
+            let data = CreateInteractionResponseMessage::new()
+                .content("You never started an upload, please use `/upload`")
+                .ephemeral(true);
+            let builder = CreateInteractionResponse::Message(data);
+            if let Err(why) = command.create_response(&ctx.http, builder).await {
+                warn!("Could not respond to slash command: {why}");
+            }
+            return;
+        };
+
+        let builder = CreateInteractionResponse::Defer(
+            CreateInteractionResponseMessage::new().ephemeral(true),
+        );
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+            return;
+        }
+
+        let Ok(skins) = collected_skins.await else {
+            warn!("The upload session ended before the skins were exported");
+            return;
+        };
+        let response = if skins.is_empty() {
+            EditInteractionResponse::new().content("There are no checked skins to export yet")
+        } else {
+            let dilate_options = config.dilate.options();
+            let skin_names: Vec<_> = skins.keys().cloned().collect();
+            let zip = tokio::task::spawn_blocking(move || {
+                let files: Vec<_> = skins
+                    .into_iter()
+                    .flat_map(|(skin_name, skin)| {
+                        let file_256x128 = if skin.file_256x128.is_empty() {
+                            downscale_hd(&skin.file_512x256, sd_resampler)
+                        } else {
+                            skin.file_256x128
+                        };
+                        // the client reads the skins folder, the uhd folder mirrors the database
+                        [
+                            (format!("skins/{skin_name}.png"), file_256x128, 256, 128),
+                            (
+                                format!("skins/uhd/{skin_name}.png"),
+                                skin.file_512x256,
+                                512,
+                                256,
+                            ),
+                        ]
+                    })
+                    .filter(|(_, img, _, _)| !img.is_empty())
+                    .map(|(path, img, w, h)| {
+                        let png = processed_png(img, w, h, apply_cleanup, &dilate_options);
+                        (path, png)
+                    })
+                    .collect();
+                create_zip(&files)
+            })
+            .await
+            .unwrap();
+            let mut content = format!(
+                "{} skins, extract the zip into the DDNet config directory to try them in game",
+                skin_names.len()
+            );
+            match notify_test_server(&config.test_server, zip.clone(), &skin_names).await {
+                Ok(true) => content += "\nThe test server reloads the skins",
+                Ok(false) => {}
+                Err(err) => {
+                    warn!("Could not notify the test server: {err:#}");
+                    content += &format!("\nCould not notify the test server: {err:#}");
+                }
+            }
+            EditInteractionResponse::new()
+                .content(content)
+                .new_attachment(CreateAttachment::bytes(zip, "skins.zip"))
+        };
+        if let Err(err) = command.edit_response(&ctx, response).await {
+            warn!("Could not send the exported skins: {err}");
+        }
+    }
+
+    /// Shows the last entries of the audit log.
+    async fn upload_log(ctx: Context, command: &CommandInteraction) {
+        let mut count = 10;
+        for option in &command.data.options {
+            if let ("count", CommandDataOptionValue::Integer(value)) =
+                (option.name.as_str(), &option.value)
+            {
+                count = *value as usize;
+            }
+        }
+
+        let audit_log = ctx.data.read().await.get::<AuditLog>().unwrap().clone();
+        let entries = audit_log.last(count);
+        let content = if entries.is_empty() {
+            "Nothing was uploaded yet".to_string()
+        } else {
+            // the newest entries are kept, if they don't all fit into a message
+            let mut log = String::new();
+            for entry in entries.iter().rev() {
+                if log.chars().count() + entry.chars().count() > 1900 {
+                    break;
+                }
+                log = format!("{entry}\n{log}");
+            }
+            format!("```\n{log}```")
+        };
+        let data = CreateInteractionResponseMessage::new()
+            .content(content)
+            .ephemeral(true);
+        let builder = CreateInteractionResponse::Message(data);
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+    }
+
+    /// `/batches list`, `/batches show <label>` and `/batches import`, from the batch history.
+    async fn batches(ctx: Context, command: &CommandInteraction) {
+        let history = ctx.data.read().await.get::<BatchHistory>().unwrap().clone();
+        let Some(subcommand) = command.data.options.first() else {
+            return;
+        };
+        let CommandDataOptionValue::SubCommand(options) = &subcommand.value else {
+            return;
+        };
+        if subcommand.name == "import" {
+            Self::import_batches(ctx, command, options, &history).await;
+            return;
+        }
+        if subcommand.name == "rerender" {
+            Self::rerender_previews(ctx, command, &history).await;
+            return;
+        }
+        let content = match subcommand.name.as_str() {
+            "show" => {
+                let label = options
+                    .iter()
+                    .find_map(|option| match (option.name.as_str(), &option.value) {
+                        ("label", CommandDataOptionValue::String(value)) => Some(value.as_str()),
+                        _ => None,
+                    })
+                    .unwrap_or_default();
+                match history.find(label) {
+                    Some(batch) => batch_details(&batch),
+                    None => {
+                        format!("No batch is labeled `{label}`, `/batches list` shows the labels")
+                    }
+                }
+            }
+            _ => {
+                let count = options
+                    .iter()
+                    .find_map(|option| match (option.name.as_str(), &option.value) {
+                        ("count", CommandDataOptionValue::Integer(value)) => Some(*value as usize),
+                        _ => None,
+                    })
+                    .unwrap_or(10);
+                let batches = history.all();
+                if batches.is_empty() {
+                    "No batch was uploaded yet".to_string()
+                } else {
+                    let mut list = String::new();
+                    for batch in batches.iter().rev().take(count) {
+                        let line = format!(
+                            "- `{}` <t:{}:f> by {}: {} of {} skins uploaded{}\n",
+                            batch.label,
+                            batch.finished_at,
+                            batch.uploader_mention(),
+                            batch.uploaded_count(),
+                            batch.skins.len(),
+                            if batch.undone_at.is_some() {
+                                " (undone)"
+                            } else {
+                                ""
+                            }
+                        );
+                        if list.chars().count() + line.chars().count() > 1900 {
+                            break;
+                        }
+                        list += &line;
+                    }
+                    list
+                }
+            }
+        };
+        let data = CreateInteractionResponseMessage::new()
+            .content(content)
+            .ephemeral(true);
+        let builder = CreateInteractionResponse::Message(data);
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+    }
+
+    /// Records the uploads from before the batch history existed, read from a csv or from the
+    /// announcements of the bot. Batches that are recorded already are skipped, so the import
+    /// can be repeated.
+    async fn import_batches(
+        ctx: Context,
+        command: &CommandInteraction,
+        options: &[CommandDataOption],
+        history: &BatchHistory,
+    ) {
+        if !is_admin(command.member.as_deref()) {
+            let data = CreateInteractionResponseMessage::new()
+                .content("Only admins can import batches")
+                .ephemeral(true);
+            let builder = CreateInteractionResponse::Message(data);
+            if let Err(why) = command.create_response(&ctx.http, builder).await {
+                warn!("Could not respond to slash command: {why}");
+            }
+            return;
+        }
+        let templates = ctx
+            .data
+            .read()
+            .await
+            .get::<Config>()
+            .unwrap()
+            .templates
+            .clone();
+        let mut channel_id = command.channel_id;
+        let mut days = 365;
+        let mut csv = None;
+        for option in options {
+            match (option.name.as_str(), &option.value) {
+                ("channel", CommandDataOptionValue::Channel(id)) => channel_id = *id,
+                ("days", CommandDataOptionValue::Integer(value)) => days = *value,
+                ("csv", CommandDataOptionValue::Attachment(id)) => {
+                    csv = command.data.resolved.attachments.get(id).cloned();
+                }
+                _ => {}
+            }
+        }
+
+        // reading the channel history can take longer than discord waits for a response
+        let builder = CreateInteractionResponse::Defer(
+            CreateInteractionResponseMessage::new().ephemeral(true),
+        );
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+            return;
+        }
+
+        let batches = match csv {
+            Some(csv) => match csv.download().await {
+                Ok(csv) => batches_from_csv(&String::from_utf8_lossy(&csv)),
+                Err(err) => Err(format!("the csv could not be downloaded: {err}")),
+            },
+            None => {
+                let since = Timestamp::now().unix_timestamp() - days * 24 * 60 * 60;
+                let bot_id = ctx.cache.current_user().id;
+                let msgs =
+                    fetch_announcements(&ctx.http, channel_id, bot_id, message_id_at(since)).await;
+                let announcements: Vec<_> = msgs.iter().map(PastAnnouncement::of).collect();
+                Ok(batches_from_announcements(&announcements, &templates))
+            }
+        };
+        let content = match batches {
+            Ok(batches) => {
+                let found = batches.len();
+                let imported = history.import(batches);
+                format!(
+                    "Imported {imported} of {found} batches, {} were recorded already",
+                    found - imported
+                )
+            }
+            Err(err) => format!("Nothing was imported, {err}"),
+        };
+        if let Err(err) = command
+            .edit_response(&ctx, EditInteractionResponse::new().content(content))
+            .await
+        {
+            warn!("Could not edit the import response: {err}");
+        }
+    }
+
+    /// Draws the tee previews of the announcements again for the batches whose previews are
+    /// from an older renderer. Only previews announced next to their skin png can be redrawn.
+    async fn rerender_previews(ctx: Context, command: &CommandInteraction, history: &BatchHistory) {
+        if !is_admin(command.member.as_deref()) {
+            let data = CreateInteractionResponseMessage::new()
+                .content("Only admins can draw the previews again")
+                .ephemeral(true);
+            let builder = CreateInteractionResponse::Message(data);
+            if let Err(why) = command.create_response(&ctx.http, builder).await {
+                warn!("Could not respond to slash command: {why}");
+            }
+            return;
+        }
+        // every announcement is downloaded and edited
+        let builder = CreateInteractionResponse::Defer(
+            CreateInteractionResponseMessage::new().ephemeral(true),
+        );
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+            return;
+        }
+
+        let outdated: Vec<_> = history
+            .all()
+            .into_iter()
+            .filter(BatchRecord::has_outdated_previews)
+            .collect();
+        let (mut drawn, mut without_skin, mut failed_batches) = (0, 0, 0);
+        for batch in outdated.iter().take(MAX_RERENDER_BATCHES) {
+            let mut failed = false;
+            for link in &batch.announcements {
+                match Self::rerender_announcement(&ctx, link).await {
+                    Ok((redrawn, missing)) => {
+                        drawn += redrawn;
+                        without_skin += missing;
+                    }
+                    Err(err) => {
+                        warn!("Could not draw the previews of {link} again: {err:#}");
+                        failed = true;
+                    }
+                }
+            }
+            if failed {
+                failed_batches += 1;
+            } else {
+                history.record_rerender(
+                    &batch.label,
+                    TEE_RENDERER_VERSION,
+                    Timestamp::now().unix_timestamp(),
+                );
+            }
+        }
+        let mut content = format!(
+            "Drew {drawn} previews of {} batches again with renderer version {TEE_RENDERER_VERSION}",
+            outdated.len().min(MAX_RERENDER_BATCHES) - failed_batches
+        );
+        if without_skin > 0 {
+            content += &format!(
+                "\n{without_skin} previews were announced without their skin and can't be drawn again"
+            );
+        }
+        if failed_batches > 0 {
+            content += &format!(
+                "\n{failed_batches} batches failed and stay outdated, the log has the reasons"
+            );
+        }
+        if outdated.len() > MAX_RERENDER_BATCHES {
+            content += &format!(
+                "\nRun it again for the other {} batches",
+                outdated.len() - MAX_RERENDER_BATCHES
+            );
+        }
+        if let Err(err) = command
+            .edit_response(&ctx, EditInteractionResponse::new().content(content))
+            .await
+        {
+            warn!("Could not edit the rerender response: {err}");
+        }
+    }
+
+    /// Replaces every `<name>_tee.png` of an announcement of the bot by a new drawing of the
+    /// `<name>.png` next to it. Returns how many were drawn and how many have no skin attached.
+    async fn rerender_announcement(ctx: &Context, link: &str) -> anyhow::Result<(usize, usize)> {
+        let mut ids = link.rsplit('/').filter_map(|id| id.parse().ok());
+        let (Some(msg_id), Some(channel_id)) = (ids.next(), ids.next()) else {
+            anyhow::bail!("`{link}` is not a message link");
+        };
+        let mut msg = ChannelId::new(channel_id)
+            .message(&ctx.http, MessageId::new(msg_id))
+            .await?;
+        let mut attachments = EditAttachments::keep_all(&msg);
+        let (mut drawn, mut without_skin) = (0, 0);
+        for tee in &msg.attachments {
+            let Some(name) = tee.filename.strip_suffix("_tee.png") else {
+                continue;
+            };
+            let Some(skin) = msg
+                .attachments
+                .iter()
+                .find(|skin| skin.filename == format!("{name}.png"))
+            else {
+                without_skin += 1;
+                continue;
+            };
+            let skin = skin.download().await?;
+            let png = tokio::task::spawn_blocking(move || render_tee_png(&skin)).await??;
+            attachments = attachments
+                .remove(tee.id)
+                .add(CreateAttachment::bytes(png, tee.filename.clone()));
+            drawn += 1;
+        }
+        if drawn > 0 {
+            msg.edit(ctx, EditMessage::new().attachments(attachments))
+                .await?;
+        }
+        Ok((drawn, without_skin))
+    }
+
+    /// Adds, removes or lists the blocked authors, users and skin names.
+    async fn blocklist(ctx: Context, command: &CommandInteraction) {
+        let Some(subcommand) = command.data.options.first() else {
+            return;
+        };
+        let CommandDataOptionValue::SubCommand(options) = &subcommand.value else {
+            return;
+        };
+        let option = |name: &str| {
+            options.iter().find_map(|option| match &option.value {
+                CommandDataOptionValue::String(value) if option.name == name => {
+                    Some(value.trim().to_string())
+                }
+                _ => None,
+            })
+        };
+        let kind = option("kind").as_deref().and_then(BlockKind::from_option);
+        let value = option("value").unwrap_or_default();
+        // users can be given as mention
+        let value = match kind {
+            Some(BlockKind::User) => value
+                .trim_start_matches("<@")
+                .trim_start_matches('!')
+                .trim_end_matches('>')
+                .to_string(),
+            _ => value,
+        };
+        let mut data = ctx.data.write().await;
+        let blocklist = data.get_mut::<Blocklist>().unwrap();
+        let content = match (subcommand.name.as_str(), kind) {
+            ("add" | "remove", Some(BlockKind::User)) if value.parse::<u64>().is_err() => {
+                format!("`{value}` is not a user id or mention")
+            }
+            ("add", Some(kind)) => {
+                let reason = option("reason").unwrap_or_default();
+                let entry = BlockEntry {
+                    kind,
+                    value: value.clone(),
+                    reason: reason.clone(),
+                    added_by: command.user.id.get(),
+                    added_at: Timestamp::now().unix_timestamp(),
+                };
+                match blocklist.add(entry) {
+                    Ok(()) => format!(
+                        "Skins of the {} `{value}` are rejected from now on: {reason}",
+                        kind.label()
+                    ),
+                    Err(err) => {
+                        error!("Could not store the blocklist: {err}");
+                        "Could not store the blocklist".to_string()
+                    }
+                }
+            }
+            ("remove", Some(kind)) => match blocklist.remove(kind, &value) {
+                Ok(true) => format!("The {} `{value}` is no longer blocked", kind.label()),
+                Ok(false) => format!("The {} `{value}` was not blocked", kind.label()),
+                Err(err) => {
+                    error!("Could not store the blocklist: {err}");
+                    "Could not store the blocklist".to_string()
+                }
+            },
+            _ if blocklist.entries().is_empty() => "Nothing is blocked".to_string(),
+            _ => {
+                let mut list = String::new();
+                for entry in blocklist.entries() {
+                    let line = format!(
+                        "- {} `{}` by {} <t:{}:d>: {}\n",
+                        entry.kind.label(),
+                        entry.value,
+                        Mention::User(UserId::new(entry.added_by)),
+                        entry.added_at,
+                        entry.reason
+                    );
+                    if list.chars().count() + line.chars().count() > 1900 {
+                        list += "…";
+                        break;
+                    }
+                    list += &line;
+                }
+                list
+            }
+        };
+        drop(data);
+        let data = CreateInteractionResponseMessage::new()
+            .content(content)
+            .ephemeral(true);
+        let builder = CreateInteractionResponse::Message(data);
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+    }
+
+    /// Asks to confirm the deletion of the skins of the last batch,
+    /// only its uploader or an admin can undo it.
+    async fn upload_undo(ctx: Context, command: &CommandInteraction, config: &Config) {
+        let history = ctx.data.read().await.get::<BatchHistory>().unwrap().clone();
+        let refusal = match history.last() {
+            None => Err("No batch was uploaded yet".to_string()),
+            Some(batch)
+                if batch.uploader != command.user.id.get()
+                    && !is_admin(command.member.as_deref()) =>
+            {
+                Err(format!(
+                    "The last batch `{}` was uploaded by {}, only they or an admin can undo it",
+                    batch.label,
+                    batch.uploader_mention()
+                ))
+            }
+            Some(batch) => match batch.undo_refusal(
+                config.upload.undo_window_mins,
+                Timestamp::now().unix_timestamp(),
+            ) {
+                Some(refusal) => Err(refusal),
+                None => Ok(batch),
+            },
+        };
+        let data = match refusal {
+            Err(refusal) => CreateInteractionResponseMessage::new().content(refusal),
+            Ok(batch) => {
+                let mut content = format!(
+                    "Undo the batch `{}`? These skins are deleted from the database:\n",
+                    batch.label
+                );
+                let mut omitted = 0;
+                for skin in batch.skins.iter().filter(|skin| skin.in_database()) {
+                    let line = format!(
+                        "- `{}` ({}){}\n",
+                        skin.name,
+                        skin.database,
+                        if skin.replaced {
+                            " ⚠️ it replaced an earlier upload, which is not restored"
+                        } else {
+                            ""
+                        }
+                    );
+                    if omitted > 0 || content.chars().count() + line.chars().count() > 1900 {
+                        omitted += 1;
+                    } else {
+                        content += &line;
+                    }
+                }
+                if omitted > 0 {
+                    content += &format!("... and {omitted} more\n");
+                }
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .components(vec![CreateActionRow::Buttons(vec![
+                        CreateButton::new(format!("{UNDO_BATCH_BUTTON_PREFIX}{}", batch.label))
+                            .label("Delete them")
+                            .style(ButtonStyle::Danger),
+                        CreateButton::new(CANCEL_UNDO_BUTTON_ID)
+                            .label("Cancel")
+                            .style(ButtonStyle::Secondary),
+                    ])])
+            }
+        };
+        let builder = CreateInteractionResponse::Message(data.ephemeral(true));
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+    }
+
+    /// Deletes the uploaded skins of the batch, if it is still the last one and in the window.
+    /// It is only marked as undone once every skin was deleted, so failures can be retried.
+    async fn confirm_upload_undo(ctx: Context, comp: &ComponentInteraction, label: &str) {
+        let config = ctx.data.read().await.get::<Config>().unwrap().clone();
+        let history = ctx.data.read().await.get::<BatchHistory>().unwrap().clone();
+        let batch = match history.last() {
+            Some(batch) if batch.label == label => batch
+                .undo_refusal(
+                    config.upload.undo_window_mins,
+                    Timestamp::now().unix_timestamp(),
+                )
+                .map_or(Ok(batch), Err),
+            _ => Err(format!(
+                "Another batch was uploaded since, `{label}` can't be undone anymore"
+            )),
+        };
+        let content = match &batch {
+            Ok(batch) => format!("Deleting the skins of the batch `{}`...", batch.label),
+            Err(refusal) => refusal.clone(),
+        };
+        let builder = CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .content(content)
+                .components(vec![]),
+        );
+        if let Err(why) = comp.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+        let Ok(batch) = batch else {
+            return;
+        };
+
+        let upload_lock = ctx
+            .data
+            .read()
+            .await
+            .get::<SkinUploads>()
+            .unwrap()
+            .upload_lock
+            .clone();
+        // don't interfere with an upload that is in progress
+        let _g = upload_lock.lock().await;
+        let database = ctx.data.read().await.get::<SkinDatabase>().unwrap().clone();
+        let audit_log = ctx.data.read().await.get::<AuditLog>().unwrap().clone();
+        let mut report = format!("Undoing the batch `{}`:\n", batch.label);
+        let mut all_deleted = true;
+        for skin in batch.skins.iter().filter(|skin| skin.in_database()) {
+            let edit = SkinEdit::Delete {
+                name: skin.name.clone(),
+                database: if skin.database == "community" {
+                    SkinToUploadDB::Community
+                } else {
+                    SkinToUploadDB::Normal
+                },
+            };
+            let action = edit.action();
+            let database = database.clone();
+            let res = tokio::task::spawn_blocking(move || database.modify(&edit.fields()))
+                .await
+                .unwrap();
+            audit_log.record(comp.user.id, &action, &skin.name, &res);
+            let line = match res {
+                Ok(()) => format!("- `{}` ({}): deleted\n", skin.name, skin.database),
+                Err(err) => {
+                    all_deleted = false;
+                    format!("- `{}` ({}): failed: {err}\n", skin.name, skin.database)
+                }
+            };
+            if report.chars().count() + line.chars().count() <= 1900 {
+                report += &line;
+            }
+        }
+        if all_deleted {
+            history.record_undo(
+                &batch.label,
+                comp.user.id.get(),
+                Timestamp::now().unix_timestamp(),
+            );
+            report += "The batch was undone";
+        } else {
+            report += "Not every skin could be deleted, use `/upload_undo` again to retry";
+        }
+        if let Err(err) = comp
+            .edit_response(&ctx, EditInteractionResponse::new().content(report))
+            .await
+        {
+            warn!("Could not edit the undo response: {err}");
+        }
+    }
+
+    /// Replies with the dilated version of the attached image, without uploading it.
+    async fn dilate(ctx: Context, command: &CommandInteraction, config: &Config) {
+        let mut attachment = None;
+        let mut options = config.dilate.options();
+        for option in &command.data.options {
+            match (option.name.as_str(), &option.value) {
+                ("image", CommandDataOptionValue::Attachment(id)) => {
+                    attachment = command.data.resolved.attachments.get(id).cloned();
+                }
+                ("radius", CommandDataOptionValue::Integer(value)) => {
+                    options.radius = *value as usize;
+                }
+                ("iterations", CommandDataOptionValue::Integer(value)) => {
+                    options.iterations = *value as usize;
+                }
+                ("alpha_threshold", CommandDataOptionValue::Integer(value)) => {
+                    options.alpha_threshold = *value as u8;
+                }
+                _ => {}
+            }
+        }
+        let Some(attachment) = attachment else {
+            return;
+        };
+
+        // downloading and dilating can take longer than discord waits for a response
+        let builder = CreateInteractionResponse::Defer(
+            CreateInteractionResponseMessage::new().ephemeral(true),
+        );
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+            return;
+        }
+
+        let pixels = attachment.width.unwrap_or(0) as u64 * attachment.height.unwrap_or(0) as u64;
+        let edit_response = if pixels > DILATE_MAX_PIXELS {
+            EditInteractionResponse::new().content(format!(
+                "The image is too big, at most {DILATE_MAX_PIXELS} pixels are supported"
+            ))
+        } else {
+            let res = match attachment.download().await {
+                Ok(file) => tokio::task::spawn_blocking(move || dilate_file(&file, &options))
+                    .await
+                    .unwrap(),
+                Err(err) => Err(err.into()),
+            };
+            match res {
+                Ok((png, w, h)) => EditInteractionResponse::new()
+                    .content(format!(
+                        "Dilated {w}x{h} image (radius {}, {} iterations, alpha threshold {})",
+                        options.radius, options.iterations, options.alpha_threshold
+                    ))
+                    .new_attachment(CreateAttachment::bytes(
+                        png,
+                        format!(
+                            "{}_dilated.png",
+                            attachment
+                                .filename
+                                .rsplit_once('.')
+                                .map_or(attachment.filename.as_str(), |(name, _)| name)
+                        ),
+                    )),
+                Err(err) => EditInteractionResponse::new()
+                    .content(format!("The image could not be dilated: {err}")),
+            }
+        };
+        if let Err(err) = command.edit_response(&ctx, edit_response).await {
+            warn!("Could not edit response of dilate: {err}");
+        }
+    }
+
+    /// Shows what the upload would do, it only starts once it is confirmed.
+    /// A label given with the command names the batch in the history.
+    async fn upload_finish<'a>(
+        ctx: Context,
+        user_id: UserId,
+        command: &CommandWrapper<'a>,
+        label: Option<String>,
+    ) {
+        let (reply, preview) = oneshot::channel();
+        let requested = ctx
+            .data
+            .read()
+            .await
+            .get::<SkinUploads>()
+            .unwrap()
+            .uploads
+            .get(&user_id)
+            .map(|item| {
+                item.send(SessionEvent::PreviewRequested {
+                    with_size: true,
+                    reply,
+                })
+            });
+        if requested.is_none() {
+            let data = CreateInteractionResponseMessage::new()
+                .content("You never started an upload, please use `/upload`")
+                .ephemeral(true);
+            let builder = CreateInteractionResponse::Message(data);
+            if let Err(why) = command.create_response(&ctx.http, builder).await {
+                warn!("Could not respond to slash command: {why}");
+            }
+            return;
+        }
+
+        // estimating the size can take longer than discord waits for a response
+        let builder = CreateInteractionResponse::Defer(
+            CreateInteractionResponseMessage::new().ephemeral(true),
+        );
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+            return;
+        }
+        let database_failed = ctx
+            .data
+            .read()
+            .await
+            .get::<DatabaseStatus>()
+            .is_some_and(|status| status.is_err());
+        if database_failed {
+            // the connection might be back, the credentials only change with a restart
+            if let Err(err) = check_database(&ctx).await {
+                let refusal = format!(
+                    "The skin database check failed, nothing is uploaded until it succeeds: {err}"
+                );
+                if let Err(err) = command
+                    .edit_response(&ctx, EditInteractionResponse::new().content(refusal))
+                    .await
+                {
+                    warn!("Could not edit response from command: {err}");
+                }
+                return;
+            }
+        }
+        let Ok(preview) = preview.await else {
+            warn!("The upload session ended before the upload was confirmed");
+            return;
+        };
+        let batch_label = ctx
+            .data
+            .write()
+            .await
+            .get_mut::<SkinUploads>()
+            .unwrap()
+            .uploads
+            .get_mut(&user_id)
+            .and_then(|item| {
+                item.confirmation = Some(preview.fingerprint);
+                if label.is_some() {
+                    item.batch_label = label;
+                }
+                item.batch_label.clone()
+            });
+
+        let mut embed = CreateEmbed::new()
+            .color(Colour::TEAL)
+            .title("Confirm the upload")
+            .field("New skins", preview.added.len().to_string(), true)
+            .field("Updated skins", preview.updated.len().to_string(), true)
+            .field(
+                "Databases",
+                format!(
+                    "{} normal, {} community",
+                    preview.normal_count, preview.community_count
+                ),
+                true,
+            );
+        if let Some(batch_label) = batch_label {
+            embed = embed.field("Label", batch_label, true);
+        }
+        if let Some(png_bytes) = preview.png_bytes {
+            embed = embed.field(
+                "Size",
+                format!("about {}", format_file_size(png_bytes)),
+                true,
+            );
+        }
+        if !preview.updated.is_empty() {
+            let updated: String = preview
+                .updated
+                .iter()
+                .map(|(skin_name, uploaded_name)| {
+                    if skin_name == uploaded_name {
+                        format!("`{skin_name}`")
+                    } else {
+                        format!("`{skin_name}` (replaces `{uploaded_name}`)")
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+                .chars()
+                .take(1000)
+                .collect();
+            embed = embed.field("Already uploaded before, they are replaced", updated, false);
+        }
+        // flagged skins can only be uploaded by pressing a button that names the warnings
+        let confirm_button = if preview.flagged.is_empty() {
+            CreateButton::new(CONFIRM_UPLOAD_BUTTON_ID)
+                .label("Confirm")
+                .style(ButtonStyle::Success)
+        } else {
+            let flagged: String = preview
+                .flagged
+                .iter()
+                .map(|(skin_name, flags)| {
+                    let flags: Vec<_> = flags.iter().map(ToString::to_string).collect();
+                    format!("`{skin_name}`: {}", flags.join(", "))
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+                .chars()
+                .take(1000)
+                .collect();
+            embed = embed.color(Colour::ORANGE).field(
+                "⚠️ Flagged by the content checks",
+                flagged,
+                false,
+            );
+            CreateButton::new(CONFIRM_UPLOAD_BUTTON_ID)
+                .label(format!(
+                    "I checked the {} flagged skins, confirm",
+                    preview.flagged.len()
+                ))
+                .style(ButtonStyle::Danger)
+        };
+        let edit_response =
+            EditInteractionResponse::new()
+                .embed(embed)
+                .components(vec![CreateActionRow::Buttons(vec![
+                    confirm_button,
+                    CreateButton::new(BACK_UPLOAD_BUTTON_ID)
+                        .label("Back")
+                        .style(ButtonStyle::Secondary),
+                ])]);
+        if let Err(err) = command.edit_response(&ctx, edit_response).await {
+            warn!("Could not show the upload confirmation: {err}");
+        }
+    }
+
+    /// Starts the upload, if the skins are still the ones that were confirmed.
+    async fn upload_confirm(ctx: Context, comp: &ComponentInteraction) {
+        let (reply, preview) = oneshot::channel();
+        let confirmation = {
+            let data = ctx.data.read().await;
+            match data
+                .get::<SkinUploads>()
+                .unwrap()
+                .uploads
+                .get(&comp.user.id)
+            {
+                // the session answers once the skins are checked
+                Some(item) if item.session.state() != SessionState::Collecting => {
+                    item.send(SessionEvent::PreviewRequested {
+                        with_size: false,
+                        reply,
+                    });
+                    item.confirmation
+                }
+                _ => None,
+            }
+        };
+        let confirmed = match confirmation {
+            Some(fingerprint) => preview
+                .await
+                .is_ok_and(|preview| preview.fingerprint == fingerprint),
+            None => false,
+        };
+        if confirmed {
+            Self::upload_confirmed(ctx, comp.user.id, &CommandWrapper::Btn(comp)).await;
+        } else {
+            let builder = CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content("The skins changed since, press 🆗 again to see what will be uploaded")
+                    .embeds(vec![])
+                    .components(vec![]),
+            );
+            if let Err(why) = comp.create_response(&ctx.http, builder).await {
+                warn!("Could not respond to slash command: {why}");
+            }
+        }
+    }
+
+    async fn upload_confirmed<'a>(ctx: Context, user_id: UserId, command: &CommandWrapper<'a>) {
+        let require_approval = ctx
+            .data
+            .read()
+            .await
+            .get::<Config>()
+            .unwrap()
+            .upload
+            .require_approval;
+        let mut data = ctx.data.write().await;
+        if let Some(item) = data
+            .get_mut::<SkinUploads>()
+            .unwrap()
+            .uploads
+            .get_mut(&user_id)
+        {
+            let transition = if require_approval {
+                item.session.request_approval()
+            } else {
+                item.session.start_upload()
+            };
+            if let Err(err) = transition {
+                let data = CreateInteractionResponseMessage::new()
+                    .content(match err.from {
+                        SessionState::Collecting => {
+                            "Some skins are still being checked, try again in a moment"
+                        }
+                        SessionState::AwaitingApproval => {
+                            "The upload is waiting for the approval of another moderator"
+                        }
+                        _ => "An upload is already in progress, wait for the previous to end",
+                    })
+                    .ephemeral(true);
+                let builder = CreateInteractionResponse::Message(data);
+                if let Err(why) = command.create_response(&ctx.http, builder).await {
+                    warn!("Could not respond to slash command: {why}");
+                }
+            } else if require_approval {
+                let (reply, needs_approval) = oneshot::channel();
+                item.send(SessionEvent::ApprovalRequested(reply));
+                drop(data);
+
+                let Ok(needs_approval) = needs_approval.await else {
+                    warn!("The upload session ended before the approval was requested");
+                    return;
+                };
+                if needs_approval {
+                    let data = CreateInteractionResponseMessage::new()
+                        .content("Another moderator has to approve the upload, the skins were posted for review")
+                        .ephemeral(true);
+                    let builder = CreateInteractionResponse::Message(data);
+                    if let Err(why) = command.create_response(&ctx.http, builder).await {
+                        warn!("Could not respond to slash command: {why}");
+                    }
+                    return;
+                }
+                // community skins don't need an approval
+                let mut data = ctx.data.write().await;
+                if let Some(item) = data
+                    .get_mut::<SkinUploads>()
+                    .unwrap()
+                    .uploads
+                    .get_mut(&user_id)
+                {
+                    if let Err(err) = item.session.approve() {
+                        warn!("{err}");
+                        return;
+                    }
+                }
+                drop(data);
+                Self::upload_collected(&ctx, user_id, user_id, command).await;
+            } else {
+                drop(data);
+                Self::upload_collected(&ctx, user_id, user_id, command).await;
+            }
+        } else {
+            let data = CreateInteractionResponseMessage::new()
+                .content("You never started an upload, please use `/upload`")
+                .ephemeral(true);
+            let builder = CreateInteractionResponse::Message(data);
+            if let Err(why) = command.create_response(&ctx.http, builder).await {
+                warn!("Could not respond to slash command: {why}");
+            }
+        }
+    }
+
+    /// Uploads the skins collected by the session of `owner`, once it started uploading.
+    /// The skins that failed can be retried by the uploader.
+    async fn upload_collected<'a>(
+        ctx: &Context,
+        owner: UserId,
+        uploader: UserId,
+        command: &CommandWrapper<'a>,
+    ) {
+        let (reply, collected_skins) = oneshot::channel();
+        let (apply_cleanup, sd_resampler, batch_label) = {
+            let mut data = ctx.data.write().await;
+            let Some(item) = data
+                .get_mut::<SkinUploads>()
+                .unwrap()
+                .uploads
+                .get_mut(&owner)
+            else {
+                return;
+            };
+            item.send(SessionEvent::FinishRequested(reply));
+            (
+                item.apply_cleanup,
+                item.sd_resampler,
+                item.batch_label.clone(),
+            )
+        };
+
+        // the session task hands over the skins it collected
+        let Ok(skins_to_upload) = collected_skins.await else {
+            warn!("The upload session ended before the skins were handed over");
+            return;
+        };
+
+        let span = info_span!("upload", owner = %owner, uploader = %uploader);
+        Self::upload_skins(
+            ctx,
+            uploader,
+            command,
+            skins_to_upload,
+            apply_cleanup,
+            sd_resampler,
+            batch_label,
+        )
+        .instrument(span)
+        .await;
+
+        let mut data = ctx.data.write().await;
+        let uploads = &mut data.get_mut::<SkinUploads>().unwrap().uploads;
+        if let Some(item) = uploads.get_mut(&owner) {
+            if let Err(err) = item.session.finish() {
+                warn!("{err}");
+            }
+            uploads.remove(&owner);
+        }
+    }
+
+    /// Lists the running upload sessions for admins, with a button to end each of them,
+    /// e.g. if the uploader disconnected and nobody wants to wait for the timeout.
+    async fn upload_sessions(ctx: Context, comp: &ComponentInteraction) {
+        let data = if !is_admin(comp.member.as_ref()) {
+            CreateInteractionResponseMessage::new()
+                .content("Only admins can manage upload sessions")
+                .ephemeral(true)
+        } else {
+            let data = ctx.data.read().await;
+            let uploads = data.get::<SkinUploads>().unwrap();
+            let buttons: Vec<_> = uploads
+                .uploads
+                .keys()
+                .map(|owner| {
+                    CreateButton::new(format!("{END_SESSION_BUTTON_PREFIX}{owner}"))
+                        .label("End session")
+                        .style(ButtonStyle::Danger)
+                })
+                .collect();
+            let content = if buttons.is_empty() {
+                "Nobody is uploading skins right now".to_string()
+            } else {
+                session_overview(uploads)
+            };
+            CreateInteractionResponseMessage::new()
+                .content(content)
+                .components(
+                    buttons
+                        .chunks(5)
+                        .map(|row| CreateActionRow::Buttons(row.to_vec()))
+                        .collect(),
+                )
+                .ephemeral(true)
+        };
+        let builder = CreateInteractionResponse::Message(data);
+        if let Err(why) = comp.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+    }
+
+    async fn end_upload_session(ctx: Context, comp: &ComponentInteraction, owner: UserId) {
+        if !is_admin(comp.member.as_ref()) {
+            let data = CreateInteractionResponseMessage::new()
+                .content("Only admins can end the upload sessions of others")
+                .ephemeral(true);
+            let builder = CreateInteractionResponse::Message(data);
+            if let Err(why) = comp.create_response(&ctx.http, builder).await {
+                warn!("Could not respond to slash command: {why}");
+            }
+            return;
+        }
+        warn!("{} ended the upload session of {owner}", comp.user.id);
+        Self::upload_cancel(ctx, owner, &CommandWrapper::Btn(comp)).await;
+    }
+
+    /// A second moderator approves or rejects the upload of `owner`.
+    async fn review_upload(
+        ctx: Context,
+        comp: &ComponentInteraction,
+        owner: UserId,
+        approved: bool,
+    ) {
+        let config = ctx.data.read().await.get::<Config>().unwrap().clone();
+        let command = CommandWrapper::Btn(comp);
+        let is_moderator = comp
+            .user
+            .has_role(&ctx, config.guild_id(), config.role_id())
+            .await
+            .unwrap_or(false);
+        let refusal = if !is_moderator {
+            Some("You don't have the required permissions to review uploads")
+        } else if approved && comp.user.id == owner {
+            Some("Another moderator has to approve your upload")
+        } else {
+            let mut data = ctx.data.write().await;
+            match data
+                .get_mut::<SkinUploads>()
+                .unwrap()
+                .uploads
+                .get_mut(&owner)
+            {
+                None => Some("The upload does not exist anymore"),
+                Some(item) if approved => item
+                    .session
+                    .approve()
+                    .err()
+                    .map(|_| "The upload was already reviewed"),
+                Some(item) => match item.session.reject() {
+                    Ok(()) => {
+                        item.send(SessionEvent::ApprovalRejected { by: comp.user.id });
+                        None
+                    }
+                    Err(_) => Some("The upload was already reviewed"),
+                },
+            }
+        };
+        if let Some(refusal) = refusal {
+            let data = CreateInteractionResponseMessage::new()
+                .content(refusal)
+                .ephemeral(true);
+            let builder = CreateInteractionResponse::Message(data);
+            if let Err(why) = command.create_response(&ctx.http, builder).await {
+                warn!("Could not respond to slash command: {why}");
+            }
+            return;
+        }
+
+        // the review can only be decided once
+        let mut review = comp.message.clone();
+        let decision = format!(
+            "{}\n**{} by {}**",
+            review.content,
+            if approved { "Approved" } else { "Rejected" },
+            Mention::User(comp.user.id)
+        );
+        if let Err(err) = review
+            .edit(
+                &ctx,
+                EditMessage::new().content(decision).components(vec![]),
+            )
+            .await
+        {
+            warn!("Could not edit the upload review: {err}");
+        }
+
+        if approved {
+            Self::upload_collected(&ctx, owner, comp.user.id, &command).await;
+        } else {
+            let data = CreateInteractionResponseMessage::new()
+                .content("Upload rejected")
+                .ephemeral(true);
+            let builder = CreateInteractionResponse::Message(data);
+            if let Err(why) = command.create_response(&ctx.http, builder).await {
+                warn!("Could not respond to slash command: {why}");
+            }
+        }
+    }
+
+    async fn upload_retry_failed<'a>(ctx: Context, user_id: UserId, command: &CommandWrapper<'a>) {
+        let failed_uploads = ctx
+            .data
+            .write()
+            .await
+            .get_mut::<SkinUploads>()
+            .unwrap()
+            .failed_uploads
+            .remove(&user_id);
+        if let Some(failed_uploads) = failed_uploads {
+            Self::upload_skins(
+                &ctx,
+                user_id,
+                command,
+                failed_uploads.skins,
+                failed_uploads.apply_cleanup,
+                failed_uploads.sd_resampler,
+                Some(format!("{} retry", failed_uploads.batch_label)),
+            )
+            .instrument(info_span!("upload_retry", uploader = %user_id))
+            .await;
+        } else {
+            let data = CreateInteractionResponseMessage::new()
+                .content("There are no failed skins to retry")
+                .ephemeral(true);
+            let builder = CreateInteractionResponse::Message(data);
+            if let Err(why) = command.create_response(&ctx.http, builder).await {
+                warn!("Could not respond to slash command: {why}");
+            }
+        }
+    }
+
+    /// Stores whether the author wants a DM once their skins are uploaded.
+    async fn set_dm_opt_out<'a>(
+        ctx: Context,
+        user_id: UserId,
+        opted_out: bool,
+        command: &CommandWrapper<'a>,
+    ) {
+        let res = ctx
+            .data
+            .write()
+            .await
+            .get_mut::<DmOptOuts>()
+            .unwrap()
+            .set(user_id, opted_out);
+        let content = match res {
+            Ok(()) if opted_out => "You won't get a DM about your uploaded skins anymore, use `/skin_notifications` to enable it again".to_string(),
+            Ok(()) => "You will get a DM once your skins are uploaded".to_string(),
+            Err(err) => {
+                error!("Could not store the DM opt-outs: {err}");
+                "Your choice could not be stored, please try again later".to_string()
+            }
+        };
+        let data = CreateInteractionResponseMessage::new()
+            .content(content)
+            .ephemeral(true);
+        let builder = CreateInteractionResponse::Message(data);
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+    }
+
+    /// Shows the form to type the info of a submission the bot could not read.
+    async fn open_skin_info_form(
+        ctx: Context,
+        comp: &ComponentInteraction,
+        channel_id: ChannelId,
+        msg_id: MessageId,
+    ) {
+        let builder = match channel_id.message(&ctx, msg_id).await {
+            Ok(msg) => {
+                CreateInteractionResponse::Modal(skin_info_modal(&msg, SKIN_INFO_MODAL_PREFIX))
+            }
+            Err(err) => {
+                warn!("{err}");
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .content("The submission was not found anymore...")
+                        .components(vec![]),
+                )
+            }
+        };
+        if let Err(why) = comp.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+    }
+
+    /// Asks the submitter of a media post for the skin info, if it can't be read. Media posts
+    /// often have nothing but the attachment, the entered info becomes the title of the post.
+    async fn ask_media_submitter(ctx: Context, msg: &Message, config: &Config) {
+        let has_skin = msg
+            .attachments
+            .iter()
+            .any(|attachment| !is_sidecar(&attachment.filename));
+        if !config.forum.ask_media_submitters || msg.author.bot || !has_skin {
+            return;
+        }
+        let Some((post, parent)) = post_of(&ctx, msg).await else {
+            return;
+        };
+        if !is_media_channel(parent.kind) || !is_upload_channel(&ctx, config, post.id).await {
+            return;
+        }
+        if submission_metadata(msg).await.is_ok()
+            || title_metadata(&post.name, &msg.content).is_some()
+        {
+            return;
+        }
+        let prompt = CreateMessage::new()
+            .content(format!(
+                "{} the skin info of this post could not be read, please enter it so the skin can be uploaded",
+                Mention::User(msg.author.id)
+            ))
+            .button(skin_info_button(msg, POST_INFO_BUTTON_PREFIX))
+            .allowed_mentions(CreateAllowedMentions::new().users([msg.author.id]));
+        if let Err(err) = post.id.send_message(&ctx, prompt).await {
+            warn!("Could not ask for the info of a media post: {err}");
+        }
+    }
+
+    /// Only the submitter and moderators can enter the info of a post.
+    async fn open_post_info_form(
+        ctx: Context,
+        comp: &ComponentInteraction,
+        channel_id: ChannelId,
+        msg_id: MessageId,
+    ) {
+        let config = ctx.data.read().await.get::<Config>().unwrap().clone();
+        let builder = match channel_id.message(&ctx, msg_id).await {
+            Ok(msg)
+                if msg.author.id == comp.user.id
+                    || comp
+                        .user
+                        .has_role(&ctx, config.guild_id(), config.role_id())
+                        .await
+                        .unwrap_or(false) =>
+            {
+                CreateInteractionResponse::Modal(skin_info_modal(&msg, POST_INFO_MODAL_PREFIX))
+            }
+            Ok(_) => CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content("Only the submitter can enter the info of this skin")
+                    .ephemeral(true),
+            ),
+            Err(err) => {
+                warn!("{err}");
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .content("The submission was not found anymore...")
+                        .components(vec![]),
+                )
+            }
+        };
+        if let Err(why) = comp.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+    }
+
+    /// Stores the entered info as the title of the post, where the upload reads it.
+    async fn post_info_entered(ctx: Context, modal: &ModalInteraction, post_id: ChannelId) {
+        let builder = match modal_metadata(&modal.data).map(|metadata| post_title(&metadata)) {
+            Ok(title) if title.chars().count() > MAX_POST_TITLE_LENGTH => {
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content(format!(
+                            "The info is too long for the post title, at most {MAX_POST_TITLE_LENGTH} characters fit, please press the button again"
+                        ))
+                        .ephemeral(true),
+                )
+            }
+            Ok(title) => match post_id
+                .edit_thread(&ctx, EditThread::new().name(title.clone()))
+                .await
+            {
+                Ok(_) => CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .content(format!("Thanks, the skin info is now the title of the post: {title}"))
+                        .components(vec![]),
+                ),
+                Err(err) => {
+                    warn!("Could not rename the media post {post_id}: {err}");
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .content("The post title could not be changed, please edit it yourself")
+                            .ephemeral(true),
+                    )
+                }
+            },
+            Err(err) => CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(format!(
+                        "The info is incomplete: {err}, please press the button again"
+                    ))
+                    .ephemeral(true),
+            ),
+        };
+        if let Err(why) = modal.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+    }
+
+    /// Hands the typed info to the upload session, which checks the skin again.
+    async fn skin_info_entered(ctx: Context, modal: &ModalInteraction, msg_id: MessageId) {
+        let content = match modal_metadata(&modal.data) {
+            Ok(metadata) => {
+                let name = metadata.name.clone();
+                let data = ctx.data.read().await;
+                match data
+                    .get::<SkinUploads>()
+                    .unwrap()
+                    .uploads
+                    .get(&modal.user.id)
+                {
+                    Some(item) if item.session.state().is_collecting() => {
+                        item.send(SessionEvent::SkinInfoEntered { msg_id, metadata });
+                        format!("`{name}` is checked again with the entered info")
+                    }
+                    Some(_) => "Cannot queue skins at this point anymore".to_string(),
+                    None => "You never started an upload, please use `/upload`".to_string(),
+                }
+            }
+            Err(err) => format!("The info is incomplete: {err}, please press the button again"),
+        };
+        let builder = CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .content(content)
+                .components(vec![]),
+        );
+        if let Err(why) = modal.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+    }
+
+    /// Starts a practice upload with canned submissions, a previous practice starts over.
+    async fn upload_practice(ctx: Context, command: &CommandInteraction, config: &Config) {
+        let practice = Practice::default();
+        let (content, components) = practice.task(config);
+        ctx.data
+            .write()
+            .await
+            .get_mut::<PracticeSessions>()
+            .unwrap()
+            .insert(command.user.id, practice);
+        let data = CreateInteractionResponseMessage::new()
+            .content(
+                "Let's practice an upload, nothing is sent to the database.\n\n".to_string()
+                    + &content,
+            )
+            .components(components)
+            .ephemeral(true);
+        let builder = CreateInteractionResponse::Message(data);
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+    }
+
+    /// Gives feedback on the answer and shows the next step of the practice.
+    async fn practice_answer(ctx: Context, comp: &ComponentInteraction, answer: PracticeAnswer) {
+        let config = ctx.data.read().await.get::<Config>().unwrap().clone();
+        let (content, components) = {
+            let mut data = ctx.data.write().await;
+            let sessions = data.get_mut::<PracticeSessions>().unwrap();
+            match sessions.get_mut(&comp.user.id) {
+                Some(practice) => {
+                    let (feedback, finished) = practice.answer(answer, &config);
+                    if finished {
+                        sessions.remove(&comp.user.id);
+                        (feedback, Vec::new())
+                    } else {
+                        let (task, components) = practice.task(&config);
+                        (feedback + "\n\n" + &task, components)
+                    }
+                }
+                None => (
+                    "This practice is over, start a new one with `/upload_practice`".to_string(),
+                    Vec::new(),
+                ),
+            }
+        };
+        let builder = CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .content(content)
+                .components(components),
+        );
+        if let Err(why) = comp.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+    }
+
+    /// Posts which skins were uploaded before the shutdown and which were not,
+    /// the ephemeral report would be gone after the restart.
+    async fn record_interrupted_upload<'a>(
+        ctx: &Context,
+        user_id: UserId,
+        command: &CommandWrapper<'a>,
+        uploaded_skins: &[UploadedSkin],
+        failed_skins: &LinkedHashMap<String, SkinToUpload>,
+    ) {
+        let mut msg = format!(
+            "The bot was stopped during the upload of {}.\n",
+            Mention::User(user_id)
+        );
+        let uploaded: Vec<_> = uploaded_skins
+            .iter()
+            .map(|skin| format!("`{}`", skin.name))
+            .collect();
+        let not_uploaded: Vec<_> = failed_skins
+            .keys()
+            .map(|skin_name| format!("`{skin_name}`"))
+            .collect();
+        for (label, skins) in [("Uploaded", uploaded), ("Not uploaded", not_uploaded)] {
+            if !skins.is_empty() {
+                msg += &format!("{label}: {}\n", skins.join(", "));
+            }
+        }
+        let msg: String = msg.chars().take(2000).collect();
+        if let Err(err) = command
+            .channel_id()
+            .send_message(
+                ctx,
+                CreateMessage::new()
+                    .content(msg)
+                    .allowed_mentions(CreateAllowedMentions::new()),
+            )
+            .await
+        {
+            warn!("Could not record the interrupted upload: {err}");
+        }
+    }
+
+    /// Waits while the website announces a maintenance of the database.
+    /// Returns false if it did not end within the configured time.
+    async fn wait_for_maintenance<'a>(
+        ctx: &Context,
+        command: &CommandWrapper<'a>,
+        config: &Config,
+        uploaded: usize,
+        skin_count: usize,
+    ) -> bool {
+        let Some(flag_url) = &config.maintenance.flag_url else {
+            return true;
+        };
+        let max_wait = Duration::from_secs(config.maintenance.max_wait_mins * 60);
+        let waiting_since = Instant::now();
+        while maintenance_active(flag_url).await {
+            if waiting_since.elapsed() >= max_wait {
+                return false;
+            }
+            let progress_msg = format!(
+                "The database is in maintenance, the upload continues once it is over ({uploaded}/{skin_count} done)"
+            );
+            if let Err(err) = command
+                .edit_response(ctx, EditInteractionResponse::new().content(progress_msg))
+                .await
+            {
+                warn!("Could not edit upload progress: {err}");
+            }
+            tokio::time::sleep(Duration::from_secs(config.maintenance.poll_secs)).await;
+        }
+        true
+    }
+
+    /// Uploads the skins to the database and announces them. Skins that failed are kept,
+    /// so they can be retried without collecting them again.
+    /// The batch is recorded in the history under the label, or one by date.
+    async fn upload_skins<'a>(
+        ctx: &Context,
+        user_id: UserId,
+        command: &CommandWrapper<'a>,
+        mut skins_to_upload: LinkedHashMap<String, SkinToUpload>,
+        apply_cleanup: bool,
+        sd_resampler: SdResampler,
+        batch_label: Option<String>,
+    ) {
+        let config = ctx.data.read().await.get::<Config>().unwrap().clone();
+        let guild_id = config.guild_id();
+        let dilate_options = config.dilate.options();
+        let database = ctx.data.read().await.get::<SkinDatabase>().unwrap().clone();
+        let audit_log = ctx.data.read().await.get::<AuditLog>().unwrap().clone();
+        let metrics = ctx.data.read().await.get::<Metrics>().unwrap().clone();
+        let batch_history = ctx.data.read().await.get::<BatchHistory>().unwrap().clone();
+        let upload_lock = ctx
+            .data
+            .read()
+            .await
+            .get::<SkinUploads>()
+            .unwrap()
+            .upload_lock
+            .clone();
+
+        let _g = upload_lock.lock().await;
+        // under the lock, two batches can't get the same label
+        let batch_label = batch_history.new_label(batch_label.as_deref(), Timestamp::now());
+        let uploaded_before = audit_log.uploaded_skins();
+
+        let data = CreateInteractionResponseMessage::new()
+            .content("Starting to upload")
+            .ephemeral(true);
+        let builder = CreateInteractionResponse::Message(data);
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+
+        let mut errors: Vec<String> = Vec::default();
+        let mut failed_skins: LinkedHashMap<String, SkinToUpload> = LinkedHashMap::default();
+        let mut uploaded_skins: Vec<UploadedSkin> = Vec::default();
+        let mut skin_reports: Vec<String> = Vec::default();
+        let mut batch_skins: Vec<BatchSkin> = Vec::default();
+        let mut announcement_links: Vec<String> = Vec::default();
+        let skin_count = skins_to_upload.len();
+        // the remaining skins are kept for a retry, once the bot is shutting down
+        // or waiting for the maintenance took too long
+        let mut stopped = false;
+        let mut shutting_down = false;
+        for (skin_index, (skin_name, mut skin_to_upload)) in skins_to_upload.drain().enumerate() {
+            if !stopped {
+                shutting_down = ctx
+                    .data
+                    .read()
+                    .await
+                    .get::<SkinUploads>()
+                    .unwrap()
+                    .shutting_down;
+                if shutting_down {
+                    errors.push(
+                        "The bot is shutting down, the remaining skins were not uploaded"
+                            .to_string(),
+                    );
+                    stopped = true;
+                } else if !Self::wait_for_maintenance(ctx, command, &config, skin_index, skin_count)
+                    .await
+                {
+                    errors.push(
+                        "The database is still in maintenance, the remaining skins were not uploaded"
+                            .to_string(),
+                    );
+                    stopped = true;
+                }
+            }
+            let submission_link = format!(
+                "https://discord.com/channels/{}/{}/{}",
+                guild_id, skin_to_upload.channel_id, skin_to_upload.original_msg_id
+            );
+            let replaced = uploaded_before
+                .contains_key(&(name_key(&skin_name), skin_to_upload.database.to_string()));
+            if stopped {
+                batch_skins.push(BatchSkin {
+                    name: skin_name.clone(),
+                    database: skin_to_upload.database.to_string(),
+                    uploaded: false,
+                    error: Some("the upload stopped before it".to_string()),
+                    submission: submission_link,
+                    replaced,
+                    author: Some(skin_to_upload.author.clone()),
+                    positive_ratio: Some(skin_to_upload.positive_ratio),
+                    original_name: skin_to_upload.original_name.clone(),
+                    uploaded_sizes: Vec::new(),
+                });
+                failed_skins.insert(skin_name, skin_to_upload);
+                continue;
+            }
+
+            let mut progress_msg = format!(
+                "Uploading {}/{skin_count}: `{skin_name}` ...",
+                skin_index + 1
+            );
+            if !errors.is_empty() {
+                progress_msg += &format!(" ({} errors so far)", errors.len());
+            }
+            if let Err(err) = command
+                .edit_response(ctx, EditInteractionResponse::new().content(progress_msg))
+                .await
+            {
+                warn!("Could not edit upload progress: {err}");
+            }
+
+            let mut auto_fixes: Vec<String> = Vec::default();
+            let mut sizes: Vec<String> = Vec::default();
+            let mut processing_time = Duration::ZERO;
+            if !skin_to_upload.sd_quality.is_empty() && skin_to_upload.file_256x128.is_empty() {
+                let start = Instant::now();
+                skin_to_upload.file_256x128 =
+                    downscale_hd(&skin_to_upload.file_512x256, sd_resampler);
+                processing_time += start.elapsed();
+                auto_fixes.push(format!("256x128 generated using {sd_resampler}"));
+            }
+            // a retry must not generate the 256x128 skin again, it might be uploaded already
+            let mut failed_skin = skin_to_upload.clone();
+            failed_skin.sd_quality.clear();
+
+            // keeps the name as written by the artist, unless the sidecar names a pack
+            let skin_pack = skin_to_upload
+                .skin_pack
+                .clone()
+                .or_else(|| skin_to_upload.original_name.clone())
+                .unwrap_or_default();
+            let span = info_span!(
+                "upload_skin",
+                skin = %skin_name,
+                msg_id = %skin_to_upload.original_msg_id
+            );
+
+            let mut skin_failed = false;
+            let mut skin_errors: Vec<String> = Vec::default();
+            for (size, uhd) in [("256x128", false), ("512x256", true)] {
+                let (img, conversion, resized) = if uhd {
+                    (
+                        &skin_to_upload.file_512x256,
+                        &skin_to_upload.conversion_512x256,
+                        skin_to_upload.resized_512x256,
+                    )
+                } else {
+                    (
+                        &skin_to_upload.file_256x128,
+                        &skin_to_upload.conversion_256x128,
+                        skin_to_upload.resized_256x128,
+                    )
+                };
+                if img.is_empty() {
+                    continue;
+                }
+                let form = SkinForm {
+                    name: skin_name.clone(),
+                    author: skin_to_upload.author.clone(),
+                    license: skin_to_upload.license.clone(),
+                    skin_pack: skin_pack.clone(),
+                    database: skin_to_upload.database.to_string(),
+                    uhd,
+                };
+                let normalize_body = config.upload.normalize_body_alpha;
+                let optimize = config.png_optimization.enabled(skin_to_upload.database);
+                let database = database.clone();
+                let span = span.clone();
+                let img = img.clone();
+                let upload = tokio::task::spawn_blocking(move || {
+                    let _span = span.entered();
+                    process_and_upload(
+                        &*database,
+                        img,
+                        &form,
+                        apply_cleanup,
+                        &dilate_options,
+                        normalize_body,
+                        optimize,
+                    )
+                })
+                .await
+                .unwrap();
+                processing_time += upload.processing_time;
+                metrics.record_upload(upload.result.is_ok());
+                if !upload.already_dilated {
+                    metrics.record_dilate(upload.dilate_time);
+                }
+                sizes.push(format!(
+                    "{size} {}{}{}",
+                    format_file_size(upload.png_size as u64),
+                    upload
+                        .unoptimized_size
+                        .filter(|&unoptimized| unoptimized > upload.png_size)
+                        .map_or_else(String::new, |unoptimized| format!(
+                            " (optimized from {}, -{}%)",
+                            format_file_size(unoptimized as u64),
+                            (unoptimized - upload.png_size) * 100 / unoptimized
+                        )),
+                    if upload.already_dilated {
+                        " (already dilated)"
+                    } else {
+                        ""
+                    }
+                ));
+                if upload.stray_pixels > 0 {
+                    auto_fixes.push(format!(
+                        "{} stray pixels removed from {size}",
+                        upload.stray_pixels
+                    ));
+                }
+                if upload.body_alpha_fixed > 0 {
+                    auto_fixes.push(format!(
+                        "{} semi-transparent body pixels made opaque in {size}",
+                        upload.body_alpha_fixed
+                    ));
+                }
+                if let Some(conversion) = conversion {
+                    auto_fixes.push(format!("{size} converted from {conversion} to a rgba png"));
+                }
+                if let Some((w, h)) = resized {
+                    auto_fixes.push(format!("{size} resized from {w}x{h}"));
+                }
+                audit_log.record(
+                    user_id,
+                    &format!("upload {size} {}", skin_to_upload.database.to_string()),
+                    &skin_name,
+                    &upload.result,
+                );
+                match upload.result {
+                    Ok(()) if uhd => failed_skin.file_512x256.clear(),
+                    Ok(()) => failed_skin.file_256x128.clear(),
+                    Err(err) => {
+                        errors.push(format!(
+                            "Uploading the {size} skin of `{skin_name}` failed: {err}\n"
+                        ));
+                        skin_errors.push(format!("{size}: {err}"));
+                        skin_failed = true;
+                    }
+                }
+            }
+
+            skin_reports.push(format!(
+                "- `{skin_name}`: {}, {} ms{}{}\n",
+                sizes.join(", "),
+                processing_time.as_millis(),
+                if auto_fixes.is_empty() {
+                    String::default()
+                } else {
+                    format!(" (auto-fixes: {})", auto_fixes.join(", "))
+                },
+                if skin_failed { " ❌" } else { "" }
+            ));
+            batch_skins.push(BatchSkin {
+                name: skin_name.clone(),
+                database: skin_to_upload.database.to_string(),
+                uploaded: !skin_failed,
+                error: skin_failed.then(|| skin_errors.join(", ")),
+                submission: submission_link,
+                replaced,
+                author: Some(skin_to_upload.author.clone()),
+                positive_ratio: Some(skin_to_upload.positive_ratio),
+                original_name: skin_to_upload.original_name.clone(),
+                uploaded_sizes: Vec::new(),
+            });
+
+            if skin_failed {
+                failed_skins.insert(skin_name, failed_skin);
+                continue;
+            }
+
+            if let Ok(msg) = skin_to_upload
+                .channel_id
+                .message(ctx, skin_to_upload.original_msg_id)
+                .await
+            {
+                uploaded_skins.push(UploadedSkin {
+                    name: skin_name.clone(),
+                    database: skin_to_upload.database,
+                    author_id: msg.author.id,
+                    author_name: msg.author.name.clone(),
+                    link: format!(
+                        "https://discord.com/channels/{}/{}/{}",
+                        guild_id, skin_to_upload.channel_id, msg.id
+                    ),
+                    positive_ratio: skin_to_upload.positive_ratio,
+                });
+            }
+
+            let decision = match skin_to_upload.database {
+                SkinToUploadDB::Normal => ForumDecision::UploadedNormal,
+                SkinToUploadDB::Community => ForumDecision::UploadedCommunity,
+            };
+            if let Err(err) =
+                tag_forum_post(ctx, skin_to_upload.channel_id, decision, &config.forum).await
+            {
+                warn!("Could not tag forum post of {skin_name}: {err}");
+            }
+        }
+
+        for database in [SkinToUploadDB::Normal, SkinToUploadDB::Community] {
+            let skins: Vec<&UploadedSkin> = uploaded_skins
+                .iter()
+                .filter(|skin| skin.database == database)
+                .collect();
+            if skins.is_empty() {
+                continue;
+            }
+            let (channel_id, role_id) = match database {
+                SkinToUploadDB::Normal => (
+                    config.announcements.normal_channel_id,
+                    config.announcements.normal_role_id,
+                ),
+                SkinToUploadDB::Community => (
+                    config.announcements.community_channel_id,
+                    config.announcements.community_role_id,
+                ),
+            };
+            let channel_id = channel_id.map_or(command.channel_id(), ChannelId::new);
+            let announcements =
+                announcement_messages(&skins, database, &config.announcements, &config.templates);
+            for (index, (upload_msg, mentions)) in announcements.into_iter().enumerate() {
+                // stagger the messages, so the pings don't arrive all at once
+                if index > 0 {
+                    tokio::time::sleep(Duration::from_millis(
+                        config.announcements.message_delay_ms,
+                    ))
+                    .await;
+                }
+                let mut allowed_mentions = CreateAllowedMentions::new().users(mentions);
+                let mut upload_msg = upload_msg;
+                // the role is only pinged once per announcement
+                if let Some(role_id) = role_id.filter(|_| index == 0) {
+                    let role_id = RoleId::new(role_id);
+                    upload_msg = format!("{}\n{upload_msg}", Mention::Role(role_id));
+                    allowed_mentions = allowed_mentions.roles([role_id]);
+                }
+                match channel_id
+                    .send_message(
+                        ctx,
+                        CreateMessage::new()
+                            .allowed_mentions(allowed_mentions)
+                            .content(upload_msg),
+                    )
+                    .await
+                {
+                    Ok(msg) => announcement_links.push(msg.link()),
+                    Err(err) => warn!("sending global uploaded skins message failed {err}."),
+                }
+            }
+        }
+
+        if config.announcements.dm_authors {
+            let skins: Vec<&UploadedSkin> = {
+                let data = ctx.data.read().await;
+                let opt_outs = data.get::<DmOptOuts>().unwrap();
+                uploaded_skins
+                    .iter()
+                    .filter(|skin| !opt_outs.contains(skin.author_id))
+                    .collect()
+            };
+            for skin in skins {
+                notify_author(ctx, skin, &config.templates).await;
+            }
+        }
+
+        if shutting_down {
+            Self::record_interrupted_upload(ctx, user_id, command, &uploaded_skins, &failed_skins)
+                .await;
+        }
+
+        if !batch_skins.is_empty() {
+            batch_history.record(&BatchRecord {
+                label: batch_label.clone(),
+                uploader: user_id.get(),
+                finished_at: Timestamp::now().unix_timestamp(),
+                skins: batch_skins,
+                announcements: announcement_links,
+                undone_at: None,
+                preview_renderer: None,
+            });
+        }
+
+        let mut new_msg = String::default();
+        new_msg += &fill_template(&config.templates.batch_finished, &[("label", &batch_label)]);
+        new_msg += "\n";
+        if !errors.is_empty() {
+            new_msg += &config.templates.batch_errors;
+            new_msg += "\n";
+            for err in &errors {
+                new_msg += &(err.clone() + "\n");
+            }
+        }
+        if !skin_reports.is_empty() {
+            new_msg += &config.templates.batch_report;
+            new_msg += "\n";
+            let mut omitted_reports = 0;
+            for report in &skin_reports {
+                // leave some space for the omitted hint
+                if new_msg.chars().count() + report.chars().count() > 1950 {
+                    omitted_reports += 1;
+                } else {
+                    new_msg += report;
+                }
+            }
+            if omitted_reports > 0 {
+                new_msg += &format!("... and {omitted_reports} more\n");
+            }
+        }
+        let mut edit_response = EditInteractionResponse::new().content(new_msg);
+        if !failed_skins.is_empty() {
+            edit_response =
+                edit_response.components(vec![CreateActionRow::Buttons(vec![CreateButton::new(
+                    "retry_failed",
+                )
+                .label(format!("Retry {} failed skins", failed_skins.len()))])]);
+            ctx.data
+                .write()
+                .await
+                .get_mut::<SkinUploads>()
+                .unwrap()
+                .failed_uploads
+                .insert(
+                    user_id,
+                    FailedUploads {
+                        skins: failed_skins,
+                        apply_cleanup,
+                        sd_resampler,
+                        batch_label,
+                    },
+                );
+        }
+        if let Err(err) = command.edit_response(ctx, edit_response).await {
+            warn!("Could edit responds of upload finish: {err}");
         }
     }
-}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum SkinUploadState {
-    Collecting,
-    Uploading,
-    Cancelled,
-}
+    /// The buttons and menus below the messages of the bot.
+    async fn component_interaction(ctx: Context, comp: ComponentInteraction) {
+        match comp.data.custom_id.as_str() {
+            "cancel" => {
+                Self::upload_cancel(ctx, comp.user.id, &CommandWrapper::Btn(&comp)).await;
+            }
+            "ok" => {
+                Self::upload_finish(ctx, comp.user.id, &CommandWrapper::Btn(&comp), None).await;
+            }
+            "retry_failed" => {
+                Self::upload_retry_failed(ctx, comp.user.id, &CommandWrapper::Btn(&comp)).await;
+            }
+            "cleanup" => {
+                Self::upload_toggle_cleanup(ctx, comp.user.id, &CommandWrapper::Btn(&comp)).await;
+            }
+            "exclude_resized" => {
+                Self::upload_exclude_resized(ctx, comp.user.id, &CommandWrapper::Btn(&comp)).await;
+            }
+            RESYNC_BUTTON_ID => {
+                Self::upload_resync(ctx, &comp).await;
+            }
+            SESSIONS_BUTTON_ID => {
+                Self::upload_sessions(ctx, &comp).await;
+            }
+            SWITCH_DATABASE_SELECT_ID => {
+                if let ComponentInteractionDataKind::StringSelect { values } = &comp.data.kind {
+                    let msg_ids = values
+                        .iter()
+                        .filter_map(|value| value.parse().ok().map(MessageId::new))
+                        .collect();
+                    Self::upload_switch_database(ctx, &comp, msg_ids).await;
+                }
+            }
+            CONFIRM_UPLOAD_BUTTON_ID => {
+                Self::upload_confirm(ctx, &comp).await;
+            }
+            BACK_UPLOAD_BUTTON_ID => {
+                Self::upload_back(ctx, &comp).await;
+            }
+            CANCEL_UNDO_BUTTON_ID => {
+                Self::cancel_upload_undo(ctx, &comp).await;
+            }
+            CONFIRM_EDIT_BUTTON_ID | CANCEL_EDIT_BUTTON_ID => {
+                let confirmed = comp.data.custom_id == CONFIRM_EDIT_BUTTON_ID;
+                Self::confirm_skin_edit(ctx, &comp, confirmed).await;
+            }
+            OPT_OUT_BUTTON_ID => {
+                Self::set_dm_opt_out(ctx, comp.user.id, true, &CommandWrapper::Btn(&comp)).await;
+            }
+            id => {
+                let review = [(APPROVE_BUTTON_PREFIX, true), (REJECT_BUTTON_PREFIX, false)]
+                    .into_iter()
+                    .find_map(|(prefix, approved)| {
+                        let owner = id.strip_prefix(prefix)?.parse().ok()?;
+                        Some((UserId::new(owner), approved))
+                    });
+                let end_session = id
+                    .strip_prefix(END_SESSION_BUTTON_PREFIX)
+                    .and_then(|owner| owner.parse().ok());
+                if let Some((owner, approved)) = review {
+                    Self::review_upload(ctx, &comp, owner, approved).await;
+                } else if let Some(label) = id.strip_prefix(UNDO_BATCH_BUTTON_PREFIX) {
+                    Self::confirm_upload_undo(ctx, &comp, label).await;
+                } else if let Some(owner) = end_session {
+                    Self::end_upload_session(ctx, &comp, UserId::new(owner)).await;
+                } else if let Some((channel_id, msg_id)) =
+                    parse_skin_info_id(id, SKIN_INFO_BUTTON_PREFIX)
+                {
+                    Self::open_skin_info_form(ctx, &comp, channel_id, msg_id).await;
+                } else if let Some((channel_id, msg_id)) =
+                    parse_skin_info_id(id, POST_INFO_BUTTON_PREFIX)
+                {
+                    Self::open_post_info_form(ctx, &comp, channel_id, msg_id).await;
+                } else if let Some(answer) = PracticeAnswer::from_button_id(id) {
+                    Self::practice_answer(ctx, &comp, answer).await;
+                } else if let Some(resampler) = SdResampler::from_button_id(id) {
+                    Self::upload_select_resampler(
+                        ctx,
+                        comp.user.id,
+                        &CommandWrapper::Btn(&comp),
+                        resampler,
+                    )
+                    .await;
+                }
+            }
+        }
+    }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum SkinToUploadDB {
-    Normal,
-    Community,
-}
+    /// The forms for the skin info of a submission or forum post.
+    async fn modal_submit(ctx: Context, modal: ModalInteraction) {
+        if let Some((_, msg_id)) = parse_skin_info_id(&modal.data.custom_id, SKIN_INFO_MODAL_PREFIX)
+        {
+            Self::skin_info_entered(ctx, &modal, msg_id).await;
+        } else if let Some((channel_id, _)) =
+            parse_skin_info_id(&modal.data.custom_id, POST_INFO_MODAL_PREFIX)
+        {
+            Self::post_info_entered(ctx, &modal, channel_id).await;
+        }
+    }
 
-impl ToString for SkinToUploadDB {
-    fn to_string(&self) -> String {
-        match self {
-            Self::Normal => "normal".to_string(),
-            Self::Community => "community".to_string(),
+    /// The slash and context menu commands, all but the first few need the role.
+    async fn command_interaction(ctx: Context, command: CommandInteraction) {
+        let config = ctx.data.read().await.get::<Config>().unwrap().clone();
+        let guild_id = config.guild_id();
+        // artists and future moderators can use them without the role
+        match command.data.name.as_str() {
+            "dilate" => return Self::dilate(ctx, &command, &config).await,
+            "upload_practice" => return Self::upload_practice(ctx, &command, &config).await,
+            "skin_notifications" => return Self::skin_notifications(ctx, &command).await,
+            _ => {}
+        }
+        if !command
+            .user
+            .has_role(ctx.clone(), guild_id, config.role_id())
+            .await
+            .unwrap_or(false)
+        {
+            let data = CreateInteractionResponseMessage::new()
+                .content("You don't have the required permissions to use this command".to_string())
+                .ephemeral(true);
+            let builder = CreateInteractionResponse::Message(data);
+            if let Err(why) = command.create_response(&ctx.http, builder).await {
+                warn!("Could not respond to slash command: {why}");
+            }
+            return;
+        }
+        match command.data.name.as_str() {
+            "upload" | "upload_scan" => Self::upload_start(ctx, command, config).await,
+            "upload_finish" => {
+                let label = command.data.options.iter().find_map(|option| {
+                    match (option.name.as_str(), &option.value) {
+                        ("label", CommandDataOptionValue::String(value)) => Some(value.clone()),
+                        _ => None,
+                    }
+                });
+                Self::upload_finish(ctx, command.user.id, &CommandWrapper::Cmd(&command), label)
+                    .await;
+            }
+            "upload_cancel" => {
+                Self::upload_cancel(ctx, command.user.id, &CommandWrapper::Cmd(&command)).await;
+            }
+            "Upload skin" => {
+                Self::queue_message(ctx, &command, SkinToUploadDB::Normal).await;
+            }
+            "Upload skin (community)" => {
+                Self::queue_message(ctx, &command, SkinToUploadDB::Community).await;
+            }
+            "Reject skin" => Self::reject_submission(ctx, &command, &config).await,
+            "Remove from upload" => Self::unqueue_message(ctx, &command).await,
+            "revalidate" => Self::revalidate(ctx, &command).await,
+            "vote_round" => Self::vote_round(ctx, guild_id, &command).await,
+            "skin_votes" => Self::skin_votes(ctx, guild_id, &command).await,
+            "skin_stats" => Self::skin_stats(ctx, &command).await,
+            "upload_export" => Self::upload_export(ctx, &command, &config).await,
+            "upload_log" => Self::upload_log(ctx, &command).await,
+            "batches" => Self::batches(ctx, &command).await,
+            "upload_undo" => Self::upload_undo(ctx, &command, &config).await,
+            "blocklist" => Self::blocklist(ctx, &command).await,
+            "skin_delete" | "skin_update" => Self::skin_edit(ctx, &command, &config).await,
+            _ => {}
         }
     }
-}
 
-#[derive(Clone)]
-pub struct SkinToUpload {
-    author: String,
-    license: String,
-    file_256x128: Vec<u8>,
-    file_512x256: Vec<u8>,
-    database: SkinToUploadDB,
-    original_msg_id: MessageId,
-    positive_ratio: f64,
-}
+    async fn skin_notifications(ctx: Context, command: &CommandInteraction) {
+        let enabled = command.data.options.iter().any(|option| {
+            matches!(
+                (option.name.as_str(), &option.value),
+                ("enabled", CommandDataOptionValue::Boolean(true))
+            )
+        });
+        Self::set_dm_opt_out(
+            ctx,
+            command.user.id,
+            !enabled,
+            &CommandWrapper::Cmd(command),
+        )
+        .await;
+    }
+
+    /// The upload was not confirmed, the uploader changes the skins first.
+    async fn upload_back(ctx: Context, comp: &ComponentInteraction) {
+        let builder = CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .content("Nothing was uploaded, change the skins and finish again")
+                .embeds(vec![])
+                .components(vec![]),
+        );
+        if let Err(why) = comp.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+    }
+
+    async fn cancel_upload_undo(ctx: Context, comp: &ComponentInteraction) {
+        let builder = CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .content("Nothing was undone")
+                .components(vec![]),
+        );
+        if let Err(why) = comp.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        }
+    }
+
+    /// `/upload` and `/upload_scan` start the upload session of the user, as long as nobody
+    /// else is uploading.
+    async fn upload_start(ctx: Context, command: CommandInteraction, config: Arc<Config>) {
+        if !is_upload_channel(&ctx, &config, command.channel_id).await {
+            let data = CreateInteractionResponseMessage::new()
+                .content(format!(
+                    "Skins cannot be uploaded from this channel, only from {}",
+                    config.allowed_channels_list()
+                ))
+                .ephemeral(true);
+            let builder = CreateInteractionResponse::Message(data);
+            if let Err(why) = command.create_response(&ctx.http, builder).await {
+                warn!("Could not respond to slash command: {why}");
+            }
+            return;
+        }
+        if ctx
+            .data
+            .read()
+            .await
+            .get::<SkinUploads>()
+            .unwrap()
+            .shutting_down
+        {
+            let data = CreateInteractionResponseMessage::new()
+                .content("The bot is shutting down, try again once it is back")
+                .ephemeral(true);
+            let builder = CreateInteractionResponse::Message(data);
+            if let Err(why) = command.create_response(&ctx.http, builder).await {
+                warn!("Could not respond to slash command: {why}");
+            }
+            return;
+        }
+
+        if !ctx
+            .data
+            .read()
+            .await
+            .get::<SkinUploads>()
+            .unwrap()
+            .uploads
+            .is_empty()
+        {
+            let overview = session_overview(ctx.data.read().await.get::<SkinUploads>().unwrap());
+            let data = CreateInteractionResponseMessage::new()
+                .content(format!(
+                    "Someone is already uploading skins. Please wait.\n{overview}"
+                ))
+                .components(vec![CreateActionRow::Buttons(vec![CreateButton::new(
+                    SESSIONS_BUTTON_ID,
+                )
+                .label("Manage upload sessions (admins)")
+                .style(ButtonStyle::Secondary)])])
+                .ephemeral(true);
+            let builder = CreateInteractionResponse::Message(data);
+            if let Err(why) = command.create_response(&ctx.http, builder).await {
+                warn!("Could not respond to slash command: {why}");
+            }
+            return;
+        }
 
-pub struct SkinUploadItem {
-    notify: Arc<Notify>,
-    reaction_list: LinkedHashMap<MessageId, UserId>,
-    skins_try_upload: LinkedHashMap<MessageId, SkinToUploadDB>,
-    errors: VecDeque<String>,
-    state: SkinUploadState,
-    skins_to_upload: LinkedHashMap<String, SkinToUpload>,
+        let default_database = command.data.options.iter().find_map(|option| {
+            match (option.name.as_str(), &option.value) {
+                ("default_database", CommandDataOptionValue::String(value)) => {
+                    Some(if value == "community" {
+                        SkinToUploadDB::Community
+                    } else {
+                        SkinToUploadDB::Normal
+                    })
+                }
+                _ => None,
+            }
+        });
+        let mut main_cmd_str = Mention::User(command.user.id).to_string()
+            + "\n\
+            __**:art: You are about to upload skins to the database.**__\n\n\
+            ";
+        if let Some(database) = default_database {
+            main_cmd_str += &format!(
+                "All skins are queued for the **{}** database, whichever queue emoji you react with\n\n",
+                database.to_string()
+            );
+        }
+        let main_cmd_embed = CreateEmbed::new().color(Colour::TEAL).field(
+            "Please react to all skins you want to upload:",
+            format!(
+                "\
+                - React with {} to upload a skin to the normal database\n\
+                - React with {} to upload a skin to the community database\n\
+                - Or use `Apps > Upload skin` on a message or forum post\n\
+                - Use `Apps > Remove from upload` to drop a queued skin again\n\
+                - Move queued skins to the other database with the menu below\n",
+                config.emojis.queue_label(SkinToUploadDB::Normal),
+                config.emojis.queue_label(SkinToUploadDB::Community),
+            ),
+            false,
+        );
+        let main_cmd_end_embed = CreateEmbed::new().color(Colour::ORANGE).field(
+            "",
+            "\
+            Once you are done, use the 🆗 button or the command `/upload_finish`\n\
+            To cancel the upload, use the 🇽 button or the command `/upload_cancel`\n\
+            To toggle the removal of stray pixels, use the 🧹 button\n\
+            To pick how missing 256x128 skins are generated, use the resampler buttons\n\
+            If reactions were missed, use the 🔄 button to read them again\n",
+            false,
+        );
+        let data = CreateInteractionResponseMessage::new()
+            .content(main_cmd_str.clone())
+            .ephemeral(true)
+            .add_embeds(vec![main_cmd_embed, main_cmd_end_embed])
+            .components(upload_buttons(&config));
+        let builder = CreateInteractionResponse::Message(data);
+        if let Err(why) = command.create_response(&ctx.http, builder).await {
+            warn!("Could not respond to slash command: {why}");
+        } else {
+            let (events, events_rx) = mpsc::unbounded_channel();
+            ctx.data
+                .write()
+                .await
+                .get_mut::<SkinUploads>()
+                .unwrap()
+                .uploads
+                .insert(
+                    command.user.id,
+                    SkinUploadItem {
+                        events,
+                        session: Session::new(command.user.id),
+                        queue_reactions: HashSet::default(),
+                        apply_cleanup: config.upload.cleanup_stray_pixels,
+                        sd_resampler: SdResampler::Lanczos,
+                        confirmation: None,
+                        default_database,
+                        channel_id: command.channel_id,
+                        started_at: Timestamp::now().unix_timestamp(),
+                        expires_at: Timestamp::now().unix_timestamp()
+                            + config.upload.timeout_secs as i64,
+                        queued_skins: 0,
+                        batch_label: None,
+                    },
+                );
+            ctx.data
+                .read()
+                .await
+                .get::<Metrics>()
+                .unwrap()
+                .record_session_started();
+            let session =
+                UploadSession::new(ctx.clone(), command.clone(), config.clone(), main_cmd_str);
+            let span = info_span!("upload_session", user = %command.user.id);
+            tokio::spawn(session.run(events_rx).instrument(span));
+            if command.data.name == "upload_scan" {
+                tokio::spawn(Self::upload_scan(ctx.clone(), command.clone(), config));
+            }
+        }
+    }
 }
 
-pub struct SkinUploads {
-    uploads: HashMap<UserId, SkinUploadItem>,
-    upload_lock: Arc<Mutex<()>>,
+#[async_trait]
+impl EventHandler for Handler {
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        match interaction {
+            Interaction::Component(comp) => Self::component_interaction(ctx, comp).await,
+            Interaction::Modal(modal) => Self::modal_submit(ctx, modal).await,
+            Interaction::Command(command) => Self::command_interaction(ctx, command).await,
+            _ => {}
+        }
+    }
+
+    async fn reaction_add(&self, ctx: Context, add_reaction: Reaction) {
+        let Some(user_id) = add_reaction.user_id else {
+            return;
+        };
+        let config = ctx.data.read().await.get::<Config>().unwrap().clone();
+        let Some(database) = config.emojis.queue_database(&add_reaction) else {
+            return;
+        };
+        // reactions in other channels are not meant for the upload
+        if !is_upload_channel(&ctx, &config, add_reaction.channel_id).await {
+            return;
+        }
+        let other_emojis = {
+            let mut data = ctx.data.write().await;
+            let Some(skin_upload) = data
+                .get_mut::<SkinUploads>()
+                .unwrap()
+                .uploads
+                .get_mut(&user_id)
+            else {
+                return;
+            };
+            if skin_upload.session.queue().is_err() {
+                return;
+            }
+            // with a default database both emojis queue for it, there is nothing to clean up
+            let (database, other_emojis) = match skin_upload.default_database {
+                Some(default_database) => (default_database, Vec::new()),
+                None => (
+                    database,
+                    config.emojis.queue_emojis(database.other()).to_vec(),
+                ),
+            };
+            skin_upload.queue_reactions.insert((
+                add_reaction.message_id,
+                add_reaction.emoji.as_data(),
+                add_reaction.burst,
+            ));
+            skin_upload.send(SessionEvent::SkinQueued {
+                msg_id: add_reaction.message_id,
+                channel_id: add_reaction.channel_id,
+                database,
+            });
+            other_emojis
+        };
+        // a skin can only be uploaded to one database, the reactions are taken from the message
+        // because clients differ in the variation selectors they send
+        let Ok(msg) = add_reaction.message(&ctx).await else {
+            return;
+        };
+        for reaction in msg.reactions {
+            if reaction_matches(&reaction.reaction_type, &other_emojis)
+                && (add_reaction
+                    .channel_id
+                    .delete_reaction_emoji(&ctx, add_reaction.message_id, reaction.reaction_type)
+                    .await)
+                    .is_err()
+            {
+                warn!("no permissions to delete reaction");
+            }
+        }
+    }
+
+    async fn reaction_remove(&self, ctx: Context, removed_reaction: Reaction) {
+        let Some(user_id) = removed_reaction.user_id else {
+            return;
+        };
+        let mut data = ctx.data.write().await;
+        if data
+            .get::<Config>()
+            .unwrap()
+            .emojis
+            .queue_database(&removed_reaction)
+            .is_some()
+        {
+            if let Some(skin_upload) = data
+                .get_mut::<SkinUploads>()
+                .unwrap()
+                .uploads
+                .get_mut(&user_id)
+            {
+                skin_upload.queue_reactions.remove(&(
+                    removed_reaction.message_id,
+                    removed_reaction.emoji.as_data(),
+                    removed_reaction.burst,
+                ));
+                let still_reacted = skin_upload
+                    .queue_reactions
+                    .iter()
+                    .any(|(msg_id, _, _)| *msg_id == removed_reaction.message_id);
+                if !still_reacted && skin_upload.session.queue().is_ok() {
+                    skin_upload.send(SessionEvent::SkinUnqueued {
+                        msg_id: removed_reaction.message_id,
+                    });
+                }
+            }
+        }
+    }
+
+    async fn guild_scheduled_event_update(&self, ctx: Context, event: ScheduledEvent) {
+        if matches!(
+            event.status,
+            ScheduledEventStatus::Completed | ScheduledEventStatus::Canceled
+        ) {
+            close_vote_round_of_event(&ctx, event.guild_id, event.id).await;
+        }
+    }
+
+    async fn message(&self, ctx: Context, msg: Message) {
+        let config = ctx.data.read().await.get::<Config>().unwrap().clone();
+        Self::ask_media_submitter(ctx, &msg, &config).await;
+    }
+
+    async fn resume(&self, ctx: Context, _: ResumedEvent) {
+        // discord doesn't replay the reactions of a disconnect that lasted too long
+        resync_all_sessions(&ctx).await;
+    }
+
+    async fn ready(&self, ctx: Context, _ready: Ready) {
+        let guild_id = ctx.data.read().await.get::<Config>().unwrap().guild_id();
+        // bad credentials would otherwise only show once a whole batch failed
+        let probe_ctx = ctx.clone();
+        tokio::spawn(async move {
+            let _ = check_database(&probe_ctx).await;
+        });
+
+        let upload_cmd = CreateCommand::new("upload")
+            .description("Upload a skin to the database")
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "default_database",
+                    "Queue every skin for this database, whichever queue emoji is used",
+                )
+                .add_string_choice("normal", "normal")
+                .add_string_choice("community", "community"),
+            );
+        let upload_finish_cmd = CreateCommand::new("upload_finish")
+            .description("Finish an upload, previously started with the `/upload` command")
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "label",
+                    "Names the batch in `/batches` (default: the date)",
+                )
+                .max_length(MAX_LABEL_LENGTH as u16),
+            );
+
+        let revalidate_cmd = CreateCommand::new("revalidate")
+            .description("Download and check a queued skin again, e.g. after its file was replaced")
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "name",
+                    "The name of the skin as shown in the summary",
+                )
+                .required(true),
+            );
+
+        let upload_cancel_cmd = CreateCommand::new("upload_cancel")
+            .description("Cancel an ongoing upload, that was started using the `/upload` command")
+            .dm_permission(false);
+
+        let vote_round_cmd = CreateCommand::new("vote_round")
+            .description("Open a community voting round for the skins posted in this channel")
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "name",
+                    "Name of the voting round, e.g. skin of the month",
+                )
+                .required(true),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "days",
+                    "How many days the voting round lasts",
+                )
+                .required(true)
+                .min_int_value(1)
+                .max_int_value(60),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "create_event",
+                "Create a discord event with the deadline (default: true)",
+            ));
+
+        let upload_scan_cmd = CreateCommand::new("upload_scan")
+            .description("Upload the skins posted in this channel during the last days")
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "days",
+                    "How many days back skins are collected (default: 7)",
+                )
+                .min_int_value(1)
+                .max_int_value(365),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "limit",
+                    "At most this many skins are collected (default: 50)",
+                )
+                .min_int_value(1)
+                .max_int_value(200),
+            );
+
+        let skin_votes_cmd = CreateCommand::new("skin_votes")
+            .description("Rank the skins posted in this channel by their community votes")
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "days",
+                    "How many days back the skins are ranked (default: 7)",
+                )
+                .min_int_value(1)
+                .max_int_value(365),
+            );
+
+        let skin_stats_cmd = CreateCommand::new("skin_stats")
+            .description("Summarize the uploads of a month")
+            .dm_permission(false)
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "month",
+                "Like 2024-05 (default: this month)",
+            ))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "csv",
+                "Also attach the uploaded skins as csv",
+            ));
+
+        let upload_export_cmd = CreateCommand::new("upload_export")
+            .description("Export the checked skins of your upload as a client skins folder")
+            .dm_permission(false);
+
+        let upload_log_cmd = CreateCommand::new("upload_log")
+            .description("Show the last upload attempts and database edits")
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "count",
+                    "How many entries are shown (default: 10)",
+                )
+                .min_int_value(1)
+                .max_int_value(50),
+            );
+
+        let batches_cmd = CreateCommand::new("batches")
+            .description("Browse the uploaded batches")
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "list",
+                    "The last batches, newest first",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::Integer,
+                        "count",
+                        "How many batches are shown (default: 10)",
+                    )
+                    .min_int_value(1)
+                    .max_int_value(25),
+                ),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "show",
+                    "The skins of a batch, their outcome and the announcements",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "label",
+                        "The label of the batch",
+                    )
+                    .required(true),
+                ),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "import",
+                    "Record the uploads from before the batch history, from announcements or a csv",
+                )
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::Channel,
+                    "channel",
+                    "Where the bot announced the skins (default: this channel)",
+                ))
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::Integer,
+                        "days",
+                        "How far back the announcements are read (default: 365)",
+                    )
+                    .min_int_value(1)
+                    // the bot is younger than ten years
+                    .max_int_value(3650),
+                )
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::Attachment,
+                    "csv",
+                    "Columns finished_at, name, database and optionally label, uploader, author, submission",
+                )),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "rerender",
+                "Draw the tee previews of announcements from an older renderer again",
+            ));
+
+        let upload_undo_cmd = CreateCommand::new("upload_undo")
+            .description("Delete the skins of the last batch from the database again")
+            .dm_permission(false);
+
+        let block_kind_option =
+            CreateCommandOption::new(CommandOptionType::String, "kind", "What is blocked")
+                .add_string_choice("author (as named in the skin info)", "author")
+                .add_string_choice("user (who posted the submission)", "user")
+                .add_string_choice("skin name", "name")
+                .required(true);
+        let block_value_option = CreateCommandOption::new(
+            CommandOptionType::String,
+            "value",
+            "The author, the user id or mention, or the skin name",
+        )
+        .required(true);
+        let blocklist_cmd = CreateCommand::new("blocklist")
+            .description("Reject the skins of authors, users or names")
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "add",
+                    "Block an author, user or skin name",
+                )
+                .add_sub_option(block_kind_option.clone())
+                .add_sub_option(block_value_option.clone())
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "reason",
+                        "Shown when a skin is rejected",
+                    )
+                    .required(true),
+                ),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "remove",
+                    "Unblock an author, user or skin name",
+                )
+                .add_sub_option(block_kind_option)
+                .add_sub_option(block_value_option),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "list",
+                "Everything that is blocked, with the reasons",
+            ));
+
+        let database_option = CreateCommandOption::new(
+            CommandOptionType::String,
+            "database",
+            "The database of the skin (default: normal)",
+        )
+        .add_string_choice("normal", "normal")
+        .add_string_choice("community", "community");
+        let skin_delete_cmd = CreateCommand::new("skin_delete")
+            .description("Remove a skin from the database")
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "name", "Name of the skin")
+                    .required(true),
+            )
+            .add_option(database_option.clone());
+        let skin_update_cmd = CreateCommand::new("skin_update")
+            .description("Fix the author, license or skin pack of a skin in the database")
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "name", "Name of the skin")
+                    .required(true),
+            )
+            .add_option(database_option)
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "author",
+                "The new author",
+            ))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "license",
+                "The new license",
+            ))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "skin_pack",
+                "The new skin pack",
+            ));
+
+        let dilate_cmd = CreateCommand::new("dilate")
+            .description("Dilate an image, to prevent dark outlines when it is resized in game")
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::Attachment, "image", "The skin image")
+                    .required(true),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "radius",
+                    "How many pixels a single pass spreads the colors",
+                )
+                .min_int_value(1)
+                .max_int_value(16),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "iterations",
+                    "How far the colors are spread into transparent pixels",
+                )
+                .min_int_value(0)
+                .max_int_value(100),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "alpha_threshold",
+                    "Pixels up to this alpha are considered transparent",
+                )
+                .min_int_value(0)
+                .max_int_value(255),
+            );
+
+        let skin_notifications_cmd = CreateCommand::new("skin_notifications")
+            .description("Get a DM once your skins are added to the database")
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Boolean,
+                    "enabled",
+                    "Whether you want the DM",
+                )
+                .required(true),
+            );
+
+        let upload_practice_cmd = CreateCommand::new("upload_practice")
+            .description("Practice an upload with fake submissions, nothing is uploaded")
+            .dm_permission(false);
+
+        let queue_normal_cmd = CreateCommand::new("Upload skin")
+            .kind(CommandType::Message)
+            .dm_permission(false);
+        let queue_community_cmd = CreateCommand::new("Upload skin (community)")
+            .kind(CommandType::Message)
+            .dm_permission(false);
+        let unqueue_cmd = CreateCommand::new("Remove from upload")
+            .kind(CommandType::Message)
+            .dm_permission(false);
+        let reject_cmd = CreateCommand::new("Reject skin")
+            .kind(CommandType::Message)
+            .dm_permission(false);
+
+        if (guild_id
+            .set_commands(
+                &ctx.http,
+                vec![
+                    upload_cmd,
+                    upload_finish_cmd,
+                    upload_cancel_cmd,
+                    revalidate_cmd,
+                    upload_scan_cmd,
+                    vote_round_cmd,
+                    skin_votes_cmd,
+                    skin_stats_cmd,
+                    upload_export_cmd,
+                    upload_log_cmd,
+                    batches_cmd,
+                    upload_undo_cmd,
+                    blocklist_cmd,
+                    skin_delete_cmd,
+                    skin_update_cmd,
+                    dilate_cmd,
+                    skin_notifications_cmd,
+                    upload_practice_cmd,
+                    queue_normal_cmd,
+                    queue_community_cmd,
+                    unqueue_cmd,
+                    reject_cmd,
+                ],
+            )
+            .await)
+            .is_err()
+        {
+            // ignore for now
+        }
+    }
 }
 
-impl TypeMapKey for SkinUploads {
-    type Value = Self;
+/// Inserts the shared state the handler relies on.
+async fn insert_client_data(
+    client: &Client,
+    config: Config,
+    plugins: ValidationPlugins,
+    default_skins: DefaultSkins,
+    blocklist: Blocklist,
+    database: Arc<dyn SkinDatabaseClient>,
+) {
+    let skin_uploads = SkinUploads {
+        uploads: HashMap::default(),
+        failed_uploads: HashMap::default(),
+        upload_lock: Arc::default(),
+        shutting_down: false,
+    };
+    let mut data = client.data.write().await;
+    let config = Arc::new(config);
+    data.insert::<Config>(config.clone());
+    data.insert::<SkinUploads>(skin_uploads);
+    data.insert::<VoteRounds>(VoteRounds::default());
+    data.insert::<PendingSkinEdits>(HashMap::default());
+    data.insert::<PracticeSessions>(HashMap::default());
+    data.insert::<ValidationPlugins>(Arc::new(plugins));
+    data.insert::<DefaultSkinHashes>(Arc::new(default_skins));
+    data.insert::<AuditLog>(AuditLog::new(config.upload.audit_log_file.clone()));
+    data.insert::<BatchHistory>(BatchHistory::new(config.upload.batch_history_file.clone()));
+    data.insert::<Blocklist>(blocklist);
+    data.insert::<SkinDatabase>(database);
+    data.insert::<Metrics>(Arc::default());
+    data.insert::<DmOptOuts>(DmOptOuts::load(
+        config.announcements.dm_opt_out_file.clone(),
+    ));
 }
 
 #[tokio::main]
@@ -1004,27 +3950,83 @@ async fn main() {
 
     dotenvy::dotenv().ok();
 
-    // Login with a bot token from the environment
-    let token = env::var("DISCORD_TOKEN").expect("token");
+    // RUST_LOG overrides what is logged, e.g. `RUST_LOG=debug`
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new("warn,ddnet_discord=info")),
+        )
+        .init();
+
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(err) => {
+            error!("{err}");
+            std::process::exit(1);
+        }
+    };
+    let plugins = match ValidationPlugins::load(&config.plugins) {
+        Ok(plugins) => plugins,
+        Err(err) => {
+            error!("{err:#}");
+            std::process::exit(1);
+        }
+    };
+    let default_skins = match &config.content_checks.default_skins_dir {
+        Some(dir) if config.content_checks.enabled => match DefaultSkins::load(Path::new(dir)) {
+            Ok(default_skins) => default_skins,
+            Err(err) => {
+                error!("{err:#}");
+                std::process::exit(1);
+            }
+        },
+        _ => DefaultSkins::default(),
+    };
+    let blocklist = match Blocklist::load(config.upload.blocklist_file.clone()) {
+        Ok(blocklist) => blocklist,
+        Err(err) => {
+            error!("{err:#}");
+            std::process::exit(1);
+        }
+    };
+
     let intents = GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT;
-    let mut client = Client::builder(token, intents)
+    let mut client = Client::builder(&config.discord_token, intents)
         .event_handler(Handler)
         .framework(framework)
         .await
         .expect("Error creating client");
 
-    let skin_uploads = SkinUploads {
-        uploads: HashMap::default(),
-        upload_lock: Arc::default(),
-    };
-    client
-        .data
-        .write()
-        .await
-        .insert::<SkinUploads>(skin_uploads);
+    let database = Arc::new(HttpDatabaseClient::new(&config));
+    insert_client_data(&client, config, plugins, default_skins, blocklist, database).await;
+    let config = client.data.read().await.get::<Config>().unwrap().clone();
+    tokio::spawn(schedule_vote_reports(client.http.clone(), config.clone()));
+    if let Some(listen) = &config.admin_http.listen {
+        // validated with the config
+        let addr = listen.parse().unwrap();
+        tokio::spawn(serve_admin_http(
+            addr,
+            client.data.clone(),
+            client.shard_manager.clone(),
+        ));
+    }
+    if let (Some(listen), Some(token)) = (&config.editor_api.listen, &config.editor_api.token) {
+        // validated with the config
+        let addr = listen.parse().unwrap();
+        tokio::spawn(serve_editor_api(
+            addr,
+            token.clone(),
+            client.data.clone(),
+            client.http.clone(),
+        ));
+    }
+    tokio::spawn(shutdown_on_signal(
+        client.data.clone(),
+        client.shard_manager.clone(),
+    ));
 
     // start listening for events by starting a single shard
     if let Err(why) = client.start().await {
-        println!("An error occurred while running the client: {why:?}");
+        error!("An error occurred while running the client: {why:?}");
     }
 }
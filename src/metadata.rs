@@ -0,0 +1,355 @@
+use anyhow::{bail, Context as _};
+use regex::Regex;
+use serde::Deserialize;
+use serenity::all::{
+    ActionRowComponent, Attachment, ChannelId, InputTextStyle, Message, MessageId,
+    ModalInteractionData,
+};
+use serenity::builder::{CreateActionRow, CreateButton, CreateInputText, CreateModal};
+
+/// at most this many tags are accepted, the database only shows a few anyway
+const MAX_TAGS: usize = 10;
+/// a sidecar is only a few lines, anything bigger is not meant for the bot
+const MAX_SIDECAR_SIZE: u32 = 16 * 1024;
+
+pub const SKIN_INFO_BUTTON_PREFIX: &str = "skin_info_button_";
+pub const SKIN_INFO_MODAL_PREFIX: &str = "skin_info_modal_";
+/// the submitter of a media post enters the info, it is stored as the title of the post
+pub const POST_INFO_BUTTON_PREFIX: &str = "post_info_button_";
+pub const POST_INFO_MODAL_PREFIX: &str = "post_info_modal_";
+/// discord's limit for the name of a thread
+pub const MAX_POST_TITLE_LENGTH: usize = 100;
+
+/// The info of a submission. Tooling can attach it as a `.json` or `.toml` sidecar file,
+/// otherwise it is read from the message text.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkinMetadata {
+    pub name: String,
+    pub author: String,
+    pub license: String,
+    /// uploaded as skin pack, instead of the original name of a transliterated skin
+    #[serde(default)]
+    pub pack: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl SkinMetadata {
+    fn validate(&self) -> anyhow::Result<()> {
+        for (field, value) in [
+            ("name", &self.name),
+            ("author", &self.author),
+            ("license", &self.license),
+        ] {
+            if value.trim().is_empty() {
+                bail!("`{field}` is empty");
+            }
+        }
+        if self
+            .pack
+            .as_ref()
+            .is_some_and(|pack| pack.trim().is_empty())
+        {
+            bail!("`pack` is empty, leave it out instead");
+        }
+        if self.tags.len() > MAX_TAGS {
+            bail!("`tags` has more than {MAX_TAGS} entries");
+        }
+        if self.tags.iter().any(|tag| tag.trim().is_empty()) {
+            bail!("`tags` contains an empty tag");
+        }
+        Ok(())
+    }
+}
+
+/// Replaces the quotes and dashes phones and word processors insert by their ascii versions.
+fn normalize_punctuation(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '“' | '”' | '„' | '‟' | '″' | '«' | '»' => '"',
+            '‘' | '’' | '‚' | '‛' | '′' => '\'',
+            '–' | '—' | '―' | '−' | '‐' | '‑' => '-',
+            _ => c,
+        })
+        .collect()
+}
+
+/// Reads `"name" by author (license)` from the text, also with single quotes,
+/// a dash instead of `by`, or the license after a dash instead of in parentheses.
+pub fn parse_skin_info(text: &str) -> anyhow::Result<(String, String, String)> {
+    let normalized = normalize_punctuation(text);
+    let patterns = [
+        r#"(?i)["'](.+)["'](?:\s+by\s+|\s*-\s*)(.+?)\s*\((.+)\)"#,
+        r#"(?im)["'](.+)["']\s+by\s+(.+?)\s+-\s*(.+?)\s*$"#,
+    ];
+    for pattern in patterns {
+        if let Some(caps) = Regex::new(pattern).unwrap().captures(&normalized) {
+            let info = (1..=3).map(|i| caps[i].trim().to_string());
+            if let [name, author, license] = info.collect::<Vec<_>>().as_slice() {
+                if !name.is_empty() && !author.is_empty() && !license.is_empty() {
+                    return Ok((name.clone(), author.clone(), license.clone()));
+                }
+            }
+        }
+    }
+    bail!(
+        "name, author or license not found in msg: {}",
+        text.replace('\n', "")
+    )
+}
+
+/// Reads `by author (license)` without a name, the name is taken from the file then.
+fn parse_author_license(text: &str) -> Option<(String, String)> {
+    let normalized = normalize_punctuation(text);
+    let caps = Regex::new(r"(?i)\bby\s+(.+?)\s*\((.+)\)")
+        .unwrap()
+        .captures(&normalized)?;
+    let (author, license) = (caps[1].trim(), caps[2].trim());
+    (!author.is_empty() && !license.is_empty()).then(|| (author.to_string(), license.to_string()))
+}
+
+/// Reads the skin info from the message text, or from the embeds of submissions
+/// relayed by webhooks. The parts can be split between the text and the embeds,
+/// without a name the name of the attached png is used.
+pub fn parse_submission(msg: &Message) -> anyhow::Result<(String, String, String)> {
+    let content_err = match parse_skin_info(&msg.content) {
+        Ok(info) => return Ok(info),
+        Err(err) => err,
+    };
+    let mut all_text = msg.content.clone();
+    for embed in &msg.embeds {
+        let title = embed.title.clone().unwrap_or_default();
+        let description = embed.description.as_deref().unwrap_or_default();
+        let text = title.clone() + " " + description;
+        if let Ok(info) = parse_skin_info(&text) {
+            return Ok(info);
+        }
+        // a plain title is the name
+        if let Ok(info) = parse_skin_info(&format!("\"{title}\" {description}")) {
+            return Ok(info);
+        }
+        all_text += &format!(" {text}");
+
+        let field = |names: &[&str]| {
+            embed
+                .fields
+                .iter()
+                .find(|field| names.contains(&field.name.trim().to_lowercase().as_str()))
+                .map(|field| field.value.trim().to_string())
+        };
+        let name = field(&["name", "skin", "skin name"])
+            .or_else(|| (!title.is_empty()).then(|| title.clone()));
+        if let (Some(name), Some(author), Some(license)) = (
+            name,
+            field(&["author", "creator", "by"]),
+            field(&["license"]),
+        ) {
+            return Ok((name, author, license));
+        }
+    }
+    if let Ok(info) = parse_skin_info(&all_text.replace('\n', " ")) {
+        return Ok(info);
+    }
+    if let (Some(name), Some((author, license))) = (png_name(msg), parse_author_license(&all_text))
+    {
+        return Ok((name.to_string(), author, license));
+    }
+    Err(content_err)
+}
+
+/// The name of the first attached png, without the extension.
+fn png_name(msg: &Message) -> Option<&str> {
+    msg.attachments.iter().find_map(|a| {
+        let (stem, extension) = a.filename.rsplit_once('.')?;
+        extension.eq_ignore_ascii_case("png").then_some(stem)
+    })
+}
+
+/// Opens the form to enter the info of a submission that could not be read.
+pub fn skin_info_button(msg: &Message, prefix: &str) -> CreateButton {
+    CreateButton::new(format!("{prefix}{}_{}", msg.channel_id, msg.id)).label("Enter the skin info")
+}
+
+/// The submission of a skin info button or form.
+pub fn parse_skin_info_id(custom_id: &str, prefix: &str) -> Option<(ChannelId, MessageId)> {
+    let (channel_id, msg_id) = custom_id.strip_prefix(prefix)?.split_once('_')?;
+    Some((
+        ChannelId::new(channel_id.parse().ok()?),
+        MessageId::new(msg_id.parse().ok()?),
+    ))
+}
+
+/// The form to type the info of the submission, prefilled with what is known.
+pub fn skin_info_modal(msg: &Message, prefix: &str) -> CreateModal {
+    let input = |label: &str, id: &str, value: Option<&str>| {
+        let input = CreateInputText::new(InputTextStyle::Short, label, id).max_length(100);
+        CreateActionRow::InputText(match value {
+            Some(value) => input.value(value),
+            None => input,
+        })
+    };
+    CreateModal::new(
+        format!("{prefix}{}_{}", msg.channel_id, msg.id),
+        "Skin info",
+    )
+    .components(vec![
+        input("Name", "name", png_name(msg)),
+        input("Author", "author", Some(&msg.author.name)),
+        input("License", "license", None),
+    ])
+}
+
+/// The info typed into the form.
+pub fn modal_metadata(data: &ModalInteractionData) -> anyhow::Result<SkinMetadata> {
+    let input = |id: &str| {
+        data.components
+            .iter()
+            .flat_map(|row| &row.components)
+            .find_map(|component| match component {
+                ActionRowComponent::InputText(input) if input.custom_id == id => {
+                    Some(input.value.clone().unwrap_or_default().trim().to_string())
+                }
+                _ => None,
+            })
+            .unwrap_or_default()
+    };
+    let metadata = SkinMetadata {
+        name: input("name"),
+        author: input("author"),
+        license: input("license"),
+        pack: None,
+        tags: Vec::new(),
+    };
+    metadata.validate()?;
+    Ok(metadata)
+}
+
+/// Whether the attachment is a metadata sidecar rather than a skin.
+pub fn is_sidecar(filename: &str) -> bool {
+    let filename = filename.to_lowercase();
+    filename.ends_with(".json") || filename.ends_with(".toml")
+}
+
+/// Parses and validates a sidecar, the format is picked by the file extension.
+pub fn parse_sidecar(filename: &str, content: &[u8]) -> anyhow::Result<SkinMetadata> {
+    let content = std::str::from_utf8(content).context("not utf-8")?;
+    let metadata: SkinMetadata = if filename.to_lowercase().ends_with(".json") {
+        serde_json::from_str(content)?
+    } else {
+        toml::from_str(content)?
+    };
+    metadata.validate()?;
+    Ok(metadata)
+}
+
+async fn download_sidecar(attachment: &Attachment) -> anyhow::Result<SkinMetadata> {
+    if attachment.size > MAX_SIDECAR_SIZE {
+        bail!("bigger than {} KiB", MAX_SIDECAR_SIZE / 1024);
+    }
+    let content = attachment.download().await?;
+    parse_sidecar(&attachment.filename, &content)
+}
+
+/// The info of a submission, a sidecar is preferred over the message text.
+/// An invalid sidecar is an error, the text might be outdated.
+pub async fn submission_metadata(msg: &Message) -> anyhow::Result<SkinMetadata> {
+    if let Some(attachment) = msg.attachments.iter().find(|a| is_sidecar(&a.filename)) {
+        return download_sidecar(attachment)
+            .await
+            .with_context(|| format!("the sidecar `{}` is invalid", attachment.filename));
+    }
+    let (name, author, license) = parse_submission(msg)?;
+    Ok(SkinMetadata {
+        name,
+        author,
+        license,
+        pack: None,
+        tags: Vec::new(),
+    })
+}
+
+/// Reads the info from the title of a forum or media post, the title can also be just the
+/// name with the rest in the text.
+pub fn title_metadata(title: &str, content: &str) -> Option<SkinMetadata> {
+    let (name, author, license) = parse_skin_info(&format!("{title} {content}"))
+        .or_else(|_| parse_skin_info(&format!("\"{title}\" {content}")))
+        .ok()?;
+    Some(SkinMetadata {
+        name,
+        author,
+        license,
+        pack: None,
+        tags: Vec::new(),
+    })
+}
+
+/// The title a post gets once its submitter entered the info, it is read back by
+/// `title_metadata`.
+pub fn post_title(metadata: &SkinMetadata) -> String {
+    format!(
+        "\"{}\" by {} ({})",
+        metadata.name, metadata.author, metadata.license
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_sidecar, parse_skin_info, post_title, title_metadata};
+
+    #[test]
+    fn parses_common_variants() {
+        let expected = Some(("kitty".to_string(), "artist".to_string(), "CC0".to_string()));
+        for text in [
+            "\"kitty\" by artist (CC0)",
+            "“kitty” by artist (CC0)",
+            "new skin: 'kitty' BY artist (CC0)",
+            "\"kitty\" – artist (CC0)",
+            "\"kitty\" by artist — CC0",
+        ] {
+            assert_eq!(parse_skin_info(text).ok(), expected, "{text}");
+        }
+        assert!(parse_skin_info("kitty by artist").is_err());
+    }
+
+    #[test]
+    fn reads_post_titles() {
+        let metadata = title_metadata("\"kitty\" by artist (CC0)", "").unwrap();
+        assert_eq!(
+            (metadata.name.as_str(), metadata.author.as_str()),
+            ("kitty", "artist")
+        );
+        assert_eq!(title_metadata(&post_title(&metadata), ""), Some(metadata));
+        let metadata = title_metadata("kitty", "by artist (CC0)").unwrap();
+        assert_eq!(metadata.name, "kitty");
+        assert_eq!(title_metadata("my new skin", ""), None);
+    }
+
+    #[test]
+    fn parses_json_and_toml() {
+        let json = parse_sidecar(
+            "skin.json",
+            br#"{"name": "kitty", "author": "artist", "license": "CC0", "tags": ["cat"]}"#,
+        )
+        .unwrap();
+        let toml = parse_sidecar(
+            "skin.TOML",
+            b"name = \"kitty\"\nauthor = \"artist\"\nlicense = \"CC0\"\ntags = [\"cat\"]\n",
+        )
+        .unwrap();
+        assert_eq!(json, toml);
+        assert_eq!(json.pack, None);
+
+        // typos must not be ignored silently
+        assert!(parse_sidecar(
+            "skin.json",
+            br#"{"name": "kitty", "autor": "artist", "license": "CC0"}"#
+        )
+        .is_err());
+        assert!(parse_sidecar(
+            "skin.json",
+            br#"{"name": " ", "author": "artist", "license": "CC0"}"#
+        )
+        .is_err());
+    }
+}
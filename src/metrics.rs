@@ -0,0 +1,151 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serenity::prelude::TypeMapKey;
+
+const PREFIX: &str = "ddnet_discord";
+
+/// Counters since the start of the bot, served as `/metrics` by the admin http server.
+#[derive(Default)]
+pub struct Metrics {
+    /// skin files, a skin with both sizes counts twice
+    uploads_attempted: AtomicU64,
+    uploads_succeeded: AtomicU64,
+    uploads_failed: AtomicU64,
+    dilations: AtomicU64,
+    dilate_micros: AtomicU64,
+    sessions_started: AtomicU64,
+}
+
+impl TypeMapKey for Metrics {
+    type Value = Arc<Self>;
+}
+
+/// What the metrics can't count themselves, read when they are rendered.
+pub struct Gauges {
+    pub active_sessions: usize,
+    pub gateway_connected: bool,
+}
+
+impl Metrics {
+    pub fn record_upload(&self, succeeded: bool) {
+        self.uploads_attempted.fetch_add(1, Ordering::Relaxed);
+        if succeeded {
+            self.uploads_succeeded.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.uploads_failed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_dilate(&self, duration: Duration) {
+        self.dilations.fetch_add(1, Ordering::Relaxed);
+        self.dilate_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_session_started(&self) {
+        self.sessions_started.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The prometheus text format.
+    pub fn render(&self, gauges: &Gauges) -> String {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: &[(&str, String)]| {
+            let _ = writeln!(text, "# HELP {PREFIX}_{name} {help}");
+            let _ = writeln!(text, "# TYPE {PREFIX}_{name} {kind}");
+            for (suffix, value) in samples {
+                let _ = writeln!(text, "{PREFIX}_{name}{suffix} {value}");
+            }
+        };
+        metric(
+            "skin_uploads_total",
+            "counter",
+            "Skin files sent to the database, by result.",
+            &[
+                (
+                    "{result=\"succeeded\"}",
+                    load(&self.uploads_succeeded).to_string(),
+                ),
+                (
+                    "{result=\"failed\"}",
+                    load(&self.uploads_failed).to_string(),
+                ),
+            ],
+        );
+        metric(
+            "skin_upload_attempts_total",
+            "counter",
+            "Skin files the bot tried to upload.",
+            &[("", load(&self.uploads_attempted).to_string())],
+        );
+        metric(
+            "dilate_duration_seconds",
+            "summary",
+            "Time spent dilating skins before uploading them.",
+            &[
+                (
+                    "_sum",
+                    (load(&self.dilate_micros) as f64 / 1_000_000.0).to_string(),
+                ),
+                ("_count", load(&self.dilations).to_string()),
+            ],
+        );
+        metric(
+            "upload_sessions_started_total",
+            "counter",
+            "Upload sessions started with a command.",
+            &[("", load(&self.sessions_started).to_string())],
+        );
+        metric(
+            "upload_sessions_active",
+            "gauge",
+            "Upload sessions that are currently open.",
+            &[("", gauges.active_sessions.to_string())],
+        );
+        metric(
+            "gateway_connected",
+            "gauge",
+            "Whether the bot is connected to the discord gateway.",
+            &[("", u8::from(gauges.gateway_connected).to_string())],
+        );
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Gauges, Metrics};
+
+    #[test]
+    fn renders_prometheus_text() {
+        let metrics = Metrics::default();
+        metrics.record_upload(true);
+        metrics.record_upload(false);
+        metrics.record_upload(true);
+        metrics.record_dilate(Duration::from_millis(1500));
+        let text = metrics.render(&Gauges {
+            active_sessions: 1,
+            gateway_connected: true,
+        });
+        for line in [
+            "# TYPE ddnet_discord_skin_uploads_total counter",
+            "ddnet_discord_skin_uploads_total{result=\"succeeded\"} 2",
+            "ddnet_discord_skin_uploads_total{result=\"failed\"} 1",
+            "ddnet_discord_skin_upload_attempts_total 3",
+            "ddnet_discord_dilate_duration_seconds_sum 1.5",
+            "ddnet_discord_dilate_duration_seconds_count 1",
+            "ddnet_discord_upload_sessions_active 1",
+            "ddnet_discord_gateway_connected 1",
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "{line} missing in:\n{text}"
+            );
+        }
+    }
+}
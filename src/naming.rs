@@ -0,0 +1,111 @@
+use std::fmt::{Display, Formatter};
+
+/// the client keeps skin names in a 24 byte buffer, including the terminating zero
+pub const MAX_NAME_LENGTH: usize = 23;
+/// the skin is written to `<name>.png` before the upload, these break the path
+const PATH_CHARACTERS: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+/// file names windows refuses, the skins are also downloaded on windows
+const RESERVED_NAMES: [&str; 4] = ["con", "prn", "aux", "nul"];
+
+/// Why a skin name can't be uploaded, even after sanitizing it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameError {
+    Empty,
+    TooLong(usize),
+    Reserved,
+    /// only printable ascii characters are allowed
+    InvalidCharacter(char),
+}
+
+impl Display for NameError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "has no name"),
+            Self::TooLong(len) => write!(
+                f,
+                "has a name of {len} characters, at most {MAX_NAME_LENGTH} are allowed"
+            ),
+            Self::Reserved => write!(f, "has a name that is reserved for devices on windows"),
+            Self::InvalidCharacter(c) => {
+                write!(f, "contains the character `{c}`, which is not allowed")
+            }
+        }
+    }
+}
+
+fn is_reserved(name: &str) -> bool {
+    let name = name.to_lowercase();
+    RESERVED_NAMES.contains(&name.as_str())
+        || ["com", "lpt"].iter().any(|prefix| {
+            name.strip_prefix(prefix)
+                .is_some_and(|n| n.len() == 1 && n.as_bytes()[0].is_ascii_digit())
+        })
+}
+
+/// The database treats names case-insensitively, `Cat` replaces `cat`.
+/// Names are compared by this key, never as written.
+pub fn name_key(name: &str) -> String {
+    name.to_ascii_lowercase()
+}
+
+pub fn same_skin_name(a: &str, b: &str) -> bool {
+    a.eq_ignore_ascii_case(b)
+}
+
+/// Brings the name into the form the client and the database accept: path characters become
+/// `_`, whitespace is trimmed and collapsed, trailing dots are removed.
+/// Names that would need a guess, like shortening them, are errors.
+pub fn sanitize_skin_name(name: &str) -> Result<String, NameError> {
+    let replaced: String = name
+        .chars()
+        .map(|c| if PATH_CHARACTERS.contains(&c) { '_' } else { c })
+        .collect();
+    let sanitized = replaced
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches('.')
+        .to_string();
+    if let Some(c) = sanitized
+        .chars()
+        .find(|c| !c.is_ascii() || c.is_ascii_control())
+    {
+        return Err(NameError::InvalidCharacter(c));
+    }
+    if sanitized.is_empty() {
+        return Err(NameError::Empty);
+    }
+    if sanitized.len() > MAX_NAME_LENGTH {
+        return Err(NameError::TooLong(sanitized.len()));
+    }
+    if is_reserved(&sanitized) {
+        return Err(NameError::Reserved);
+    }
+    Ok(sanitized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{name_key, same_skin_name, sanitize_skin_name, NameError};
+
+    #[test]
+    fn sanitizes_what_is_safe() {
+        assert_eq!(sanitize_skin_name("greyfox").unwrap(), "greyfox");
+        assert_eq!(
+            sanitize_skin_name("  red/blue  fox. ").unwrap(),
+            "red_blue fox"
+        );
+        assert_eq!(sanitize_skin_name("..."), Err(NameError::Empty));
+        assert_eq!(
+            sanitize_skin_name("a very long skin name indeed"),
+            Err(NameError::TooLong(28))
+        );
+        assert_eq!(sanitize_skin_name("COM1"), Err(NameError::Reserved));
+        assert_eq!(
+            sanitize_skin_name("fox 🦊"),
+            Err(NameError::InvalidCharacter('🦊'))
+        );
+        assert!(same_skin_name("Cat", "cAT"));
+        assert_eq!(name_key("Cat"), name_key("cat"));
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::HashSet;
+
+use serenity::all::{ButtonStyle, UserId};
+use serenity::builder::{CreateButton, CreateMessage};
+use serenity::prelude::*;
+use tracing::warn;
+
+use crate::announce::{fill_template, UploadedSkin};
+use crate::config::TemplateConfig;
+
+pub const OPT_OUT_BUTTON_ID: &str = "dm_opt_out";
+
+/// Authors that don't want a DM about their uploaded skins, stored as one user id per line.
+pub struct DmOptOuts {
+    path: String,
+    users: HashSet<UserId>,
+}
+
+impl TypeMapKey for DmOptOuts {
+    type Value = Self;
+}
+
+impl DmOptOuts {
+    /// A missing file means nobody opted out yet.
+    pub fn load(path: String) -> Self {
+        let users = std::fs::read_to_string(&path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.trim().parse().ok())
+            .map(UserId::new)
+            .collect();
+        Self { path, users }
+    }
+
+    pub fn contains(&self, user_id: UserId) -> bool {
+        self.users.contains(&user_id)
+    }
+
+    /// Changes the choice of the user and stores all of them.
+    pub fn set(&mut self, user_id: UserId, opted_out: bool) -> std::io::Result<()> {
+        let changed = if opted_out {
+            self.users.insert(user_id)
+        } else {
+            self.users.remove(&user_id)
+        };
+        if !changed {
+            return Ok(());
+        }
+        let mut users: Vec<String> = self.users.iter().map(|user| user.to_string()).collect();
+        users.sort();
+        std::fs::write(&self.path, users.join("\n"))
+    }
+}
+
+/// Tells the author that their skin was added, with a button to not be notified again.
+pub async fn notify_author(ctx: &Context, skin: &UploadedSkin, templates: &TemplateConfig) {
+    let msg = CreateMessage::new()
+        .content(fill_template(
+            &templates.author_dm,
+            &[
+                ("name", &skin.name),
+                ("database", templates.database(skin.database)),
+                ("link", &skin.link),
+            ],
+        ))
+        .button(
+            CreateButton::new(OPT_OUT_BUTTON_ID)
+                .label("Don't notify me again")
+                .style(ButtonStyle::Secondary),
+        );
+    // fails if the author doesn't accept DMs, which is fine
+    if let Err(err) = skin.author_id.direct_message(ctx, msg).await {
+        warn!(
+            "Could not notify {} about {}: {err}",
+            skin.author_id, skin.name
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::{ColorType, ImageEncoder};
+
+/// every filter is tried, which one compresses best depends on the art
+const FILTERS: [FilterType; 6] = [
+    FilterType::NoFilter,
+    FilterType::Sub,
+    FilterType::Up,
+    FilterType::Avg,
+    FilterType::Paeth,
+    FilterType::Adaptive,
+];
+
+fn encode(img: &[u8], w: u32, h: u32, compression: CompressionType, filter: FilterType) -> Vec<u8> {
+    let mut png = Vec::new();
+    PngEncoder::new_with_quality(&mut png, compression, filter)
+        .write_image(img, w, h, ColorType::Rgba8)
+        .unwrap();
+    png
+}
+
+/// Encodes the rgba image as small as possible without changing a pixel: the best compression
+/// with the filter that suits the image. Like every png the bot writes, it has no ancillary
+/// chunks, the database only needs the pixels.
+pub fn optimized_png(img: &[u8], w: u32, h: u32) -> Vec<u8> {
+    FILTERS
+        .iter()
+        .map(|&filter| encode(img, w, h, CompressionType::Best, filter))
+        .min_by_key(Vec::len)
+        .unwrap()
+}
+
+/// The png `image` writes by default, fast but larger.
+pub fn default_png(img: &[u8], w: u32, h: u32) -> Vec<u8> {
+    encode(img, w, h, CompressionType::Default, FilterType::Adaptive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{default_png, optimized_png};
+
+    #[test]
+    fn optimized_png_is_smaller_and_lossless() {
+        let (w, h) = (256, 128);
+        let mut img = vec![0; w * h * 4];
+        for (i, px) in img.chunks_exact_mut(4).enumerate() {
+            let (x, y) = (i % w, i / w);
+            if (x / 16 + y / 16) % 2 == 0 {
+                px.copy_from_slice(&[(x * 2) as u8, (y * 2) as u8, 90, 255]);
+            }
+        }
+        let optimized = optimized_png(&img, w as u32, h as u32);
+        assert!(optimized.len() <= default_png(&img, w as u32, h as u32).len());
+        let decoded = image::load_from_memory(&optimized).unwrap().into_rgba8();
+        assert_eq!(decoded.into_raw(), img);
+    }
+}
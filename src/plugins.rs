@@ -0,0 +1,280 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serenity::prelude::TypeMapKey;
+
+use crate::config::PluginConfig;
+
+/// What a plugin gets to know about the skin, as json next to the rgba pixels.
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginSkinInfo {
+    pub name: String,
+    pub author: String,
+    pub license: String,
+    /// `normal` or `community`
+    pub database: String,
+}
+
+/// The answer of a plugin, `{"warnings": [...], "errors": [...]}`, both lists can be left out.
+/// Errors keep the skin file from being uploaded, warnings are shown in the summary.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PluginFindings {
+    pub warnings: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+/// The validation plugins of `[plugins]`, compiled once at startup.
+#[derive(Default)]
+pub struct ValidationPlugins {
+    #[cfg(feature = "wasm-plugins")]
+    runtime: Option<wasm::Runtime>,
+}
+
+impl ValidationPlugins {
+    /// Reads and compiles the configured plugins, a plugin that can't be loaded is an error,
+    /// the bot would otherwise upload skins it was meant to reject.
+    pub fn load(config: &PluginConfig) -> anyhow::Result<Self> {
+        if config.paths.is_empty() {
+            return Ok(Self::default());
+        }
+        #[cfg(feature = "wasm-plugins")]
+        {
+            let sources = config
+                .paths
+                .iter()
+                .map(|path| {
+                    let content = std::fs::read(path)
+                        .map_err(|err| anyhow::anyhow!("could not read plugin {path}: {err}"))?;
+                    let name = std::path::Path::new(path)
+                        .file_stem()
+                        .map_or_else(|| path.clone(), |stem| stem.to_string_lossy().into());
+                    Ok((name, content))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            Ok(Self {
+                runtime: Some(wasm::Runtime::new(config, sources)?),
+            })
+        }
+        #[cfg(not(feature = "wasm-plugins"))]
+        anyhow::bail!(
+            "plugins.paths is set, but the bot was built without the `wasm-plugins` feature"
+        )
+    }
+
+    /// Runs all plugins on one skin file. A plugin that fails, runs out of fuel or answers
+    /// nonsense is reported as warning, a broken plugin must not block every upload.
+    pub fn check(
+        &self,
+        img: &[u8],
+        width: u32,
+        height: u32,
+        info: &PluginSkinInfo,
+    ) -> PluginFindings {
+        #[cfg(feature = "wasm-plugins")]
+        if let Some(runtime) = &self.runtime {
+            return runtime.check(img, width, height, info);
+        }
+        let _ = (img, width, height, info);
+        PluginFindings::default()
+    }
+}
+
+impl TypeMapKey for ValidationPlugins {
+    type Value = Arc<Self>;
+}
+
+#[cfg(feature = "wasm-plugins")]
+mod wasm {
+    use anyhow::{bail, Context as _};
+    use tracing::warn;
+    use wasmtime::{Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+    use super::{PluginFindings, PluginSkinInfo};
+    use crate::config::PluginConfig;
+
+    /// the answer of a plugin is a few lines of text
+    const MAX_ANSWER_LEN: usize = 64 * 1024;
+
+    impl PluginFindings {
+        fn extend(&mut self, plugin: &str, findings: Self) {
+            let prefixed = |text: String| format!("`{plugin}`: {text}");
+            self.warnings
+                .extend(findings.warnings.into_iter().map(prefixed));
+            self.errors
+                .extend(findings.errors.into_iter().map(prefixed));
+        }
+    }
+
+    pub struct Runtime {
+        engine: Engine,
+        plugins: Vec<(String, Module)>,
+        fuel: u64,
+        max_memory_bytes: usize,
+    }
+
+    impl Runtime {
+        /// Compiles the `.wasm` or `.wat` sources, a plugin can't import anything from the bot.
+        pub fn new(config: &PluginConfig, sources: Vec<(String, Vec<u8>)>) -> anyhow::Result<Self> {
+            let mut engine_config = wasmtime::Config::new();
+            engine_config.consume_fuel(true);
+            let engine = Engine::new(&engine_config)?;
+            let plugins = sources
+                .into_iter()
+                .map(|(name, source)| {
+                    let module = Module::new(&engine, source)
+                        .with_context(|| format!("could not compile plugin {name}"))?;
+                    if module.imports().len() > 0 {
+                        bail!("plugin {name} imports functions, plugins can't import anything");
+                    }
+                    Ok((name, module))
+                })
+                .collect::<anyhow::Result<_>>()?;
+            Ok(Self {
+                engine,
+                plugins,
+                fuel: config.fuel,
+                max_memory_bytes: usize::try_from(config.max_memory_mib << 20)
+                    .unwrap_or(usize::MAX),
+            })
+        }
+
+        pub fn check(
+            &self,
+            img: &[u8],
+            width: u32,
+            height: u32,
+            info: &PluginSkinInfo,
+        ) -> PluginFindings {
+            let mut findings = PluginFindings::default();
+            for (name, module) in &self.plugins {
+                let plugin_findings =
+                    self.run(module, img, width, height, info)
+                        .unwrap_or_else(|err| {
+                            warn!("Plugin {name} failed on skin {}: {err:#}", info.name);
+                            PluginFindings {
+                                warnings: vec![format!("could not check the skin: {err}")],
+                                errors: Vec::new(),
+                            }
+                        });
+                findings.extend(name, plugin_findings);
+            }
+            findings
+        }
+
+        /// Every check gets a fresh instance, plugins can't keep state between skins.
+        fn run(
+            &self,
+            module: &Module,
+            img: &[u8],
+            width: u32,
+            height: u32,
+            info: &PluginSkinInfo,
+        ) -> anyhow::Result<PluginFindings> {
+            let limits = StoreLimitsBuilder::new()
+                .memory_size(self.max_memory_bytes)
+                .build();
+            let mut store: Store<StoreLimits> = Store::new(&self.engine, limits);
+            store.limiter(|limits| limits);
+            store.set_fuel(self.fuel)?;
+            let instance = Linker::new(&self.engine).instantiate(&mut store, module)?;
+            let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+            let validate = instance
+                .get_typed_func::<(i32, i32, i32, i32, i32), i64>(&mut store, "validate")?;
+
+            let info = serde_json::to_vec(info)?;
+            let img_ptr = alloc.call(&mut store, i32::try_from(img.len())?)?;
+            let info_ptr = alloc.call(&mut store, i32::try_from(info.len())?)?;
+            let memory = instance
+                .get_memory(&mut store, "memory")
+                .context("the plugin exports no memory")?;
+            memory.write(&mut store, img_ptr as u32 as usize, img)?;
+            memory.write(&mut store, info_ptr as u32 as usize, &info)?;
+
+            let answer = validate.call(
+                &mut store,
+                (
+                    img_ptr,
+                    i32::try_from(width)?,
+                    i32::try_from(height)?,
+                    info_ptr,
+                    i32::try_from(info.len())?,
+                ),
+            )?;
+            // the pointer in the upper, the length in the lower 32 bits
+            let answer_ptr = (answer as u64 >> 32) as usize;
+            let answer_len = answer as u32 as usize;
+            if answer_len > MAX_ANSWER_LEN {
+                bail!("the answer is longer than {MAX_ANSWER_LEN} bytes");
+            }
+            let mut answer = vec![0; answer_len];
+            memory.read(&store, answer_ptr, &mut answer)?;
+            serde_json::from_slice(&answer).context("the answer is no valid json")
+        }
+    }
+}
+
+#[cfg(all(test, feature = "wasm-plugins"))]
+mod tests {
+    use super::{wasm::Runtime, PluginFindings, PluginSkinInfo, ValidationPlugins};
+    use crate::config::PluginConfig;
+
+    /// rejects skins whose first pixel is not transparent
+    const CORNER_PLUGIN: &str = r#"(module
+        (memory (export "memory") 4)
+        (global $next (mut i32) (i32.const 1024))
+        (func (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+        (data (i32.const 0) "{\"errors\": [\"the corner is not transparent\"]}")
+        (data (i32.const 512) "{}")
+        (func (export "validate")
+            (param $img i32) (param $width i32) (param $height i32)
+            (param $info i32) (param $info_len i32) (result i64)
+            (if (result i64) (i32.load8_u offset=3 (local.get $img))
+                (then (i64.const 45))
+                (else (i64.or (i64.shl (i64.const 512) (i64.const 32)) (i64.const 2))))))"#;
+
+    const ENDLESS_PLUGIN: &str = r#"(module
+        (memory (export "memory") 4)
+        (func (export "alloc") (param $len i32) (result i32) (i32.const 0))
+        (func (export "validate")
+            (param i32) (param i32) (param i32) (param i32) (param i32) (result i64)
+            (loop $forever (br $forever))
+            (i64.const 0)))"#;
+
+    #[test]
+    fn runs_the_plugins() {
+        let config = PluginConfig::default();
+        let plugins = ValidationPlugins {
+            runtime: Some(
+                Runtime::new(
+                    &config,
+                    vec![
+                        ("corner".to_string(), CORNER_PLUGIN.into()),
+                        ("endless".to_string(), ENDLESS_PLUGIN.into()),
+                    ],
+                )
+                .unwrap(),
+            ),
+        };
+        let info = PluginSkinInfo {
+            name: "greyfox".to_string(),
+            author: "nameless tee".to_string(),
+            license: "CC0".to_string(),
+            database: "normal".to_string(),
+        };
+        let mut img = vec![0; 256 * 128 * 4];
+        let findings = plugins.check(&img, 256, 128, &info);
+        assert!(findings.errors.is_empty());
+        // the endless loop runs out of fuel
+        assert_eq!(findings.warnings.len(), 1);
+        assert!(findings.warnings[0].starts_with("`endless`: "));
+
+        img[3] = 255;
+        let PluginFindings { errors, .. } = plugins.check(&img, 256, 128, &info);
+        assert_eq!(errors, ["`corner`: the corner is not transparent"]);
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+use serenity::all::{ButtonStyle, Timestamp, UserId};
+use serenity::builder::{CreateActionRow, CreateButton};
+use serenity::prelude::*;
+
+use crate::config::Config;
+use crate::license::LicenseRules;
+use crate::metadata::parse_skin_info;
+use crate::votes::{meets_vote_threshold, positive_ratio};
+
+/// What the moderator decides in a step of the practice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PracticeAnswer {
+    Normal,
+    Community,
+    AskForFix,
+    Finish,
+    Cancel,
+}
+
+impl PracticeAnswer {
+    pub const ALL: [Self; 5] = [
+        Self::Normal,
+        Self::Community,
+        Self::AskForFix,
+        Self::Finish,
+        Self::Cancel,
+    ];
+
+    pub const fn button_id(&self) -> &'static str {
+        match self {
+            Self::Normal => "practice_normal",
+            Self::Community => "practice_community",
+            Self::AskForFix => "practice_fix",
+            Self::Finish => "practice_finish",
+            Self::Cancel => "practice_cancel",
+        }
+    }
+
+    pub fn from_button_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.button_id() == id)
+    }
+
+    fn button(&self, config: &Config) -> CreateButton {
+        let label = match self {
+            Self::Normal => format!("React {}", config.emojis.queue_normal[0]),
+            Self::Community => format!("React {}", config.emojis.queue_community[0]),
+            Self::AskForFix => "Ask the author to fix it".to_string(),
+            Self::Finish => "Finish the upload".to_string(),
+            Self::Cancel => "Cancel the upload".to_string(),
+        };
+        CreateButton::new(self.button_id())
+            .label(label)
+            .style(ButtonStyle::Secondary)
+    }
+}
+
+/// A canned submission, judged by the same rules as a real one.
+struct PracticeSubmission {
+    content: &'static str,
+    positive_votes: u64,
+    negative_votes: u64,
+}
+
+const SUBMISSIONS: [PracticeSubmission; 4] = [
+    PracticeSubmission {
+        content: "\"greyfox\" by nameless tee (CC0)",
+        positive_votes: 14,
+        negative_votes: 1,
+    },
+    PracticeSubmission {
+        content: "\"kitty\" by artist (all rights reserved)",
+        positive_votes: 12,
+        negative_votes: 0,
+    },
+    PracticeSubmission {
+        content: "my new skin, what do you think?",
+        positive_votes: 9,
+        negative_votes: 2,
+    },
+    PracticeSubmission {
+        content: "\"bluebird\" by someone (CC-BY-SA)",
+        positive_votes: 3,
+        negative_votes: 2,
+    },
+];
+
+impl PracticeSubmission {
+    /// The answer and why, from the configured license and vote rules.
+    fn expected(&self, config: &Config) -> (PracticeAnswer, String) {
+        let (_, _, license) = match parse_skin_info(self.content) {
+            Ok(info) => info,
+            Err(_) => {
+                return (
+                    PracticeAnswer::AskForFix,
+                    "The message lacks the name, author or license, the bot can't read it. \
+                    The author has to edit it to `\"name\" by author (license)`."
+                        .to_string(),
+                )
+            }
+        };
+        let license_rules = LicenseRules::new(&config.license);
+        if let Err(license) = license_rules.at(Timestamp::now()).validate(&license) {
+            return (
+                PracticeAnswer::AskForFix,
+                format!(
+                    "The license `{license}` is not allowed, the author has to pick another one."
+                ),
+            );
+        }
+        let ratio = positive_ratio(self.positive_votes, self.negative_votes) * 100.0;
+        let votes = format!(
+            "{} positive and {} negative votes ({ratio:.0}%)",
+            self.positive_votes, self.negative_votes
+        );
+        if meets_vote_threshold(&config.votes, self.positive_votes, self.negative_votes) {
+            (
+                PracticeAnswer::Normal,
+                format!("{votes} reach the threshold, it goes to the normal database."),
+            )
+        } else {
+            (
+                PracticeAnswer::Community,
+                format!(
+                    "{votes} are below the threshold of {} votes with {:.0}% positive, it goes to the community database.",
+                    config.votes.min_votes,
+                    config.votes.min_positive_ratio * 100.0
+                ),
+            )
+        }
+    }
+}
+
+/// A practice upload of a moderator, nothing is sent to the database.
+#[derive(Debug, Default)]
+pub struct Practice {
+    /// the submissions come first, the last step is finishing the upload
+    step: usize,
+    right_answers: usize,
+}
+
+impl Practice {
+    const STEPS: usize = SUBMISSIONS.len() + 1;
+
+    /// The task of the current step.
+    pub fn task(&self, config: &Config) -> (String, Vec<CreateActionRow>) {
+        let progress = format!(
+            "**Practice upload, step {}/{}**\n",
+            self.step + 1,
+            Self::STEPS
+        );
+        match SUBMISSIONS.get(self.step) {
+            Some(submission) => {
+                let content = format!(
+                    "{progress}A submission was posted:\n> {}\n> votes: {} positive, {} negative\n\
+                    What do you do with it?",
+                    submission.content, submission.positive_votes, submission.negative_votes
+                );
+                let buttons = [
+                    PracticeAnswer::Normal,
+                    PracticeAnswer::Community,
+                    PracticeAnswer::AskForFix,
+                ]
+                .iter()
+                .map(|answer| answer.button(config))
+                .collect();
+                (content, vec![CreateActionRow::Buttons(buttons)])
+            }
+            None => {
+                let content = format!(
+                    "{progress}All submissions are checked and the summary shows no errors. \
+                    How do you continue?"
+                );
+                let buttons = [PracticeAnswer::Finish, PracticeAnswer::Cancel]
+                    .iter()
+                    .map(|answer| answer.button(config))
+                    .collect();
+                (content, vec![CreateActionRow::Buttons(buttons)])
+            }
+        }
+    }
+
+    /// Checks the answer of the current step and moves on,
+    /// returns the feedback and whether the practice is over.
+    pub fn answer(&mut self, answer: PracticeAnswer, config: &Config) -> (String, bool) {
+        let (expected, reason) = match SUBMISSIONS.get(self.step) {
+            Some(submission) => submission.expected(config),
+            None => (
+                PracticeAnswer::Finish,
+                "`/upload_finish` or the 🆗 button uploads the skins and announces them."
+                    .to_string(),
+            ),
+        };
+        let mut feedback = if answer == expected {
+            self.right_answers += 1;
+            format!(":white_check_mark: Right! {reason}")
+        } else {
+            format!(":x: Not quite. {reason}")
+        };
+        self.step += 1;
+        let finished = self.step == Self::STEPS;
+        if finished {
+            feedback += &format!(
+                "\n\nYou got {} of {} steps right. Nothing was uploaded, \
+                start a real upload with `/upload` once you are ready.",
+                self.right_answers,
+                Self::STEPS
+            );
+        }
+        (feedback, finished)
+    }
+}
+
+pub struct PracticeSessions;
+
+impl TypeMapKey for PracticeSessions {
+    type Value = HashMap<UserId, Practice>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Practice, PracticeAnswer};
+    use crate::config::Config;
+
+    #[test]
+    fn follows_the_configured_rules() {
+        let config = Config::default();
+        let mut practice = Practice::default();
+        let answers = [
+            PracticeAnswer::Normal,
+            PracticeAnswer::AskForFix,
+            PracticeAnswer::AskForFix,
+            PracticeAnswer::Normal,
+            PracticeAnswer::Finish,
+        ];
+        let mut finished = false;
+        for answer in answers {
+            assert!(!finished);
+            (_, finished) = practice.answer(answer, &config);
+        }
+        assert!(finished);
+        // the last skin has too few votes for the normal database
+        assert_eq!(practice.right_answers, 4);
+    }
+}
@@ -0,0 +1,312 @@
+use std::fmt;
+use std::io::Cursor;
+
+use image::{ColorType, ImageFormat};
+
+const BPP: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SdResampler {
+    Lanczos,
+    Box,
+    Mitchell,
+}
+
+impl SdResampler {
+    pub const ALL: [Self; 3] = [Self::Lanczos, Self::Box, Self::Mitchell];
+
+    pub const fn button_id(&self) -> &'static str {
+        match self {
+            Self::Lanczos => "sd_lanczos",
+            Self::Box => "sd_box",
+            Self::Mitchell => "sd_mitchell",
+        }
+    }
+
+    pub fn from_button_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|r| r.button_id() == id)
+    }
+
+    const fn support(&self) -> f32 {
+        match self {
+            Self::Lanczos => 3.0,
+            Self::Box => 0.5,
+            Self::Mitchell => 2.0,
+        }
+    }
+
+    fn weight(&self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            Self::Lanczos => {
+                if x < f32::EPSILON {
+                    1.0
+                } else if x < 3.0 {
+                    let px = std::f32::consts::PI * x;
+                    3.0 * px.sin() * (px / 3.0).sin() / (px * px)
+                } else {
+                    0.0
+                }
+            }
+            Self::Box => {
+                if x <= 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Self::Mitchell => {
+                // Mitchell-Netravali with B = C = 1/3
+                const B: f32 = 1.0 / 3.0;
+                const C: f32 = 1.0 / 3.0;
+                if x < 1.0 {
+                    ((12.0 - 9.0 * B - 6.0 * C) * x * x * x
+                        + (-18.0 + 12.0 * B + 6.0 * C) * x * x
+                        + (6.0 - 2.0 * B))
+                        / 6.0
+                } else if x < 2.0 {
+                    ((-B - 6.0 * C) * x * x * x
+                        + (6.0 * B + 30.0 * C) * x * x
+                        + (-12.0 * B - 48.0 * C) * x
+                        + (8.0 * B + 24.0 * C))
+                        / 6.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for SdResampler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lanczos => write!(f, "lanczos"),
+            Self::Box => write!(f, "box"),
+            Self::Mitchell => write!(f, "mitchell"),
+        }
+    }
+}
+
+/// Shrinks one axis of a premultiplied float image by an integer factor.
+fn downscale_axis(
+    src: &[f32],
+    w: usize,
+    h: usize,
+    horizontal: bool,
+    factor: usize,
+    resampler: SdResampler,
+) -> Vec<f32> {
+    let (dw, dh) = if horizontal {
+        (w / factor, h)
+    } else {
+        (w, h / factor)
+    };
+    let src_len = if horizontal { w } else { h } as i64;
+    let scale = factor as f32;
+    // the kernel is stretched by the scale factor
+    let radius = (resampler.support() * scale).ceil() as i64;
+
+    let mut dst = vec![0.0; dw * dh * BPP];
+    for dy in 0..dh {
+        for dx in 0..dw {
+            let d = if horizontal { dx } else { dy };
+            let center = (d * factor) as f32 + scale / 2.0;
+            let first = center.floor() as i64 - radius;
+            let mut sum = [0.0f32; BPP];
+            let mut weight_sum = 0.0;
+            for s in first..first + 2 * radius {
+                let weight = resampler.weight((s as f32 + 0.5 - center) / scale);
+                if weight == 0.0 {
+                    continue;
+                }
+                let s = s.clamp(0, src_len - 1) as usize;
+                let (sx, sy) = if horizontal { (s, dy) } else { (dx, s) };
+                let k = (sy * w + sx) * BPP;
+                for (c, sum) in sum.iter_mut().enumerate() {
+                    *sum += src[k + c] * weight;
+                }
+                weight_sum += weight;
+            }
+            let k = (dy * dw + dx) * BPP;
+            for c in 0..BPP {
+                dst[k + c] = sum[c] / weight_sum;
+            }
+        }
+    }
+    dst
+}
+
+/// Creates a 256x128 skin out of a 512x256 one.
+pub fn downscale_hd(src_buff: &[u8], resampler: SdResampler) -> Vec<u8> {
+    downscale(src_buff, 512, 256, 2, resampler)
+}
+
+/// Shrinks an rgba image by an integer factor.
+pub fn downscale(
+    src_buff: &[u8],
+    w: usize,
+    h: usize,
+    factor: usize,
+    resampler: SdResampler,
+) -> Vec<u8> {
+    // premultiply, so fully transparent colors don't bleed into visible pixels
+    let premultiplied: Vec<f32> = src_buff
+        .chunks_exact(BPP)
+        .flat_map(|px| {
+            let a = px[3] as f32 / 255.0;
+            [
+                px[0] as f32 * a,
+                px[1] as f32 * a,
+                px[2] as f32 * a,
+                px[3] as f32,
+            ]
+        })
+        .collect();
+
+    let narrow = downscale_axis(&premultiplied, w, h, true, factor, resampler);
+    let small = downscale_axis(&narrow, w / factor, h, false, factor, resampler);
+
+    small
+        .chunks_exact(BPP)
+        .flat_map(|px| {
+            let a = px[3].clamp(0.0, 255.0);
+            if a < 0.5 {
+                return [0, 0, 0, 0];
+            }
+            let unmultiply = |c: f32| (c * 255.0 / a).round().clamp(0.0, 255.0) as u8;
+            [
+                unmultiply(px[0]),
+                unmultiply(px[1]),
+                unmultiply(px[2]),
+                a.round() as u8,
+            ]
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SdQuality {
+    /// How much of the hd edge contrast survived, 1.0 means all of it
+    pub edge_preservation: f64,
+    /// Share of smooth hd gradients that collapsed into flat bands
+    pub banding: f64,
+}
+
+impl SdQuality {
+    pub fn is_poor(&self) -> bool {
+        self.edge_preservation < 0.75 || self.banding > 0.25
+    }
+}
+
+fn luminance(buff: &[u8], w: usize, x: usize, y: usize) -> Option<i32> {
+    let k = (y * w + x) * BPP;
+    if buff[k + 3] == 0 {
+        return None;
+    }
+    Some((buff[k] as i32 * 299 + buff[k + 1] as i32 * 587 + buff[k + 2] as i32 * 114) / 1000)
+}
+
+/// Compares a derived 256x128 skin with its 512x256 source.
+pub fn check_sd_quality(hd_buff: &[u8], sd_buff: &[u8]) -> SdQuality {
+    let (w, h) = (256, 128);
+    let mut hd_edges = 0u64;
+    let mut sd_edges = 0u64;
+    let mut smooth_gradients = 0u64;
+    let mut banded_gradients = 0u64;
+
+    for y in 0..h {
+        for x in 0..w {
+            for (nx, ny) in [(x + 1, y), (x, y + 1)] {
+                if nx >= w || ny >= h {
+                    continue;
+                }
+                // the hd pixels at twice the distance correspond to the sd neighbours
+                let (Some(hd_a), Some(hd_b), Some(sd_a), Some(sd_b)) = (
+                    luminance(hd_buff, w * 2, x * 2, y * 2),
+                    luminance(hd_buff, w * 2, nx * 2, ny * 2),
+                    luminance(sd_buff, w, x, y),
+                    luminance(sd_buff, w, nx, ny),
+                ) else {
+                    continue;
+                };
+                let hd_diff = (hd_a - hd_b).unsigned_abs() as u64;
+                let sd_diff = (sd_a - sd_b).unsigned_abs() as u64;
+                hd_edges += hd_diff;
+                sd_edges += sd_diff;
+                if (1..=8).contains(&hd_diff) {
+                    smooth_gradients += 1;
+                    if sd_diff == 0 {
+                        banded_gradients += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    SdQuality {
+        edge_preservation: if hd_edges > 0 {
+            (sd_edges as f64 / hd_edges as f64).min(1.0)
+        } else {
+            1.0
+        },
+        banding: if smooth_gradients > 0 {
+            banded_gradients as f64 / smooth_gradients as f64
+        } else {
+            0.0
+        },
+    }
+}
+
+/// The skin size an image can be shrunk to, if it is an exact multiple of one,
+/// together with the factor. 512x256 is preferred, to keep the details.
+pub fn auto_resize_target(w: u32, h: u32) -> Option<(u32, u32, u32)> {
+    [(512, 256), (256, 128)]
+        .into_iter()
+        .find_map(|(skin_w, skin_h)| {
+            let factor = w / skin_w;
+            (factor >= 2 && w == skin_w * factor && h == skin_h * factor)
+                .then_some((skin_w, skin_h, factor))
+        })
+}
+
+/// Encodes an rgba image as PNG, to show it before uploading.
+pub fn render_png(buff: &[u8], w: u32, h: u32) -> anyhow::Result<Vec<u8>> {
+    let mut png = Vec::new();
+    image::write_buffer_with_format(
+        &mut Cursor::new(&mut png),
+        buff,
+        w,
+        h,
+        ColorType::Rgba8,
+        ImageFormat::Png,
+    )?;
+    Ok(png)
+}
+
+/// Renders the sd result of every resampler next to each other as PNG.
+pub fn render_resampler_preview(hd_buff: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let (w, h) = (256, 128);
+    let variants: Vec<Vec<u8>> = SdResampler::ALL
+        .iter()
+        .map(|&r| downscale_hd(hd_buff, r))
+        .collect();
+
+    let mut preview = Vec::with_capacity(w * variants.len() * h * BPP);
+    for y in 0..h {
+        for variant in &variants {
+            preview.extend_from_slice(&variant[y * w * BPP..(y + 1) * w * BPP]);
+        }
+    }
+
+    let mut png = Vec::new();
+    image::write_buffer_with_format(
+        &mut Cursor::new(&mut png),
+        &preview,
+        (w * variants.len()) as u32,
+        h as u32,
+        ColorType::Rgba8,
+        ImageFormat::Png,
+    )?;
+    Ok(png)
+}
@@ -0,0 +1,315 @@
+use std::collections::{HashMap, HashSet};
+
+use serenity::all::{ChannelId, Message, MessageId, ReactionType, UserId};
+use serenity::http::Http;
+use serenity::prelude::*;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::forum::is_upload_channel;
+use crate::scan::{fetch_submissions, message_id_at};
+use crate::session::SessionEvent;
+use crate::{SkinToUploadDB, SkinUploads};
+
+/// At most this many pages of reaction users are requested per resync, the messages after
+/// that are left as they are.
+const MAX_REACTION_REQUESTS: usize = 200;
+
+/// A queue reaction of the uploader, as found on the message.
+struct FoundReaction {
+    channel_id: ChannelId,
+    database: SkinToUploadDB,
+    emojis: HashSet<String>,
+}
+
+/// Whether the user is among those that reacted with the emoji, `None` once `requests` reached
+/// [`MAX_REACTION_REQUESTS`].
+async fn reacted_by(
+    http: &Http,
+    msg: &Message,
+    emoji: &ReactionType,
+    user_id: UserId,
+    requests: &mut usize,
+) -> serenity::Result<Option<bool>> {
+    let mut after = None;
+    loop {
+        if *requests >= MAX_REACTION_REQUESTS {
+            return Ok(None);
+        }
+        *requests += 1;
+        let users = msg
+            .channel_id
+            .reaction_users(http, msg.id, emoji.clone(), Some(100), after)
+            .await?;
+        if users.iter().any(|user| user.id == user_id) {
+            return Ok(Some(true));
+        }
+        match users.last() {
+            Some(last) if users.len() == 100 => after = Some(last.id),
+            _ => return Ok(Some(false)),
+        }
+    }
+}
+
+/// The queue reactions of the user on the submissions, and the submissions whose reactions
+/// were all read.
+async fn find_reactions(
+    http: &Http,
+    config: &Config,
+    submissions: &[Message],
+    user_id: UserId,
+) -> (HashMap<MessageId, FoundReaction>, HashSet<MessageId>) {
+    let mut found: HashMap<MessageId, FoundReaction> = HashMap::new();
+    let mut checked = HashSet::new();
+    let mut requests = 0;
+    for msg in submissions {
+        let mut complete = true;
+        for reaction in &msg.reactions {
+            let Some(database) = config.emojis.queue_database_of(&reaction.reaction_type) else {
+                continue;
+            };
+            match reacted_by(http, msg, &reaction.reaction_type, user_id, &mut requests).await {
+                Ok(Some(true)) => {
+                    found
+                        .entry(msg.id)
+                        .or_insert_with(|| FoundReaction {
+                            channel_id: msg.channel_id,
+                            database,
+                            emojis: HashSet::new(),
+                        })
+                        .emojis
+                        .insert(reaction.reaction_type.as_data());
+                }
+                Ok(Some(false)) => {}
+                Ok(None) => {
+                    complete = false;
+                    break;
+                }
+                Err(err) => {
+                    warn!("Could not fetch the reactions of {}: {err}", msg.id);
+                    complete = false;
+                }
+            }
+        }
+        if complete {
+            checked.insert(msg.id);
+        } else {
+            // only some of the emojis are known, the message is left as it is
+            found.remove(&msg.id);
+        }
+        if requests >= MAX_REACTION_REQUESTS {
+            warn!(
+                "Stopped resyncing the reactions of {user_id} after {MAX_REACTION_REQUESTS} requests"
+            );
+            break;
+        }
+    }
+    (found, checked)
+}
+
+/// The messages whose queue reactions were added or removed since `before` was taken.
+fn changed_reactions(
+    before: &HashSet<(MessageId, String, bool)>,
+    now: &HashSet<(MessageId, String, bool)>,
+) -> HashSet<MessageId> {
+    before
+        .symmetric_difference(now)
+        .map(|(msg_id, _, _)| *msg_id)
+        .collect()
+}
+
+/// The scanned messages that have to be queued because the emojis on them differ from the
+/// known reactions, and the ones that have to be unqueued because the reactions are gone.
+/// Discord only lists the users of normal reactions, messages with a super reaction of the user
+/// are never unqueued.
+fn reaction_changes(
+    known: &HashSet<(MessageId, String, bool)>,
+    scanned: &HashSet<MessageId>,
+    found: &HashMap<MessageId, HashSet<String>>,
+) -> (Vec<MessageId>, Vec<MessageId>) {
+    let mut known_emojis: HashMap<MessageId, HashSet<String>> = HashMap::new();
+    let mut super_reacted = HashSet::new();
+    for (msg_id, emoji, burst) in known {
+        known_emojis
+            .entry(*msg_id)
+            .or_default()
+            .insert(emoji.clone());
+        if *burst {
+            super_reacted.insert(*msg_id);
+        }
+    }
+    let mut queued: Vec<_> = found
+        .iter()
+        .filter(|(msg_id, emojis)| known_emojis.get(msg_id) != Some(emojis))
+        .map(|(msg_id, _)| *msg_id)
+        .collect();
+    let mut unqueued: Vec<_> = known_emojis
+        .keys()
+        .filter(|msg_id| {
+            scanned.contains(msg_id)
+                && !found.contains_key(msg_id)
+                && !super_reacted.contains(msg_id)
+        })
+        .copied()
+        .collect();
+    queued.sort();
+    unqueued.sort();
+    (queued, unqueued)
+}
+
+/// Reads the queue reactions of the uploader in the channel of the upload and the allowed
+/// channels again and queues or unqueues the skins they differ on, e.g. after reactions were
+/// missed while the gateway was disconnected. Skins queued without a reaction, like with
+/// `Apps > Upload skin`, are kept. Returns how many skins were queued and unqueued, `None` if
+/// the session doesn't collect skins anymore.
+pub async fn resync_reactions(ctx: &Context, user_id: UserId) -> Option<(usize, usize)> {
+    let config = ctx.data.read().await.get::<Config>().unwrap().clone();
+    let (channel_id, started_at, known_before) = {
+        let data = ctx.data.read().await;
+        let item = data.get::<SkinUploads>().unwrap().uploads.get(&user_id)?;
+        (
+            item.channel_id,
+            item.started_at,
+            item.queue_reactions.clone(),
+        )
+    };
+    let mut channels: Vec<ChannelId> = config
+        .upload
+        .allowed_channels
+        .iter()
+        .copied()
+        .map(ChannelId::new)
+        .collect();
+    if !channels.contains(&channel_id) && is_upload_channel(ctx, &config, channel_id).await {
+        channels.push(channel_id);
+    }
+    let since = message_id_at(started_at - config.upload.resync_days as i64 * 24 * 60 * 60);
+    let mut submissions = Vec::new();
+    for channel_id in channels {
+        submissions.extend(fetch_submissions(&ctx.http, channel_id, since, usize::MAX).await);
+    }
+    let (found, scanned) = find_reactions(&ctx.http, &config, &submissions, user_id).await;
+
+    let mut data = ctx.data.write().await;
+    let item = data
+        .get_mut::<SkinUploads>()
+        .unwrap()
+        .uploads
+        .get_mut(&user_id)?;
+    if item.session.queue().is_err() {
+        return None;
+    }
+    let found_emojis = found
+        .iter()
+        .map(|(msg_id, reaction)| (*msg_id, reaction.emojis.clone()))
+        .collect();
+    let (mut queued, mut unqueued) =
+        reaction_changes(&item.queue_reactions, &scanned, &found_emojis);
+    // reaction events that arrived during the fetch are newer than what was read
+    let changed = changed_reactions(&known_before, &item.queue_reactions);
+    queued.retain(|msg_id| !changed.contains(msg_id));
+    unqueued.retain(|msg_id| !changed.contains(msg_id));
+    for msg_id in &queued {
+        let reaction = &found[msg_id];
+        item.queue_reactions.retain(|(known, _, _)| known != msg_id);
+        item.queue_reactions.extend(
+            reaction
+                .emojis
+                .iter()
+                .map(|emoji| (*msg_id, emoji.clone(), false)),
+        );
+        item.send(SessionEvent::SkinQueued {
+            msg_id: *msg_id,
+            channel_id: reaction.channel_id,
+            database: item.default_database.unwrap_or(reaction.database),
+        });
+    }
+    for msg_id in &unqueued {
+        item.queue_reactions.retain(|(known, _, _)| known != msg_id);
+        item.send(SessionEvent::SkinUnqueued { msg_id: *msg_id });
+    }
+    if !queued.is_empty() || !unqueued.is_empty() {
+        info!(
+            "Resynced the reactions of {user_id}: {} queued, {} unqueued",
+            queued.len(),
+            unqueued.len()
+        );
+    }
+    Some((queued.len(), unqueued.len()))
+}
+
+/// After the gateway resumed, the reactions of every upload are read again.
+pub async fn resync_all_sessions(ctx: &Context) {
+    let users: Vec<UserId> = ctx
+        .data
+        .read()
+        .await
+        .get::<SkinUploads>()
+        .unwrap()
+        .uploads
+        .keys()
+        .copied()
+        .collect();
+    for user_id in users {
+        resync_reactions(ctx, user_id).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use serenity::all::MessageId;
+
+    use super::{changed_reactions, reaction_changes};
+
+    #[test]
+    fn reactions_are_the_source_of_truth() {
+        let msg = MessageId::new;
+        let known = HashSet::from([
+            (msg(1), "✅".to_string(), false),
+            (msg(2), "✅".to_string(), true),
+            (msg(3), "☑️".to_string(), false),
+            // outside of the scanned messages, it can't be checked
+            (msg(9), "✅".to_string(), false),
+        ]);
+        let scanned = HashSet::from([msg(1), msg(2), msg(3), msg(4), msg(5)]);
+        let emojis = |emoji: &str| HashSet::from([emoji.to_string()]);
+        let found = HashMap::from([
+            (msg(1), emojis("✅")),
+            (msg(2), emojis("✅")),
+            // switched to the other database while disconnected
+            (msg(3), emojis("✅")),
+            // reacted while disconnected
+            (msg(4), emojis("☑️")),
+        ]);
+
+        let (queued, unqueued) = reaction_changes(&known, &scanned, &found);
+        assert_eq!(queued, [msg(3), msg(4)]);
+        assert!(unqueued.is_empty());
+
+        let found = HashMap::from([(msg(1), emojis("✅"))]);
+        let (queued, unqueued) = reaction_changes(&known, &scanned, &found);
+        assert!(queued.is_empty());
+        // the super reaction of msg 2 can't be checked
+        assert_eq!(unqueued, [msg(3)]);
+    }
+
+    #[test]
+    fn finds_reactions_changed_during_the_fetch() {
+        let msg = MessageId::new;
+        let before = HashSet::from([
+            (msg(1), "✅".to_string(), false),
+            (msg(2), "✅".to_string(), false),
+        ]);
+        let now = HashSet::from([
+            (msg(1), "✅".to_string(), false),
+            // removed and reacted again with the other emoji
+            (msg(2), "☑️".to_string(), false),
+            (msg(3), "✅".to_string(), false),
+        ]);
+        let mut changed: Vec<_> = changed_reactions(&before, &now).into_iter().collect();
+        changed.sort();
+        assert_eq!(changed, [msg(2), msg(3)]);
+    }
+}
@@ -0,0 +1,262 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use crate::watermark::is_uv;
+
+const BPP: usize = 4;
+const ALPHA_THRESHOLD: u8 = 128;
+/// the cells of the perceptual hash, every row compares its neighbouring cells
+const HASH_COLUMNS: usize = 17;
+const HASH_ROWS: usize = 8;
+/// skins with less of the tee drawn are blank, not only low effort
+const BLANK_DRAWN_PERCENT: u32 = 1;
+
+/// A perceptual hash of a skin sheet: whether the luminance falls from one cell of a coarse
+/// grid to the next. Recolors of a skin keep most of its shading, so they keep the hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkinHash(u128);
+
+impl SkinHash {
+    pub fn of(rgba: &[u8], w: usize, h: usize) -> Self {
+        let mut cells = [[0u64; HASH_COLUMNS]; HASH_ROWS];
+        for y in 0..h {
+            for x in 0..w {
+                let px = &rgba[(y * w + x) * BPP..(y * w + x + 1) * BPP];
+                // transparent pixels count as black, the outline of the parts matters too
+                let lum = (299 * px[0] as u64 + 587 * px[1] as u64 + 114 * px[2] as u64) / 1000;
+                cells[y * HASH_ROWS / h][x * HASH_COLUMNS / w] += lum * px[3] as u64 / 255;
+            }
+        }
+        let mut hash = 0u128;
+        for row in cells {
+            for pair in row.windows(2) {
+                hash = hash << 1 | u128::from(pair[0] > pair[1]);
+            }
+        }
+        Self(hash)
+    }
+
+    /// The number of differing bits, out of 128.
+    pub const fn distance(self, other: Self) -> u32 {
+        (self.0 ^ other.0).count_ones()
+    }
+}
+
+/// The skins submissions must not just recolor, usually the default skins of the client.
+#[derive(Default)]
+pub struct DefaultSkins {
+    skins: Vec<(String, SkinHash)>,
+}
+
+impl DefaultSkins {
+    /// Reads the pngs of the directory, a png that is not a skin is an error.
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let mut skins = Vec::new();
+        for entry in std::fs::read_dir(dir)
+            .map_err(|err| anyhow::anyhow!("could not read {}: {err}", dir.display()))?
+        {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("png") {
+                continue;
+            }
+            let img = image::open(&path)
+                .map_err(|err| anyhow::anyhow!("could not read {}: {err}", path.display()))?
+                .to_rgba8();
+            let (w, h) = img.dimensions();
+            if !matches!((w, h), (256, 128) | (512, 256)) {
+                anyhow::bail!("{} is not a 256x128 or 512x256 skin", path.display());
+            }
+            let name = path
+                .file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+            skins.push((name, SkinHash::of(&img, w as usize, h as usize)));
+        }
+        skins.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(Self { skins })
+    }
+
+    pub fn insert(&mut self, name: String, hash: SkinHash) {
+        self.skins.push((name, hash));
+    }
+
+    pub fn len(&self) -> usize {
+        self.skins.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.skins.is_empty()
+    }
+
+    /// The default skin that is most like the hash, with their distance.
+    fn closest(&self, hash: SkinHash) -> Option<(&str, u32)> {
+        self.skins
+            .iter()
+            .map(|(name, default)| (name.as_str(), default.distance(hash)))
+            .min_by_key(|(_, distance)| *distance)
+    }
+}
+
+/// When a skin is suspicious.
+#[derive(Debug, Clone, Copy)]
+pub struct ContentLimits {
+    /// out of 128 bits of the perceptual hash
+    pub max_default_distance: u32,
+    /// of the parts the client draws
+    pub min_drawn_percent: u32,
+    /// distinct colors of the drawn pixels
+    pub min_colors: usize,
+}
+
+/// Why a submission looks like it was not worth uploading.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentFlag {
+    /// nothing or a single color is drawn
+    Blank,
+    DefaultRecolor {
+        default: String,
+        distance: u32,
+    },
+    LowEffort {
+        drawn_percent: u32,
+        colors: usize,
+    },
+}
+
+impl Display for ContentFlag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Blank => write!(f, "the skin is blank"),
+            Self::DefaultRecolor { default, distance } => write!(
+                f,
+                "looks like a recolor of the default skin `{default}` ({distance} of 128 bits differ)"
+            ),
+            Self::LowEffort {
+                drawn_percent,
+                colors,
+            } => write!(
+                f,
+                "low effort: {drawn_percent}% of the tee drawn with {colors} colors"
+            ),
+        }
+    }
+}
+
+/// The share of the parts of the tee that are drawn and how many colors they have.
+fn drawn_area(rgba: &[u8], w: usize, h: usize) -> (u32, usize) {
+    let scale = (w / 256).max(1);
+    let (mut uv, mut drawn) = (0usize, 0usize);
+    let mut colors = HashSet::new();
+    for y in 0..h {
+        for x in 0..w {
+            if !is_uv(x, y, scale) {
+                continue;
+            }
+            uv += 1;
+            let px = &rgba[(y * w + x) * BPP..(y * w + x + 1) * BPP];
+            if px[3] >= ALPHA_THRESHOLD {
+                drawn += 1;
+                colors.insert([px[0], px[1], px[2]]);
+            }
+        }
+    }
+    ((drawn * 100 / uv.max(1)) as u32, colors.len())
+}
+
+/// Blank sheets, recolors of a default skin and skins with barely anything drawn.
+pub fn check_content(
+    rgba: &[u8],
+    w: usize,
+    h: usize,
+    defaults: &DefaultSkins,
+    limits: &ContentLimits,
+) -> Vec<ContentFlag> {
+    let (drawn_percent, colors) = drawn_area(rgba, w, h);
+    if drawn_percent < BLANK_DRAWN_PERCENT || colors <= 1 {
+        return vec![ContentFlag::Blank];
+    }
+    let mut flags = Vec::new();
+    if let Some((default, distance)) = defaults.closest(SkinHash::of(rgba, w, h)) {
+        if distance <= limits.max_default_distance {
+            flags.push(ContentFlag::DefaultRecolor {
+                default: default.to_string(),
+                distance,
+            });
+        }
+    }
+    if drawn_percent < limits.min_drawn_percent || colors < limits.min_colors {
+        flags.push(ContentFlag::LowEffort {
+            drawn_percent,
+            colors,
+        });
+    }
+    flags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_content, ContentFlag, ContentLimits, DefaultSkins, SkinHash, BPP};
+
+    const LIMITS: ContentLimits = ContentLimits {
+        max_default_distance: 12,
+        min_drawn_percent: 25,
+        min_colors: 8,
+    };
+
+    /// a 256x128 sheet with shaded circles in the body and feet cells, in the given hue
+    fn shaded_skin(hue: [f32; 3]) -> Vec<u8> {
+        let (w, h) = (256, 128);
+        let mut rgba = vec![0; w * h * BPP];
+        for (cx, cy, r) in [(48, 48, 44), (144, 48, 46), (224, 48, 14), (224, 80, 14)] {
+            for y in 0..h {
+                for x in 0..w {
+                    let (dx, dy) = (x as i32 - cx, y as i32 - cy);
+                    if dx * dx + dy * dy > r * r {
+                        continue;
+                    }
+                    // lighter towards the top left
+                    let shade = 255.0 - (dx + dy + 2 * r) as f32 * 200.0 / (4 * r) as f32;
+                    let px = &mut rgba[(y * w + x) * BPP..(y * w + x + 1) * BPP];
+                    for c in 0..3 {
+                        px[c] = (shade * hue[c]) as u8;
+                    }
+                    px[3] = 255;
+                }
+            }
+        }
+        rgba
+    }
+
+    #[test]
+    fn flags_recolors_and_blank_skins() {
+        let mut defaults = DefaultSkins::default();
+        defaults.insert(
+            "default".to_string(),
+            SkinHash::of(&shaded_skin([1.0, 1.0, 1.0]), 256, 128),
+        );
+
+        let recolor = shaded_skin([0.9, 0.4, 0.2]);
+        assert!(matches!(
+            check_content(&recolor, 256, 128, &defaults, &LIMITS)[..],
+            [ContentFlag::DefaultRecolor { .. }]
+        ));
+        assert!(check_content(&recolor, 256, 128, &DefaultSkins::default(), &LIMITS).is_empty());
+
+        let blank = vec![0; 256 * 128 * BPP];
+        assert_eq!(
+            check_content(&blank, 256, 128, &defaults, &LIMITS),
+            vec![ContentFlag::Blank]
+        );
+
+        // a flat body with a few pixels of a second color
+        let mut flat = vec![0; 256 * 128 * BPP];
+        for (i, px) in flat.chunks_mut(BPP).enumerate() {
+            let value = if i % 97 == 0 { 200 } else { 100 };
+            px.copy_from_slice(&[value, value, value, 255]);
+        }
+        assert!(matches!(
+            check_content(&flat, 256, 128, &DefaultSkins::default(), &LIMITS)[..],
+            [ContentFlag::LowEffort { colors: 2, .. }]
+        ));
+    }
+}
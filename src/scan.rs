@@ -0,0 +1,143 @@
+use serenity::all::{ChannelId, GetMessages, GuildChannel, Message, MessageId, UserId};
+use serenity::http::Http;
+use serenity::prelude::*;
+use tracing::warn;
+
+use crate::config::Config;
+use crate::forum::forum_of;
+use crate::metadata::{is_sidecar, parse_submission};
+use crate::session::SessionEvent;
+use crate::votes::{count_votes, meets_vote_threshold};
+use crate::{SkinToUploadDB, SkinUploads};
+
+/// ms since 1970 of the first discord snowflake
+const DISCORD_EPOCH_MS: u64 = 1_420_070_400_000;
+
+/// The id a message posted at the unix timestamp would have, to fetch the messages after it.
+pub fn message_id_at(unix_timestamp: i64) -> MessageId {
+    let ms = (unix_timestamp.max(0) as u64 * 1000).saturating_sub(DISCORD_EPOCH_MS);
+    MessageId::new((ms << 22).max(1))
+}
+
+fn is_submission(msg: &Message) -> bool {
+    (!msg.attachments.is_empty() || msg.embeds.iter().any(|embed| embed.image.is_some()))
+        && (parse_submission(msg).is_ok()
+            || msg.attachments.iter().any(|a| is_sidecar(&a.filename)))
+}
+
+/// The submissions with a skin posted in the channel after the message, oldest first.
+/// Stops after `limit` submissions. In a forum, or one of its posts,
+/// the submissions are the starter messages of the posts.
+pub async fn fetch_submissions(
+    http: &Http,
+    channel_id: ChannelId,
+    after: MessageId,
+    limit: usize,
+) -> Vec<Message> {
+    if let Some(forum) = forum_of(http, channel_id).await {
+        return fetch_forum_submissions(http, &forum, after, limit).await;
+    }
+    let mut submissions = Vec::default();
+    let mut after = after;
+    while submissions.len() < limit {
+        let mut msgs = match channel_id
+            .messages(http, GetMessages::new().after(after).limit(100))
+            .await
+        {
+            Ok(msgs) => msgs,
+            Err(err) => {
+                warn!("Could not fetch the messages of the channel: {err}");
+                break;
+            }
+        };
+        let Some(newest) = msgs.iter().map(|msg| msg.id).max() else {
+            break;
+        };
+        after = newest;
+        msgs.sort_by_key(|msg| msg.id);
+        submissions.extend(msgs.into_iter().filter(is_submission));
+    }
+    submissions.truncate(limit);
+    submissions
+}
+
+/// The starter messages of the posts created after the message, from the active posts
+/// and the 100 most recently archived ones.
+async fn fetch_forum_submissions(
+    http: &Http,
+    forum: &GuildChannel,
+    after: MessageId,
+    limit: usize,
+) -> Vec<Message> {
+    let mut posts = match forum.guild_id.get_active_threads(http).await {
+        Ok(active) => active.threads,
+        Err(err) => {
+            warn!("Could not fetch the active posts of the forum: {err}");
+            Vec::new()
+        }
+    };
+    match forum
+        .id
+        .get_archived_public_threads(http, None, Some(100))
+        .await
+    {
+        Ok(archived) => posts.extend(archived.threads),
+        Err(err) => warn!("Could not fetch the archived posts of the forum: {err}"),
+    }
+    posts.retain(|post| post.parent_id == Some(forum.id) && post.id.get() > after.get());
+    posts.sort_by_key(|post| post.id);
+    posts.dedup_by_key(|post| post.id);
+
+    let mut submissions = Vec::default();
+    for post in posts {
+        if submissions.len() >= limit {
+            break;
+        }
+        // the starter message of a forum post shares the id of the thread
+        match post.id.message(http, MessageId::new(post.id.get())).await {
+            Ok(msg) if is_submission(&msg) => submissions.push(msg),
+            Ok(_) => {}
+            Err(err) => warn!("Could not fetch the starter message of {}: {err}", post.id),
+        }
+    }
+    submissions
+}
+
+/// Queues the submissions for the upload session of the user, skins with enough community
+/// votes for the normal database, the others for the community database.
+/// Returns how many were queued.
+pub async fn queue_submissions(
+    ctx: &Context,
+    user_id: UserId,
+    submissions: &[Message],
+    config: &Config,
+) -> usize {
+    let mut data = ctx.data.write().await;
+    let Some(skin_upload) = data
+        .get_mut::<SkinUploads>()
+        .unwrap()
+        .uploads
+        .get_mut(&user_id)
+    else {
+        return 0;
+    };
+    let mut queued = 0;
+    for msg in submissions {
+        if skin_upload.session.queue().is_err() {
+            break;
+        }
+        let (positive_count, negative_count) = count_votes(msg, &config.emojis);
+        let database = if meets_vote_threshold(&config.votes, positive_count, negative_count) {
+            SkinToUploadDB::Normal
+        } else {
+            SkinToUploadDB::Community
+        };
+        skin_upload.send(SessionEvent::SkinQueued {
+            msg_id: msg.id,
+            channel_id: msg.channel_id,
+            database,
+        });
+        queued += 1;
+    }
+    queued
+}
@@ -0,0 +1,1745 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use hashlink::LinkedHashMap;
+use rayon::prelude::*;
+use serenity::all::{
+    ButtonStyle, ChannelId, CommandInteraction, CreateActionRow, CreateAllowedMentions,
+    CreateAttachment, CreateButton, CreateInteractionResponseFollowup, CreateMessage,
+    CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, EditInteractionResponse,
+    Mention, Message, MessageId, Timestamp, UserId,
+};
+use serenity::prelude::*;
+use tokio::select;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{info, info_span, warn, Instrument};
+
+use crate::audit::AuditLog;
+use crate::blocklist::Blocklist;
+use crate::cleanup::render_cleanup_diff;
+use crate::config::{Config, VotePolicyKind, WatermarkPolicyKind};
+use crate::forum::{is_upload_channel, post_of};
+use crate::license::{LicensePolicy, LicenseRules};
+use crate::metadata::{
+    skin_info_button, submission_metadata, title_metadata, SkinMetadata, SKIN_INFO_BUTTON_PREFIX,
+};
+use crate::naming::{name_key, same_skin_name, sanitize_skin_name};
+use crate::plugins::{PluginFindings, PluginSkinInfo, ValidationPlugins};
+use crate::resample::{
+    check_sd_quality, downscale_hd, render_png, render_resampler_preview, SdResampler,
+};
+use crate::safety::{check_content, ContentFlag, ContentLimits, DefaultSkins};
+use crate::skin::{analyze_skin_file, claimed_widths, AnalyzedSkin, SkinFileError};
+use crate::translit::transliterate;
+use crate::uploads::{download_submission_files, upload_buttons};
+use crate::votes::{count_votes, meets_vote_threshold, positive_ratio};
+use crate::watermark::{find_watermarks, render_watermark_preview, WatermarkRegion};
+use crate::{DefaultSkinHashes, SkinToUpload, SkinToUploadDB, SkinUploads};
+
+/// the review buttons are followed by the id of the user whose upload they belong to
+pub const APPROVE_BUTTON_PREFIX: &str = "approve_upload:";
+pub const REJECT_BUTTON_PREFIX: &str = "reject_upload:";
+
+/// the menu of the status message, to move skins to the other database
+pub const SWITCH_DATABASE_SELECT_ID: &str = "upload_switch_database";
+/// discord shows at most this many options in a menu
+const MAX_SELECT_OPTIONS: usize = 25;
+
+/// close enough to flag old submissions
+const SECS_PER_MONTH: i64 = 30 * 24 * 60 * 60;
+
+/// Lifecycle of an upload started with `/upload`:
+/// `Collecting -> Validated -> Uploading -> Done`,
+/// until the upload starts it can also end as `Cancelled` or `TimedOut`.
+/// With approvals, `Validated -> AwaitingApproval -> Uploading` or back to `Collecting`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionState {
+    /// skins were queued, but not checked yet
+    Collecting,
+    /// all queued skins were checked, the upload can start
+    Validated,
+    /// a second moderator has to approve the upload, the skins cannot change meanwhile
+    AwaitingApproval,
+    Uploading,
+    Done,
+    Cancelled,
+    TimedOut,
+}
+
+impl SessionState {
+    /// Skins can be queued and the upload options changed.
+    pub const fn is_collecting(self) -> bool {
+        matches!(self, Self::Collecting | Self::Validated)
+    }
+}
+
+/// A transition that is not allowed from the current state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IllegalTransition {
+    pub from: SessionState,
+    pub to: SessionState,
+}
+
+impl Display for IllegalTransition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "upload session cannot go from {:?} to {:?}",
+            self.from, self.to
+        )
+    }
+}
+
+/// The state of one upload session, it only changes through the transition methods.
+#[derive(Debug)]
+pub struct Session {
+    user_id: UserId,
+    state: SessionState,
+}
+
+impl Session {
+    pub const fn new(user_id: UserId) -> Self {
+        Self {
+            user_id,
+            state: SessionState::Collecting,
+        }
+    }
+
+    pub const fn state(&self) -> SessionState {
+        self.state
+    }
+
+    fn transition(
+        &mut self,
+        to: SessionState,
+        allowed_from: &[SessionState],
+    ) -> Result<(), IllegalTransition> {
+        let from = self.state;
+        if !allowed_from.contains(&from) {
+            return Err(IllegalTransition { from, to });
+        }
+        self.state = to;
+        if from != to {
+            info!("upload session of {}: {from:?} -> {to:?}", self.user_id);
+        }
+        Ok(())
+    }
+
+    /// A skin was queued or removed, it has to be checked again before uploading.
+    pub fn queue(&mut self) -> Result<(), IllegalTransition> {
+        self.transition(
+            SessionState::Collecting,
+            &[SessionState::Collecting, SessionState::Validated],
+        )
+    }
+
+    /// All queued skins were checked.
+    pub fn validate(&mut self) -> Result<(), IllegalTransition> {
+        self.transition(
+            SessionState::Validated,
+            &[SessionState::Collecting, SessionState::Validated],
+        )
+    }
+
+    pub fn start_upload(&mut self) -> Result<(), IllegalTransition> {
+        self.transition(SessionState::Uploading, &[SessionState::Validated])
+    }
+
+    pub fn request_approval(&mut self) -> Result<(), IllegalTransition> {
+        self.transition(SessionState::AwaitingApproval, &[SessionState::Validated])
+    }
+
+    pub fn approve(&mut self) -> Result<(), IllegalTransition> {
+        self.transition(SessionState::Uploading, &[SessionState::AwaitingApproval])
+    }
+
+    /// The skins can be changed again, before approval is requested another time.
+    pub fn reject(&mut self) -> Result<(), IllegalTransition> {
+        self.transition(SessionState::Collecting, &[SessionState::AwaitingApproval])
+    }
+
+    pub fn finish(&mut self) -> Result<(), IllegalTransition> {
+        self.transition(SessionState::Done, &[SessionState::Uploading])
+    }
+
+    pub fn cancel(&mut self) -> Result<(), IllegalTransition> {
+        self.transition(
+            SessionState::Cancelled,
+            &[
+                SessionState::Collecting,
+                SessionState::Validated,
+                SessionState::AwaitingApproval,
+            ],
+        )
+    }
+
+    pub fn time_out(&mut self) -> Result<(), IllegalTransition> {
+        self.transition(
+            SessionState::TimedOut,
+            &[SessionState::Collecting, SessionState::Validated],
+        )
+    }
+}
+
+/// The images the summary attaches to a skin, rendered once when it is checked.
+/// The sizes are `(width, png)`.
+#[derive(Clone, Default)]
+pub struct SkinPreviews {
+    /// before and after the cleanup of stray pixels
+    cleanup: Vec<(u32, Vec<u8>)>,
+    /// the possible watermarks outlined in red
+    watermarks: Vec<(u32, Vec<u8>)>,
+    /// the auto-resized skin
+    resized: Vec<(u32, Vec<u8>)>,
+    /// the 256x128 skin generated by every resampler
+    resamplers: Option<Vec<u8>>,
+}
+
+impl SkinPreviews {
+    fn render(skin: &SkinToUpload) -> Self {
+        let mut previews = Self::default();
+        for (file, w, stray_pixels, watermarks, resized) in [
+            (
+                &skin.file_256x128,
+                256,
+                &skin.stray_pixels_256x128,
+                &skin.watermarks_256x128,
+                skin.resized_256x128,
+            ),
+            (
+                &skin.file_512x256,
+                512,
+                &skin.stray_pixels_512x256,
+                &skin.watermarks_512x256,
+                skin.resized_512x256,
+            ),
+        ] {
+            let (w, h) = (w as usize, w as usize / 2);
+            if !stray_pixels.is_empty() {
+                if let Ok(diff) = render_cleanup_diff(file, w, h, stray_pixels) {
+                    previews.cleanup.push((w as u32, diff));
+                }
+            }
+            if !watermarks.is_empty() {
+                if let Ok(preview) = render_watermark_preview(file, w, h, watermarks) {
+                    previews.watermarks.push((w as u32, preview));
+                }
+            }
+            if resized.is_some() {
+                if let Ok(png) = render_png(file, w as u32, h as u32) {
+                    previews.resized.push((w as u32, png));
+                }
+            }
+        }
+        if !skin.sd_quality.is_empty() {
+            previews.resamplers = render_resampler_preview(&skin.file_512x256).ok();
+        }
+        previews
+    }
+}
+
+/// The names of a skin: the ascii name it is uploaded as, the name as written by the artist if
+/// it had to be transliterated and the name before it was made safe for the client.
+pub struct SkinName {
+    pub name: String,
+    pub original: Option<String>,
+    pub renamed_from: Option<String>,
+}
+
+impl SkinName {
+    /// The same for queued submissions and the skin editor. The error follows the name.
+    pub fn normalize(name: &str) -> Result<Self, String> {
+        let mut skin_name = Self {
+            name: name.to_string(),
+            original: None,
+            renamed_from: None,
+        };
+        if !name.is_ascii() {
+            let ascii_name = transliterate(name).map_err(|unknown| {
+                format!("contains characters that cannot be transliterated ({unknown}), please use an ascii name")
+            })?;
+            skin_name.original = Some(std::mem::replace(&mut skin_name.name, ascii_name));
+        }
+        let sanitized = sanitize_skin_name(&skin_name.name).map_err(|err| err.to_string())?;
+        if sanitized != skin_name.name {
+            skin_name.renamed_from = Some(std::mem::replace(&mut skin_name.name, sanitized));
+        }
+        Ok(skin_name)
+    }
+
+    /// Every name of the skin, the blocklist matches any of them.
+    pub fn all(&self) -> Vec<&str> {
+        [
+            Some(&self.name),
+            self.original.as_ref(),
+            self.renamed_from.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect()
+    }
+}
+
+/// Why the skin is blocked, if it is. The reason follows the name.
+pub fn blocklist_refusal(
+    blocklist: &Blocklist,
+    skin_name: &SkinName,
+    author: &str,
+    user_id: Option<UserId>,
+) -> Option<String> {
+    blocklist
+        .blocked(author, user_id, &skin_name.all())
+        .map(|entry| {
+            format!(
+                "is blocked, the {} `{}` is on the blocklist: {}",
+                entry.kind.label(),
+                entry.value,
+                entry.reason
+            )
+        })
+}
+
+/// The checks of a skin file, the same for queued submissions and the skin editor.
+#[derive(Clone)]
+pub struct FileChecks {
+    auto_resize: bool,
+    pub watermark_policy: WatermarkPolicyKind,
+    plugins: Arc<ValidationPlugins>,
+    default_skins: Arc<DefaultSkins>,
+    content_limits: Option<ContentLimits>,
+}
+
+/// A decoded skin file with what the checks found.
+pub struct CheckedFile {
+    pub skin: AnalyzedSkin,
+    pub plugin_findings: PluginFindings,
+    pub watermarks: Vec<WatermarkRegion>,
+    pub content_flags: Vec<ContentFlag>,
+}
+
+impl FileChecks {
+    pub async fn new(data: &RwLock<TypeMap>, config: &Config, database: SkinToUploadDB) -> Self {
+        let data = data.read().await;
+        Self {
+            auto_resize: config.upload.auto_resize,
+            watermark_policy: config.watermark.policy(database),
+            plugins: data.get::<ValidationPlugins>().unwrap().clone(),
+            default_skins: data.get::<DefaultSkinHashes>().unwrap().clone(),
+            content_limits: config
+                .content_checks
+                .enabled
+                .then(|| config.content_checks.limits()),
+        }
+    }
+
+    /// Decodes and checks the file, blocks while doing so.
+    pub fn check(&self, file: &[u8], info: &PluginSkinInfo) -> Result<CheckedFile, SkinFileError> {
+        let skin = analyze_skin_file(file, self.auto_resize)?;
+        let (w, h) = (skin.width as usize, skin.width as usize / 2);
+        let plugin_findings = self
+            .plugins
+            .check(&skin.img, skin.width, skin.width / 2, info);
+        let watermarks = if self.watermark_policy == WatermarkPolicyKind::Off {
+            Vec::new()
+        } else {
+            find_watermarks(&skin.img, w, h)
+        };
+        let content_flags = self.content_limits.map_or_else(Vec::new, |limits| {
+            check_content(&skin.img, w, h, &self.default_skins, &limits)
+        });
+        Ok(CheckedFile {
+            skin,
+            plugin_findings,
+            watermarks,
+            content_flags,
+        })
+    }
+}
+
+/// What finishing the upload would do, shown before it starts.
+pub struct UploadPreview {
+    pub added: Vec<String>,
+    /// uploaded before according to the audit log, they replace the skin in the database,
+    /// with the name of the earlier upload, which can differ in case
+    pub updated: Vec<(String, String)>,
+    pub normal_count: usize,
+    pub community_count: usize,
+    /// the pngs before they are processed, only estimated if requested
+    pub png_bytes: Option<u64>,
+    /// skins the content checks flagged, the moderator has to acknowledge them
+    pub flagged: Vec<(String, Vec<ContentFlag>)>,
+    /// changes with every skin that is added, removed, moved to the other database or replaced
+    /// by another image, and with the cleanup and resampler choice
+    pub fingerprint: u64,
+}
+
+/// Sent by the handlers to the task of an upload session, so they never wait for the
+/// skins to be checked.
+pub enum SessionEvent {
+    /// a message was queued by reaction or context menu, replacing a previous reaction
+    SkinQueued {
+        msg_id: MessageId,
+        channel_id: ChannelId,
+        database: SkinToUploadDB,
+    },
+    /// a reaction was removed or the submission was rejected
+    SkinUnqueued {
+        msg_id: MessageId,
+    },
+    /// the uploader entered the info of a submission it could not be read of
+    SkinInfoEntered {
+        msg_id: MessageId,
+        metadata: SkinMetadata,
+    },
+    /// the skin is downloaded and checked again, e.g. after the artist replaced the attachment
+    SkinRevalidated {
+        name: String,
+    },
+    /// the auto-resized skins looked bad
+    ResizedExcluded,
+    /// the skins of these messages are moved to the other database
+    DatabaseSwitched {
+        msg_ids: HashSet<MessageId>,
+    },
+    /// the cleanup or the resampler changed, the summary has to be updated
+    OptionsChanged,
+    CancelRequested,
+    /// the bot is stopping, replies with the links of the queued skins, so they can be recorded
+    ShutdownRequested(oneshot::Sender<Vec<String>>),
+    /// a review message is posted if the skins need an approval, replies whether they do
+    ApprovalRequested(oneshot::Sender<bool>),
+    /// the reviewer rejected the upload
+    ApprovalRejected {
+        by: UserId,
+    },
+    /// replies what the upload would do, so it can be confirmed
+    PreviewRequested {
+        with_size: bool,
+        reply: oneshot::Sender<UploadPreview>,
+    },
+    /// a copy of the checked skins, to try them in the client before uploading
+    ExportRequested(oneshot::Sender<LinkedHashMap<String, SkinToUpload>>),
+    /// the collected skins are handed over to be uploaded
+    FinishRequested(oneshot::Sender<LinkedHashMap<String, SkinToUpload>>),
+}
+
+/// The task of an upload session, it owns the collected skins and consumes the events
+/// until the session is cancelled, finished or timed out.
+pub struct UploadSession {
+    ctx: Context,
+    command: CommandInteraction,
+    config: Arc<Config>,
+    /// the text the summary starts with
+    header: String,
+    skins_try_upload: LinkedHashMap<MessageId, (ChannelId, SkinToUploadDB)>,
+    errors: VecDeque<String>,
+    skins_to_upload: LinkedHashMap<String, SkinToUpload>,
+    /// the time spent checking skins, counted against `max_validation_secs`
+    validation_time: Duration,
+    /// the last limit that refused skins and how many were refused
+    limit_reached: Option<(String, usize)>,
+    /// submissions the info could not be read of, until the uploader enters it
+    missing_info: HashMap<MessageId, (ChannelId, SkinToUploadDB)>,
+    /// the info the uploader entered, it replaces the one of the message
+    entered_info: HashMap<MessageId, SkinMetadata>,
+    /// the review message while it is pinned
+    pinned_review: Option<Message>,
+}
+
+impl UploadSession {
+    pub fn new(
+        ctx: Context,
+        command: CommandInteraction,
+        config: Arc<Config>,
+        header: String,
+    ) -> Self {
+        Self {
+            ctx,
+            command,
+            config,
+            header,
+            skins_try_upload: LinkedHashMap::default(),
+            errors: VecDeque::default(),
+            skins_to_upload: LinkedHashMap::default(),
+            validation_time: Duration::ZERO,
+            limit_reached: None,
+            missing_info: HashMap::default(),
+            entered_info: HashMap::default(),
+            pinned_review: None,
+        }
+    }
+
+    pub async fn run(mut self, mut events: mpsc::UnboundedReceiver<SessionEvent>) {
+        let timeout = Duration::from_secs(self.config.upload.timeout_secs);
+        let warning = Duration::from_secs(self.config.upload.expiry_warning_secs);
+        let mut last_activity = tokio::time::Instant::now();
+        let mut warned = false;
+        let mut pending = None;
+        // edits of the summary are coalesced, while skins are queued quickly it is edited once
+        // per interval and the latest summary is sent once the interval is over
+        let refresh_interval = Duration::from_millis(self.config.upload.summary_refresh_ms);
+        let mut last_summary_edit: Option<tokio::time::Instant> = None;
+        let mut unsent_summary = None;
+        loop {
+            let mut event = if let Some(event) = pending.take() {
+                event
+            } else {
+                let expires_at = last_activity + timeout;
+                let warn_at = (!warned && !warning.is_zero() && warning < timeout)
+                    .then(|| expires_at - warning);
+                let flush_at = last_summary_edit
+                    .map_or_else(tokio::time::Instant::now, |at| at + refresh_interval);
+                select! {
+                    _ = tokio::time::sleep_until(flush_at), if unsent_summary.is_some() => {
+                        self.edit_summary(unsent_summary.take().unwrap()).await;
+                        last_summary_edit = Some(tokio::time::Instant::now());
+                        continue;
+                    }
+                    _ = tokio::time::sleep_until(warn_at.unwrap_or(expires_at)) => {
+                        if warn_at.is_some() {
+                            self.warn_expiry(warning).await;
+                            warned = true;
+                            continue;
+                        }
+                        if self.time_out().await {
+                            break;
+                        }
+                        // the upload just started and its event is on the way,
+                        // or the upload waits for an approval
+                        last_activity = tokio::time::Instant::now();
+                        continue;
+                    }
+                    event = events.recv() => match event {
+                        Some(event) => event,
+                        None => break,
+                    },
+                }
+            };
+            last_activity = tokio::time::Instant::now();
+            warned = false;
+
+            // handle everything that was sent, before checking the skins
+            loop {
+                if !self.handle(event).await {
+                    self.unpin_review().await;
+                    return;
+                }
+                match events.try_recv() {
+                    Ok(next) => event = next,
+                    Err(_) => break,
+                }
+            }
+            self.check_queued_skins().await;
+
+            let mut data = self.ctx.data.write().await;
+            let Some(item) = data
+                .get_mut::<SkinUploads>()
+                .unwrap()
+                .uploads
+                .get_mut(&self.command.user.id)
+            else {
+                break;
+            };
+            // skins queued in the meantime have to be checked before the upload can start
+            if let Ok(event) = events.try_recv() {
+                pending = Some(event);
+            } else if item.session.state().is_collecting() {
+                if let Err(err) = item.session.validate() {
+                    warn!("{err}");
+                }
+            }
+            item.queued_skins = self.skins_to_upload.len() + self.skins_try_upload.len();
+            item.expires_at = Timestamp::now().unix_timestamp() + timeout.as_secs() as i64;
+            let summary = self.summary(item.apply_cleanup, item.sd_resampler);
+            drop(data);
+            let recently_edited =
+                last_summary_edit.is_some_and(|at| at.elapsed() < refresh_interval);
+            if recently_edited {
+                unsent_summary = Some(summary);
+            } else {
+                unsent_summary = None;
+                self.edit_summary(summary).await;
+                last_summary_edit = Some(tokio::time::Instant::now());
+            }
+        }
+        self.unpin_review().await;
+    }
+
+    async fn edit_summary(&self, summary: EditInteractionResponse) {
+        if let Err(err) = self.command.edit_response(&self.ctx, summary).await {
+            warn!("Could not edit response from command: {err}");
+        }
+    }
+
+    /// Returns false, if the session ends with the event.
+    async fn handle(&mut self, event: SessionEvent) -> bool {
+        match event {
+            SessionEvent::SkinQueued {
+                msg_id,
+                channel_id,
+                database,
+            } => {
+                let known = self.skins_try_upload.contains_key(&msg_id)
+                    || self
+                        .skins_to_upload
+                        .values()
+                        .any(|skin| skin.original_msg_id == msg_id);
+                // switching the database of a skin is still fine
+                if !known && self.refuse_beyond_limits() {
+                    return true;
+                }
+                // remove the already inserted skin, if any
+                self.skins_to_upload
+                    .retain(|_, skin| skin.original_msg_id != msg_id);
+                self.skins_try_upload.insert(msg_id, (channel_id, database));
+            }
+            SessionEvent::SkinUnqueued { msg_id } => {
+                self.skins_try_upload.remove(&msg_id);
+                self.missing_info.remove(&msg_id);
+                self.skins_to_upload
+                    .retain(|_, skin| skin.original_msg_id != msg_id);
+            }
+            SessionEvent::SkinInfoEntered { msg_id, metadata } => {
+                self.entered_info.insert(msg_id, metadata);
+                // the skin was dropped, it is checked again with the entered info
+                if let Some(queued) = self.missing_info.remove(&msg_id) {
+                    self.skins_try_upload.insert(msg_id, queued);
+                }
+            }
+            SessionEvent::SkinRevalidated { name } => {
+                let queued = self
+                    .skins_to_upload
+                    .iter()
+                    .find(|(skin_name, _)| same_skin_name(skin_name, &name))
+                    .map(|(_, skin)| (skin.original_msg_id, (skin.channel_id, skin.database)));
+                match queued {
+                    Some((msg_id, queued)) => {
+                        self.skins_to_upload
+                            .retain(|_, skin| skin.original_msg_id != msg_id);
+                        self.skins_try_upload.insert(msg_id, queued);
+                    }
+                    None => self.errors.push_back(format!(
+                        "skin: {name} is not queued, it cannot be checked again"
+                    )),
+                }
+            }
+            SessionEvent::ResizedExcluded => {
+                self.skins_to_upload.retain(|_, skin| {
+                    skin.resized_256x128.is_none() && skin.resized_512x256.is_none()
+                });
+            }
+            SessionEvent::DatabaseSwitched { msg_ids } => {
+                // checked again, the vote threshold only applies to the normal database
+                let switched: Vec<_> = self
+                    .skins_to_upload
+                    .values()
+                    .filter(|skin| msg_ids.contains(&skin.original_msg_id))
+                    .map(|skin| {
+                        (
+                            skin.original_msg_id,
+                            (skin.channel_id, skin.database.other()),
+                        )
+                    })
+                    .collect();
+                self.skins_to_upload
+                    .retain(|_, skin| !msg_ids.contains(&skin.original_msg_id));
+                self.skins_try_upload.extend(switched);
+            }
+            SessionEvent::OptionsChanged => {}
+            SessionEvent::ApprovalRequested(reply) => {
+                let needs_approval = self
+                    .skins_to_upload
+                    .values()
+                    .any(|skin| skin.database == SkinToUploadDB::Normal);
+                if needs_approval {
+                    self.post_review().await;
+                }
+                if reply.send(needs_approval).is_err() {
+                    warn!("The approval request was aborted");
+                }
+            }
+            SessionEvent::ApprovalRejected { by } => {
+                self.unpin_review().await;
+                self.errors.push_back(format!(
+                    "{} rejected the upload, change the skins and finish it again",
+                    Mention::User(by)
+                ));
+            }
+            SessionEvent::CancelRequested => {
+                if (self.command.delete_response(&self.ctx).await).is_err() {
+                    warn!("Response not deleted.");
+                }
+                self.remove().await;
+                return false;
+            }
+            SessionEvent::ShutdownRequested(reply) => {
+                let guild_id = self.config.guild_id();
+                let links = self
+                    .skins_try_upload
+                    .iter()
+                    .map(|(msg_id, (channel_id, _))| (*channel_id, *msg_id))
+                    .chain(
+                        self.skins_to_upload
+                            .values()
+                            .map(|skin| (skin.channel_id, skin.original_msg_id)),
+                    )
+                    .map(|(channel_id, msg_id)| {
+                        format!("https://discord.com/channels/{guild_id}/{channel_id}/{msg_id}")
+                    })
+                    .collect();
+                let summary = EditInteractionResponse::new()
+                    .content("The bot is shutting down, this upload was cancelled. Queue the skins again once it is back")
+                    .components(vec![]);
+                if let Err(err) = self.command.edit_response(&self.ctx, summary).await {
+                    warn!("Could not edit response from command: {err}");
+                }
+                if reply.send(links).is_err() {
+                    warn!("The shutdown did not wait for the upload session");
+                }
+                self.remove().await;
+                return false;
+            }
+            SessionEvent::PreviewRequested { with_size, reply } => {
+                let preview = self.preview(with_size).await;
+                if reply.send(preview).is_err() {
+                    warn!("The confirmation of the upload was aborted");
+                }
+            }
+            SessionEvent::ExportRequested(reply) => {
+                if reply.send(self.skins_to_upload.clone()).is_err() {
+                    warn!("The export of the collected skins was aborted");
+                }
+            }
+            SessionEvent::FinishRequested(reply) => {
+                // the session is removed once the upload is done
+                if (self.command.delete_response(&self.ctx).await).is_err() {
+                    warn!("Response not deleted.");
+                }
+                if reply
+                    .send(std::mem::take(&mut self.skins_to_upload))
+                    .is_err()
+                {
+                    warn!("The upload of the collected skins was aborted");
+                }
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Posts the skins for a second moderator, with buttons to approve or reject the upload.
+    async fn post_review(&mut self) {
+        let user_id = self.command.user.id;
+        let mut content = format!(
+            "{} wants to upload these skins, another moderator has to approve it:\n",
+            Mention::User(user_id)
+        );
+        let guild_id = self.config.guild_id();
+        for (skin_name, skin) in &self.skins_to_upload {
+            let line = format!(
+                "- {} `{skin_name}` by `{}` https://discord.com/channels/{guild_id}/{}/{}\n",
+                self.config.emojis.queue_label(skin.database),
+                skin.author,
+                skin.channel_id,
+                skin.original_msg_id
+            );
+            // leave some space for the omitted hint
+            if content.chars().count() + line.chars().count() > 1950 {
+                content += "...\n";
+                break;
+            }
+            content += &line;
+        }
+        let msg = CreateMessage::new()
+            .content(content)
+            .allowed_mentions(CreateAllowedMentions::new())
+            .button(
+                CreateButton::new(format!("{APPROVE_BUTTON_PREFIX}{user_id}"))
+                    .label("Approve")
+                    .style(ButtonStyle::Success),
+            )
+            .button(
+                CreateButton::new(format!("{REJECT_BUTTON_PREFIX}{user_id}"))
+                    .label("Reject")
+                    .style(ButtonStyle::Danger),
+            );
+        let review = match self.command.channel_id.send_message(&self.ctx, msg).await {
+            Ok(review) => review,
+            Err(err) => {
+                warn!("Could not post the upload review: {err}");
+                return;
+            }
+        };
+        if self.config.upload.pin_review {
+            // replaces the review of an earlier finish that was never decided
+            self.unpin_review().await;
+            match review.pin(&self.ctx).await {
+                Ok(()) => self.pinned_review = Some(review),
+                Err(err) => warn!("Could not pin the upload review: {err}"),
+            }
+        }
+    }
+
+    async fn unpin_review(&mut self) {
+        let Some(review) = self.pinned_review.take() else {
+            return;
+        };
+        if let Err(err) = review.unpin(&self.ctx).await {
+            warn!("Could not unpin the upload review: {err}");
+        }
+    }
+
+    /// Tells the uploader that the session is about to be dropped, while it still collects skins.
+    async fn warn_expiry(&self, warning: Duration) {
+        let collecting = self
+            .ctx
+            .data
+            .read()
+            .await
+            .get::<SkinUploads>()
+            .unwrap()
+            .uploads
+            .get(&self.command.user.id)
+            .is_some_and(|item| item.session.state().is_collecting());
+        if !collecting {
+            return;
+        }
+        let followup = CreateInteractionResponseFollowup::new()
+            .content(format!(
+                "Your upload session expires in {}s, react to a skin to keep it open or press 🆗 to finish it",
+                warning.as_secs()
+            ))
+            .ephemeral(true);
+        if let Err(err) = self.command.create_followup(&self.ctx, followup).await {
+            warn!("Could not warn about the expiring session: {err}");
+        }
+    }
+
+    /// Returns false, if the session cannot time out anymore, because the upload started.
+    async fn time_out(&self) -> bool {
+        let mut data = self.ctx.data.write().await;
+        let uploads = &mut data.get_mut::<SkinUploads>().unwrap().uploads;
+        let Some(item) = uploads.get_mut(&self.command.user.id) else {
+            return true;
+        };
+        if item.session.time_out().is_err() {
+            return false;
+        }
+        uploads.remove(&self.command.user.id);
+        drop(data);
+        if let Err(err) = self
+            .command
+            .edit_response(
+                &self.ctx,
+                EditInteractionResponse::new()
+                    .content("Upload timed out. Also only do one upload at a time"),
+            )
+            .await
+        {
+            warn!("Could not edit response from command: {err}");
+        }
+        true
+    }
+
+    async fn remove(&self) {
+        self.ctx
+            .data
+            .write()
+            .await
+            .get_mut::<SkinUploads>()
+            .unwrap()
+            .uploads
+            .remove(&self.command.user.id);
+    }
+
+    /// Checks if all queued skins are valid and adds them to the skins to upload.
+    async fn check_queued_skins(&mut self) {
+        let license_rules = LicenseRules::new(&self.config.license);
+        let queued: Vec<_> = self.skins_try_upload.drain().collect();
+        for (msg_id, (channel_id, database)) in queued {
+            if self.refuse_beyond_limits() {
+                continue;
+            }
+            if !is_upload_channel(&self.ctx, &self.config, channel_id).await {
+                self.errors.push_back(format!(
+                    "A message in <#{channel_id}> was ignored, skins are only collected from {}",
+                    self.config.allowed_channels_list()
+                ));
+                continue;
+            }
+            match self.ctx.http.get_message(channel_id, msg_id).await {
+                Ok(skin_msg) => {
+                    let license_policy = license_rules.at(skin_msg.timestamp);
+                    let start = Instant::now();
+                    let span = info_span!("check_skin", msg_id = %msg_id);
+                    self.check_skin(&skin_msg, database, license_policy)
+                        .instrument(span)
+                        .await;
+                    self.validation_time += start.elapsed();
+                }
+                Err(err) => {
+                    warn!("{err}");
+                    self.errors.push_back(
+                        "One of the reacted messages was not found anymore...".to_string(),
+                    );
+                }
+            }
+        }
+    }
+
+    async fn preview(&self, with_size: bool) -> UploadPreview {
+        let (uploaded, processing) = {
+            let data = self.ctx.data.read().await;
+            let processing = data
+                .get::<SkinUploads>()
+                .unwrap()
+                .uploads
+                .get(&self.command.user.id)
+                .map(|item| (item.apply_cleanup, item.sd_resampler.button_id()));
+            (data.get::<AuditLog>().unwrap().uploaded_skins(), processing)
+        };
+        let mut hasher = DefaultHasher::new();
+        // the confirmed skins must be processed the way they were shown
+        processing.hash(&mut hasher);
+        let mut added = Vec::new();
+        let mut updated = Vec::new();
+        for (skin_name, skin) in &self.skins_to_upload {
+            let database = skin.database.to_string();
+            (
+                skin_name,
+                &database,
+                skin.original_msg_id,
+                skin.content_hash_256x128,
+                skin.content_hash_512x256,
+            )
+                .hash(&mut hasher);
+            if let Some(uploaded_name) = uploaded.get(&(name_key(skin_name), database)) {
+                updated.push((skin_name.clone(), uploaded_name.clone()));
+            } else {
+                added.push(skin_name.clone());
+            }
+        }
+        let normal_count = self
+            .skins_to_upload
+            .values()
+            .filter(|skin| skin.database == SkinToUploadDB::Normal)
+            .count();
+
+        let png_bytes = if with_size {
+            let files: Vec<_> = self
+                .skins_to_upload
+                .values()
+                .flat_map(|skin| {
+                    [
+                        (skin.file_256x128.clone(), 256, 128),
+                        (skin.file_512x256.clone(), 512, 256),
+                    ]
+                })
+                .filter(|(file, _, _)| !file.is_empty())
+                .collect();
+            let png_bytes = tokio::task::spawn_blocking(move || {
+                files
+                    .into_par_iter()
+                    .map(|(file, w, h)| render_png(&file, w, h).map_or(0, |png| png.len() as u64))
+                    .sum()
+            })
+            .await
+            .unwrap();
+            Some(png_bytes)
+        } else {
+            None
+        };
+        UploadPreview {
+            added,
+            updated,
+            normal_count,
+            community_count: self.skins_to_upload.len() - normal_count,
+            png_bytes,
+            flagged: self
+                .skins_to_upload
+                .iter()
+                .filter(|(_, skin)| !skin.content_flags.is_empty())
+                .map(|(skin_name, skin)| (skin_name.clone(), skin.content_flags.clone()))
+                .collect(),
+            fingerprint: hasher.finish(),
+        }
+    }
+
+    /// Offers the uploader a form to type the info the bot could not read,
+    /// instead of dropping the skin.
+    async fn ask_for_skin_info(&mut self, skin_msg: &Message, database: SkinToUploadDB) {
+        if self
+            .missing_info
+            .insert(skin_msg.id, (skin_msg.channel_id, database))
+            .is_some()
+        {
+            return;
+        }
+        let followup = CreateInteractionResponseFollowup::new()
+            .content(format!(
+                "The info of https://discord.com/channels/{}/{}/{} could not be read, you can enter it instead",
+                self.config.guild_id(),
+                skin_msg.channel_id,
+                skin_msg.id
+            ))
+            .button(skin_info_button(skin_msg, SKIN_INFO_BUTTON_PREFIX))
+            .ephemeral(true);
+        if let Err(err) = self.command.create_followup(&self.ctx, followup).await {
+            warn!("Could not ask for the skin info: {err}");
+        }
+    }
+
+    async fn check_skin(
+        &mut self,
+        skin_msg: &Message,
+        database: SkinToUploadDB,
+        license_policy: &LicensePolicy,
+    ) {
+        let SkinMetadata {
+            name: mut skin_name,
+            author: author_name,
+            license: license_name,
+            pack: skin_pack,
+            tags,
+        } = match self.entered_info.get(&skin_msg.id) {
+            Some(metadata) => metadata.clone(),
+            None => match submission_metadata(skin_msg).await {
+                Ok(metadata) => metadata,
+                Err(err) => match post_of(&self.ctx, skin_msg)
+                    .await
+                    .and_then(|(post, _)| title_metadata(&post.name, &skin_msg.content))
+                {
+                    Some(metadata) => metadata,
+                    None => {
+                        self.errors.push_back(format!("{err:#}"));
+                        self.ask_for_skin_info(skin_msg, database).await;
+                        return;
+                    }
+                },
+            },
+        };
+        let mut all_required_info = true;
+        let mut original_name = None;
+        let mut renamed_from = None;
+        match SkinName::normalize(&skin_name) {
+            Ok(names) => {
+                skin_name = names.name;
+                original_name = names.original;
+                renamed_from = names.renamed_from;
+            }
+            Err(err) => {
+                self.errors.push_back(format!("skin: {skin_name} {err}"));
+                all_required_info = false;
+            }
+        }
+        let mut license_unknown = false;
+        let license_name = match license_policy.validate(&license_name) {
+            Ok(license) => license,
+            Err(license) if license_policy.rejects_unknown() => {
+                self.errors.push_back(format!(
+                    "skin: {skin_name} has the license `{license}`, which is not allowed"
+                ));
+                all_required_info = false;
+                license
+            }
+            Err(license) => {
+                license_unknown = true;
+                license
+            }
+        };
+        let names = SkinName {
+            name: skin_name.clone(),
+            original: original_name.clone(),
+            renamed_from: renamed_from.clone(),
+        };
+        let blocked = blocklist_refusal(
+            self.ctx.data.read().await.get::<Blocklist>().unwrap(),
+            &names,
+            &author_name,
+            Some(skin_msg.author.id),
+        );
+        if let Some(err) = blocked {
+            self.errors.push_back(format!("skin: {skin_name} {err}"));
+            all_required_info = false;
+        }
+        let (positive_count, negative_count) = count_votes(skin_msg, &self.config.emojis);
+        let below_vote_threshold = database == SkinToUploadDB::Normal
+            && !meets_vote_threshold(&self.config.votes, positive_count, negative_count);
+        if below_vote_threshold && self.config.votes.policy == VotePolicyKind::Reject {
+            self.errors.push_back(format!(
+                "skin: {skin_name} has {} of {} votes positive, the normal database needs {}% of at least {} votes. Consider the community database instead",
+                positive_count,
+                positive_count + negative_count,
+                self.config.votes.min_positive_ratio * 100.0,
+                self.config.votes.min_votes
+            ));
+            all_required_info = false;
+        }
+        if let Some(skin) = self
+            .skins_to_upload
+            .iter()
+            .find_map(|(name, skin)| same_skin_name(name, &skin_name).then_some(skin))
+        {
+            if skin.database != database && skin.original_msg_id == skin_msg.id {
+                self.errors.push_back(format!(
+                    "you changed the database upload type of: {skin_name}. If you did a mistake cancel the upload and try again."
+                ));
+                all_required_info = false;
+            }
+        }
+        if !all_required_info {
+            return;
+        }
+
+        let files = download_submission_files(skin_msg).await;
+        let has_files = !files.is_empty();
+        // `greyfox.png` and `greyfox_uhd.png` are the two sizes of one skin
+        let claims = claimed_widths(
+            &files
+                .iter()
+                .map(|file| file.as_ref().map_or("", |(name, _)| name.as_str()))
+                .collect::<Vec<_>>(),
+        );
+        let checks = FileChecks::new(&self.ctx.data, &self.config, database).await;
+        let watermark_policy = checks.watermark_policy;
+        let plugin_info = PluginSkinInfo {
+            name: skin_name.clone(),
+            author: author_name.clone(),
+            license: license_name.clone(),
+            database: database.to_string(),
+        };
+        // decoding big HD images would hold up the other events of the gateway
+        let analyzed = tokio::task::spawn_blocking(move || {
+            files
+                .into_par_iter()
+                .zip(claims)
+                .map(|(file, claim)| match file {
+                    Ok((name, file)) => checks
+                        .check(&file, &plugin_info)
+                        .map(|checked| ((name, claim), checked)),
+                    Err(_) => Err(SkinFileError::Download),
+                })
+                .collect::<Vec<_>>()
+        })
+        .await
+        .unwrap();
+        let mut valid_files = Vec::new();
+        for file in analyzed {
+            match file {
+                Ok(file) => valid_files.push(file),
+                Err(SkinFileError::Download) => {
+                    self.errors.push_back(
+                        "One of the reacted messages did not contain a valid skin file..."
+                            .to_string(),
+                    );
+                }
+                Err(SkinFileError::Decode) => {
+                    self.errors.push_back(
+                        "One of the reacted messages contained an invalid image file..."
+                            .to_string(),
+                    );
+                }
+                Err(SkinFileError::Size) => {
+                    self.errors.push_back(format!(
+                        "skin: {skin_name} did not contain a valid 256x128 or 512x256 skin"
+                    ));
+                }
+            }
+        }
+        let content_hashes: Vec<_> = valid_files
+            .iter()
+            .map(|(_, file)| file.skin.content_hash)
+            .collect();
+        if let Some(err) = self.batch_conflict(skin_msg, &skin_name, &content_hashes) {
+            self.errors.push_back(err);
+            return;
+        }
+        // of two files of the same size, the one that fits its name is used
+        valid_files.sort_by_key(|((_, claim), file)| *claim == Some(file.skin.width));
+        let paired_name = |width| {
+            valid_files
+                .iter()
+                .find(|((_, claim), file)| *claim == Some(width) && file.skin.width == width)
+                .map(|((name, _), _)| name.clone())
+        };
+        let paired_files = paired_name(256).zip(paired_name(512));
+        for ((filename, claim), file) in valid_files {
+            let CheckedFile {
+                skin:
+                    AnalyzedSkin {
+                        img,
+                        width: w,
+                        stray_pixels,
+                        conversion,
+                        resized_from,
+                        content_hash,
+                    },
+                plugin_findings,
+                watermarks,
+                content_flags,
+            } = file;
+            if !plugin_findings.errors.is_empty() {
+                for err in plugin_findings.errors {
+                    self.errors
+                        .push_back(format!("skin: {skin_name} ({w}px) was rejected by {err}"));
+                }
+                continue;
+            }
+            if !watermarks.is_empty() && watermark_policy == WatermarkPolicyKind::Block {
+                self.errors.push_back(format!(
+                    "skin: {skin_name} ({w}px) looks like it has a signature or watermark outside the tee, which the database does not allow"
+                ));
+                continue;
+            }
+            if !self.skins_to_upload.contains_key(&skin_name) {
+                self.skins_to_upload.insert(
+                    skin_name.clone(),
+                    SkinToUpload {
+                        author: author_name.clone(),
+                        license: license_name.clone(),
+                        file_256x128: Vec::new(),
+                        file_512x256: Vec::new(),
+                        database,
+                        original_msg_id: skin_msg.id,
+                        channel_id: skin_msg.channel_id,
+                        stray_pixels_256x128: Vec::new(),
+                        stray_pixels_512x256: Vec::new(),
+                        content_flags: Vec::new(),
+                        paired_files: None,
+                        file_name_warnings: Vec::new(),
+                        watermarks_256x128: Vec::new(),
+                        watermarks_512x256: Vec::new(),
+                        conversion_256x128: None,
+                        conversion_512x256: None,
+                        resized_256x128: None,
+                        resized_512x256: None,
+                        content_hash_256x128: None,
+                        content_hash_512x256: None,
+                        sd_quality: Vec::new(),
+                        previews: SkinPreviews::default(),
+                        license_unknown,
+                        original_name: original_name.clone(),
+                        renamed_from: renamed_from.clone(),
+                        plugin_warnings: Vec::new(),
+                        skin_pack: skin_pack.clone(),
+                        tags: tags.clone(),
+                        positive_ratio: positive_ratio(positive_count, negative_count),
+                        below_vote_threshold,
+                        age_months: self.submission_age_months(skin_msg),
+                    },
+                );
+            }
+            let skin = self.skins_to_upload.get_mut(&skin_name).unwrap();
+            for warning in plugin_findings.warnings {
+                if !skin.plugin_warnings.contains(&warning) {
+                    skin.plugin_warnings.push(warning);
+                }
+            }
+            if let Some(claim) = claim.filter(|&claim| claim != w) {
+                let warning = format!(
+                    "`{filename}` is named like the {claim}x{} version but is {w}x{}",
+                    claim / 2,
+                    w / 2
+                );
+                if !skin.file_name_warnings.contains(&warning) {
+                    skin.file_name_warnings.push(warning);
+                }
+            }
+            skin.paired_files = paired_files.clone();
+            // both sizes are usually flagged for the same reason
+            for flag in content_flags {
+                if !skin.content_flags.contains(&flag) {
+                    skin.content_flags.push(flag);
+                }
+            }
+            if w == 256 {
+                skin.content_hash_256x128 = Some(content_hash);
+                skin.file_256x128 = img;
+                skin.stray_pixels_256x128 = stray_pixels;
+                skin.watermarks_256x128 = watermarks;
+                skin.conversion_256x128 = conversion;
+                skin.resized_256x128 = resized_from;
+            } else {
+                skin.content_hash_512x256 = Some(content_hash);
+                skin.file_512x256 = img;
+                skin.stray_pixels_512x256 = stray_pixels;
+                skin.watermarks_512x256 = watermarks;
+                skin.conversion_512x256 = conversion;
+                skin.resized_512x256 = resized_from;
+            }
+        }
+
+        if !has_files {
+            self.errors.push_back(
+                "No skin file attachments found in one of the messages you reacted to..."
+                    .to_string(),
+            );
+        }
+
+        let Some(skin) = self.skins_to_upload.get(&skin_name) else {
+            return;
+        };
+        // encoding the previews would hold up the gateway on every summary edit otherwise
+        let mut checked = skin.clone();
+        let (sd_quality, previews) = tokio::task::spawn_blocking(move || {
+            checked.sd_quality = if checked.file_256x128.is_empty() {
+                // no non hd skin was posted, it will be derived from the hd one
+                let hd = &checked.file_512x256;
+                SdResampler::ALL
+                    .par_iter()
+                    .map(|&resampler| {
+                        let sd = downscale_hd(hd, resampler);
+                        (resampler, check_sd_quality(hd, &sd))
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            let previews = SkinPreviews::render(&checked);
+            (checked.sd_quality, previews)
+        })
+        .await
+        .unwrap();
+        if let Some(skin) = self.skins_to_upload.get_mut(&skin_name) {
+            skin.sd_quality = sd_quality;
+            skin.previews = previews;
+        }
+    }
+
+    /// Compares a submission with the skins of the other messages in the batch: two messages
+    /// must not claim the same name with different images, or the same image under different
+    /// names. Conflicting skins are dropped both, so neither of them wins silently.
+    fn batch_conflict(
+        &mut self,
+        skin_msg: &Message,
+        skin_name: &str,
+        content_hashes: &[u64],
+    ) -> Option<String> {
+        if content_hashes.is_empty() {
+            return None;
+        }
+        let guild_id = self.config.guild_id();
+        let link = |channel_id, msg_id| {
+            format!("https://discord.com/channels/{guild_id}/{channel_id}/{msg_id}")
+        };
+        let same_image = |skin: &SkinToUpload| {
+            [skin.content_hash_256x128, skin.content_hash_512x256]
+                .into_iter()
+                .flatten()
+                .any(|hash| content_hashes.contains(&hash))
+        };
+        // the skins are written to files, which can be case-insensitive
+        let (other_name, other) = self.skins_to_upload.iter().find(|(name, skin)| {
+            skin.original_msg_id != skin_msg.id
+                && (same_skin_name(name, skin_name) || same_image(skin))
+        })?;
+        let this_link = link(skin_msg.channel_id, skin_msg.id);
+        let other_link = link(other.channel_id, other.original_msg_id);
+        let err = match (same_skin_name(other_name, skin_name), same_image(other)) {
+            (true, true) => {
+                return Some(format!(
+                    "skin: {skin_name} of {this_link} is the same as {other_link}, the copy is ignored"
+                ))
+            }
+            (true, false) => format!(
+                "skin: {skin_name} has different images in {other_link} and {this_link}, neither is uploaded. Queue the right one again"
+            ),
+            (false, _) => format!(
+                "skin: {skin_name} of {this_link} has the same image as `{other_name}` of {other_link}, neither is uploaded. Queue the right one again"
+            ),
+        };
+        let other_name = other_name.clone();
+        self.skins_to_upload.remove(&other_name);
+        Some(err)
+    }
+
+    /// Which limit of the session was exceeded, a big sweep must not hold up the whole bot.
+    fn exceeded_limit(&self) -> Option<String> {
+        let limits = &self.config.upload;
+        let queued_msgs: HashSet<_> = self
+            .skins_to_upload
+            .values()
+            .map(|skin| skin.original_msg_id)
+            .chain(self.skins_try_upload.keys().copied())
+            .collect();
+        let decoded_bytes: usize = self
+            .skins_to_upload
+            .values()
+            .map(|skin| skin.file_256x128.len() + skin.file_512x256.len())
+            .sum();
+        if limits.max_queued_skins > 0 && queued_msgs.len() >= limits.max_queued_skins {
+            Some(format!(
+                "the upload reached the limit of {} skins",
+                limits.max_queued_skins
+            ))
+        } else if limits.max_decoded_mib > 0 && decoded_bytes as u64 >= limits.max_decoded_mib << 20
+        {
+            Some(format!(
+                "the skins of the upload take up more than {} MiB",
+                limits.max_decoded_mib
+            ))
+        } else if limits.max_validation_secs > 0
+            && self.validation_time.as_secs() >= limits.max_validation_secs
+        {
+            Some(format!(
+                "checking the skins took longer than {} seconds",
+                limits.max_validation_secs
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Returns true and counts the skin as refused, if a limit of the session was reached.
+    fn refuse_beyond_limits(&mut self) -> bool {
+        let Some(reason) = self.exceeded_limit() else {
+            return false;
+        };
+        let refused = self.limit_reached.take().map_or(0, |(_, refused)| refused);
+        self.limit_reached = Some((reason, refused + 1));
+        true
+    }
+
+    /// The age of the submission in months, if it is older than configured.
+    fn submission_age_months(&self, skin_msg: &Message) -> Option<u32> {
+        let max_months = self.config.upload.flag_older_than_months;
+        let age_secs = Timestamp::now().unix_timestamp() - skin_msg.timestamp.unix_timestamp();
+        let age_months = u32::try_from(age_secs / SECS_PER_MONTH).unwrap_or(0);
+        (max_months > 0 && age_months >= max_months).then_some(age_months)
+    }
+
+    /// The message listing the errors and the skins that will be uploaded,
+    /// with previews of the cleanup and the generated 256x128 skins.
+    fn summary(&self, apply_cleanup: bool, sd_resampler: SdResampler) -> EditInteractionResponse {
+        let guild_id = self.config.guild_id();
+        let mut new_msg = self.header.clone();
+        if !self.errors.is_empty() {
+            new_msg += "__**Errors**__:\n";
+            self.errors.iter().for_each(|err| {
+                new_msg += "> - ";
+                new_msg += err;
+                new_msg += "\n";
+            });
+        }
+        if let Some((reason, refused)) = &self.limit_reached {
+            new_msg += &format!(
+                "__**No more skins are accepted**__: {reason}, {refused} skins were not queued. Finish this upload and start another one for the rest\n"
+            );
+        }
+        if !self.skins_to_upload.is_empty() {
+            new_msg += "__Skins to upload:__\n";
+            self.skins_to_upload.iter().for_each(|(skin_name, skin)| {
+                let mut add_msg = "> - ".to_string();
+                add_msg += &self.config.emojis.queue_label(skin.database);
+                add_msg += " ";
+                add_msg += "`";
+                add_msg += skin_name;
+                add_msg += "`";
+                if let Some(original_name) = &skin.original_name {
+                    add_msg += &format!(" (transliterated from `{original_name}`)");
+                }
+                if let Some(renamed_from) = &skin.renamed_from {
+                    add_msg += &format!(" (renamed from `{renamed_from}`)");
+                }
+                if let Some(skin_pack) = &skin.skin_pack {
+                    add_msg += &format!(" pack `{skin_pack}`");
+                }
+                add_msg += " by `";
+                add_msg += &skin.author;
+                add_msg += "` license: `";
+                add_msg += &skin.license;
+                if skin.license_unknown {
+                    add_msg += " (not in the allow-list)";
+                }
+                if skin.sd_quality.is_empty() {
+                    add_msg += &format!(
+                        "` (has 256x128 skin: {}, has 512x256 skin: {})",
+                        !skin.file_256x128.is_empty(),
+                        !skin.file_512x256.is_empty()
+                    );
+                } else {
+                    add_msg += "` (256x128 skin generated from 512x256:";
+                    for (resampler, quality) in &skin.sd_quality {
+                        // the selected resampler is shown in bold
+                        let bold = if *resampler == sd_resampler { "**" } else { "" };
+                        add_msg += &format!(
+                            " {bold}{resampler} edges {:.0}% banding {:.0}%{}{bold}",
+                            quality.edge_preservation * 100.0,
+                            quality.banding * 100.0,
+                            if quality.is_poor() { " ⚠️" } else { "" },
+                        );
+                    }
+                    add_msg += ")";
+                }
+                let stray_pixels =
+                    skin.stray_pixels_256x128.len() + skin.stray_pixels_512x256.len();
+                if stray_pixels > 0 {
+                    add_msg += &format!(" - stray pixels: {stray_pixels}");
+                }
+                for (w, h) in [skin.resized_256x128, skin.resized_512x256]
+                    .into_iter()
+                    .flatten()
+                {
+                    add_msg += &format!(" - auto-resized from {w}x{h}");
+                }
+                if !skin.tags.is_empty() {
+                    add_msg += &format!(" - tags: {}", skin.tags.join(", "));
+                }
+                if skin.positive_ratio > 0.0 {
+                    add_msg += &format!(" - positive ratio: {}%", skin.positive_ratio * 100.0);
+                }
+                if skin.below_vote_threshold {
+                    add_msg += " - below the vote threshold, consider the community database";
+                }
+                if let Some(age_months) = skin.age_months {
+                    add_msg += &format!(
+                        " - posted {age_months} months ago, check the license and format against the current rules"
+                    );
+                }
+                if !skin.watermarks_256x128.is_empty() || !skin.watermarks_512x256.is_empty() {
+                    add_msg += " - possible signature or watermark";
+                }
+                for flag in &skin.content_flags {
+                    add_msg += &format!(" - {flag} ⚠️");
+                }
+                if let Some((sd, hd)) = &skin.paired_files {
+                    add_msg += &format!(" - paired `{sd}` + `{hd}` by their names");
+                }
+                for warning in &skin.file_name_warnings {
+                    add_msg += &format!(" - {warning} ⚠️");
+                }
+                for warning in &skin.plugin_warnings {
+                    add_msg += &format!(" - {warning}");
+                }
+                add_msg += &format!(
+                    " https://discord.com/channels/{}/{}/{}",
+                    guild_id, skin.channel_id, skin.original_msg_id
+                );
+                add_msg += "\n";
+                new_msg += &add_msg;
+            });
+        }
+
+        if new_msg.chars().count() >= 2000 {
+            // try a compact view
+            new_msg = self.header.clone();
+            if !self.errors.is_empty() {
+                new_msg += &format!("There are {} errors\n", self.errors.len());
+            }
+            if !self.skins_to_upload.is_empty() {
+                new_msg += "Upload:\n";
+                self.skins_to_upload.iter().for_each(|(skin_name, skin)| {
+                    let mut add_msg = String::new();
+                    add_msg += &self.config.emojis.queue_label(skin.database);
+                    add_msg += " ";
+                    add_msg += "`";
+                    add_msg += skin_name;
+                    add_msg += "`";
+                    if let Some(original_name) = &skin.original_name {
+                        add_msg += &format!(" (transliterated from `{original_name}`)");
+                    }
+                    if skin.renamed_from.is_some() {
+                        add_msg += " (renamed)";
+                    }
+                    add_msg += " by `";
+                    add_msg += &skin.author;
+                    add_msg += "` license: `";
+                    add_msg += &skin.license;
+                    if skin.license_unknown {
+                        add_msg += " (not in the allow-list)";
+                    }
+                    add_msg += "`";
+                    if skin.resized_256x128.is_some() || skin.resized_512x256.is_some() {
+                        add_msg += " (auto-resized)";
+                    }
+                    if skin.below_vote_threshold {
+                        add_msg += " (few votes)";
+                    }
+                    if skin.age_months.is_some() {
+                        add_msg += " (old submission)";
+                    }
+                    if !skin.watermarks_256x128.is_empty() || !skin.watermarks_512x256.is_empty() {
+                        add_msg += " (watermark?)";
+                    }
+                    if !skin.content_flags.is_empty() {
+                        add_msg += " (flagged ⚠️)";
+                    }
+                    if !skin.file_name_warnings.is_empty() {
+                        add_msg += " (file names?)";
+                    }
+                    if !skin.plugin_warnings.is_empty() {
+                        add_msg += " (plugin warnings)";
+                    }
+                    add_msg += "\n";
+                    new_msg += &add_msg;
+                });
+            }
+        }
+        // if still over 2000, simply say how many skins to upload
+        if new_msg.chars().count() >= 2000 {
+            new_msg = self.header.clone();
+            if !self.errors.is_empty() {
+                new_msg += &format!("There are {} errors\n", self.errors.len());
+            }
+            if !self.skins_to_upload.is_empty() {
+                new_msg += &format!("{} skins will be uploaded\n", self.skins_to_upload.len());
+            }
+        }
+        // show what the cleanup would change, so it can be approved
+        let mut edit_response = EditInteractionResponse::new().clear_attachments();
+        let mut attachment_count = 0;
+        let mut attach = |edit_response: EditInteractionResponse, png: &[u8], name: String| {
+            // discord allows at most 10 attachments per message
+            if attachment_count >= 10 {
+                return edit_response;
+            }
+            attachment_count += 1;
+            edit_response.new_attachment(CreateAttachment::bytes(png.to_vec(), name))
+        };
+        let skins = || self.skins_to_upload.iter();
+        let has_stray_pixels = skins().any(|(_, skin)| {
+            !skin.stray_pixels_256x128.is_empty() || !skin.stray_pixels_512x256.is_empty()
+        });
+        for (skin_name, skin) in skins() {
+            for (w, diff) in &skin.previews.cleanup {
+                let name = format!("{skin_name}_{w}x{}_cleanup.png", w / 2);
+                edit_response = attach(edit_response, diff, name);
+            }
+        }
+        let has_watermarks = skins().any(|(_, skin)| {
+            !skin.watermarks_256x128.is_empty() || !skin.watermarks_512x256.is_empty()
+        });
+        for (skin_name, skin) in skins() {
+            for (w, preview) in &skin.previews.watermarks {
+                let name = format!("{skin_name}_{w}x{}_watermark.png", w / 2);
+                edit_response = attach(edit_response, preview, name);
+            }
+        }
+        let has_resized = skins()
+            .any(|(_, skin)| skin.resized_256x128.is_some() || skin.resized_512x256.is_some());
+        for (skin_name, skin) in skins() {
+            for (w, png) in &skin.previews.resized {
+                let name = format!("{skin_name}_{w}x{}_resized.png", w / 2);
+                edit_response = attach(edit_response, png, name);
+            }
+        }
+        let has_generated_sd = skins().any(|(_, skin)| !skin.sd_quality.is_empty());
+        for (skin_name, skin) in skins() {
+            if let Some(preview) = &skin.previews.resamplers {
+                let name = format!("{skin_name}_256x128_resamplers.png");
+                edit_response = attach(edit_response, preview, name);
+            }
+        }
+        if has_generated_sd && new_msg.chars().count() < 1900 {
+            new_msg += &format!(
+                "Generated 256x128 skins use the {} resampler (previews from left to right: lanczos, box, mitchell)\n",
+                sd_resampler
+            );
+        }
+        if has_watermarks && new_msg.chars().count() < 1900 {
+            new_msg += "Possible signatures or watermarks are outlined in red, remove the queue reaction if the database does not allow them\n";
+        }
+        if has_resized && new_msg.chars().count() < 1900 {
+            new_msg +=
+                "Auto-resized skins are attached, if they look bad press `Exclude auto-resized`\n";
+        }
+        if has_stray_pixels && new_msg.chars().count() < 1900 {
+            new_msg += if apply_cleanup {
+                "Stray pixels will be removed (left: before, right: after)\n"
+            } else {
+                "Stray pixels will be kept, press 🧹 to remove them\n"
+            };
+        }
+
+        let mut components = upload_buttons(&self.config);
+        components.extend(self.database_select());
+        edit_response.content(new_msg).components(components)
+    }
+
+    /// The menu to move queued skins to the other database, for the first 25 skins.
+    fn database_select(&self) -> Option<CreateActionRow> {
+        let options: Vec<_> = self
+            .skins_to_upload
+            .iter()
+            .take(MAX_SELECT_OPTIONS)
+            .map(|(skin_name, skin)| {
+                CreateSelectMenuOption::new(skin_name, skin.original_msg_id.to_string())
+                    .description(format!(
+                        "{} -> {}",
+                        skin.database.to_string(),
+                        skin.database.other().to_string()
+                    ))
+            })
+            .collect();
+        if options.is_empty() {
+            return None;
+        }
+        let count = options.len() as u8;
+        Some(CreateActionRow::SelectMenu(
+            CreateSelectMenu::new(
+                SWITCH_DATABASE_SELECT_ID,
+                CreateSelectMenuKind::String { options },
+            )
+            .placeholder("Move skins to the other database")
+            .max_values(count),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::all::UserId;
+
+    use super::{Session, SessionState};
+
+    #[test]
+    fn upload_needs_checked_skins() {
+        let mut session = Session::new(UserId::new(1));
+        session.queue().unwrap();
+        let err = session.start_upload().unwrap_err();
+        assert_eq!(err.from, SessionState::Collecting);
+
+        session.validate().unwrap();
+        session.start_upload().unwrap();
+        // the collected skins are handed over, nothing can change anymore
+        assert!(session.queue().is_err());
+        assert!(session.cancel().is_err());
+        assert!(session.time_out().is_err());
+        session.finish().unwrap();
+        assert_eq!(session.state(), SessionState::Done);
+    }
+
+    #[test]
+    fn approval_freezes_the_skins() {
+        let mut session = Session::new(UserId::new(1));
+        session.validate().unwrap();
+        session.request_approval().unwrap();
+        assert!(session.queue().is_err());
+        assert!(session.start_upload().is_err());
+        assert!(session.time_out().is_err());
+
+        session.reject().unwrap();
+        assert_eq!(session.state(), SessionState::Collecting);
+        assert!(session.request_approval().is_err());
+
+        session.validate().unwrap();
+        session.request_approval().unwrap();
+        session.approve().unwrap();
+        assert_eq!(session.state(), SessionState::Uploading);
+    }
+
+    #[test]
+    fn ended_sessions_stay_ended() {
+        let mut session = Session::new(UserId::new(1));
+        session.cancel().unwrap();
+        assert!(session.validate().is_err());
+        assert!(session.start_upload().is_err());
+        assert!(session.time_out().is_err());
+        assert_eq!(session.state(), SessionState::Cancelled);
+    }
+}
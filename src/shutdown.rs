@@ -0,0 +1,108 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serenity::all::{ShardManager, Timestamp};
+use serenity::prelude::*;
+use tokio::sync::oneshot;
+use tracing::{error, info, warn};
+
+use crate::config::Config;
+use crate::session::SessionEvent;
+use crate::SkinUploads;
+
+/// Resolves once the process is asked to stop, by SIGTERM (e.g. `docker stop`) or ctrl-c.
+async fn stop_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let Ok(mut terminate) = signal(SignalKind::terminate()) else {
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        };
+        tokio::select! {
+            _ = terminate.recv() => {}
+            _ = tokio::signal::ctrl_c() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// Waits for the stop signal, then lets the running upload finish its current skin and
+/// records everything that was not uploaded, before the shards are stopped.
+pub async fn shutdown_on_signal(data: Arc<RwLock<TypeMap>>, shard_manager: Arc<ShardManager>) {
+    stop_signal().await;
+    info!("shutting down");
+    let config = data.read().await.get::<Config>().unwrap().clone();
+
+    // no new sessions or uploads from now on, a running upload stops after its current skin
+    let (sessions, upload_lock) = {
+        let mut data = data.write().await;
+        let skin_uploads = data.get_mut::<SkinUploads>().unwrap();
+        skin_uploads.shutting_down = true;
+        let sessions: Vec<_> = skin_uploads
+            .uploads
+            .iter_mut()
+            .filter_map(|(user_id, item)| {
+                item.session.cancel().ok()?;
+                let (reply, links) = oneshot::channel();
+                item.send(SessionEvent::ShutdownRequested(reply));
+                Some((*user_id, links))
+            })
+            .collect();
+        (sessions, skin_uploads.upload_lock.clone())
+    };
+
+    let mut record = format!("# {}: the bot was stopped\n", Timestamp::now());
+    for (user_id, links) in sessions {
+        let Ok(links) = links.await else {
+            continue;
+        };
+        record += &format!("cancelled upload session of {user_id}:\n");
+        for link in links {
+            record += &format!("- {link}\n");
+        }
+    }
+
+    let timeout = Duration::from_secs(config.upload.shutdown_timeout_secs);
+    match tokio::time::timeout(timeout, upload_lock.lock()).await {
+        Ok(_guard) => {}
+        Err(_) => {
+            warn!("the running upload did not stop within {timeout:?}");
+            record += "an upload was still running, check its skins in the database\n";
+        }
+    }
+
+    let data = data.read().await;
+    for (user_id, failed) in &data.get::<SkinUploads>().unwrap().failed_uploads {
+        record += &format!("skins of {user_id} that were not uploaded:\n");
+        for (skin_name, skin) in &failed.skins {
+            record += &format!(
+                "- `{skin_name}` https://discord.com/channels/{}/{}/{}\n",
+                config.guild_id(),
+                skin.channel_id,
+                skin.original_msg_id
+            );
+        }
+    }
+    drop(data);
+
+    // nothing was interrupted
+    if record.lines().count() == 1 {
+        shard_manager.shutdown_all().await;
+        return;
+    }
+    let path = &config.upload.shutdown_state_file;
+    let written = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(record.as_bytes()));
+    if let Err(err) = written {
+        error!("Could not record the interrupted uploads in {path}: {err}\n{record}");
+    }
+
+    shard_manager.shutdown_all().await;
+}
@@ -0,0 +1,208 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::Cursor;
+use std::path::Path;
+
+use image::{ColorType, ImageFormat, RgbaImage};
+
+use crate::archive::extract_zip;
+use crate::cleanup::remove_stray_pixels;
+use crate::dilate::{dilate_image, is_dilated, DilateOptions};
+use crate::resample::{auto_resize_target, downscale, SdResampler};
+
+/// A skin file that was decoded and brought to a skin size.
+pub struct AnalyzedSkin {
+    pub img: Vec<u8>,
+    pub width: u32,
+    pub stray_pixels: Vec<usize>,
+    pub conversion: Option<String>,
+    pub resized_from: Option<(u32, u32)>,
+    /// of the decoded pixels, so re-encoded copies of an image match as well
+    pub content_hash: u64,
+}
+
+#[derive(Debug)]
+pub enum SkinFileError {
+    Download,
+    Decode,
+    Size,
+}
+
+/// suffixes of file names that mark the 512x256 version of a skin, compared in lower case
+const HD_NAME_SUFFIXES: [&str; 6] = ["_uhd", "-uhd", "_hd", "-hd", "_x2", "@2x"];
+
+/// The skin a file name belongs to and whether it marks the 512x256 version,
+/// e.g. `greyfox_uhd.png` is the 512x256 version of `greyfox`.
+fn skin_file_role(filename: &str) -> (String, bool) {
+    let stem = Path::new(filename)
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().to_lowercase());
+    match HD_NAME_SUFFIXES
+        .iter()
+        .find_map(|suffix| stem.strip_suffix(suffix))
+    {
+        Some(base) => (base.to_string(), true),
+        None => (stem, false),
+    }
+}
+
+/// The width the name of each file claims: names with a 512x256 suffix claim 512,
+/// plain names only if a file with the same name and the suffix is among them.
+pub fn claimed_widths(filenames: &[&str]) -> Vec<Option<u32>> {
+    let roles: Vec<_> = filenames.iter().map(|name| skin_file_role(name)).collect();
+    roles
+        .iter()
+        .map(|(base, hd)| {
+            if *hd {
+                Some(512)
+            } else if roles.iter().any(|(other, hd)| *hd && other == base) {
+                Some(256)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// The skins of a zip archive with their file names, other files like the 0.7 skin parts
+/// are matched by their dimensions and skipped.
+pub fn zip_skin_files(file: &[u8]) -> Vec<anyhow::Result<(String, Vec<u8>)>> {
+    let entries = match extract_zip(file) {
+        Ok(entries) => entries,
+        Err(err) => return vec![Err(err)],
+    };
+    let skins: Vec<_> = entries
+        .into_iter()
+        .filter(|(name, _)| !name.starts_with("__MACOSX/"))
+        .filter_map(|(name, content)| {
+            let (w, h) = image::io::Reader::new(Cursor::new(&content))
+                .with_guessed_format()
+                .ok()?
+                .into_dimensions()
+                .ok()?;
+            (w == h * 2).then_some(Ok((name, content)))
+        })
+        .collect();
+    if skins.is_empty() {
+        vec![Err(anyhow::anyhow!("the zip file did not contain a skin"))]
+    } else {
+        skins
+    }
+}
+
+/// Decodes a skin of any supported format (png, webp, jpeg, bmp, ...) to rgba8.
+/// Returns the original format, if it had to be converted.
+pub fn decode_skin_image(file: &[u8]) -> image::ImageResult<(RgbaImage, Option<String>)> {
+    let format = image::guess_format(file)?;
+    let img = image::load_from_memory_with_format(file, format)?;
+    let conversion = (format != ImageFormat::Png || img.color() != ColorType::Rgba8)
+        .then(|| format!("{format:?} {:?}", img.color()));
+    Ok((img.into_rgba8(), conversion))
+}
+
+/// Decodes the file and checks it, the cpu heavy part of collecting a skin.
+/// The bot runs it on the blocking pool, never on the async runtime.
+pub fn analyze_skin_file(file: &[u8], auto_resize: bool) -> Result<AnalyzedSkin, SkinFileError> {
+    let (img_rgba, conversion) = decode_skin_image(file).map_err(|_| SkinFileError::Decode)?;
+    let (mut w, mut h) = img_rgba.dimensions();
+    let mut img = img_rgba.into_raw();
+    let mut resized_from = None;
+    if (w, h) != (256, 128) && (w, h) != (512, 256) {
+        let (skin_w, skin_h, factor) = auto_resize_target(w, h)
+            .filter(|_| auto_resize)
+            .ok_or(SkinFileError::Size)?;
+        img = downscale(
+            &img,
+            w as usize,
+            h as usize,
+            factor as usize,
+            SdResampler::Lanczos,
+        );
+        resized_from = Some((w, h));
+        (w, h) = (skin_w, skin_h);
+    }
+    let stray_pixels = remove_stray_pixels(&mut img.clone(), w as usize, h as usize, 4);
+    let mut hasher = DefaultHasher::new();
+    (w, &img).hash(&mut hasher);
+    Ok(AnalyzedSkin {
+        img,
+        width: w,
+        stray_pixels,
+        conversion,
+        resized_from,
+        content_hash: hasher.finish(),
+    })
+}
+
+/// Encodes the skin like it is uploaded: optionally without stray pixels and dilated,
+/// unless it already was.
+pub fn processed_png(
+    mut img: Vec<u8>,
+    w: usize,
+    h: usize,
+    apply_cleanup: bool,
+    dilate_options: &DilateOptions,
+) -> Vec<u8> {
+    let already_dilated = is_dilated(&img, w, h, 4, dilate_options.alpha_threshold);
+    if apply_cleanup {
+        remove_stray_pixels(&mut img, w, h, 4);
+    }
+    if !already_dilated {
+        dilate_image(&mut img, w, h, 4, dilate_options);
+    }
+    let mut png = Vec::new();
+    image::write_buffer_with_format(
+        &mut Cursor::new(&mut png),
+        &img,
+        w as u32,
+        h as u32,
+        ColorType::Rgba8,
+        ImageFormat::Png,
+    )
+    .unwrap();
+    png
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{ImageFormat, RgbaImage};
+
+    use super::{analyze_skin_file, claimed_widths, SkinFileError};
+
+    #[test]
+    fn pairs_files_by_name() {
+        assert_eq!(
+            claimed_widths(&["Greyfox.png", "greyfox_UHD.png", "preview.png"]),
+            vec![Some(256), Some(512), None]
+        );
+        assert_eq!(
+            claimed_widths(&["skins/cat.png", "skins/cat@2x.png"]),
+            vec![Some(256), Some(512)]
+        );
+        // without the other half, only the 512x256 suffix says something
+        assert_eq!(
+            claimed_widths(&["cat.png", "dog_x2.png"]),
+            vec![None, Some(512)]
+        );
+    }
+
+    #[test]
+    fn oversized_skins_need_auto_resize() {
+        let mut png = Vec::new();
+        RgbaImage::new(1024, 512)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        assert!(matches!(
+            analyze_skin_file(&png, false),
+            Err(SkinFileError::Size)
+        ));
+        let skin = analyze_skin_file(&png, true).ok().unwrap();
+        assert_eq!(skin.resized_from, Some((1024, 512)));
+        assert_eq!(
+            skin.img.len(),
+            skin.width as usize * skin.width as usize / 2 * 4
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+
+use serenity::all::Timestamp;
+
+use crate::batches::BatchRecord;
+use crate::naming::name_key;
+
+/// how many authors `/skin_stats` lists
+const TOP_AUTHORS: usize = 5;
+
+/// The uploads of a period, from the batch history. Undone batches and failed skins don't count.
+pub struct UploadStats {
+    pub batches: usize,
+    pub normal: usize,
+    pub community: usize,
+    /// the most skins first, authors are compared case-insensitively
+    pub top_authors: Vec<(String, usize)>,
+    /// of the skins the votes were recorded of
+    pub average_ratio: Option<f64>,
+    /// the date and how many skins were uploaded that day
+    pub busiest_day: Option<(String, usize)>,
+}
+
+/// The first second of the month, like `2024-05`, and of the month after it.
+pub fn month_period(month: &str) -> Option<(i64, i64)> {
+    let (year, month) = month.trim().split_once('-')?;
+    let (year, month): (i32, u32) = (year.parse().ok()?, month.parse().ok()?);
+    if !(1..=12).contains(&month) {
+        return None;
+    }
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    let start = |year: i32, month: u32| {
+        Timestamp::parse(&format!("{year:04}-{month:02}-01T00:00:00Z"))
+            .ok()
+            .map(|at| at.unix_timestamp())
+    };
+    Some((start(year, month)?, start(next_year, next_month)?))
+}
+
+/// `2024-05-01`
+fn date_of(unix_timestamp: i64) -> String {
+    Timestamp::from_unix_timestamp(unix_timestamp)
+        .map(|at| at.to_string().chars().take(10).collect())
+        .unwrap_or_default()
+}
+
+fn batches_in(
+    batches: &[BatchRecord],
+    since: i64,
+    until: i64,
+) -> impl Iterator<Item = &BatchRecord> {
+    batches.iter().filter(move |batch| {
+        batch.undone_at.is_none() && (since..until).contains(&batch.finished_at)
+    })
+}
+
+pub fn upload_stats(batches: &[BatchRecord], since: i64, until: i64) -> UploadStats {
+    let mut stats = UploadStats {
+        batches: 0,
+        normal: 0,
+        community: 0,
+        top_authors: Vec::new(),
+        average_ratio: None,
+        busiest_day: None,
+    };
+    let mut authors: HashMap<String, (String, usize)> = HashMap::new();
+    let mut days: HashMap<String, usize> = HashMap::new();
+    let mut ratios = Vec::new();
+    for batch in batches_in(batches, since, until) {
+        let uploaded: Vec<_> = batch.skins.iter().filter(|skin| skin.uploaded).collect();
+        if uploaded.is_empty() {
+            continue;
+        }
+        stats.batches += 1;
+        *days.entry(date_of(batch.finished_at)).or_default() += uploaded.len();
+        for skin in uploaded {
+            if skin.database == "community" {
+                stats.community += 1;
+            } else {
+                stats.normal += 1;
+            }
+            if let Some(author) = skin.author.as_deref().map(str::trim) {
+                authors
+                    .entry(name_key(author))
+                    .or_insert_with(|| (author.to_string(), 0))
+                    .1 += 1;
+            }
+            ratios.extend(skin.positive_ratio);
+        }
+    }
+
+    let mut top_authors: Vec<_> = authors.into_values().collect();
+    top_authors.sort_by(|(a_name, a_count), (b_name, b_count)| {
+        b_count.cmp(a_count).then_with(|| a_name.cmp(b_name))
+    });
+    top_authors.truncate(TOP_AUTHORS);
+    stats.top_authors = top_authors;
+    if !ratios.is_empty() {
+        stats.average_ratio = Some(ratios.iter().sum::<f64>() / ratios.len() as f64);
+    }
+    // the earliest of equally busy days
+    stats.busiest_day = days
+        .into_iter()
+        .max_by(|(a_day, a_count), (b_day, b_count)| a_count.cmp(b_count).then(b_day.cmp(a_day)));
+    stats
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains(&[',', '"', '\n'][..]) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// One line per uploaded skin of the period, in the columns `/batches import` reads.
+pub fn stats_csv(batches: &[BatchRecord], since: i64, until: i64) -> String {
+    let mut csv =
+        "finished_at,label,uploader,name,database,author,positive_ratio,submission\n".to_string();
+    for batch in batches_in(batches, since, until) {
+        let finished_at = Timestamp::from_unix_timestamp(batch.finished_at)
+            .map_or_else(|_| batch.finished_at.to_string(), |at| at.to_string());
+        for skin in batch.skins.iter().filter(|skin| skin.uploaded) {
+            let fields = [
+                finished_at.clone(),
+                csv_field(&batch.label),
+                batch.uploader.to_string(),
+                csv_field(&skin.name),
+                skin.database.clone(),
+                csv_field(skin.author.as_deref().unwrap_or_default()),
+                skin.positive_ratio
+                    .map_or_else(String::new, |ratio| format!("{ratio:.3}")),
+                csv_field(&skin.submission),
+            ];
+            csv += &fields.join(",");
+            csv += "\n";
+        }
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{month_period, stats_csv, upload_stats};
+    use crate::backfill::batches_from_csv;
+    use crate::batches::{BatchRecord, BatchSkin};
+
+    fn skin(name: &str, database: &str, author: &str, ratio: Option<f64>) -> BatchSkin {
+        BatchSkin {
+            name: name.to_string(),
+            database: database.to_string(),
+            uploaded: true,
+            error: None,
+            submission: String::new(),
+            replaced: false,
+            author: Some(author.to_string()),
+            positive_ratio: ratio,
+            original_name: None,
+            uploaded_sizes: Vec::new(),
+        }
+    }
+
+    fn batch(label: &str, finished_at: i64, skins: Vec<BatchSkin>) -> BatchRecord {
+        BatchRecord {
+            label: label.to_string(),
+            uploader: 1,
+            finished_at,
+            skins,
+            announcements: Vec::new(),
+            undone_at: None,
+            preview_renderer: None,
+        }
+    }
+
+    #[test]
+    fn months() {
+        let (since, until) = month_period("2024-12").unwrap();
+        assert_eq!(until - since, 31 * 24 * 60 * 60);
+        assert_eq!(
+            month_period("2024-02").unwrap().1 - month_period("2024-02").unwrap().0,
+            29 * 24 * 60 * 60
+        );
+        assert!(month_period("2024-13").is_none());
+        assert!(month_period("may").is_none());
+    }
+
+    #[test]
+    fn counts_the_uploads_of_the_period() {
+        let (since, until) = month_period("2024-05").unwrap();
+        let day = 24 * 60 * 60;
+        let mut failed = skin("broken", "normal", "Ninjed", None);
+        failed.uploaded = false;
+        let mut undone = batch("undone", since, vec![skin("x", "normal", "Ninjed", None)]);
+        undone.undone_at = Some(since + 1);
+        let batches = [
+            batch(
+                "april",
+                since - 1,
+                vec![skin("old", "normal", "Ninjed", None)],
+            ),
+            batch(
+                "first",
+                since + day,
+                vec![
+                    skin("greyfox", "normal", "Ninjed", Some(0.5)),
+                    skin("cat", "community", "ninjed ", Some(1.0)),
+                    failed,
+                ],
+            ),
+            batch(
+                "second",
+                since + 2 * day,
+                vec![skin("dog, too", "community", "Pinky", None)],
+            ),
+            undone,
+            batch("june", until, vec![skin("new", "normal", "Pinky", None)]),
+        ];
+
+        let stats = upload_stats(&batches, since, until);
+        assert_eq!(stats.batches, 2);
+        assert_eq!((stats.normal, stats.community), (1, 2));
+        assert_eq!(
+            stats.top_authors,
+            [("Ninjed".to_string(), 2), ("Pinky".to_string(), 1)]
+        );
+        assert_eq!(stats.average_ratio, Some(0.75));
+        assert_eq!(stats.busiest_day, Some(("2024-05-02".to_string(), 2)));
+
+        // the export can be imported again
+        let csv = stats_csv(&batches, since, until);
+        let imported = batches_from_csv(&csv).unwrap();
+        assert_eq!(imported.len(), 2);
+        assert_eq!(imported[1].skins[0].name, "dog, too");
+        assert_eq!(imported[0].skins[1].author.as_deref(), Some("ninjed"));
+    }
+}
@@ -0,0 +1,131 @@
+use std::io::Cursor;
+
+use image::{ColorType, ImageFormat};
+
+const BPP: usize = 4;
+/// the side of the rendered tee
+pub const TEE_SIZE: usize = 128;
+/// raised whenever the tee is drawn differently, `/batches rerender` draws the previews of
+/// older batches again
+pub const TEE_RENDERER_VERSION: u32 = 1;
+
+/// A rectangle of a 256x128 skin or of the rendered tee.
+#[derive(Clone, Copy)]
+struct Rect {
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+}
+
+const fn rect(x: usize, y: usize, w: usize, h: usize) -> Rect {
+    Rect { x, y, w, h }
+}
+
+const BODY: Rect = rect(0, 0, 96, 96);
+const BODY_SHADOW: Rect = rect(96, 0, 96, 96);
+const FOOT: Rect = rect(192, 32, 64, 32);
+const FOOT_SHADOW: Rect = rect(192, 64, 64, 32);
+const EYE: Rect = rect(64, 96, 32, 32);
+
+/// where the parts are drawn, like the client draws a standing tee looking ahead
+const BODY_POS: Rect = rect(16, 10, 96, 96);
+const BACK_FOOT_POS: Rect = rect(30, 67, 48, 24);
+const FRONT_FOOT_POS: Rect = rect(50, 67, 48, 24);
+const LEFT_EYE_POS: Rect = rect(38, 33, 38, 38);
+const RIGHT_EYE_POS: Rect = rect(50, 33, 38, 38);
+
+/// Draws a part of the skin over the tee, scaled with the nearest pixel.
+fn draw(tee: &mut [u8], skin: &[u8], w: usize, src: Rect, dst: Rect, mirror: bool) {
+    let scale = w / 256;
+    for dy in 0..dst.h {
+        for dx in 0..dst.w {
+            let sx = if mirror { dst.w - 1 - dx } else { dx };
+            let x = (src.x + sx * src.w / dst.w) * scale;
+            let y = (src.y + dy * src.h / dst.h) * scale;
+            let from = &skin[(y * w + x) * BPP..(y * w + x + 1) * BPP];
+            let to_index = ((dst.y + dy) * TEE_SIZE + dst.x + dx) * BPP;
+            let to = &mut tee[to_index..to_index + BPP];
+
+            // alpha blending, the part is drawn over what is already there
+            let src_a = from[3] as f32 / 255.0;
+            let dst_a = to[3] as f32 / 255.0;
+            let out_a = src_a + dst_a * (1.0 - src_a);
+            if out_a == 0.0 {
+                continue;
+            }
+            for c in 0..3 {
+                let color = from[c] as f32 * src_a + to[c] as f32 * dst_a * (1.0 - src_a);
+                to[c] = (color / out_a).round() as u8;
+            }
+            to[3] = (out_a * 255.0).round() as u8;
+        }
+    }
+}
+
+fn render_tee_rgba(skin: &[u8], w: usize) -> Vec<u8> {
+    let mut tee = vec![0; TEE_SIZE * TEE_SIZE * BPP];
+    // the outlines of all parts are below the colored parts
+    draw(&mut tee, skin, w, FOOT_SHADOW, BACK_FOOT_POS, false);
+    draw(&mut tee, skin, w, BODY_SHADOW, BODY_POS, false);
+    draw(&mut tee, skin, w, FOOT_SHADOW, FRONT_FOOT_POS, false);
+    draw(&mut tee, skin, w, FOOT, BACK_FOOT_POS, false);
+    draw(&mut tee, skin, w, BODY, BODY_POS, false);
+    draw(&mut tee, skin, w, FOOT, FRONT_FOOT_POS, false);
+    draw(&mut tee, skin, w, EYE, LEFT_EYE_POS, false);
+    draw(&mut tee, skin, w, EYE, RIGHT_EYE_POS, true);
+    tee
+}
+
+/// The skin as the client shows a standing tee, a `TEE_SIZE` square png.
+/// `w` is the width of the rgba skin, 256 or 512.
+pub fn render_tee(skin: &[u8], w: usize) -> anyhow::Result<Vec<u8>> {
+    let tee = render_tee_rgba(skin, w);
+    let mut png = Vec::new();
+    image::write_buffer_with_format(
+        &mut Cursor::new(&mut png),
+        &tee,
+        TEE_SIZE as u32,
+        TEE_SIZE as u32,
+        ColorType::Rgba8,
+        ImageFormat::Png,
+    )?;
+    Ok(png)
+}
+
+/// Draws the tee of a 256x128 or 512x256 skin png again, e.g. for an announced skin.
+pub fn render_tee_png(file: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let img = image::load_from_memory(file)?.into_rgba8();
+    let (w, h) = img.dimensions();
+    if (w, h) != (256, 128) && (w, h) != (512, 256) {
+        anyhow::bail!("a {w}x{h} image is not a skin");
+    }
+    render_tee(img.as_raw(), w as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_tee_rgba, BPP, TEE_SIZE};
+
+    #[test]
+    fn draws_the_body_over_its_outline() {
+        let (w, h) = (512, 256);
+        let mut skin = vec![0; w * h * BPP];
+        // an opaque red body and a black outline, both covering their whole cell
+        for y in 0..192 {
+            for x in 0..384 {
+                let color = if x < 192 {
+                    [255, 0, 0, 255]
+                } else {
+                    [0, 0, 0, 255]
+                };
+                skin[(y * w + x) * BPP..(y * w + x + 1) * BPP].copy_from_slice(&color);
+            }
+        }
+        let tee = render_tee_rgba(&skin, w);
+        let pixel =
+            |x: usize, y: usize| &tee[(y * TEE_SIZE + x) * BPP..(y * TEE_SIZE + x + 1) * BPP];
+        assert_eq!(pixel(64, 58), [255, 0, 0, 255]);
+        assert_eq!(pixel(2, 2), [0, 0, 0, 0]);
+    }
+}
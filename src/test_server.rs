@@ -0,0 +1,61 @@
+use anyhow::Context as _;
+use reqwest::multipart::{Form, Part};
+use reqwest::{header, Client, RequestBuilder};
+use serde::Serialize;
+
+use crate::config::TestServerConfig;
+
+#[derive(Serialize)]
+struct RconRequest<'a> {
+    commands: &'a [String],
+}
+
+fn authorized(request: RequestBuilder, config: &TestServerConfig) -> RequestBuilder {
+    match &config.token {
+        Some(token) => request.bearer_auth(token),
+        None => request,
+    }
+}
+
+/// Hands the exported skins to the test server and lets it reload them,
+/// so the reviewers can see them in game right away.
+/// Returns false, if no test server is configured.
+pub async fn notify_test_server(
+    config: &TestServerConfig,
+    zip: Vec<u8>,
+    skin_names: &[String],
+) -> anyhow::Result<bool> {
+    if config.webhook_url.is_none() && config.rcon_url.is_none() {
+        return Ok(false);
+    }
+    let client = Client::new();
+
+    if let Some(webhook_url) = &config.webhook_url {
+        let form = Form::new().text("names", skin_names.join("\n")).part(
+            "skins",
+            Part::bytes(zip)
+                .file_name("skins.zip")
+                .mime_str("application/zip")?,
+        );
+        authorized(client.post(webhook_url), config)
+            .multipart(form)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .context("the webhook failed")?;
+    }
+
+    if let Some(rcon_url) = &config.rcon_url {
+        let body = serde_json::to_vec(&RconRequest {
+            commands: &config.rcon_commands,
+        })?;
+        authorized(client.post(rcon_url), config)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .context("the rcon commands failed")?;
+    }
+    Ok(true)
+}
@@ -0,0 +1,261 @@
+const HIRAGANA_START: u32 = 0x3041;
+const KATAKANA_START: u32 = 0x30A1;
+/// romaji of ぁ (U+3041) to ゖ (U+3096), katakana use the same order
+const KANA: [&str; 86] = [
+    "a", "a", "i", "i", "u", "u", "e", "e", "o", "o", "ka", "ga", "ki", "gi", "ku", "gu", "ke",
+    "ge", "ko", "go", "sa", "za", "shi", "ji", "su", "zu", "se", "ze", "so", "zo", "ta", "da",
+    "chi", "ji", "", "tsu", "zu", "te", "de", "to", "do", "na", "ni", "nu", "ne", "no", "ha", "ba",
+    "pa", "hi", "bi", "pi", "fu", "bu", "pu", "he", "be", "pe", "ho", "bo", "po", "ma", "mi", "mu",
+    "me", "mo", "ya", "ya", "yu", "yu", "yo", "yo", "ra", "ri", "ru", "re", "ro", "wa", "wa", "wi",
+    "we", "wo", "n", "vu", "ka", "ke",
+];
+const KANA_SMALL_TSU: u32 = 0x22;
+const KANA_SMALL_YA: u32 = 0x42;
+const KANA_SMALL_YU: u32 = 0x44;
+const KANA_SMALL_YO: u32 = 0x46;
+
+const HANGUL_START: u32 = 0xAC00;
+const HANGUL_END: u32 = 0xD7A3;
+const HANGUL_INITIALS: [&str; 19] = [
+    "g", "kk", "n", "d", "tt", "r", "m", "b", "pp", "s", "ss", "", "j", "jj", "ch", "k", "t", "p",
+    "h",
+];
+const HANGUL_MEDIALS: [&str; 21] = [
+    "a", "ae", "ya", "yae", "eo", "e", "yeo", "ye", "o", "wa", "wae", "oe", "yo", "u", "wo", "we",
+    "wi", "yu", "eu", "ui", "i",
+];
+const HANGUL_FINALS: [&str; 28] = [
+    "", "k", "k", "k", "n", "n", "n", "t", "l", "k", "m", "l", "l", "l", "p", "l", "m", "p", "p",
+    "t", "t", "ng", "t", "t", "k", "t", "p", "t",
+];
+
+fn transliterate_lowercase(c: char) -> Option<&'static str> {
+    Some(match c {
+        // latin
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'ď' | 'đ' | 'ð' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'ĥ' | 'ħ' => "h",
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'ĵ' => "j",
+        'ķ' => "k",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'œ' => "oe",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'ś' | 'ŝ' | 'ş' | 'š' => "s",
+        'ß' => "ss",
+        'ţ' | 'ť' | 'ŧ' => "t",
+        'þ' => "th",
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'ŵ' => "w",
+        'ý' | 'ÿ' | 'ŷ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        // cyrillic
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' | 'ґ' => "g",
+        'д' => "d",
+        'е' | 'э' => "e",
+        'ё' => "yo",
+        'є' => "ye",
+        'ж' => "zh",
+        'з' => "z",
+        'и' | 'і' => "i",
+        'ї' => "yi",
+        'й' | 'ы' => "y",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' | 'ў' => "u",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "ts",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' | 'ь' => "",
+        'ю' => "yu",
+        'я' => "ya",
+        // greek
+        'α' | 'ά' => "a",
+        'β' => "v",
+        'γ' => "g",
+        'δ' => "d",
+        'ε' | 'έ' => "e",
+        'ζ' => "z",
+        'η' | 'ή' | 'ι' | 'ί' | 'ϊ' | 'ΐ' => "i",
+        'θ' => "th",
+        'κ' => "k",
+        'λ' => "l",
+        'μ' => "m",
+        'ν' => "n",
+        'ξ' => "x",
+        'ο' | 'ό' | 'ω' | 'ώ' => "o",
+        'π' => "p",
+        'ρ' => "r",
+        'σ' | 'ς' => "s",
+        'τ' => "t",
+        'υ' | 'ύ' | 'ϋ' | 'ΰ' => "y",
+        'φ' => "f",
+        'χ' => "ch",
+        'ψ' => "ps",
+        _ => return None,
+    })
+}
+
+fn transliterate_hangul(c: char) -> Option<String> {
+    let code = c as u32;
+    if !(HANGUL_START..=HANGUL_END).contains(&code) {
+        return None;
+    }
+    let index = (code - HANGUL_START) as usize;
+    Some(
+        HANGUL_INITIALS[index / (21 * 28)].to_string()
+            + HANGUL_MEDIALS[(index / 28) % 21]
+            + HANGUL_FINALS[index % 28],
+    )
+}
+
+/// Index into [`KANA`] for hiragana and katakana.
+fn kana_index(c: char) -> Option<u32> {
+    let code = c as u32;
+    [HIRAGANA_START, KATAKANA_START]
+        .into_iter()
+        .find(|start| (*start..*start + KANA.len() as u32).contains(&code))
+        .map(|start| code - start)
+}
+
+/// Returns the ascii version of the name, or the characters that could not be transliterated.
+/// Latin with diacritics, cyrillic, greek, hangul and kana are supported,
+/// chinese characters would need a dictionary.
+pub fn transliterate(name: &str) -> Result<String, String> {
+    let mut result = String::new();
+    let mut unknown = String::new();
+    // small tsu doubles the following consonant
+    let mut double_next = false;
+    for c in name.chars() {
+        let romaji = if c.is_ascii() {
+            c.to_string()
+        } else if let Some(index) = kana_index(c) {
+            match index {
+                KANA_SMALL_TSU => {
+                    double_next = true;
+                    continue;
+                }
+                KANA_SMALL_YA | KANA_SMALL_YU | KANA_SMALL_YO if result.ends_with('i') => {
+                    // き + ゃ = kya, し + ゃ = sha
+                    result.pop();
+                    let vowel = &KANA[index as usize][1..];
+                    if result.ends_with("sh") || result.ends_with("ch") || result.ends_with('j') {
+                        result += vowel;
+                    } else {
+                        result += "y";
+                        result += vowel;
+                    }
+                    continue;
+                }
+                _ => KANA[index as usize].to_string(),
+            }
+        } else if c == 'ー' {
+            // long vowel mark
+            continue;
+        } else if c == '\u{3000}' {
+            " ".to_string()
+        } else if ('\u{FF01}'..='\u{FF5E}').contains(&c) {
+            // full width ascii
+            char::from_u32(c as u32 - 0xFEE0).unwrap().to_string()
+        } else if let Some(romanized) = transliterate_hangul(c) {
+            romanized
+        } else if let Some(lowercase) = c.to_lowercase().next().and_then(transliterate_lowercase) {
+            if c.is_uppercase() {
+                let mut chars = lowercase.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                    .unwrap_or_default()
+            } else {
+                lowercase.to_string()
+            }
+        } else {
+            unknown.push(c);
+            continue;
+        };
+
+        if double_next {
+            // っち = tchi, like in hepburn
+            if romaji.starts_with("ch") {
+                result.push('t');
+            } else if let Some(first) = romaji
+                .chars()
+                .next()
+                .filter(|c| c.is_ascii_alphabetic() && !"aeiou".contains(*c))
+            {
+                result.push(first);
+            }
+            double_next = false;
+        }
+        result += &romaji;
+    }
+
+    if unknown.is_empty() {
+        Ok(result)
+    } else {
+        Err(unknown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::transliterate;
+
+    #[test]
+    fn transliterates_kana() {
+        assert_eq!(transliterate("さくら").unwrap(), "sakura");
+        assert_eq!(transliterate("カタカナ").unwrap(), "katakana");
+        assert_eq!(transliterate("きゃしょじゃ").unwrap(), "kyashoja");
+        // small tsu doubles the consonant
+        assert_eq!(transliterate("きって").unwrap(), "kitte");
+        assert_eq!(transliterate("まっちゃ").unwrap(), "matcha");
+        assert_eq!(transliterate("ぼっちゃん").unwrap(), "botchan");
+        // the long vowel mark is dropped
+        assert_eq!(transliterate("ラーメン").unwrap(), "ramen");
+        assert_eq!(transliterate("スーパー").unwrap(), "supa");
+    }
+
+    #[test]
+    fn transliterates_hangul() {
+        assert_eq!(transliterate("한글").unwrap(), "hangeul");
+        assert_eq!(transliterate("서울").unwrap(), "seoul");
+    }
+
+    #[test]
+    fn transliterates_cyrillic() {
+        assert_eq!(transliterate("Привет").unwrap(), "Privet");
+        assert_eq!(transliterate("Щука").unwrap(), "Shchuka");
+        assert_eq!(transliterate("ёж").unwrap(), "yozh");
+    }
+
+    #[test]
+    fn transliterates_greek() {
+        assert_eq!(transliterate("Αθήνα").unwrap(), "Athina");
+        assert_eq!(transliterate("ψυχή").unwrap(), "psychi");
+    }
+
+    #[test]
+    fn reports_unknown_characters() {
+        assert_eq!(transliterate("tee 漢字").unwrap_err(), "漢字");
+        assert_eq!(transliterate("Ｔｅｅ　１").unwrap(), "Tee 1");
+    }
+}
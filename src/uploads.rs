@@ -0,0 +1,221 @@
+//! The state of the running uploads that the handlers and the upload sessions share.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use hashlink::LinkedHashMap;
+use serenity::all::{
+    ChannelId, CreateActionRow, CreateButton, Message, MessageId, ReactionType, UserId,
+};
+use serenity::prelude::*;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::archive::is_zip;
+use crate::config::Config;
+use crate::metadata::is_sidecar;
+use crate::resample::{SdQuality, SdResampler};
+use crate::safety::{ContentFlag, DefaultSkins};
+use crate::session::{Session, SessionEvent, SkinPreviews};
+use crate::skin::zip_skin_files;
+use crate::watermark::WatermarkRegion;
+
+/// The id of the button below the status message of an upload.
+pub const RESYNC_BUTTON_ID: &str = "upload_resync";
+
+/// Downloads the attachments and embed images of a submission with their file names,
+/// zip archives are extracted and metadata sidecars skipped.
+pub async fn download_submission_files(msg: &Message) -> Vec<anyhow::Result<(String, Vec<u8>)>> {
+    let mut files = Vec::new();
+    for attachment in msg
+        .attachments
+        .iter()
+        .filter(|attachment| !is_sidecar(&attachment.filename))
+    {
+        match attachment.download().await {
+            Ok(file) if is_zip(&file) => files.extend(zip_skin_files(&file)),
+            res => files.push(
+                res.map(|file| (attachment.filename.clone(), file))
+                    .map_err(anyhow::Error::from),
+            ),
+        }
+    }
+    for embed in &msg.embeds {
+        if let Some(image) = &embed.image {
+            let download = async {
+                let file = reqwest::get(&image.url)
+                    .await?
+                    .error_for_status()?
+                    .bytes()
+                    .await?
+                    .to_vec();
+                // the last segment of the url, without the query
+                let name = image
+                    .url
+                    .split('?')
+                    .next()
+                    .and_then(|url| url.rsplit('/').next())
+                    .unwrap_or_default()
+                    .to_string();
+                Ok((name, file))
+            };
+            files.push(download.await);
+        }
+    }
+    files
+}
+
+/// The buttons below the status message of an upload.
+pub fn upload_buttons(config: &Config) -> Vec<CreateActionRow> {
+    let mut buttons = vec![
+        CreateButton::new("ok").emoji(ReactionType::Unicode("🆗".to_string())),
+        CreateButton::new("cancel").emoji(ReactionType::Unicode("🇽".to_string())),
+        CreateButton::new("cleanup").emoji(ReactionType::Unicode("🧹".to_string())),
+        CreateButton::new(RESYNC_BUTTON_ID).emoji(ReactionType::Unicode("🔄".to_string())),
+    ];
+    buttons.extend(SdResampler::ALL.iter().map(|resampler| {
+        CreateButton::new(resampler.button_id()).label(format!("256x128: {resampler}"))
+    }));
+    if config.upload.auto_resize {
+        buttons.push(CreateButton::new("exclude_resized").label("Exclude auto-resized"));
+    }
+    // discord allows 5 buttons per row
+    buttons
+        .chunks(5)
+        .map(|row| CreateActionRow::Buttons(row.to_vec()))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkinToUploadDB {
+    Normal,
+    Community,
+}
+
+impl SkinToUploadDB {
+    pub const fn other(self) -> Self {
+        match self {
+            Self::Normal => Self::Community,
+            Self::Community => Self::Normal,
+        }
+    }
+}
+
+impl ToString for SkinToUploadDB {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Normal => "normal".to_string(),
+            Self::Community => "community".to_string(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SkinToUpload {
+    pub author: String,
+    pub license: String,
+    pub file_256x128: Vec<u8>,
+    pub file_512x256: Vec<u8>,
+    pub database: SkinToUploadDB,
+    pub original_msg_id: MessageId,
+    /// the channel or thread of the original message
+    pub channel_id: ChannelId,
+    pub license_unknown: bool,
+    /// the name as written by the artist, if it had to be transliterated
+    pub original_name: Option<String>,
+    /// the name before characters the client or the database don't accept were replaced
+    pub renamed_from: Option<String>,
+    /// what the validation plugins noticed, without rejecting the skin
+    pub plugin_warnings: Vec<String>,
+    /// from the sidecar of the submission
+    pub skin_pack: Option<String>,
+    pub tags: Vec<String>,
+    pub stray_pixels_256x128: Vec<usize>,
+    pub stray_pixels_512x256: Vec<usize>,
+    /// blank, recolored default or low-effort skins, only checked if the content checks are enabled
+    pub content_flags: Vec<ContentFlag>,
+    /// the file names of the 256x128 and 512x256 version, if they were paired by their names
+    pub paired_files: Option<(String, String)>,
+    /// files whose name claims another size than they have
+    pub file_name_warnings: Vec<String>,
+    /// text-like regions outside the tee, only searched if the database has a watermark policy
+    pub watermarks_256x128: Vec<WatermarkRegion>,
+    pub watermarks_512x256: Vec<WatermarkRegion>,
+    /// the original format of the posted files, if they were not rgba8 pngs
+    pub conversion_256x128: Option<String>,
+    pub conversion_512x256: Option<String>,
+    /// the size of the posted files, if they were shrunk to the skin size
+    pub resized_256x128: Option<(u32, u32)>,
+    pub resized_512x256: Option<(u32, u32)>,
+    /// of the decoded pixels, to find the same image in other messages of the batch
+    pub content_hash_256x128: Option<u64>,
+    pub content_hash_512x256: Option<u64>,
+    /// only filled if the 256x128 skin has to be generated
+    pub sd_quality: Vec<(SdResampler, SdQuality)>,
+    pub previews: SkinPreviews,
+    pub positive_ratio: f64,
+    /// queued for the normal database without enough community votes
+    pub below_vote_threshold: bool,
+    /// the age of the submission, if it is older than configured
+    pub age_months: Option<u32>,
+}
+
+/// The part of an upload session the handlers share, the skins are collected by its task.
+pub struct SkinUploadItem {
+    pub events: mpsc::UnboundedSender<SessionEvent>,
+    pub session: Session,
+    /// the queue reactions of the moderator, a message can have a normal and a super reaction
+    /// of the same emoji, it is only unqueued once all of them are removed
+    pub queue_reactions: HashSet<(MessageId, String, bool)>,
+    pub apply_cleanup: bool,
+    pub sd_resampler: SdResampler,
+    /// the fingerprint of the skins the moderator was asked to confirm
+    pub confirmation: Option<u64>,
+    /// chosen with `/upload`, every queue reaction uses it instead of the database of its emoji
+    pub default_database: Option<SkinToUploadDB>,
+    /// the channel `/upload` was used in, a resync reads the reactions in it
+    pub channel_id: ChannelId,
+    /// unix timestamps, the expiry is moved by the session on every activity
+    pub started_at: i64,
+    pub expires_at: i64,
+    /// skins that are checked or waiting to be checked, kept up to date by the session
+    pub queued_skins: usize,
+    /// given with `/upload_finish`, the batch gets a label by date without it
+    pub batch_label: Option<String>,
+}
+
+impl SkinUploadItem {
+    pub fn send(&self, event: SessionEvent) {
+        if self.events.send(event).is_err() {
+            warn!("The task of the upload session ended already");
+        }
+    }
+}
+
+/// Skins of a finished upload that could not be added to the database.
+pub struct FailedUploads {
+    pub skins: LinkedHashMap<String, SkinToUpload>,
+    pub apply_cleanup: bool,
+    pub sd_resampler: SdResampler,
+    /// of the batch they failed in, the retry is labeled after it
+    pub batch_label: String,
+}
+
+pub struct SkinUploads {
+    pub uploads: HashMap<UserId, SkinUploadItem>,
+    pub failed_uploads: HashMap<UserId, FailedUploads>,
+    pub upload_lock: Arc<Mutex<()>>,
+    /// set by a stop signal, no new uploads are started and the running one stops early
+    pub shutting_down: bool,
+}
+
+impl TypeMapKey for SkinUploads {
+    type Value = Self;
+}
+
+/// The skins the content checks compare submissions to.
+pub struct DefaultSkinHashes;
+
+impl TypeMapKey for DefaultSkinHashes {
+    type Value = Arc<DefaultSkins>;
+}
@@ -0,0 +1,192 @@
+use std::time::Duration;
+
+use serenity::all::{
+    ChannelId, GuildId, MessageId, ScheduledEventId, ScheduledEventStatus, ScheduledEventType,
+    Timestamp,
+};
+use serenity::builder::{CreateMessage, CreateScheduledEvent, EditScheduledEvent};
+use serenity::prelude::*;
+use tracing::warn;
+
+use crate::config::Config;
+use crate::votes::{leaderboard_messages, tally_votes};
+
+pub struct VoteRound {
+    name: String,
+    channel_id: ChannelId,
+    /// the announcement message, every skin posted after it takes part in the round
+    start_msg_id: MessageId,
+    event_id: Option<ScheduledEventId>,
+}
+
+#[derive(Default)]
+pub struct VoteRounds {
+    rounds: Vec<VoteRound>,
+}
+
+impl TypeMapKey for VoteRounds {
+    type Value = Self;
+}
+
+/// Opens a voting round in the channel, optionally announcing it as scheduled event.
+pub async fn start_vote_round(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    name: String,
+    days: u64,
+    create_event: bool,
+) -> anyhow::Result<()> {
+    let now = Timestamp::now().unix_timestamp();
+    // external events must start in the future
+    let start = now + 60;
+    let end = now + (days * 24 * 60 * 60) as i64;
+
+    let start_msg = channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new().content(format!(
+                "__**:ballot_box: Voting round \"{name}\" is open!**__\n\
+                Vote on the skins posted in this channel until <t:{end}:f> (<t:{end}:R>)."
+            )),
+        )
+        .await?;
+
+    let event_id = if create_event {
+        match guild_id
+            .create_scheduled_event(
+                ctx,
+                CreateScheduledEvent::new(
+                    ScheduledEventType::External,
+                    format!("Skin voting: {name}"),
+                    Timestamp::from_unix_timestamp(start)?,
+                )
+                .end_time(Timestamp::from_unix_timestamp(end)?)
+                .location(format!(
+                    "https://discord.com/channels/{guild_id}/{channel_id}/{}",
+                    start_msg.id
+                ))
+                .description("Vote for the skins that should be added to the database"),
+            )
+            .await
+        {
+            Ok(event) => Some(event.id),
+            Err(err) => {
+                warn!("Could not create scheduled event for the voting round: {err}");
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    ctx.data
+        .write()
+        .await
+        .get_mut::<VoteRounds>()
+        .unwrap()
+        .rounds
+        .push(VoteRound {
+            name,
+            channel_id,
+            start_msg_id: start_msg.id,
+            event_id,
+        });
+
+    let ctx = ctx.clone();
+    tokio::spawn(async move {
+        if let Some(event_id) = event_id {
+            tokio::time::sleep(Duration::from_secs((start - now) as u64)).await;
+            if let Err(err) = guild_id
+                .edit_scheduled_event(
+                    &ctx,
+                    event_id,
+                    EditScheduledEvent::new().status(ScheduledEventStatus::Active),
+                )
+                .await
+            {
+                warn!("Could not start the voting round event: {err}");
+            }
+            tokio::time::sleep(Duration::from_secs((end - start) as u64)).await;
+        } else {
+            tokio::time::sleep(Duration::from_secs((end - now) as u64)).await;
+        }
+        close_vote_round(&ctx, guild_id, start_msg.id).await;
+    });
+
+    Ok(())
+}
+
+/// Closes the voting round once its scheduled event was ended or cancelled on discord.
+pub async fn close_vote_round_of_event(
+    ctx: &Context,
+    guild_id: GuildId,
+    event_id: ScheduledEventId,
+) {
+    let start_msg_id = ctx
+        .data
+        .read()
+        .await
+        .get::<VoteRounds>()
+        .unwrap()
+        .rounds
+        .iter()
+        .find(|round| round.event_id == Some(event_id))
+        .map(|round| round.start_msg_id);
+    if let Some(start_msg_id) = start_msg_id {
+        close_vote_round(ctx, guild_id, start_msg_id).await;
+    }
+}
+
+/// Tallies the votes of all skins posted during the round and posts the results.
+pub async fn close_vote_round(ctx: &Context, guild_id: GuildId, start_msg_id: MessageId) {
+    let round = {
+        let mut data = ctx.data.write().await;
+        let rounds = &mut data.get_mut::<VoteRounds>().unwrap().rounds;
+        // the round might have been closed already by its event
+        let Some(index) = rounds
+            .iter()
+            .position(|round| round.start_msg_id == start_msg_id)
+        else {
+            return;
+        };
+        rounds.remove(index)
+    };
+
+    let config = ctx.data.read().await.get::<Config>().unwrap().clone();
+    let results = tally_votes(
+        &ctx.http,
+        round.channel_id,
+        round.start_msg_id,
+        &config.emojis,
+    )
+    .await;
+    let result_msgs = leaderboard_messages(
+        format!(
+            "__**:ballot_box: Voting round \"{}\" is closed!**__\n",
+            round.name
+        ),
+        &results,
+        guild_id,
+    );
+    for result_msg in result_msgs {
+        if let Err(err) = round
+            .channel_id
+            .send_message(ctx, CreateMessage::new().content(result_msg))
+            .await
+        {
+            warn!("sending voting round results failed {err}.");
+        }
+    }
+
+    if let Some(event_id) = round.event_id {
+        // fails if the event was already ended manually, which is fine
+        let _ = guild_id
+            .edit_scheduled_event(
+                ctx,
+                event_id,
+                EditScheduledEvent::new().status(ScheduledEventStatus::Completed),
+            )
+            .await;
+    }
+}
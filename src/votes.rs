@@ -0,0 +1,162 @@
+use std::cmp::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serenity::all::{
+    ChannelId, GuildId, Message, MessageId, MessageReaction, ReactionType, Timestamp,
+};
+use serenity::builder::CreateMessage;
+use serenity::http::Http;
+use tracing::warn;
+
+use crate::config::{Config, EmojiConfig, VoteConfig};
+use crate::metadata::parse_submission;
+use crate::scan::{fetch_submissions, message_id_at};
+
+/// The votes of one submission.
+pub struct SkinVotes {
+    pub skin_name: String,
+    pub author: String,
+    pub positive_count: u64,
+    pub negative_count: u64,
+    /// the forum post for submissions in a forum
+    pub channel_id: ChannelId,
+    pub msg_id: MessageId,
+}
+
+/// Counts the community votes on a submission, without the bot's own reaction.
+/// A super reaction counts as a vote of its own, like a normal reaction.
+pub fn count_votes(msg: &Message, emojis: &EmojiConfig) -> (u64, u64) {
+    let mut positive_count = 0;
+    let mut negative_count = 0;
+    msg.reactions.iter().for_each(|reaction| {
+        if let ReactionType::Custom {
+            animated: _,
+            id,
+            name: _,
+        } = &reaction.reaction_type
+        {
+            if id.get() == emojis.positive_vote_id {
+                positive_count = reaction_votes(reaction);
+            } else if id.get() == emojis.negative_vote_id {
+                negative_count = reaction_votes(reaction);
+            }
+        }
+    });
+    (positive_count, negative_count)
+}
+
+/// `count` also contains the super reactions, so the bot's own reaction is only
+/// subtracted from the normal ones.
+fn reaction_votes(reaction: &MessageReaction) -> u64 {
+    reaction.count_details.normal.saturating_sub(1) + reaction.count_details.burst
+}
+
+pub fn positive_ratio(positive_count: u64, negative_count: u64) -> f64 {
+    if positive_count + negative_count > 0 {
+        positive_count as f64 / (positive_count + negative_count) as f64
+    } else {
+        0.0
+    }
+}
+
+/// Whether the votes are enough for the normal database.
+pub fn meets_vote_threshold(config: &VoteConfig, positive_count: u64, negative_count: u64) -> bool {
+    positive_count + negative_count >= config.min_votes
+        && positive_ratio(positive_count, negative_count) >= config.min_positive_ratio
+}
+
+/// Tallies the votes of all submissions posted in the channel after the message,
+/// sorted by positive votes and then by their ratio.
+pub async fn tally_votes(
+    http: &Http,
+    channel_id: ChannelId,
+    after: MessageId,
+    emojis: &EmojiConfig,
+) -> Vec<SkinVotes> {
+    let mut results = Vec::default();
+    for msg in fetch_submissions(http, channel_id, after, usize::MAX).await {
+        if let Ok((skin_name, author, _)) = parse_submission(&msg) {
+            let (positive_count, negative_count) = count_votes(&msg, emojis);
+            results.push(SkinVotes {
+                skin_name,
+                author,
+                positive_count,
+                negative_count,
+                channel_id: msg.channel_id,
+                msg_id: msg.id,
+            });
+        }
+    }
+    results.sort_by(|a, b| {
+        b.positive_count.cmp(&a.positive_count).then(
+            positive_ratio(b.positive_count, b.negative_count)
+                .partial_cmp(&positive_ratio(a.positive_count, a.negative_count))
+                .unwrap_or(Ordering::Equal),
+        )
+    });
+    results
+}
+
+/// The ranked results with jump links, split into messages below discord's length limit.
+pub fn leaderboard_messages(
+    header: String,
+    results: &[SkinVotes],
+    guild_id: GuildId,
+) -> Vec<String> {
+    let mut msgs = vec![header];
+    if results.is_empty() {
+        *msgs.last_mut().unwrap() += "No skins were submitted.\n";
+    }
+    for (place, skin) in results.iter().enumerate() {
+        let line = format!(
+            "{}. \"{}\" by {}: {} positive, {} negative ({:.0}%) https://discord.com/channels/{guild_id}/{}/{}\n",
+            place + 1,
+            skin.skin_name,
+            skin.author,
+            skin.positive_count,
+            skin.negative_count,
+            positive_ratio(skin.positive_count, skin.negative_count) * 100.0,
+            skin.channel_id,
+            skin.msg_id,
+        );
+        if msgs.last().unwrap().chars().count() + line.chars().count() <= 2000 {
+            *msgs.last_mut().unwrap() += &line;
+        } else {
+            msgs.push(line);
+        }
+    }
+    msgs
+}
+
+/// Posts the leaderboard of the submission channel every `interval_days`,
+/// if the report is configured.
+pub async fn schedule_vote_reports(http: Arc<Http>, config: Arc<Config>) {
+    let report = &config.vote_report;
+    let Some(channel_id) = report.channel_id.map(ChannelId::new) else {
+        return;
+    };
+    let report_channel_id = report.report_channel_id.map_or(channel_id, ChannelId::new);
+    let interval = Duration::from_secs(report.interval_days * 24 * 60 * 60);
+    loop {
+        tokio::time::sleep(interval).await;
+        let since = Timestamp::now().unix_timestamp() - (report.days * 24 * 60 * 60) as i64;
+        let results = tally_votes(&http, channel_id, message_id_at(since), &config.emojis).await;
+        let msgs = leaderboard_messages(
+            format!(
+                "__**:ballot_box: Votes of the skins posted in the last {} days**__\n",
+                report.days
+            ),
+            &results,
+            config.guild_id(),
+        );
+        for msg in msgs {
+            if let Err(err) = report_channel_id
+                .send_message(&http, CreateMessage::new().content(msg))
+                .await
+            {
+                warn!("sending the vote report failed {err}.");
+            }
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::io::Cursor;
+
+use image::{ColorType, ImageFormat};
+
+const BPP: usize = 4;
+/// the side of the checked blocks on a 256x128 skin, scaled with the skin
+const BLOCK: usize = 8;
+/// the border of the image where signatures are usually put, on a 256x128 skin
+const EDGE_BAND: usize = 4;
+/// luminance difference of neighbouring pixels that counts as a stroke edge
+const MIN_CONTRAST: i32 = 96;
+/// stroke edges a block needs on a 256x128 skin, text has many of them in a small area
+const MIN_EDGES: usize = 10;
+/// and the share of the neighbouring pixel pairs they make up, gradients and dilated
+/// borders only have a few
+const MIN_EDGE_RATIO: f32 = 0.2;
+const ALPHA_THRESHOLD: u8 = 128;
+const OUTLINE: [u8; 4] = [255, 0, 0, 255];
+
+/// A block of a skin that looks like text, in pixels of the skin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatermarkRegion {
+    pub x: usize,
+    pub y: usize,
+    pub size: usize,
+}
+
+/// Whether the pixel is drawn by the client. The body and its shadow are drawn as circles,
+/// the corners of their cells and the two cells in front of the eyes are never visible.
+pub(crate) fn is_uv(x: usize, y: usize, scale: usize) -> bool {
+    let (x, y) = (x / scale, y / scale);
+    if y >= 96 && x < 64 {
+        return false;
+    }
+    if y < 96 && x < 192 {
+        let (cx, cy) = ((x % 96) as i32 - 48, y as i32 - 48);
+        return cx * cx + cy * cy <= 48 * 48;
+    }
+    true
+}
+
+fn is_edge(x: usize, y: usize, w: usize, h: usize, scale: usize) -> bool {
+    let band = EDGE_BAND * scale;
+    x < band || y < band || x >= w - band || y >= h - band
+}
+
+fn luminance(px: &[u8]) -> i32 {
+    (299 * px[0] as i32 + 587 * px[1] as i32 + 114 * px[2] as i32) / 1000
+}
+
+/// The blocks of a skin with many high-contrast edges in areas the client doesn't draw or along
+/// the border of the image, where signatures and watermarks are put. The parts of the tee are
+/// skipped, detailed art would look like text there.
+pub fn find_watermarks(rgba: &[u8], w: usize, h: usize) -> Vec<WatermarkRegion> {
+    let scale = (w / 256).max(1);
+    let checked = |x: usize, y: usize| {
+        rgba[(y * w + x) * BPP + 3] >= ALPHA_THRESHOLD
+            && (!is_uv(x, y, scale) || is_edge(x, y, w, h, scale))
+    };
+    let block = BLOCK * scale;
+    let mut regions = Vec::new();
+    for by in (0..h).step_by(block) {
+        for bx in (0..w).step_by(block) {
+            let (mut pairs, mut edges) = (0, 0);
+            for y in by..(by + block).min(h) {
+                for x in bx..(bx + block).min(w) {
+                    if !checked(x, y) {
+                        continue;
+                    }
+                    let lum = luminance(&rgba[(y * w + x) * BPP..]);
+                    for (nx, ny) in [(x + 1, y), (x, y + 1)] {
+                        if nx >= (bx + block).min(w) || ny >= (by + block).min(h) {
+                            continue;
+                        }
+                        if !checked(nx, ny) {
+                            continue;
+                        }
+                        pairs += 1;
+                        let contrast = (lum - luminance(&rgba[(ny * w + nx) * BPP..])).abs();
+                        if contrast >= MIN_CONTRAST {
+                            edges += 1;
+                        }
+                    }
+                }
+            }
+            if edges >= MIN_EDGES * scale && edges as f32 >= pairs as f32 * MIN_EDGE_RATIO {
+                regions.push(WatermarkRegion {
+                    x: bx,
+                    y: by,
+                    size: block,
+                });
+            }
+        }
+    }
+    regions
+}
+
+/// The skin with the found regions outlined in red, neighbouring regions share one outline.
+pub fn render_watermark_preview(
+    rgba: &[u8],
+    w: usize,
+    h: usize,
+    regions: &[WatermarkRegion],
+) -> anyhow::Result<Vec<u8>> {
+    let flagged = |x: usize, y: usize| {
+        regions
+            .iter()
+            .any(|r| x >= r.x && x < r.x + r.size && y >= r.y && y < r.y + r.size)
+    };
+    let mut preview = rgba.to_vec();
+    for y in 0..h {
+        for x in 0..w {
+            if !flagged(x, y) {
+                continue;
+            }
+            let border = x == 0
+                || y == 0
+                || x == w - 1
+                || y == h - 1
+                || !flagged(x - 1, y)
+                || !flagged(x + 1, y)
+                || !flagged(x, y - 1)
+                || !flagged(x, y + 1);
+            if border {
+                preview[(y * w + x) * BPP..(y * w + x + 1) * BPP].copy_from_slice(&OUTLINE);
+            }
+        }
+    }
+
+    let mut png = Vec::new();
+    image::write_buffer_with_format(
+        &mut Cursor::new(&mut png),
+        &preview,
+        w as u32,
+        h as u32,
+        ColorType::Rgba8,
+        ImageFormat::Png,
+    )?;
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_watermarks, WatermarkRegion, BPP};
+
+    /// a 256x128 skin with an opaque grey body and stripes like letters at `(x, y)`
+    fn skin_with_stripes(x: usize, y: usize) -> Vec<u8> {
+        let (w, h) = (256, 128);
+        let mut rgba = vec![0; w * h * BPP];
+        for py in 0..96 {
+            for px in 0..96 {
+                let (cx, cy) = (px as i32 - 48, py as i32 - 48);
+                if cx * cx + cy * cy <= 40 * 40 {
+                    rgba[(py * w + px) * BPP..(py * w + px + 1) * BPP]
+                        .copy_from_slice(&[128, 128, 128, 255]);
+                }
+            }
+        }
+        for py in y..y + 8 {
+            for px in x..x + 8 {
+                let value = if px % 2 == 0 { 255 } else { 0 };
+                rgba[(py * w + px) * BPP..(py * w + px + 1) * BPP]
+                    .copy_from_slice(&[value, value, value, 255]);
+            }
+        }
+        rgba
+    }
+
+    #[test]
+    fn flags_text_outside_the_tee() {
+        assert_eq!(
+            find_watermarks(&skin_with_stripes(16, 104), 256, 128),
+            vec![WatermarkRegion {
+                x: 16,
+                y: 104,
+                size: 8
+            }]
+        );
+        // along the border, even in a part of the tee
+        assert_eq!(
+            find_watermarks(&skin_with_stripes(200, 0), 256, 128).len(),
+            1
+        );
+        // detailed art on the body is fine
+        assert!(find_watermarks(&skin_with_stripes(40, 40), 256, 128).is_empty());
+    }
+}